@@ -0,0 +1,146 @@
+#[macro_use]
+extern crate criterion;
+extern crate crayon;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use criterion::Criterion;
+
+use crayon::bincode;
+use crayon::errors::*;
+use crayon::res::prelude::*;
+use crayon::res::registry::{Register, Registry};
+use crayon::res::vfs::manifest::{self, Manifest, ManifestItem};
+use crayon::res::vfs::VFS;
+use crayon::sched::ScheduleSystem;
+use crayon::utils::{FastHashMap, Handle};
+use crayon::uuid::Uuid;
+
+const RESOURCES: usize = 64;
+const BYTES: usize = 16 * 1024;
+
+// Keeps the files in memory, so the disk is not measured.
+struct Memory(FastHashMap<PathBuf, Vec<u8>>);
+
+impl Memory {
+    fn new() -> (Self, Vec<Uuid>) {
+        let mut files = FastHashMap::default();
+        let mut manifest = Manifest::new();
+        let mut uuids = Vec::new();
+
+        for i in 0..RESOURCES {
+            let uuid = Uuid::from_bytes([i as u8 + 1; 16]);
+            let filename = manifest.buf.extend_from_str(format!("{}.bytes", i).as_str());
+            let dependencies = manifest.buf.extend_from_slice(&[]);
+            manifest.items.push(ManifestItem {
+                filename: filename,
+                dependencies: dependencies,
+                uuid: uuid,
+            });
+
+            let location = format!("{:X}", uuid.to_simple());
+            files.insert(location.into(), vec![i as u8; BYTES]);
+            uuids.push(uuid);
+        }
+
+        let mut bytes = manifest::MAGIC.to_vec();
+        bytes.extend(bincode::serialize(&manifest).unwrap());
+        files.insert(PathBuf::from(manifest::NAME), bytes);
+        (Memory(files), uuids)
+    }
+}
+
+impl VFS for Memory {
+    fn read_to_end(&self, location: &Path, buf: &mut Vec<u8>) -> Result<usize> {
+        match self.0.get(location) {
+            Some(bytes) => {
+                buf.extend_from_slice(bytes);
+                Ok(bytes.len())
+            }
+            None => {
+                let err = ::std::io::Error::new(::std::io::ErrorKind::NotFound, "not found");
+                Err(err.into())
+            }
+        }
+    }
+
+    fn is_dir(&self, _: &Path) -> bool {
+        false
+    }
+
+    fn exists(&self, location: &Path) -> bool {
+        self.0.contains_key(location)
+    }
+
+    fn modified_since(&self, _: &Path, _: SystemTime) -> bool {
+        false
+    }
+}
+
+// Keeps the bytes as they are, so only the overhead of resource system is measured.
+#[derive(Clone)]
+struct BytesRegister;
+
+impl Register for BytesRegister {
+    type Handle = Handle;
+    type Intermediate = Vec<u8>;
+    type Value = Vec<u8>;
+
+    fn load(&self, _: Handle, bytes: &[u8]) -> Result<Vec<u8>> {
+        Ok(bytes.to_vec())
+    }
+
+    fn attach(&self, _: Handle, item: Vec<u8>) -> Result<Vec<u8>> {
+        Ok(item)
+    }
+
+    fn detach(&self, _: Handle, _: Vec<u8>) {}
+}
+
+fn testbed(sched: &ScheduleSystem) -> (Arc<ResourceSystemShared>, Vec<Uuid>) {
+    let (vfs, uuids) = Memory::new();
+    let mut res = ResourceSystem::new(sched.shared()).unwrap();
+    res.mount("mem", vfs).unwrap();
+    (res.shared(), uuids)
+}
+
+fn load_throughput(c: &mut Criterion) {
+    let sched = ScheduleSystem::new(4, None, None);
+
+    c.bench_function("res::load 64x16KB", move |b| {
+        // Every iteration loads into a fresh resource system, so the loads of previous
+        // iterations never collide with the new ones.
+        b.iter_with_setup(
+            || testbed(&sched),
+            |(res, uuids)| {
+                let registry = Registry::new(res.clone(), BytesRegister);
+                let handles: Vec<_> = uuids
+                    .iter()
+                    .map(|&v| registry.create_from_uuid(v).unwrap())
+                    .collect();
+
+                for v in handles {
+                    registry.wait_until(v).unwrap();
+                }
+
+                let stats = res.load_stats();
+                assert_eq!(stats.finished, RESOURCES);
+                assert_eq!(stats.bytes, RESOURCES * BYTES);
+            },
+        )
+    });
+}
+
+fn redirect(c: &mut Criterion) {
+    let sched = ScheduleSystem::new(1, None, None);
+    let (res, _) = testbed(&sched);
+
+    c.bench_function("res::redirect", move |b| {
+        b.iter(|| res.redirect("mem:31.bytes".into()).unwrap())
+    });
+}
+
+criterion_group!(benches, load_throughput, redirect);
+criterion_main!(benches);
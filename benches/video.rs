@@ -0,0 +1,52 @@
+#[macro_use]
+extern crate criterion;
+extern crate crayon;
+
+use criterion::Criterion;
+
+use crayon::application::window::Window;
+use crayon::math;
+use crayon::video::prelude::*;
+
+const DRAWCALLS: usize = 1024;
+
+// Submits draw calls through the headless backend, which measures the command buffers
+// and sorting of batches without any driver overhead.
+fn draw_submission(c: &mut Criterion) {
+    let window = Window::headless();
+    let mut video = VideoSystem::headless(None);
+    let shared = video.shared();
+
+    let surface = shared.create_surface(SurfaceParams::default()).unwrap();
+    let mesh = shared.create_mesh(MeshParams::default(), None).unwrap();
+
+    let mut params = ShaderParams::default();
+    params.uniforms = UniformVariableLayout::build()
+        .with("u_Color", UniformVariableType::Vector4f)
+        .with("u_MVPMatrix", UniformVariableType::Matrix4f)
+        .finish();
+    let shader = shared
+        .create_shader(params, "..".into(), "..".into())
+        .unwrap();
+
+    c.bench_function("video::submit 1024 drawcalls", move |b| {
+        let mut batch = OrderDrawBatch::new();
+        b.iter(|| {
+            for i in 0..DRAWCALLS {
+                let mut dc = DrawCall::new(shader, mesh);
+                dc.set_uniform_variable("u_Color", math::Vector4::new(1.0, 0.0, 0.0, 1.0));
+                dc.set_uniform_variable("u_MVPMatrix", math::Matrix4::from_scale(i as f32));
+                batch.draw(DRAWCALLS - i, dc);
+            }
+
+            batch.submit(&shared, surface).unwrap();
+            video.swap_frames();
+
+            let info = video.advance(&window).unwrap();
+            assert_eq!(info.drawcall as usize, DRAWCALLS);
+        })
+    });
+}
+
+criterion_group!(benches, draw_submission);
+criterion_main!(benches);
@@ -72,7 +72,7 @@ impl Application for Window {
             });
 
         if play_sfx {
-            self.audio.play(self.sfx).unwrap();
+            self.audio.play_once(self.sfx).unwrap();
         }
 
         if play_music {
@@ -0,0 +1,485 @@
+//! Mesh simplification based on quadric error metrics, which could be used to
+//! generate the reduced-triangle levels of a mesh, both in asset pipelines and at
+//! runtime.
+//!
+//! Edges are always collapsed into one of their endpoints, so the vertices of a
+//! simplified mesh are a subset of the original ones and every vertex attribute is
+//! preserved as it is. Vertices on open borders, attribute seams and sub-mesh
+//! boundaries are never removed, which keeps silhouettes and texture layouts intact.
+
+use std::cmp::Ordering;
+
+use crayon::errors::*;
+use crayon::math;
+use crayon::math::InnerSpace;
+use crayon::utils::hash::FastHashMap;
+use crayon::video::assets::mesh::*;
+use crayon::video::assets::shader::Attribute;
+
+/// Simplifies a triangle mesh until the number of triangles drops to `ratio` of
+/// the original, or until no edge could be collapsed without breaking its shape.
+///
+/// The position attribute must be consisted of three floats. Unused vertices are
+/// removed from the result.
+pub fn simplify(
+    params: &MeshParams,
+    data: &MeshData,
+    ratio: f32,
+) -> Result<(MeshParams, MeshData)> {
+    let mut mesh = Simplifier::new(params, data)?;
+    let target = (mesh.len() as f32 * ratio.max(0.0).min(1.0)).ceil() as usize;
+    mesh.collapse(target);
+    Ok(mesh.finish(params, data))
+}
+
+/// Generates the levels of details with triangle `ratios` of the original mesh,
+/// every level is simplified from its predecessor.
+pub fn generate_lods(
+    params: &MeshParams,
+    data: &MeshData,
+    ratios: &[f32],
+) -> Result<Vec<(MeshParams, MeshData)>> {
+    let mut lods: Vec<(MeshParams, MeshData)> = Vec::with_capacity(ratios.len());
+    let mut last = 1.0;
+
+    for &ratio in ratios {
+        if ratio > last {
+            bail!("The ratios of levels must be in descending order.");
+        }
+
+        let lod = match lods.last() {
+            Some(v) => simplify(&v.0, &v.1, ratio / last)?,
+            None => simplify(params, data, ratio)?,
+        };
+
+        last = ratio;
+        lods.push(lod);
+    }
+
+    Ok(lods)
+}
+
+/// The symmetric 4x4 matrix of the sum of squared distances to a set of planes.
+#[derive(Debug, Default, Copy, Clone)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn plane(n: math::Vector3<f64>, d: f64, weight: f64) -> Self {
+        Quadric([
+            n.x * n.x * weight,
+            n.x * n.y * weight,
+            n.x * n.z * weight,
+            n.x * d * weight,
+            n.y * n.y * weight,
+            n.y * n.z * weight,
+            n.y * d * weight,
+            n.z * n.z * weight,
+            n.z * d * weight,
+            d * d * weight,
+        ])
+    }
+
+    fn add(&mut self, rhs: &Quadric) {
+        for i in 0..10 {
+            self.0[i] += rhs.0[i];
+        }
+    }
+
+    fn error(&self, p: math::Vector3<f64>) -> f64 {
+        let q = &self.0;
+        let (x, y, z) = (p.x, p.y, p.z);
+
+        q[0] * x * x
+            + 2.0 * q[1] * x * y
+            + 2.0 * q[2] * x * z
+            + 2.0 * q[3] * x
+            + q[4] * y * y
+            + 2.0 * q[5] * y * z
+            + 2.0 * q[6] * y
+            + q[7] * z * z
+            + 2.0 * q[8] * z
+            + q[9]
+    }
+}
+
+struct Simplifier {
+    positions: Vec<math::Vector3<f64>>,
+    // The first vertex with the same position, which represents the vertex in quadrics.
+    welds: Vec<u32>,
+    locks: Vec<bool>,
+    quadrics: Vec<Quadric>,
+    indices: Vec<u32>,
+    sub_meshes: Vec<usize>,
+    alives: Vec<bool>,
+    len: usize,
+}
+
+impl Simplifier {
+    fn new(params: &MeshParams, data: &MeshData) -> Result<Self> {
+        if params.primitive != MeshPrimitive::Triangles {
+            bail!("Only triangle lists could be simplified.");
+        }
+
+        let element = params.layout.element(Attribute::Position);
+        let offset = params.layout.offset(Attribute::Position);
+        let offset = match (element, offset) {
+            (Some(e), Some(offset)) if e.format == VertexFormat::Float && e.size == 3 => {
+                offset as usize
+            }
+            _ => bail!("Position attribute with 3 floats is required to simplify mesh."),
+        };
+
+        if data.vptr.len() < params.vertex_buffer_len()
+            || data.iptr.len() < params.index_buffer_len()
+            || params.num_idxes % 3 != 0
+        {
+            bail!("The buffers of mesh does not match its params.");
+        }
+
+        // The offsets of sub-meshes are used to split triangles below.
+        params.validate(None)?;
+
+        let stride = params.layout.stride() as usize;
+        let mut positions = Vec::with_capacity(params.num_verts);
+        for i in 0..params.num_verts {
+            let v = read::<[f32; 3]>(&data.vptr, i * stride + offset);
+            positions.push(math::Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64));
+        }
+
+        let mut indices = Vec::with_capacity(params.num_idxes);
+        for i in 0..params.num_idxes {
+            let v = match params.index_format {
+                IndexFormat::U16 => read::<u16>(&data.iptr, i * 2) as u32,
+                IndexFormat::U32 => read::<u32>(&data.iptr, i * 4),
+            };
+
+            if v as usize >= params.num_verts {
+                bail!("Index {} is out of bounds.", v);
+            }
+
+            indices.push(v);
+        }
+
+        let len = indices.len() / 3;
+        let mut sub_meshes = vec![0; len];
+        for (i, &start) in params.sub_mesh_offsets.iter().enumerate() {
+            for v in &mut sub_meshes[(start / 3)..] {
+                *v = i;
+            }
+        }
+
+        let mut simplifier = Simplifier {
+            welds: Vec::new(),
+            locks: vec![false; positions.len()],
+            quadrics: vec![Quadric::default(); positions.len()],
+            positions: positions,
+            indices: indices,
+            sub_meshes: sub_meshes,
+            alives: vec![true; len],
+            len: len,
+        };
+
+        simplifier.weld();
+        simplifier.lock();
+        simplifier.accumulate();
+        Ok(simplifier)
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn weld(&mut self) {
+        let mut remap = FastHashMap::default();
+        let mut welds = Vec::with_capacity(self.positions.len());
+        let mut seams = vec![false; self.positions.len()];
+
+        for (i, p) in self.positions.iter().enumerate() {
+            let key = (p.x.to_bits(), p.y.to_bits(), p.z.to_bits());
+            let w = *remap.entry(key).or_insert(i as u32);
+            if w as usize != i {
+                seams[w as usize] = true;
+            }
+
+            welds.push(w);
+        }
+
+        // Vertices with the same position but different attributes are seams.
+        for i in 0..welds.len() {
+            self.locks[i] = seams[welds[i] as usize];
+        }
+
+        self.welds = welds;
+    }
+
+    fn lock(&mut self) {
+        let mut edges = FastHashMap::default();
+        let mut owners = FastHashMap::default();
+
+        for t in 0..self.len {
+            let tri = self.triangle(t);
+            for i in 0..3 {
+                let (a, b) = (
+                    self.welds[tri[i] as usize],
+                    self.welds[tri[(i + 1) % 3] as usize],
+                );
+                *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+
+                let owner = owners.entry(a).or_insert(self.sub_meshes[t]);
+                if *owner != self.sub_meshes[t] {
+                    self.locks[a as usize] = true;
+                }
+            }
+        }
+
+        // Edges that are used by only one triangle are on the open borders.
+        for (&(a, b), &count) in &edges {
+            if count == 1 {
+                self.locks[a as usize] = true;
+                self.locks[b as usize] = true;
+            }
+        }
+
+        for i in 0..self.welds.len() {
+            let w = self.welds[i] as usize;
+            self.locks[i] = self.locks[i] || self.locks[w];
+        }
+    }
+
+    fn accumulate(&mut self) {
+        for t in 0..self.len {
+            let tri = self.triangle(t);
+            let (p0, p1, p2) = (
+                self.positions[tri[0] as usize],
+                self.positions[tri[1] as usize],
+                self.positions[tri[2] as usize],
+            );
+
+            let n = (p1 - p0).cross(p2 - p0);
+            let area = n.magnitude();
+            if area <= ::std::f64::EPSILON {
+                continue;
+            }
+
+            let n = n / area;
+            let q = Quadric::plane(n, -n.dot(p0), area * 0.5);
+            for &v in &tri {
+                let w = self.welds[v as usize] as usize;
+                self.quadrics[w].add(&q);
+            }
+        }
+    }
+
+    #[inline]
+    fn triangle(&self, t: usize) -> [u32; 3] {
+        [
+            self.indices[t * 3],
+            self.indices[t * 3 + 1],
+            self.indices[t * 3 + 2],
+        ]
+    }
+
+    #[inline]
+    fn degenerated(&self, tri: [u32; 3]) -> bool {
+        let (a, b, c) = (
+            self.welds[tri[0] as usize],
+            self.welds[tri[1] as usize],
+            self.welds[tri[2] as usize],
+        );
+
+        a == b || b == c || c == a
+    }
+
+    fn collapse(&mut self, target: usize) {
+        while self.len > target {
+            if !self.collapse_pass(target) {
+                break;
+            }
+        }
+    }
+
+    /// Collapses the cheapest edges that are not adjacent to each other. Returns
+    /// false if nothing could be collapsed anymore.
+    fn collapse_pass(&mut self, target: usize) -> bool {
+        let mut adjacency = vec![Vec::new(); self.positions.len()];
+        let mut candidates = Vec::new();
+
+        for t in (0..self.alives.len()).filter(|&t| self.alives[t]) {
+            let tri = self.triangle(t);
+            for i in 0..3 {
+                adjacency[tri[i] as usize].push(t);
+
+                let (a, b) = (tri[i], tri[(i + 1) % 3]);
+                for &(from, to) in &[(a, b), (b, a)] {
+                    if !self.locks[from as usize] {
+                        let mut q = self.quadrics[from as usize];
+                        q.add(&self.quadrics[self.welds[to as usize] as usize]);
+                        let cost = q.error(self.positions[to as usize]);
+                        candidates.push((cost, from, to));
+                    }
+                }
+            }
+        }
+
+        candidates.sort_by(|lhs, rhs| lhs.0.partial_cmp(&rhs.0).unwrap_or(Ordering::Equal));
+
+        let mut sub_mesh_lens = FastHashMap::default();
+        for t in (0..self.alives.len()).filter(|&t| self.alives[t]) {
+            *sub_mesh_lens.entry(self.sub_meshes[t]).or_insert(0) += 1;
+        }
+
+        let mut touched = vec![false; self.positions.len()];
+        let mut collapsed = false;
+
+        for (_, from, to) in candidates {
+            if self.len <= target {
+                break;
+            }
+
+            let (from, to) = (from as usize, to as usize);
+            let wto = self.welds[to] as usize;
+            if touched[from] || touched[wto] {
+                continue;
+            }
+
+            if !self.collapsible(&adjacency[from], from, to, &sub_mesh_lens) {
+                continue;
+            }
+
+            // Marks the whole fan, since the triangles around it are changed.
+            for &t in &adjacency[from] {
+                for v in &self.triangle(t) {
+                    touched[self.welds[*v as usize] as usize] = true;
+                }
+            }
+
+            for &t in &adjacency[from] {
+                for i in 0..3 {
+                    if self.indices[t * 3 + i] as usize == from {
+                        self.indices[t * 3 + i] = to as u32;
+                    }
+                }
+
+                if self.degenerated(self.triangle(t)) {
+                    self.alives[t] = false;
+                    self.len -= 1;
+                    *sub_mesh_lens.get_mut(&self.sub_meshes[t]).unwrap() -= 1;
+                }
+            }
+
+            let q = self.quadrics[from];
+            self.quadrics[wto].add(&q);
+            collapsed = true;
+        }
+
+        collapsed
+    }
+
+    /// Checks if collapsing `from` into `to` would flip any triangle, or remove the
+    /// last triangle of a sub-mesh.
+    fn collapsible(
+        &self,
+        fan: &[usize],
+        from: usize,
+        to: usize,
+        sub_mesh_lens: &FastHashMap<usize, usize>,
+    ) -> bool {
+        let mut removes = FastHashMap::default();
+
+        for &t in fan {
+            let tri = self.triangle(t);
+            let mut moved = tri;
+            for v in &mut moved {
+                if *v as usize == from {
+                    *v = to as u32;
+                }
+            }
+
+            if self.degenerated(moved) {
+                *removes.entry(self.sub_meshes[t]).or_insert(0) += 1;
+                continue;
+            }
+
+            let n0 = self.normal(tri);
+            let n1 = self.normal(moved);
+            if n0.dot(n1) <= 0.0 {
+                return false;
+            }
+        }
+
+        removes
+            .iter()
+            .all(|(k, &v)| sub_mesh_lens.get(k).cloned().unwrap_or(0) > v)
+    }
+
+    #[inline]
+    fn normal(&self, tri: [u32; 3]) -> math::Vector3<f64> {
+        let p0 = self.positions[tri[0] as usize];
+        let p1 = self.positions[tri[1] as usize];
+        let p2 = self.positions[tri[2] as usize];
+        (p1 - p0).cross(p2 - p0)
+    }
+
+    fn finish(&self, params: &MeshParams, data: &MeshData) -> (MeshParams, MeshData) {
+        let stride = params.layout.stride() as usize;
+
+        let mut remap = vec![None; self.positions.len()];
+        for t in (0..self.alives.len()).filter(|&t| self.alives[t]) {
+            for &v in &self.triangle(t) {
+                remap[v as usize] = Some(0);
+            }
+        }
+
+        // Keeps the original order of vertices.
+        let mut vptr = Vec::new();
+        let mut num_verts = 0;
+        for (i, v) in remap.iter_mut().enumerate() {
+            if v.is_some() {
+                *v = Some(num_verts as u32);
+                vptr.extend_from_slice(&data.vptr[(i * stride)..((i + 1) * stride)]);
+                num_verts += 1;
+            }
+        }
+
+        let mut iptr = Vec::new();
+        let mut sub_mesh_offsets = Vec::new();
+        let mut last_sub_mesh = None;
+        let mut num_idxes = 0;
+
+        for t in (0..self.alives.len()).filter(|&t| self.alives[t]) {
+            if !params.sub_mesh_offsets.is_empty() && last_sub_mesh != Some(self.sub_meshes[t]) {
+                last_sub_mesh = Some(self.sub_meshes[t]);
+                sub_mesh_offsets.push(num_idxes);
+            }
+
+            for &v in &self.triangle(t) {
+                let v = remap[v as usize].unwrap();
+                match params.index_format {
+                    IndexFormat::U16 => iptr.extend_from_slice(IndexFormat::encode(&[v as u16])),
+                    IndexFormat::U32 => iptr.extend_from_slice(IndexFormat::encode(&[v])),
+                }
+
+                num_idxes += 1;
+            }
+        }
+
+        let mut params = params.clone();
+        params.num_verts = num_verts;
+        params.num_idxes = num_idxes;
+        params.sub_mesh_offsets = sub_mesh_offsets;
+
+        let data = MeshData {
+            vptr: vptr.into_boxed_slice(),
+            iptr: iptr.into_boxed_slice(),
+        };
+
+        (params, data)
+    }
+}
+
+#[inline]
+fn read<T: Copy>(bytes: &[u8], offset: usize) -> T {
+    assert!(offset + ::std::mem::size_of::<T>() <= bytes.len());
+    unsafe { ::std::ptr::read_unaligned(bytes.as_ptr().add(offset) as *const T) }
+}
@@ -5,4 +5,5 @@ pub mod prefab_loader;
 pub use self::prefab_loader::PrefabLoader;
 
 pub mod mesh_builder;
+pub mod mesh_simplifier;
 pub mod texture_builder;
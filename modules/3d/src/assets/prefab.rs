@@ -43,7 +43,33 @@ pub struct PrefabMeshRenderer {
 }
 
 impl Prefab {
+    /// Checks that the indices of nodes and meshes are valid, and the nodes form a tree
+    /// rooted at the first node.
     pub fn validate(&self) -> Result<()> {
+        if self.nodes.is_empty() {
+            bail!("Prefab does not have any nodes.");
+        }
+
+        let mut visited = vec![false; self.nodes.len()];
+        let mut nodes = vec![0];
+
+        while let Some(idx) = nodes.pop() {
+            if idx >= self.nodes.len() || visited[idx] {
+                bail!("Prefab node {} is undefined or referenced twice.", idx);
+            }
+
+            visited[idx] = true;
+
+            let n = &self.nodes[idx];
+            if let Some(mesh) = n.mesh_renderer {
+                if mesh >= self.universe_meshes.len() {
+                    bail!("Prefab node {} references undefined mesh {}.", idx, mesh);
+                }
+            }
+
+            nodes.extend(n.next_sib.iter().chain(n.first_child.iter()));
+        }
+
         Ok(())
     }
 }
@@ -1,11 +1,11 @@
-use std::io::Cursor;
 use std::sync::Arc;
 
 use crayon::application::Context;
-use crayon::bincode;
 use crayon::errors::*;
 use crayon::res::registry::Register;
+use crayon::res::utils;
 use crayon::res::ResourceSystemShared;
+use crayon::uuid::Uuid;
 use crayon::video::VideoSystemShared;
 
 use super::prefab::*;
@@ -35,12 +35,12 @@ impl Register for PrefabLoader {
     type Value = Arc<Prefab>;
 
     fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
-        if &bytes[0..8] != &MAGIC[..] {
+        if !utils::check_magic(bytes, &MAGIC) {
             bail!("[PrefabLoader] MAGIC number not match.");
         }
 
-        let mut file = Cursor::new(&bytes[8..]);
-        let mut prefab: Prefab = bincode::deserialize_from(&mut file)?;
+        let mut prefab: Prefab = utils::deserialize_from(&bytes[8..], bytes.len() as u64)?;
+        prefab.validate()?;
 
         for &v in &prefab.universe_meshes {
             let mesh = self.video.create_mesh_from_uuid(v)?;
@@ -66,6 +66,10 @@ impl Register for PrefabLoader {
         Ok(Arc::new(item))
     }
 
+    fn dependencies(&self, prefab: &Self::Intermediate) -> Vec<Uuid> {
+        prefab.universe_meshes.clone()
+    }
+
     fn detach(&self, handle: Self::Handle, prefab: Self::Value) {
         info!("[PrefabLoader] detach {:?}.", handle);
 
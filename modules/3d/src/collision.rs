@@ -0,0 +1,568 @@
+//! Kinematic collision queries without rigid-body dynamics.
+//!
+//! The `CollisionWorld` holds static triangle meshes, e.g. the level geometry, and moving
+//! shapes like characters and projectiles. It answers two kinds of queries:
+//!
+//! * `overlap` finds everything that intersects a shape at some position.
+//! * `sweep` moves a shape along a line and finds the first thing it hits, which is
+//!   enough to implement character controllers and bullets.
+//!
+//! Shapes only translate, boxes are always axis-aligned and capsules always stand along
+//! the y-axis. Since the distance between convex shapes is convex along a translation,
+//! sweeps search the closest approach to every obstacle instead of sampling the motion,
+//! so fast and thin shapes never tunnel through triangles.
+
+use crayon::errors::*;
+use crayon::math;
+use crayon::math::{EuclideanSpace, InnerSpace};
+
+use component::Component;
+use scene::Transform;
+use Entity;
+
+/// The shapes of moving colliders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    /// A sphere with radius.
+    Sphere(f32),
+    /// A capsule along the y-axis, with radius and the half height of its segment.
+    Capsule(f32, f32),
+    /// An axis-aligned box with half extents.
+    Aabb(math::Vector3<f32>),
+}
+
+impl Shape {
+    /// Gets the bounds of this shape at `position`.
+    pub fn bounds(&self, position: math::Vector3<f32>) -> math::Aabb3<f32> {
+        let extents = match *self {
+            Shape::Sphere(radius) => math::Vector3::new(radius, radius, radius),
+            Shape::Capsule(radius, half_height) => {
+                math::Vector3::new(radius, radius + half_height, radius)
+            }
+            Shape::Aabb(extents) => extents,
+        };
+
+        math::Aabb3::new(
+            math::Point3::from_vec(position - extents),
+            math::Point3::from_vec(position + extents),
+        )
+    }
+
+    // The convex core and radius of this shape.
+    fn core(&self, position: math::Vector3<f32>) -> (Convex, f32) {
+        match *self {
+            Shape::Sphere(radius) => (Convex::Point(position), radius),
+            Shape::Capsule(radius, half_height) => {
+                let offset = math::Vector3::new(0.0, half_height, 0.0);
+                (
+                    Convex::Segment(position - offset, position + offset),
+                    radius,
+                )
+            }
+            Shape::Aabb(extents) => (Convex::Box(position, extents), 0.0),
+        }
+    }
+}
+
+/// The first hit of a sweep.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    /// The entity of mesh or collider that is hit.
+    pub entity: Entity,
+    /// The fraction of motion before the hit, in `[0, 1]`.
+    pub toi: f32,
+    /// The position of shape when it touches the other one.
+    pub position: math::Vector3<f32>,
+    /// The normal of contact, pointing from the other one towards the shape.
+    pub normal: math::Vector3<f32>,
+}
+
+struct StaticMesh {
+    triangles: Vec<[math::Vector3<f32>; 3]>,
+    bounds: math::Aabb3<f32>,
+}
+
+/// A world of static triangle meshes and moving colliders.
+pub struct CollisionWorld {
+    meshes: Component<StaticMesh>,
+    colliders: Component<(Shape, math::Vector3<f32>)>,
+}
+
+impl Default for CollisionWorld {
+    fn default() -> Self {
+        CollisionWorld::new()
+    }
+}
+
+impl CollisionWorld {
+    pub fn new() -> Self {
+        CollisionWorld {
+            meshes: Component::new(),
+            colliders: Component::new(),
+        }
+    }
+
+    /// Adds a static mesh with triangle list `indices`, the vertices are transformed
+    /// into world space with `transform`. It replaces the old mesh of entity.
+    pub fn add_mesh(
+        &mut self,
+        ent: Entity,
+        transform: Transform,
+        vertices: &[math::Vector3<f32>],
+        indices: &[u32],
+    ) -> Result<()> {
+        if indices.len() % 3 != 0 {
+            bail!("The length of indices must be multiple of 3.");
+        }
+
+        if let Some(&v) = indices.iter().find(|&&v| v as usize >= vertices.len()) {
+            bail!("Index {} is out of {} vertices.", v, vertices.len());
+        }
+
+        let vertices: Vec<_> = vertices
+            .iter()
+            .map(|&v| transform.transform_point(v))
+            .collect();
+
+        let mut bounds: Option<math::Aabb3<f32>> = None;
+        for &v in &vertices {
+            let p = math::Point3::from_vec(v);
+            bounds = Some(bounds.map(|b| b.grow(p)).unwrap_or(math::Aabb3::new(p, p)));
+        }
+
+        let triangles = indices
+            .chunks(3)
+            .map(|v| {
+                [
+                    vertices[v[0] as usize],
+                    vertices[v[1] as usize],
+                    vertices[v[2] as usize],
+                ]
+            })
+            .collect();
+
+        let mesh = StaticMesh {
+            triangles: triangles,
+            bounds: bounds.unwrap_or_else(math::Aabb3::zero),
+        };
+
+        self.meshes.add(ent, mesh);
+        Ok(())
+    }
+
+    /// Removes the static mesh of entity.
+    #[inline]
+    pub fn remove_mesh(&mut self, ent: Entity) {
+        self.meshes.remove(ent);
+    }
+
+    /// Adds a moving collider, or replaces the old one of entity.
+    #[inline]
+    pub fn add(&mut self, ent: Entity, shape: Shape, position: math::Vector3<f32>) {
+        self.colliders.add(ent, (shape, position));
+    }
+
+    /// Removes the collider of entity.
+    #[inline]
+    pub fn remove(&mut self, ent: Entity) {
+        self.colliders.remove(ent);
+    }
+
+    /// Gets the shape and position of collider.
+    #[inline]
+    pub fn get(&self, ent: Entity) -> Option<(Shape, math::Vector3<f32>)> {
+        self.colliders.get(ent).cloned()
+    }
+
+    /// Moves the collider to `position`, without checking collisions.
+    #[inline]
+    pub fn set_position(&mut self, ent: Entity, position: math::Vector3<f32>) {
+        if let Some(v) = self.colliders.get_mut(ent) {
+            v.1 = position;
+        }
+    }
+
+    /// Finds the meshes and colliders that intersect with `shape` at `position`. The
+    /// collider of `ignore` is skipped, e.g. the one that queries.
+    pub fn overlap(
+        &self,
+        shape: Shape,
+        position: math::Vector3<f32>,
+        ignore: Option<Entity>,
+    ) -> Vec<Entity> {
+        let bounds = shape.bounds(position);
+        let (core, radius) = shape.core(position);
+        let mut entities = Vec::new();
+
+        for (&ent, mesh) in self.meshes.entities.iter().zip(&self.meshes.data) {
+            if intersects(&bounds, &mesh.bounds) {
+                let hit = mesh
+                    .triangles
+                    .iter()
+                    .any(|v| separation(&core, &Convex::Triangle(v[0], v[1], v[2])).0 <= radius);
+
+                if hit {
+                    entities.push(ent);
+                }
+            }
+        }
+
+        for (&ent, v) in self.colliders.entities.iter().zip(&self.colliders.data) {
+            if Some(ent) != ignore && intersects(&bounds, &v.0.bounds(v.1)) {
+                let (other, other_radius) = v.0.core(v.1);
+                if separation(&core, &other).0 <= radius + other_radius {
+                    entities.push(ent);
+                }
+            }
+        }
+
+        entities
+    }
+
+    /// Moves `shape` from `from` to `to`, and returns the first contact if there is any.
+    /// A shape that intersects with something at `from` hits it at once.
+    pub fn sweep(
+        &self,
+        shape: Shape,
+        from: math::Vector3<f32>,
+        to: math::Vector3<f32>,
+        ignore: Option<Entity>,
+    ) -> Option<Contact> {
+        let delta = to - from;
+
+        let from_bounds = shape.bounds(from);
+        let to_bounds = shape.bounds(to);
+        let bounds = math::Aabb3::new(
+            math::Point3::from_vec(min(from_bounds.min.to_vec(), to_bounds.min.to_vec())),
+            math::Point3::from_vec(max(from_bounds.max.to_vec(), to_bounds.max.to_vec())),
+        );
+
+        let mut first: Option<(Entity, f32, Convex, f32)> = None;
+        let mut test = |ent: Entity, other: Convex, other_radius: f32| {
+            let distance = |t: f32| {
+                let (core, radius) = shape.core(from + delta * t);
+                separation(&core, &other).0 - radius - other_radius
+            };
+
+            let limit = first.as_ref().map(|v| v.1).unwrap_or(1.0);
+            if let Some(toi) = time_of_impact(distance, limit) {
+                first = Some((ent, toi, other, other_radius));
+            }
+        };
+
+        for (&ent, mesh) in self.meshes.entities.iter().zip(&self.meshes.data) {
+            if intersects(&bounds, &mesh.bounds) {
+                for v in &mesh.triangles {
+                    let triangle = Convex::Triangle(v[0], v[1], v[2]);
+                    if intersects(&bounds, &triangle.bounds()) {
+                        test(ent, triangle, 0.0);
+                    }
+                }
+            }
+        }
+
+        for (&ent, v) in self.colliders.entities.iter().zip(&self.colliders.data) {
+            if Some(ent) != ignore && intersects(&bounds, &v.0.bounds(v.1)) {
+                let (other, other_radius) = v.0.core(v.1);
+                test(ent, other, other_radius);
+            }
+        }
+
+        first.map(|(ent, toi, other, _)| {
+            let position = from + delta * toi;
+            let (core, _) = shape.core(position);
+            let mut normal = separation(&core, &other).1;
+
+            if normal.magnitude2() <= ::std::f32::EPSILON {
+                normal = if delta.magnitude2() > 0.0 {
+                    -delta.normalize()
+                } else {
+                    math::Vector3::new(0.0, 1.0, 0.0)
+                };
+            }
+
+            Contact {
+                entity: ent,
+                toi: toi,
+                position: position,
+                normal: normal,
+            }
+        })
+    }
+}
+
+// The distance at which shapes are considered touching.
+const TOLERANCE: f32 = 1e-3;
+
+// Finds the time in `[0, limit)` when the shapes start touching. The distance is convex
+// in time, so its minimum is found with ternary search, and it decreases until then.
+fn time_of_impact<F: Fn(f32) -> f32>(distance: F, limit: f32) -> Option<f32> {
+    if distance(0.0) <= TOLERANCE {
+        return Some(0.0);
+    }
+
+    let (mut lo, mut hi) = (0.0, limit);
+    for _ in 0..40 {
+        let m1 = lo + (hi - lo) / 3.0;
+        let m2 = hi - (hi - lo) / 3.0;
+        if distance(m1) <= distance(m2) {
+            hi = m2;
+        } else {
+            lo = m1;
+        }
+    }
+
+    let closest = (lo + hi) * 0.5;
+    if distance(closest) > TOLERANCE {
+        return None;
+    }
+
+    let (mut lo, mut hi) = (0.0, closest);
+    for _ in 0..32 {
+        let mid = (lo + hi) * 0.5;
+        if distance(mid) <= TOLERANCE {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+
+    if lo < limit {
+        Some(lo)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Convex {
+    Point(math::Vector3<f32>),
+    Segment(math::Vector3<f32>, math::Vector3<f32>),
+    Box(math::Vector3<f32>, math::Vector3<f32>),
+    Triangle(math::Vector3<f32>, math::Vector3<f32>, math::Vector3<f32>),
+}
+
+impl Convex {
+    fn bounds(&self) -> math::Aabb3<f32> {
+        let (lo, hi) = match *self {
+            Convex::Point(p) => (p, p),
+            Convex::Segment(a, b) => (min(a, b), max(a, b)),
+            Convex::Box(c, e) => (c - e, c + e),
+            Convex::Triangle(a, b, c) => (min(min(a, b), c), max(max(a, b), c)),
+        };
+
+        math::Aabb3::new(math::Point3::from_vec(lo), math::Point3::from_vec(hi))
+    }
+
+    // Gets the closest point to `p`.
+    fn closest(&self, p: math::Vector3<f32>) -> math::Vector3<f32> {
+        match *self {
+            Convex::Point(v) => v,
+            Convex::Segment(a, b) => {
+                let ab = b - a;
+                let t = ab.dot(p - a) / ab.magnitude2().max(::std::f32::EPSILON);
+                a + ab * t.max(0.0).min(1.0)
+            }
+            Convex::Box(c, e) => math::Vector3::new(
+                p.x.max(c.x - e.x).min(c.x + e.x),
+                p.y.max(c.y - e.y).min(c.y + e.y),
+                p.z.max(c.z - e.z).min(c.z + e.z),
+            ),
+            Convex::Triangle(a, b, c) => closest_on_triangle(p, a, b, c),
+        }
+    }
+
+    // Projects onto `axis`.
+    fn project(&self, axis: math::Vector3<f32>) -> (f32, f32) {
+        match *self {
+            Convex::Point(p) => (p.dot(axis), p.dot(axis)),
+            Convex::Segment(a, b) => {
+                let (a, b) = (a.dot(axis), b.dot(axis));
+                (a.min(b), a.max(b))
+            }
+            Convex::Box(c, e) => {
+                let r = e.x * axis.x.abs() + e.y * axis.y.abs() + e.z * axis.z.abs();
+                (c.dot(axis) - r, c.dot(axis) + r)
+            }
+            Convex::Triangle(a, b, c) => {
+                let (a, b, c) = (a.dot(axis), b.dot(axis), c.dot(axis));
+                (a.min(b).min(c), a.max(b).max(c))
+            }
+        }
+    }
+}
+
+// Gets the distance between two convex sets, which is negative or zero if they
+// intersect, and the direction from `b` to `a`.
+fn separation(a: &Convex, b: &Convex) -> (f32, math::Vector3<f32>) {
+    match (*a, *b) {
+        (Convex::Box(..), Convex::Box(..))
+        | (Convex::Box(..), Convex::Triangle(..))
+        | (Convex::Triangle(..), Convex::Box(..)) => separating_axis(a, b),
+        (Convex::Point(p), _) => {
+            let q = b.closest(p);
+            direction(p, q)
+        }
+        (_, Convex::Point(q)) => {
+            let p = a.closest(q);
+            direction(p, q)
+        }
+        (Convex::Segment(s0, s1), _) => {
+            let (p, q) = closest_along(s0, s1, b);
+            direction(p, q)
+        }
+        (_, Convex::Segment(s0, s1)) => {
+            let (q, p) = closest_along(s0, s1, a);
+            direction(p, q)
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn direction(p: math::Vector3<f32>, q: math::Vector3<f32>) -> (f32, math::Vector3<f32>) {
+    let d = (p - q).magnitude();
+    if d > ::std::f32::EPSILON {
+        (d, (p - q) / d)
+    } else {
+        (0.0, math::Vector3::new(0.0, 0.0, 0.0))
+    }
+}
+
+// Finds the point on segment closest to `other`, and its closest point on `other`. The
+// distance to a convex set is convex along a segment, so ternary search converges.
+fn closest_along(
+    s0: math::Vector3<f32>,
+    s1: math::Vector3<f32>,
+    other: &Convex,
+) -> (math::Vector3<f32>, math::Vector3<f32>) {
+    let distance = |t: f32| {
+        let p = s0 + (s1 - s0) * t;
+        (p - other.closest(p)).magnitude2()
+    };
+
+    let (mut lo, mut hi) = (0.0f32, 1.0f32);
+    for _ in 0..32 {
+        let m1 = lo + (hi - lo) / 3.0;
+        let m2 = hi - (hi - lo) / 3.0;
+        if distance(m1) <= distance(m2) {
+            hi = m2;
+        } else {
+            lo = m1;
+        }
+    }
+
+    let p = s0 + (s1 - s0) * ((lo + hi) * 0.5);
+    (p, other.closest(p))
+}
+
+// Separating axis test between boxes and triangles. Returns the largest gap along the
+// candidate axes, and that axis pointing from `b` to `a`.
+fn separating_axis(a: &Convex, b: &Convex) -> (f32, math::Vector3<f32>) {
+    let units = [
+        math::Vector3::new(1.0, 0.0, 0.0),
+        math::Vector3::new(0.0, 1.0, 0.0),
+        math::Vector3::new(0.0, 0.0, 1.0),
+    ];
+
+    let mut axes = units.to_vec();
+    for v in &[a, b] {
+        if let Convex::Triangle(p0, p1, p2) = **v {
+            axes.push((p1 - p0).cross(p2 - p0));
+            for edge in &[p1 - p0, p2 - p1, p0 - p2] {
+                for unit in &units {
+                    axes.push(unit.cross(*edge));
+                }
+            }
+        }
+    }
+
+    let mut best = (::std::f32::MIN, math::Vector3::new(0.0, 0.0, 0.0));
+    for axis in axes {
+        if axis.magnitude2() <= 1e-12 {
+            continue;
+        }
+
+        let axis = axis.normalize();
+        let (a0, a1) = a.project(axis);
+        let (b0, b1) = b.project(axis);
+
+        let (gap, normal) = if a0 - b1 > b0 - a1 {
+            (a0 - b1, axis)
+        } else {
+            (b0 - a1, -axis)
+        };
+
+        if gap > best.0 {
+            best = (gap, normal);
+        }
+    }
+
+    best
+}
+
+// The closest point on triangle from Real-Time Collision Detection, 5.1.5.
+fn closest_on_triangle(
+    p: math::Vector3<f32>,
+    a: math::Vector3<f32>,
+    b: math::Vector3<f32>,
+    c: math::Vector3<f32>,
+) -> math::Vector3<f32> {
+    let ab = b - a;
+    let ac = c - a;
+
+    let ap = p - a;
+    let d1 = ab.dot(ap);
+    let d2 = ac.dot(ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+
+    let bp = p - b;
+    let d3 = ab.dot(bp);
+    let d4 = ac.dot(bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = p - c;
+    let d5 = ab.dot(cp);
+    let d6 = ac.dot(cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denom = 1.0 / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+fn intersects(a: &math::Aabb3<f32>, b: &math::Aabb3<f32>) -> bool {
+    a.min.x <= b.max.x
+        && a.max.x >= b.min.x
+        && a.min.y <= b.max.y
+        && a.max.y >= b.min.y
+        && a.min.z <= b.max.z
+        && a.max.z >= b.min.z
+}
+
+fn min(a: math::Vector3<f32>, b: math::Vector3<f32>) -> math::Vector3<f32> {
+    math::Vector3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z))
+}
+
+fn max(a: math::Vector3<f32>, b: math::Vector3<f32>) -> math::Vector3<f32> {
+    math::Vector3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z))
+}
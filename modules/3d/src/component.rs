@@ -1,6 +1,19 @@
+use std::hash::Hash;
+use std::mem;
+
 use crayon::utils::hash::FastHashMap;
 use Entity;
 
+/// Gets the approximate heap memory of a vector in bytes.
+pub fn vec_bytes<T>(v: &Vec<T>) -> usize {
+    v.capacity() * mem::size_of::<T>()
+}
+
+/// Gets the approximate heap memory of a hash map in bytes.
+pub fn map_bytes<K: Hash + Eq, V>(v: &FastHashMap<K, V>) -> usize {
+    v.capacity() * (mem::size_of::<K>() + mem::size_of::<V>())
+}
+
 pub struct Component<T> {
     pub remap: FastHashMap<Entity, usize>,
     pub entities: Vec<Entity>,
@@ -30,6 +43,23 @@ impl<T> Component<T> {
         }
     }
 
+    pub fn reserve(&mut self, additional: usize) {
+        self.remap.reserve(additional);
+        self.entities.reserve(additional);
+        self.data.reserve(additional);
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Gets the approximate memory of this storage in bytes, excluding the heap memory
+    /// owned by components.
+    pub fn memory(&self) -> usize {
+        map_bytes(&self.remap) + vec_bytes(&self.entities) + vec_bytes(&self.data)
+    }
+
     #[inline]
     pub fn has(&self, ent: Entity) -> bool {
         self.remap.contains_key(&ent)
@@ -46,6 +76,16 @@ impl<T> Component<T> {
         }
     }
 
+    /// Moves the components of `other` into this, with their entities remapped. The
+    /// components of entities that are not in `remap` are dropped.
+    pub fn merge(&mut self, other: Component<T>, remap: &FastHashMap<Entity, Entity>) {
+        for (ent, v) in other.entities.into_iter().zip(other.data) {
+            if let Some(&ent) = remap.get(&ent) {
+                self.add(ent, v);
+            }
+        }
+    }
+
     #[inline]
     pub fn get(&self, ent: Entity) -> Option<&T> {
         let data = &self.data;
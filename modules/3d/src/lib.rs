@@ -6,23 +6,35 @@ extern crate failure;
 extern crate serde;
 
 pub mod assets;
+pub mod collision;
+pub mod minimap;
+pub mod path;
+pub mod photo;
 pub mod renderers;
+pub mod resources;
 pub mod scene;
+pub mod sky;
+pub mod streaming;
 pub mod tags;
+pub mod triggers;
+pub mod weather;
 
 mod component;
 use self::component::Component;
 
 mod world;
-pub use self::world::{world_impl, Entity, World};
+pub use self::world::{world_impl, Entity, EntityRef, World, WorldStats};
 
 mod world_resources;
 pub use self::world_resources::{WorldResources, WorldResourcesShared};
 
 pub mod prelude {
     pub use assets::Prefab;
-    pub use renderers::{Camera, Lit, MeshRenderer, SimpleMaterial, SimpleRenderer};
+    pub use renderers::{
+        Camera, CameraShake, GateFit, Lit, MeshRenderer, PhysicalParams, RenderQueue,
+        SimpleMaterial, SimpleOutline, SimplePropertyBlock, SimpleRenderer, SortPolicy,
+    };
     pub use scene::{SceneGraph, Transform};
-    pub use world::{Entity, World};
+    pub use world::{Entity, EntityRef, World};
     pub use world_resources::{WorldResources, WorldResourcesShared};
 }
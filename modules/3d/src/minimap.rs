@@ -0,0 +1,253 @@
+//! Top-down views of the world for HUDs, e.g. minimaps of levels.
+//!
+//! A `Minimap` renders the entities with watched tags into a render texture through an
+//! orthographic camera looking down the negative y-axis. Objects that would be too small
+//! to notice are drawn with the icons of their tags instead:
+//!
+//! ```rust,ignore
+//! let mut minimap = Minimap::new(ctx.video.clone(), 256)?;
+//! minimap.watch("building");
+//! minimap.set_icon("player", MinimapIcon::new(arrow, 12.0));
+//! ...
+//! minimap.center = math::Vector2::new(player.x, player.z);
+//! minimap.draw(&mut world.renderer, &world.scene, &world.tags, &world.renderables);
+//! ```
+
+use std::sync::Arc;
+
+use crayon::errors::*;
+use crayon::math;
+use crayon::math::Rotation3;
+use crayon::utils::hash::{FastHashMap, FastHashSet};
+use crayon::video::prelude::*;
+
+use renderers::{Camera, MeshRenderer, Renderable, Renderer};
+use scene::{SceneGraph, Transform};
+use tags::Tags;
+
+/// The icon of small objects, which is a mesh lying on the xz-plane with unit size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinimapIcon {
+    pub mesh: MeshHandle,
+    /// The size of icon in pixels.
+    pub size: f32,
+}
+
+impl MinimapIcon {
+    pub fn new(mesh: MeshHandle, size: f32) -> Self {
+        MinimapIcon {
+            mesh: mesh,
+            size: size,
+        }
+    }
+}
+
+/// A top-down orthographic view of tagged entities, which is rendered into a square
+/// render texture.
+pub struct Minimap {
+    /// The center of area in world space, on the xz-plane.
+    pub center: math::Vector2<f32>,
+    /// The half size of the square area in world units.
+    pub extent: f32,
+    /// The height of camera, everything above it is clipped.
+    pub height: f32,
+    /// The distance below the camera where the objects are clipped.
+    pub depth: f32,
+    /// The objects whose footprints are smaller than this in pixels are drawn with the
+    /// icons of their tags, if there are any.
+    pub icon_threshold: f32,
+
+    video: Arc<VideoSystemShared>,
+    dimensions: u32,
+    color: RenderTextureHandle,
+    depth_stencil: RenderTextureHandle,
+    surface: SurfaceHandle,
+
+    tags: Vec<String>,
+    icons: FastHashMap<String, MinimapIcon>,
+    meshes: Vec<MeshRenderer>,
+}
+
+impl Minimap {
+    /// Creates a new `Minimap` with a render texture of `dimensions` x `dimensions`.
+    pub fn new(video: Arc<VideoSystemShared>, dimensions: u32) -> Result<Self> {
+        let mut params = RenderTextureParams::default();
+        params.format = RenderTextureFormat::RGBA8;
+        params.dimensions = math::Vector2::new(dimensions, dimensions);
+        let color = video.create_render_texture(params)?;
+
+        params.format = RenderTextureFormat::Depth24;
+        params.sampler = false;
+        let depth_stencil = video.create_render_texture(params)?;
+
+        let mut params = SurfaceParams::default();
+        params.set_attachments(&[color], depth_stencil)?;
+        params.set_clear(math::Color::transparent(), 1.0, None);
+        let surface = video.create_surface(params)?;
+
+        Ok(Minimap {
+            center: math::Vector2::new(0.0, 0.0),
+            extent: 50.0,
+            height: 100.0,
+            depth: 200.0,
+            icon_threshold: 4.0,
+            video: video,
+            dimensions: dimensions,
+            color: color,
+            depth_stencil: depth_stencil,
+            surface: surface,
+            tags: Vec::new(),
+            icons: FastHashMap::default(),
+            meshes: Vec::new(),
+        })
+    }
+
+    /// Gets the render texture which the minimap is drawn into.
+    #[inline]
+    pub fn texture(&self) -> RenderTextureHandle {
+        self.color
+    }
+
+    /// Gets the width and height of render texture in pixels.
+    #[inline]
+    pub fn dimensions(&self) -> u32 {
+        self.dimensions
+    }
+
+    /// Draws the entities tagged with `tag` on the minimap.
+    pub fn watch<T: Into<String>>(&mut self, tag: T) {
+        let tag = tag.into();
+        if !self.tags.contains(&tag) {
+            self.tags.push(tag);
+        }
+    }
+
+    /// Stops drawing the entities tagged with `tag`.
+    pub fn unwatch<T: AsRef<str>>(&mut self, tag: T) {
+        self.tags.retain(|v| v != tag.as_ref());
+    }
+
+    /// Sets the icon of the small objects tagged with `tag`, and watches the tag.
+    pub fn set_icon<T: Into<String>>(&mut self, tag: T, icon: MinimapIcon) {
+        let tag = tag.into();
+        self.watch(tag.clone());
+        self.icons.insert(tag, icon);
+    }
+
+    /// Removes the icon of `tag`, so its small objects are drawn as they are.
+    pub fn remove_icon<T: AsRef<str>>(&mut self, tag: T) {
+        self.icons.remove(tag.as_ref());
+    }
+
+    /// Gets the number of world units per pixel.
+    #[inline]
+    pub fn units_per_pixel(&self) -> f32 {
+        self.extent * 2.0 / self.dimensions.max(1) as f32
+    }
+
+    /// Converts a position in world space into the uv of minimap, which is in [0, 1]
+    /// if it's inside the area. The v grows along the positive z-axis.
+    pub fn world_to_uv<T: Into<math::Vector3<f32>>>(&self, position: T) -> math::Vector2<f32> {
+        let position = position.into();
+        let size = self.extent * 2.0;
+        math::Vector2::new(
+            (position.x - self.center.x) / size + 0.5,
+            (position.z - self.center.y) / size + 0.5,
+        )
+    }
+
+    /// Converts the uv of minimap into a position on the xz-plane of world space.
+    pub fn uv_to_world(&self, uv: math::Vector2<f32>) -> math::Vector2<f32> {
+        let size = self.extent * 2.0;
+        math::Vector2::new(
+            (uv.x - 0.5) * size + self.center.x,
+            (uv.y - 0.5) * size + self.center.y,
+        )
+    }
+
+    /// Returns true if the position in world space is inside the area of minimap.
+    pub fn contains<T: Into<math::Vector3<f32>>>(&self, position: T) -> bool {
+        let uv = self.world_to_uv(position);
+        uv.x >= 0.0 && uv.x <= 1.0 && uv.y >= 0.0 && uv.y <= 1.0
+    }
+
+    /// Gets the camera of minimap, which looks down from `height` above the center.
+    pub fn camera(&self) -> Camera {
+        let size = self.extent * 2.0;
+        let mut camera = Camera::ortho(size, size, 0.01, self.depth);
+        camera.set_surface(self.surface);
+
+        // Looks down the negative y-axis, with the positive z-axis up.
+        camera.transform.position = math::Vector3::new(self.center.x, self.height, self.center.y);
+        camera.transform.rotation = math::Quaternion::from_angle_x(math::Deg(90.0));
+        camera
+    }
+
+    /// Draws the watched entities that have mesh renderers with `renderer`. The lits
+    /// are the visible ones of the last `Renderable::draw`, so it should be called after
+    /// the world has been drawn.
+    pub fn draw<R: Renderer>(
+        &mut self,
+        renderer: &mut R,
+        scene: &SceneGraph,
+        tags: &Tags,
+        renderables: &Renderable,
+    ) {
+        let units = self.units_per_pixel();
+        let mut visited = FastHashSet::default();
+
+        self.meshes.clear();
+        for tag in &self.tags {
+            let icon = self.icons.get(tag);
+
+            for &ent in tags.tagged(tag) {
+                if !visited.insert(ent) || !scene.is_enabled_in_hierarchy(ent) {
+                    continue;
+                }
+
+                let mut mesh = match renderables.mesh(ent) {
+                    Some(mesh) if mesh.visible => *mesh,
+                    _ => continue,
+                };
+
+                let transform = match scene.transform(ent) {
+                    Some(transform) => transform,
+                    None => continue,
+                };
+
+                mesh.ent = ent;
+                mesh.transform = transform;
+
+                if let Some(icon) = icon {
+                    let footprint = self
+                        .video
+                        .mesh_aabb(mesh.mesh)
+                        .map(|v| v.dim() * transform.scale)
+                        .map(|v| v.x.max(v.z))
+                        .unwrap_or(0.0);
+
+                    if footprint < self.icon_threshold * units {
+                        mesh.mesh = icon.mesh;
+                        mesh.transform = Transform {
+                            scale: icon.size * units,
+                            position: transform.position,
+                            ..Default::default()
+                        };
+                    }
+                }
+
+                self.meshes.push(mesh);
+            }
+        }
+
+        renderer.submit(&self.camera(), renderables.visible_lits(), &self.meshes);
+    }
+}
+
+impl Drop for Minimap {
+    fn drop(&mut self) {
+        self.video.delete_surface(self.surface);
+        self.video.delete_render_texture(self.color);
+        self.video.delete_render_texture(self.depth_stencil);
+    }
+}
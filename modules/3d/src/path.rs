@@ -0,0 +1,215 @@
+//! Moves entities along splines, e.g. camera rails, moving platforms and patrol routes.
+
+use crayon::math;
+use crayon::math::{ArcLengths, InnerSpace, Spline};
+
+use scene::SceneGraph;
+use Entity;
+
+// The number of arc-length samples per segment of spline.
+const SAMPLES: usize = 16;
+
+/// What happens when a `PathFollower` reaches the end of its path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathWrap {
+    /// Stops at the end.
+    Once,
+    /// Jumps back to the start, which is seamless for closed splines.
+    Loop,
+    /// Turns around and travels back and forth.
+    PingPong,
+}
+
+/// How a `PathFollower` rotates the entity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathOrientation {
+    /// Keeps the rotation of entity.
+    Keep,
+    /// Faces the direction of travel.
+    Tangent,
+    /// Faces a fixed point in world space.
+    Target(math::Vector3<f32>),
+}
+
+/// A `PathFollower` travels along a spline at constant speed. The control points of
+/// spline are its waypoints, and the ones passed during a frame are reported by
+/// `reached`, e.g. to trigger sounds or wait at patrol points.
+///
+/// It should be advanced every frame with the entity it moves:
+///
+/// ```rust,ignore
+/// follower.update(&mut world.scene, platform, dt);
+/// for &waypoint in follower.reached() {
+///     ...
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PathFollower {
+    /// The speed in units per second.
+    pub speed: f32,
+    /// What happens at the end of path.
+    pub wrap: PathWrap,
+    /// How the entity is rotated.
+    pub orientation: PathOrientation,
+    /// The up vector used to orient the entity.
+    pub up: math::Vector3<f32>,
+
+    spline: Spline,
+    table: ArcLengths,
+    // The distances of waypoints from the start.
+    waypoints: Vec<(f32, usize)>,
+    distance: f32,
+    forward: bool,
+    finished: bool,
+    reached: Vec<usize>,
+}
+
+impl PathFollower {
+    /// Creates a new `PathFollower` at the start of `spline`.
+    pub fn new(spline: Spline, speed: f32) -> Self {
+        let table = ArcLengths::new(&spline, SAMPLES);
+
+        let n = spline.segments();
+        let mut waypoints: Vec<_> = (0..(n + 1))
+            .map(|i| (table.distance(i as f32), i % spline.points.len().max(1)))
+            .collect();
+
+        if spline.points.is_empty() {
+            waypoints.clear();
+        }
+
+        PathFollower {
+            speed: speed,
+            wrap: PathWrap::Once,
+            orientation: PathOrientation::Keep,
+            up: math::Vector3::new(0.0, 1.0, 0.0),
+            spline: spline,
+            table: table,
+            waypoints: waypoints,
+            distance: 0.0,
+            forward: true,
+            finished: false,
+            reached: Vec::new(),
+        }
+    }
+
+    /// Gets the spline.
+    #[inline]
+    pub fn spline(&self) -> &Spline {
+        &self.spline
+    }
+
+    /// Gets the total length of path.
+    #[inline]
+    pub fn length(&self) -> f32 {
+        self.table.length()
+    }
+
+    /// Gets the traveled distance from the start of path.
+    #[inline]
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    /// Moves to `distance` from the start of path without reporting waypoints.
+    pub fn seek(&mut self, distance: f32) {
+        self.distance = distance.max(0.0).min(self.length());
+        self.finished = false;
+        self.reached.clear();
+    }
+
+    /// Returns true if this follower has stopped at the end of path.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Gets the waypoints passed during the last advance, in the order of passing.
+    #[inline]
+    pub fn reached(&self) -> &[usize] {
+        &self.reached
+    }
+
+    /// Gets the current position.
+    pub fn position(&self) -> math::Vector3<f32> {
+        self.spline.position(self.table.param(self.distance))
+    }
+
+    /// Gets the normalized direction of travel, or zero if it's not moving anywhere.
+    pub fn direction(&self) -> math::Vector3<f32> {
+        let tangent = self.spline.tangent(self.table.param(self.distance));
+        if tangent.magnitude2() <= ::std::f32::EPSILON {
+            return math::Vector3::new(0.0, 0.0, 0.0);
+        }
+
+        let tangent = tangent.normalize();
+        if self.forward {
+            tangent
+        } else {
+            -tangent
+        }
+    }
+
+    /// Travels `dt` seconds along the path.
+    pub fn advance(&mut self, dt: f32) {
+        self.reached.clear();
+
+        let length = self.length();
+        let mut remaining = self.speed.max(0.0) * dt.max(0.0);
+
+        while remaining > 0.0 && !self.finished && length > 0.0 {
+            let from = self.distance;
+            let to = if self.forward {
+                (from + remaining).min(length)
+            } else {
+                (from - remaining).max(0.0)
+            };
+
+            remaining -= (to - from).abs();
+            self.distance = to;
+
+            if self.forward {
+                let passed = self.waypoints.iter().filter(|v| v.0 > from && v.0 <= to);
+                self.reached.extend(passed.map(|v| v.1));
+            } else {
+                let passed = self
+                    .waypoints
+                    .iter()
+                    .rev()
+                    .filter(|v| v.0 < from && v.0 >= to);
+                self.reached.extend(passed.map(|v| v.1));
+            }
+
+            let end = if self.forward { length } else { 0.0 };
+            if to == end {
+                match self.wrap {
+                    PathWrap::Once => self.finished = true,
+                    PathWrap::Loop => {
+                        self.distance = 0.0;
+                        if !self.spline.closed {
+                            self.reached.push(0);
+                        }
+                    }
+                    PathWrap::PingPong => self.forward = !self.forward,
+                }
+            }
+        }
+    }
+
+    /// Travels `dt` seconds along the path, and places `ent` at the new position.
+    pub fn update(&mut self, scene: &mut SceneGraph, ent: Entity, dt: f32) {
+        self.advance(dt);
+        scene.set_position(ent, self.position());
+
+        match self.orientation {
+            PathOrientation::Keep => {}
+            PathOrientation::Tangent => {
+                let direction = self.direction();
+                if direction.magnitude2() > 0.0 {
+                    scene.look_at(ent, self.position() + direction, self.up);
+                }
+            }
+            PathOrientation::Target(target) => scene.look_at(ent, target, self.up),
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Photo mode, which pauses the game and lets players fly a camera around freely.
+//!
+//! The simulation is paused through `TimeSystemShared::set_paused`, so the free camera
+//! should be advanced with the unscaled `frame_delta`:
+//!
+//! ```rust,ignore
+//! if input.is_key_press(Key::P) {
+//!     if photo.is_active() {
+//!         photo.exit(&ctx.time, &mut world.scene);
+//!     } else {
+//!         photo.enter(&ctx.time, &world.scene, camera);
+//!     }
+//! }
+//!
+//! photo.update(&mut world.scene, &ctx.input, dt);
+//! ```
+
+use crayon::application::time::TimeSystemShared;
+use crayon::input::prelude::{InputSystemShared, Key, MouseButton};
+use crayon::math;
+use crayon::math::{InnerSpace, Rotation3};
+
+use scene::{SceneGraph, Transform};
+use Entity;
+
+// Keeps the camera from flipping over when looking straight up or down.
+const MAX_PITCH: f32 = 89.0;
+
+/// The controls of a `FlyCamera` during one frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlyInput {
+    /// The direction of movement in the space of camera, e.g. (0, 0, 1) moves forward.
+    pub movement: math::Vector3<f32>,
+    /// The rotation in points, the positive x turns right and the positive y looks up.
+    pub look: math::Vector2<f32>,
+    /// Moves faster.
+    pub boost: bool,
+}
+
+impl Default for FlyInput {
+    fn default() -> Self {
+        FlyInput {
+            movement: math::Vector3::new(0.0, 0.0, 0.0),
+            look: math::Vector2::new(0.0, 0.0),
+            boost: false,
+        }
+    }
+}
+
+impl FlyInput {
+    /// Samples the default controls, which move with WASD, rise and fall with E and Q,
+    /// boost with shift and look around while the right mouse button is held.
+    pub fn sample(input: &InputSystemShared) -> Self {
+        let axis = |positive: Key, negative: Key| {
+            let mut v = 0.0;
+            if input.is_key_down(positive) {
+                v += 1.0;
+            }
+
+            if input.is_key_down(negative) {
+                v -= 1.0;
+            }
+
+            v
+        };
+
+        let mut controls = FlyInput::default();
+        controls.movement = math::Vector3::new(
+            axis(Key::D, Key::A),
+            axis(Key::E, Key::Q),
+            axis(Key::W, Key::S),
+        );
+
+        if input.is_mouse_down(MouseButton::Right) {
+            controls.look = input.mouse_movement_in_points();
+        }
+
+        controls.boost = input.is_key_down(Key::LShift) || input.is_key_down(Key::RShift);
+        controls
+    }
+}
+
+/// A camera that flies freely through the scene, without colliding with anything.
+#[derive(Debug, Clone, Copy)]
+pub struct FlyCamera {
+    /// The speed in units per second.
+    pub speed: f32,
+    /// The multiplier of speed while boosting.
+    pub boost: f32,
+    /// The rotation in degrees per point of look input.
+    pub sensitivity: f32,
+
+    yaw: f32,
+    pitch: f32,
+}
+
+impl Default for FlyCamera {
+    fn default() -> Self {
+        FlyCamera {
+            speed: 5.0,
+            boost: 4.0,
+            sensitivity: 0.2,
+            yaw: 0.0,
+            pitch: 0.0,
+        }
+    }
+}
+
+impl FlyCamera {
+    pub fn new() -> Self {
+        FlyCamera::default()
+    }
+
+    /// Takes over the orientation of `transform`, dropping its roll.
+    pub fn reset(&mut self, transform: &Transform) {
+        let forward = transform.forward();
+        self.yaw = forward.x.atan2(forward.z).to_degrees();
+        self.pitch = forward
+            .y
+            .max(-1.0)
+            .min(1.0)
+            .asin()
+            .to_degrees()
+            .max(-MAX_PITCH)
+            .min(MAX_PITCH);
+    }
+
+    /// Gets the yaw and pitch in degrees.
+    #[inline]
+    pub fn angles(&self) -> (f32, f32) {
+        (self.yaw, self.pitch)
+    }
+
+    /// Applies the controls of `dt` seconds to `transform`.
+    pub fn advance(&mut self, transform: &mut Transform, controls: FlyInput, dt: f32) {
+        self.yaw += controls.look.x * self.sensitivity;
+        self.pitch = (self.pitch + controls.look.y * self.sensitivity)
+            .max(-MAX_PITCH)
+            .min(MAX_PITCH);
+
+        transform.rotation = math::Quaternion::from_angle_y(math::Deg(self.yaw))
+            * math::Quaternion::from_angle_x(math::Deg(-self.pitch));
+
+        if controls.movement.magnitude2() > 0.0 {
+            let speed = if controls.boost {
+                self.speed * self.boost
+            } else {
+                self.speed
+            };
+
+            let direction = transform.rotation * controls.movement.normalize();
+            transform.position += direction * speed * dt;
+        }
+    }
+}
+
+// The states that are restored when leaving photo mode.
+struct Saved {
+    camera: Entity,
+    transform: Transform,
+    paused: bool,
+}
+
+/// Pauses the game and hands a camera over to a `FlyCamera`. The pose of camera and
+/// the pausing state are restored on exit.
+#[derive(Default)]
+pub struct PhotoMode {
+    pub camera: FlyCamera,
+    saved: Option<Saved>,
+}
+
+impl PhotoMode {
+    pub fn new() -> Self {
+        PhotoMode::default()
+    }
+
+    /// Returns true if the photo mode is active.
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.saved.is_some()
+    }
+
+    /// Pauses the game and starts flying `camera` from its current pose. Returns false
+    /// if it's already active or the camera is not in the scene.
+    pub fn enter(&mut self, time: &TimeSystemShared, scene: &SceneGraph, camera: Entity) -> bool {
+        if self.saved.is_some() {
+            return false;
+        }
+
+        let transform = match scene.local_transform(camera) {
+            Some(transform) => transform,
+            None => return false,
+        };
+
+        self.camera.reset(&transform);
+        self.saved = Some(Saved {
+            camera: camera,
+            transform: transform,
+            paused: time.is_paused(),
+        });
+
+        time.set_paused(true);
+        true
+    }
+
+    /// Flies the camera with the default controls for `dt` seconds, which should be the
+    /// unscaled frame delta. It does nothing if the photo mode is inactive.
+    pub fn update(&mut self, scene: &mut SceneGraph, input: &InputSystemShared, dt: f32) {
+        self.update_with(scene, FlyInput::sample(input), dt);
+    }
+
+    /// Flies the camera with custom controls for `dt` seconds.
+    pub fn update_with(&mut self, scene: &mut SceneGraph, controls: FlyInput, dt: f32) {
+        let camera = match self.saved {
+            Some(ref v) => v.camera,
+            None => return,
+        };
+
+        if let Some(mut transform) = scene.local_transform(camera) {
+            self.camera.advance(&mut transform, controls, dt);
+            scene.set_local_transform(camera, transform);
+        }
+    }
+
+    /// Restores the camera and the pausing state from before entering.
+    pub fn exit(&mut self, time: &TimeSystemShared, scene: &mut SceneGraph) {
+        if let Some(saved) = self.saved.take() {
+            scene.set_local_transform(saved.camera, saved.transform);
+            time.set_paused(saved.paused);
+        }
+    }
+}
@@ -5,11 +5,92 @@ use crayon::video::assets::surface::SurfaceHandle;
 
 use scene::Transform;
 
+/// Describes how the sensor gate is fitted into the viewport when their aspect ratios
+/// differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GateFit {
+    /// Keeps the vertical extent of the sensor.
+    Vertical,
+    /// Keeps the horizontal extent of the sensor.
+    Horizontal,
+    /// Fits the sensor to the viewport, cropping the sensor on the other axis.
+    Fill,
+    /// Fits the viewport to the sensor, showing more than the sensor on the other axis.
+    Overscan,
+}
+
+/// The physical properties of a real world camera. It drives the field of view with
+/// focal length and sensor size, and the exposure with aperture, shutter speed and ISO,
+/// the same way a DCC package or a reference footage does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicalParams {
+    /// The f-number of the lens, e.g. 16.0 for f/16.
+    pub aperture: f32,
+    /// The shutter speed in seconds.
+    pub shutter_speed: f32,
+    /// The sensor sensitivity.
+    pub iso: f32,
+    /// The focal length in millimeters.
+    pub focal_length: f32,
+    /// The width and height of the sensor in millimeters.
+    pub sensor_size: math::Vector2<f32>,
+    /// How the sensor is fitted into the viewport.
+    pub gate_fit: GateFit,
+}
+
+impl Default for PhysicalParams {
+    /// A 50mm lens on a full frame sensor, exposed with the sunny 16 rule.
+    fn default() -> Self {
+        PhysicalParams {
+            aperture: 16.0,
+            shutter_speed: 1.0 / 100.0,
+            iso: 100.0,
+            focal_length: 50.0,
+            sensor_size: math::Vector2::new(36.0, 24.0),
+            gate_fit: GateFit::Fill,
+        }
+    }
+}
+
+impl PhysicalParams {
+    /// Gets the exposure value at ISO 100.
+    pub fn ev100(&self) -> f32 {
+        ((self.aperture * self.aperture) / self.shutter_speed * 100.0 / self.iso).log2()
+    }
+
+    /// Gets the scale that maps scene luminance into the [0, 1] range of the sensor,
+    /// with the saturation based method of ISO 12232.
+    pub fn exposure(&self) -> f32 {
+        1.0 / (1.2 * 2.0f32.powf(self.ev100()))
+    }
+
+    /// Gets the vertical field of view for a viewport with `aspect` (width / height).
+    pub fn fovy(&self, aspect: f32) -> math::Rad<f32> {
+        let sensor_aspect = self.sensor_size.x / self.sensor_size.y;
+        let horizontal = match self.gate_fit {
+            GateFit::Vertical => false,
+            GateFit::Horizontal => true,
+            GateFit::Fill => aspect > sensor_aspect,
+            GateFit::Overscan => aspect <= sensor_aspect,
+        };
+
+        let h = if horizontal {
+            self.sensor_size.x / aspect
+        } else {
+            self.sensor_size.y
+        };
+
+        math::Rad(2.0 * (h * 0.5 / self.focal_length).atan())
+    }
+}
+
 /// A `Camera` is a device through which the player views the world.
 #[derive(Debug, Clone, Copy)]
 pub struct Camera {
     frustum: math::Frustum<f32>,
     surface: Option<SurfaceHandle>,
+    depth_prepass: bool,
+    physical: Option<PhysicalParams>,
 
     #[doc(hidden)]
     pub(crate) transform: Transform,
@@ -34,6 +115,8 @@ impl Camera {
         Camera {
             frustum: math::Frustum::new(projection),
             surface: None,
+            depth_prepass: false,
+            physical: None,
             transform: Transform::default(),
         }
     }
@@ -79,6 +162,58 @@ impl Camera {
         self.surface
     }
 
+    /// Enables a depth-only pre-pass of opaque objects before shading them, so every
+    /// pixel is shaded at most once. It pays off in scenes with heavy overdraw, but
+    /// doubles the vertex cost of opaque objects.
+    #[inline]
+    pub fn set_depth_prepass(&mut self, enabled: bool) {
+        self.depth_prepass = enabled;
+    }
+
+    /// Returns true if the depth pre-pass is enabled.
+    #[inline]
+    pub fn depth_prepass(&self) -> bool {
+        self.depth_prepass
+    }
+
+    /// Sets the physical properties of this camera. The perspective projection is rebuilt
+    /// from focal length and sensor size, fitted into a viewport with `aspect` (width /
+    /// height), while the clip planes are kept as they are.
+    ///
+    /// Passing `None` removes the physical properties and the exposure falls back to 1.0,
+    /// but the current projection is left untouched.
+    pub fn set_physical<T>(&mut self, params: T, aspect: f32)
+    where
+        T: Into<Option<PhysicalParams>>,
+    {
+        self.physical = params.into();
+
+        if let Some(params) = self.physical {
+            let projection = math::Projection::Perspective {
+                fovy: params.fovy(aspect),
+                aspect: aspect,
+                near: self.near_clip_plane(),
+                far: self.far_clip_plane(),
+            };
+
+            self.set_projection(projection);
+        }
+    }
+
+    /// Gets the physical properties of this camera.
+    #[inline]
+    pub fn physical(&self) -> Option<PhysicalParams> {
+        self.physical
+    }
+
+    /// Gets the exposure that scales the shaded colors of this camera. It's 1.0 unless
+    /// the physical properties are assigned, in which case the lits are expected to be
+    /// authored in physical units.
+    #[inline]
+    pub fn exposure(&self) -> f32 {
+        self.physical.map(|v| v.exposure()).unwrap_or(1.0)
+    }
+
     /// Sets the near/far clipping plane distances.
     #[inline]
     pub fn set_clip_plane(&mut self, near: f32, far: f32) {
@@ -0,0 +1,121 @@
+//! Trauma based camera shake.
+
+use crayon::math;
+
+use scene::Transform;
+
+/// A `CameraShake` generates a noisy offset for cameras. Shaking is driven by the
+/// trauma, which is added by impacts or explosions and decays linearly over time.
+/// The offset grows with the square of trauma, so small hits stay subtle.
+///
+/// The offset should be applied on top of the camera's own pose every frame, e.g.
+/// `scene.set_local_transform(camera, pose * shake.advance(dt))`.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraShake {
+    /// The maximum positional offset along each axis at full trauma.
+    pub max_offset: math::Vector3<f32>,
+    /// The maximum pitch, yaw and roll in degrees at full trauma.
+    pub max_angles: math::Vector3<f32>,
+    /// The frequency of noise in hertz.
+    pub frequency: f32,
+    /// The amount of trauma removed per second.
+    pub decay: f32,
+
+    trauma: f32,
+    time: f32,
+    seed: u32,
+}
+
+impl Default for CameraShake {
+    fn default() -> Self {
+        CameraShake {
+            max_offset: math::Vector3::new(0.0, 0.0, 0.0),
+            max_angles: math::Vector3::new(5.0, 5.0, 5.0),
+            frequency: 15.0,
+            decay: 1.0,
+            trauma: 0.0,
+            time: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+impl CameraShake {
+    /// Creates a new `CameraShake` whose noise is generated with `seed`.
+    pub fn new(seed: u32) -> Self {
+        let mut shake = CameraShake::default();
+        shake.seed = seed;
+        shake
+    }
+
+    /// Adds trauma, the sum is clamped into [0, 1].
+    #[inline]
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).max(0.0).min(1.0);
+    }
+
+    /// Gets the current trauma.
+    #[inline]
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// Removes all the trauma, so the camera stops shaking at once.
+    #[inline]
+    pub fn stop(&mut self) {
+        self.trauma = 0.0;
+    }
+
+    /// Advances the shake by `dt` seconds, and returns the offset of this frame.
+    pub fn advance(&mut self, dt: f32) -> Transform {
+        self.time += dt;
+
+        let shake = self.trauma * self.trauma;
+        let t = self.time * self.frequency;
+        let n = |channel: u32| shake * noise(self.seed.wrapping_add(channel), t);
+
+        let mut offset = Transform::default();
+        if shake > 0.0 {
+            offset.position = math::Vector3::new(
+                self.max_offset.x * n(0),
+                self.max_offset.y * n(1),
+                self.max_offset.z * n(2),
+            );
+
+            let euler = math::Euler::new(
+                math::Deg(self.max_angles.x * n(3)),
+                math::Deg(self.max_angles.y * n(4)),
+                math::Deg(self.max_angles.z * n(5)),
+            );
+
+            offset.rotation = euler.into();
+        }
+
+        self.trauma = (self.trauma - self.decay * dt).max(0.0);
+        offset
+    }
+}
+
+/// Smooth 1D gradient noise in [-1, 1], which is zero at every integer.
+pub(crate) fn noise(seed: u32, x: f32) -> f32 {
+    let i = x.floor();
+    let f = x - i;
+
+    let g0 = gradient(seed, i as i32);
+    let g1 = gradient(seed, i as i32 + 1);
+
+    let t = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+    let v = g0 * f + (g1 * (f - 1.0) - g0 * f) * t;
+    (v * 2.0).max(-1.0).min(1.0)
+}
+
+fn gradient(seed: u32, i: i32) -> f32 {
+    let mut h = (i as u32).wrapping_mul(0x27d4_eb2d) ^ seed.wrapping_mul(0x1656_67b1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+
+    (h as f32 / ::std::u32::MAX as f32) * 2.0 - 1.0
+}
@@ -1,5 +1,6 @@
 use crayon::video::prelude::*;
 
+use super::RenderQueue;
 use scene::Transform;
 use Entity;
 
@@ -13,6 +14,13 @@ pub struct MeshRenderer {
     pub shadow_receiver: bool,
     /// Is this renderer visible.
     pub visible: bool,
+    /// Indicates whether this object never moves, so that renderers could bake or
+    /// batch it.
+    pub is_static: bool,
+    /// Indicates whether this object outputs per-object motion vectors.
+    pub motion_vectors: bool,
+    /// The queue bucket that this object will be drawn in.
+    pub queue: RenderQueue,
 
     #[doc(hidden)]
     pub(crate) transform: Transform,
@@ -36,6 +44,9 @@ impl Default for MeshRenderer {
             shadow_caster: false,
             shadow_receiver: false,
             visible: true,
+            is_static: false,
+            motion_vectors: false,
+            queue: RenderQueue::Opaque,
             transform: Transform::default(),
             ent: Entity::default(),
         }
@@ -1,5 +1,9 @@
 mod camera;
-pub use self::camera::Camera;
+pub use self::camera::{Camera, GateFit, PhysicalParams};
+
+mod camera_shake;
+pub use self::camera_shake::CameraShake;
+pub(crate) use self::camera_shake::noise;
 
 mod lit;
 pub use self::lit::{Lit, LitSource};
@@ -7,9 +11,15 @@ pub use self::lit::{Lit, LitSource};
 mod mesh_renderer;
 pub use self::mesh_renderer::MeshRenderer;
 
+mod render_queue;
+pub use self::render_queue::{DrawOrder, RenderQueue, SortPolicy};
+
 pub mod simple;
-pub use self::simple::{SimpleMaterial, SimpleRenderer};
+pub use self::simple::{SimpleMaterial, SimpleOutline, SimplePropertyBlock, SimpleRenderer};
+
+use crayon::utils::hash::FastHashMap;
 
+use component::vec_bytes;
 use scene::SceneGraph;
 use {Component, Entity};
 
@@ -21,6 +31,9 @@ pub struct Renderable {
     cameras: Component<Camera>,
     lits: Component<Lit>,
     meshes: Component<MeshRenderer>,
+
+    visible_lits: Vec<Lit>,
+    visible_meshes: Vec<MeshRenderer>,
 }
 
 impl Renderable {
@@ -29,6 +42,8 @@ impl Renderable {
             cameras: Component::new(),
             lits: Component::new(),
             meshes: Component::new(),
+            visible_lits: Vec::new(),
+            visible_meshes: Vec::new(),
         }
     }
 
@@ -91,6 +106,43 @@ impl Renderable {
     pub fn remove_mesh(&mut self, ent: Entity) {
         self.meshes.remove(ent);
     }
+
+    /// Gets the lits that were visible on the last draw.
+    #[inline]
+    pub fn visible_lits(&self) -> &[Lit] {
+        &self.visible_lits
+    }
+
+    /// Gets the names of components and the number of them.
+    pub fn components(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("Camera", self.cameras.len()),
+            ("Lit", self.lits.len()),
+            ("MeshRenderer", self.meshes.len()),
+        ]
+    }
+
+    /// Gets the approximate memory of components in bytes.
+    pub fn memory(&self) -> usize {
+        self.cameras.memory()
+            + self.lits.memory()
+            + self.meshes.memory()
+            + vec_bytes(&self.visible_lits)
+            + vec_bytes(&self.visible_meshes)
+    }
+
+    /// Reserves capacity for at least `additional` more mesh renderers.
+    pub fn reserve_meshes(&mut self, additional: usize) {
+        self.meshes.reserve(additional);
+        self.visible_meshes.reserve(additional);
+    }
+
+    /// Moves the components of `other` into this, with their entities remapped.
+    pub(crate) fn merge(&mut self, other: Renderable, remap: &FastHashMap<Entity, Entity>) {
+        self.cameras.merge(other.cameras, remap);
+        self.lits.merge(other.lits, remap);
+        self.meshes.merge(other.meshes, remap);
+    }
 }
 
 impl Renderable {
@@ -114,8 +166,25 @@ impl Renderable {
             }
         }
 
-        for v in &self.cameras.data {
-            pipeline.submit(&v, &self.lits.data, &self.meshes.data);
+        // Filters out objects that were disabled by themselves or their ancestors.
+        self.visible_lits.clear();
+        for (i, v) in self.lits.data.iter().enumerate() {
+            if v.enable && scene.is_enabled_in_hierarchy(self.lits.entities[i]) {
+                self.visible_lits.push(*v);
+            }
+        }
+
+        self.visible_meshes.clear();
+        for (i, v) in self.meshes.data.iter().enumerate() {
+            if v.visible && scene.is_enabled_in_hierarchy(self.meshes.entities[i]) {
+                self.visible_meshes.push(*v);
+            }
+        }
+
+        for (i, v) in self.cameras.data.iter().enumerate() {
+            if scene.is_enabled_in_hierarchy(self.cameras.entities[i]) {
+                pipeline.submit(&v, &self.visible_lits, &self.visible_meshes);
+            }
         }
     }
 }
@@ -0,0 +1,106 @@
+use crayon::video::assets::shader::ShaderHandle;
+
+/// The render queue buckets. Objects are always drawn bucket by bucket in the
+/// order of declaration, and are sorted inside each bucket with its own
+/// `SortPolicy`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RenderQueue {
+    /// Solid objects, which makes up the most of scene.
+    Opaque = 0,
+    /// Objects that discard fragments with alpha testing, like foliage or fences.
+    AlphaTest = 1,
+    /// Objects that should be blended with whatever behind them.
+    Transparent = 2,
+    /// Objects that are drawn on top of everything else, like lens flares.
+    Overlay = 3,
+}
+
+impl RenderQueue {
+    /// The number of queue buckets.
+    pub const LEN: usize = 4;
+
+    /// Gets the index of this bucket, which could be used to index arrays of
+    /// `RenderQueue::LEN` items.
+    #[inline]
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl Default for RenderQueue {
+    fn default() -> Self {
+        RenderQueue::Opaque
+    }
+}
+
+/// The order that objects in the same `RenderQueue` will be drawn.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SortPolicy {
+    /// Draws the nearest objects first, which reduces overdraw of opaque objects.
+    FrontToBack,
+    /// Draws the farthest objects first, as required by blending.
+    BackToFront,
+    /// Keeps the submission order of objects.
+    Unsorted,
+}
+
+impl SortPolicy {
+    /// Gets the default sort policy of queue bucket.
+    pub fn default_of(queue: RenderQueue) -> Self {
+        match queue {
+            RenderQueue::Opaque | RenderQueue::AlphaTest => SortPolicy::FrontToBack,
+            RenderQueue::Transparent => SortPolicy::BackToFront,
+            RenderQueue::Overlay => SortPolicy::Unsorted,
+        }
+    }
+
+    /// Converts the (squared) distance to camera into a sortable key.
+    #[inline]
+    pub fn zorder(self, distance: f32) -> u32 {
+        match self {
+            SortPolicy::FrontToBack => distance as u32,
+            SortPolicy::BackToFront => !(distance as u32),
+            SortPolicy::Unsorted => 0,
+        }
+    }
+}
+
+/// The sort key of drawcalls, which packs the pass into the highest 8 bits, the z-order
+/// into the following 32 bits and the index of shader into the lowest 24 bits. The
+/// depth pre-pass comes first, followed by the queue buckets.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DrawOrder(u64);
+
+impl DrawOrder {
+    /// Creates the sort key of drawcall in `queue`.
+    pub fn new(queue: RenderQueue, zorder: u32, shader: ShaderHandle) -> Self {
+        Self::encode(queue.index() as u64 + 1, zorder, shader)
+    }
+
+    /// Creates the sort key of drawcall in the depth pre-pass.
+    pub fn prepass(zorder: u32, shader: ShaderHandle) -> Self {
+        Self::encode(0, zorder, shader)
+    }
+
+    /// Gets the queue bucket of drawcall, or `None` if it's in the depth pre-pass.
+    pub fn queue(self) -> Option<RenderQueue> {
+        match self.0 >> 56 {
+            1 => Some(RenderQueue::Opaque),
+            2 => Some(RenderQueue::AlphaTest),
+            3 => Some(RenderQueue::Transparent),
+            4 => Some(RenderQueue::Overlay),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn zorder(self) -> u32 {
+        (self.0 >> 24) as u32
+    }
+
+    #[inline]
+    fn encode(pass: u64, zorder: u32, shader: ShaderHandle) -> Self {
+        let suffix = u64::from(shader.index()) & 0xFF_FFFF;
+        DrawOrder((pass << 56) | (u64::from(zorder) << 24) | suffix)
+    }
+}
@@ -9,6 +9,9 @@ pub struct SimpleMaterial {
     pub specular: math::Color<f32>,
     pub specular_texture: Option<TextureHandle>,
     pub shininess: f32,
+    /// The wetness of surface in [0, 1], which is added to the global wetness of
+    /// `SimpleRenderer`, e.g. for puddles.
+    pub wetness: f32,
 }
 
 impl Default for SimpleMaterial {
@@ -20,6 +23,28 @@ impl Default for SimpleMaterial {
             specular: math::Color::black(),
             specular_texture: None,
             shininess: 0.0,
+            wetness: 0.0,
         }
     }
 }
+
+impl SimpleMaterial {
+    /// Gets the look of this material when it's wet by `wetness` in [0, 1]. Wet
+    /// surfaces are darker and glossier.
+    pub fn wet(&self, wetness: f32) -> SimpleMaterial {
+        let w = wetness.max(0.0).min(1.0);
+        let mut material = *self;
+
+        let darken = 1.0 - 0.4 * w;
+        material.diffuse.r *= darken;
+        material.diffuse.g *= darken;
+        material.diffuse.b *= darken;
+
+        let glossy = |v: f32| v + (v.max(0.3) - v) * w;
+        material.specular.r = glossy(self.specular.r);
+        material.specular.g = glossy(self.specular.g);
+        material.specular.b = glossy(self.specular.b);
+        material.shininess += (self.shininess.max(64.0) - self.shininess) * w;
+        material
+    }
+}
@@ -1,6 +1,12 @@
 mod material;
 pub use self::material::SimpleMaterial;
 
+mod outline;
+pub use self::outline::SimpleOutline;
+
+mod property_block;
+pub use self::property_block::SimplePropertyBlock;
+
 use crayon::application::Context;
 use crayon::errors::*;
 use crayon::math;
@@ -9,7 +15,7 @@ use crayon::video::prelude::*;
 
 use std::sync::Arc;
 
-use super::{Camera, Lit, LitSource, MeshRenderer};
+use super::{Camera, DrawOrder, Lit, LitSource, MeshRenderer, RenderQueue, SortPolicy};
 use {Component, Entity, WorldResourcesShared};
 
 pub const MAX_DIR_LITS: usize = 1;
@@ -18,13 +24,20 @@ pub const MAX_POINT_LITS: usize = 4;
 /// A simple renderer that draws some color into mesh objects.
 pub struct SimpleRenderer {
     materials: Component<SimpleMaterial>,
+    outlines: Component<SimpleOutline>,
+    property_blocks: Component<SimplePropertyBlock>,
 
     surface: SurfaceHandle,
     shader: ShaderHandle,
+    prepassed_shader: ShaderHandle,
+    depth_shader: ShaderHandle,
+    outline_shader: ShaderHandle,
     video: Arc<VideoSystemShared>,
     drawcalls: OrderDrawBatch<DrawOrder>,
+    sort_policies: [SortPolicy; RenderQueue::LEN],
 
     global_ambient: math::Color<f32>,
+    global_wetness: f32,
     dir_lits: Vec<(String, String)>,
     point_lits: Vec<(String, String, String)>,
 
@@ -46,11 +59,13 @@ impl SimpleRenderer {
             .with("u_MVPMatrix", UniformVariableType::Matrix4f)
             .with("u_ViewNormalMatrix", UniformVariableType::Matrix4f)
             .with("u_GlobalAmbient", UniformVariableType::Vector3f)
+            .with("u_Emission", UniformVariableType::Vector3f)
             .with("u_Diffuse", UniformVariableType::Vector3f)
             .with("u_DiffuseTexture", UniformVariableType::Texture)
             .with("u_Specular", UniformVariableType::Vector3f)
             .with("u_SpecularTexture", UniformVariableType::Texture)
-            .with("u_Shininess", UniformVariableType::F32);
+            .with("u_Shininess", UniformVariableType::F32)
+            .with("u_Exposure", UniformVariableType::F32);
 
         let mut dir_lits = Vec::new();
         let mut point_lits = Vec::new();
@@ -117,20 +132,41 @@ impl SimpleRenderer {
             include_str!("shaders/simple.fs")
         );
 
-        let shader = ctx.video.create_shader(params, vs, fs)?;
+        let shader = ctx.video.create_shader(params.clone(), vs.clone(), fs.clone())?;
+
+        // Fragments hidden by the depth pre-pass fail the depth test here, so only the
+        // visible ones get shaded.
+        params.state.depth_write = false;
+        params.state.depth_test = Comparison::LessOrEqual;
+        let prepassed_shader = ctx.video.create_shader(params, vs, fs)?;
+
+        let depth_shader = Self::create_depth_shader(ctx)?;
+        let outline_shader = Self::create_outline_shader(ctx)?;
 
         let params = SurfaceParams::default();
         let surface = ctx.video.create_surface(params)?;
 
         Ok(SimpleRenderer {
             materials: Component::new(),
+            outlines: Component::new(),
+            property_blocks: Component::new(),
             video: ctx.video.clone(),
             surface: surface,
             shader: shader,
+            prepassed_shader: prepassed_shader,
+            depth_shader: depth_shader,
+            outline_shader: outline_shader,
             drawcalls: OrderDrawBatch::new(),
+            sort_policies: [
+                SortPolicy::default_of(RenderQueue::Opaque),
+                SortPolicy::default_of(RenderQueue::AlphaTest),
+                SortPolicy::default_of(RenderQueue::Transparent),
+                SortPolicy::default_of(RenderQueue::Overlay),
+            ],
             dir_lits: dir_lits,
             point_lits: point_lits,
             global_ambient: math::Color::gray(),
+            global_wetness: 0.0,
             res: res,
         })
     }
@@ -157,13 +193,216 @@ impl SimpleRenderer {
 
     #[inline]
     pub fn remove(&mut self, ent: Entity) {
-        self.materials.remove(ent)
+        self.materials.remove(ent);
+        self.outlines.remove(ent);
+        self.property_blocks.remove(ent);
+    }
+
+    /// Overrides some of the material properties of entity, replacing the previous
+    /// block if any.
+    #[inline]
+    pub fn set_property_block(
+        &mut self,
+        ent: Entity,
+        block: SimplePropertyBlock,
+    ) -> Option<SimplePropertyBlock> {
+        self.property_blocks.add(ent, block)
+    }
+
+    #[inline]
+    pub fn property_block(&self, ent: Entity) -> Option<&SimplePropertyBlock> {
+        self.property_blocks.get(ent)
+    }
+
+    #[inline]
+    pub fn property_block_mut(&mut self, ent: Entity) -> Option<&mut SimplePropertyBlock> {
+        self.property_blocks.get_mut(ent)
+    }
+
+    #[inline]
+    pub fn remove_property_block(&mut self, ent: Entity) {
+        self.property_blocks.remove(ent)
+    }
+
+    /// Gets the material that entity is drawn with and its emission. Entities without
+    /// material are drawn with the default one, which gets wet as well as the tint of
+    /// property block applied. The emission is black without property block.
+    pub fn shading(&self, ent: Entity) -> (SimpleMaterial, math::Color<f32>) {
+        let mat = self.material(ent).cloned().unwrap_or_default();
+        let mat = mat.wet(mat.wetness + self.global_wetness);
+        let block = self.property_block(ent).cloned().unwrap_or_default();
+        (block.apply(&mat), block.emission)
+    }
+
+    /// Highlights the entity with a outline, replacing the previous settings if any.
+    #[inline]
+    pub fn set_outline(&mut self, ent: Entity, outline: SimpleOutline) -> Option<SimpleOutline> {
+        self.outlines.add(ent, outline)
+    }
+
+    #[inline]
+    pub fn outline(&self, ent: Entity) -> Option<&SimpleOutline> {
+        self.outlines.get(ent)
+    }
+
+    #[inline]
+    pub fn outline_mut(&mut self, ent: Entity) -> Option<&mut SimpleOutline> {
+        self.outlines.get_mut(ent)
+    }
+
+    #[inline]
+    pub fn remove_outline(&mut self, ent: Entity) {
+        self.outlines.remove(ent)
     }
 
     #[inline]
     pub fn set_global_ambient<T: Into<math::Color<f32>>>(&mut self, color: T) {
         self.global_ambient = color.into();
     }
+
+    /// Sets the wetness in [0, 1] of all the surfaces, e.g. from `weather::Weather`.
+    #[inline]
+    pub fn set_global_wetness(&mut self, wetness: f32) {
+        self.global_wetness = wetness;
+    }
+
+    #[inline]
+    pub fn global_wetness(&self) -> f32 {
+        self.global_wetness
+    }
+
+    /// Sets the order that objects in the queue bucket will be drawn.
+    #[inline]
+    pub fn set_sort_policy(&mut self, queue: RenderQueue, policy: SortPolicy) {
+        self.sort_policies[queue.index()] = policy;
+    }
+
+    #[inline]
+    pub fn sort_policy(&self, queue: RenderQueue) -> SortPolicy {
+        self.sort_policies[queue.index()]
+    }
+
+    /// Gets the shader of the depth pre-pass, which only writes depth.
+    #[inline]
+    pub fn depth_shader(&self) -> ShaderHandle {
+        self.depth_shader
+    }
+
+    /// Gets the passes that `mesh` is drawn in by `camera` with their sort keys and
+    /// shaders. The main pass comes first, followed by the depth pre-pass if camera
+    /// enables it, and the outline if there is one.
+    pub fn draw_orders(
+        &self,
+        camera: &Camera,
+        mesh: &MeshRenderer,
+    ) -> Vec<(DrawOrder, ShaderHandle)> {
+        use crayon::math::MetricSpace;
+
+        let distance = mesh.transform.position.distance2(camera.transform.position);
+        let zorder = self.sort_policy(mesh.queue).zorder(distance);
+        let mut passes = Vec::with_capacity(3);
+
+        // Alpha-tested and blended objects can not write depth ahead.
+        if camera.depth_prepass() && mesh.queue == RenderQueue::Opaque {
+            let shader = self.prepassed_shader;
+            passes.push((DrawOrder::new(mesh.queue, zorder, shader), shader));
+
+            let shader = self.depth_shader;
+            passes.push((DrawOrder::prepass(zorder, shader), shader));
+        } else {
+            passes.push((DrawOrder::new(mesh.queue, zorder, self.shader), self.shader));
+        }
+
+        if self.outlines.has(mesh.ent) {
+            let shader = self.outline_shader;
+            passes.push((DrawOrder::new(mesh.queue, zorder, shader), shader));
+        }
+
+        passes
+    }
+
+    fn create_depth_shader(ctx: &Context) -> Result<ShaderHandle> {
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .finish();
+
+        let uniforms = UniformVariableLayout::build()
+            .with("u_MVPMatrix", UniformVariableType::Matrix4f)
+            .finish();
+
+        let mut params = ShaderParams::default();
+        params.state.depth_write = true;
+        params.state.depth_test = Comparison::Less;
+        params.state.color_write = (false, false, false, false);
+        params.attributes = attributes;
+        params.uniforms = uniforms;
+
+        let vs = format!(
+            "
+            #version 100
+            precision lowp float;
+            {0}
+            ",
+            include_str!("shaders/depth.vs")
+        );
+
+        let fs = format!(
+            "
+            #version 100
+            precision lowp float;
+            {0}
+            ",
+            include_str!("shaders/depth.fs")
+        );
+
+        let shader = ctx.video.create_shader(params, vs, fs)?;
+        Ok(shader)
+    }
+
+    fn create_outline_shader(ctx: &Context) -> Result<ShaderHandle> {
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .with(Attribute::Normal, 3)
+            .finish();
+
+        let uniforms = UniformVariableLayout::build()
+            .with("u_ModelViewMatrix", UniformVariableType::Matrix4f)
+            .with("u_ProjectionMatrix", UniformVariableType::Matrix4f)
+            .with("u_ViewNormalMatrix", UniformVariableType::Matrix4f)
+            .with("u_Width", UniformVariableType::F32)
+            .with("u_Color", UniformVariableType::Vector4f)
+            .finish();
+
+        // Only the back faces of the inflated mesh are drawn, the original mesh will
+        // covers the inner part of them.
+        let mut params = ShaderParams::default();
+        params.state.cull_face = CullFace::Front;
+        params.state.depth_write = true;
+        params.state.depth_test = Comparison::Less;
+        params.attributes = attributes;
+        params.uniforms = uniforms;
+
+        let vs = format!(
+            "
+            #version 100
+            precision lowp float;
+            {0}
+            ",
+            include_str!("shaders/outline.vs")
+        );
+
+        let fs = format!(
+            "
+            #version 100
+            precision lowp float;
+            {0}
+            ",
+            include_str!("shaders/outline.fs")
+        );
+
+        let shader = ctx.video.create_shader(params, vs, fs)?;
+        Ok(shader)
+    }
 }
 
 impl super::Renderer for SimpleRenderer {
@@ -180,12 +419,15 @@ impl super::Renderer for SimpleRenderer {
             let mvp = projection_matrix * mv;
             let vn = mv.invert().and_then(|v| Some(v.transpose())).unwrap_or(mv);
 
-            let mut dc = DrawCall::new(self.shader, mesh.mesh);
+            let passes = self.draw_orders(camera, mesh);
+            let (order, shader) = passes[0];
+
+            let mut dc = DrawCall::new(shader, mesh.mesh);
             dc.set_uniform_variable("u_ModelViewMatrix", mv);
             dc.set_uniform_variable("u_MVPMatrix", mvp);
             dc.set_uniform_variable("u_ViewNormalMatrix", vn);
 
-            let mat = self.material(mesh.ent).cloned().unwrap_or_default();
+            let (mat, emission) = self.shading(mesh.ent);
             let diffuse = mat.diffuse_texture.unwrap_or(self.res.textures.white);
             let specular = mat.specular_texture.unwrap_or(self.res.textures.white);
 
@@ -195,11 +437,13 @@ impl super::Renderer for SimpleRenderer {
             ambient[2] *= self.global_ambient.b;
 
             dc.set_uniform_variable("u_GlobalAmbient", ambient);
+            dc.set_uniform_variable("u_Emission", emission.rgb());
             dc.set_uniform_variable("u_Diffuse", mat.diffuse.rgb());
             dc.set_uniform_variable("u_DiffuseTexture", diffuse);
             dc.set_uniform_variable("u_Specular", mat.specular.rgb());
             dc.set_uniform_variable("u_SpecularTexture", specular);
             dc.set_uniform_variable("u_Shininess", mat.shininess);
+            dc.set_uniform_variable("u_Exposure", camera.exposure());
 
             lits.sort_by_key(|v| mesh.transform.position.distance2(v.transform.position) as u32);
 
@@ -242,27 +486,26 @@ impl super::Renderer for SimpleRenderer {
                 }
             }
 
-            let order = DrawOrder::new(
-                self.shader,
-                false,
-                mesh.transform.position.distance2(camera.transform.position) as u32,
-            );
-
             self.drawcalls.draw(order, dc);
+
+            for &(order, shader) in &passes[1..] {
+                let mut dc = DrawCall::new(shader, mesh.mesh);
+
+                if shader == self.depth_shader {
+                    dc.set_uniform_variable("u_MVPMatrix", mvp);
+                } else if let Some(outline) = self.outlines.get(mesh.ent) {
+                    dc.set_uniform_variable("u_ModelViewMatrix", mv);
+                    dc.set_uniform_variable("u_ProjectionMatrix", projection_matrix);
+                    dc.set_uniform_variable("u_ViewNormalMatrix", vn);
+                    dc.set_uniform_variable("u_Width", outline.width);
+                    dc.set_uniform_variable("u_Color", outline.color.rgba());
+                }
+
+                self.drawcalls.draw(order, dc);
+            }
         }
 
         let surface = camera.surface().unwrap_or(self.surface);
         self.drawcalls.submit(&self.video, surface).unwrap();
     }
 }
-
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
-struct DrawOrder(u64);
-
-impl DrawOrder {
-    fn new(shader: ShaderHandle, translucent: bool, zorder: u32) -> Self {
-        let prefix = if translucent { (!zorder) } else { zorder };
-        let suffix = shader.index();
-        DrawOrder((u64::from(prefix) << 32) | u64::from(suffix))
-    }
-}
@@ -0,0 +1,24 @@
+use crayon::math;
+
+/// Per-entity settings of the selection outline drawn by `SimpleRenderer`.
+///
+/// The outline is produced by drawing the back faces of the mesh once more,
+/// pushed outwards along their normals, so it works without any extra render
+/// targets. Meshes with hard edges and split normals will show small gaps at
+/// their corners.
+#[derive(Debug, Copy, Clone)]
+pub struct SimpleOutline {
+    /// The color of outline.
+    pub color: math::Color<f32>,
+    /// The thickness of outline, in view space units.
+    pub width: f32,
+}
+
+impl Default for SimpleOutline {
+    fn default() -> Self {
+        SimpleOutline {
+            color: math::Color::new(1.0, 0.5, 0.0, 1.0),
+            width: 0.02,
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crayon::math;
+
+use super::SimpleMaterial;
+
+/// A light-weight set of per-entity overrides that are applied on top of the
+/// `SimpleMaterial` when drawing, so instances could be tinted without copying
+/// their material around.
+///
+/// Property blocks only change uniform values, they never affect the shader or
+/// draw order, so thousands of tinted instances still sort and batch together.
+#[derive(Debug, Copy, Clone)]
+pub struct SimplePropertyBlock {
+    /// The color that modulates the diffuse and ambient color of material.
+    pub tint: math::Color<f32>,
+    /// The color that is emitted by the object regardless of lighting.
+    pub emission: math::Color<f32>,
+}
+
+impl Default for SimplePropertyBlock {
+    fn default() -> Self {
+        SimplePropertyBlock {
+            tint: math::Color::white(),
+            emission: math::Color::black(),
+        }
+    }
+}
+
+impl SimplePropertyBlock {
+    /// Modulates the ambient and diffuse color of `material` with the tint.
+    pub fn apply(&self, material: &SimpleMaterial) -> SimpleMaterial {
+        let mut material = *material;
+        material.ambient.r *= self.tint.r;
+        material.ambient.g *= self.tint.g;
+        material.ambient.b *= self.tint.b;
+        material.diffuse.r *= self.tint.r;
+        material.diffuse.g *= self.tint.g;
+        material.diffuse.b *= self.tint.b;
+        material
+    }
+}
@@ -0,0 +1,6 @@
+uniform vec4 u_Color;
+
+void main()
+{
+    gl_FragColor = u_Color;
+}
\ No newline at end of file
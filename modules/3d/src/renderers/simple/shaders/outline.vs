@@ -0,0 +1,15 @@
+attribute vec3 Position;
+attribute vec3 Normal;
+
+uniform mat4 u_ModelViewMatrix;
+uniform mat4 u_ProjectionMatrix;
+uniform mat4 u_ViewNormalMatrix;
+uniform float u_Width;
+
+void main() {
+    vec4 eyePos = u_ModelViewMatrix * vec4(Position, 1.0);
+    vec3 eyeNormal = normalize(vec3(u_ViewNormalMatrix * vec4(Normal, 0.0)));
+
+    eyePos.xyz += eyeNormal * u_Width * eyePos.w;
+    gl_Position = u_ProjectionMatrix * eyePos;
+}
\ No newline at end of file
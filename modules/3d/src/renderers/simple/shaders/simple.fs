@@ -10,6 +10,7 @@ uniform vec3 u_PointLitColor[MAX_POINT_LITS];
 uniform vec3 u_PointLitAttenuation[MAX_POINT_LITS];
 
 uniform vec3 u_GlobalAmbient;
+uniform vec3 u_Emission;
 
 uniform vec3 u_Diffuse;
 uniform sampler2D u_DiffuseTexture;
@@ -18,6 +19,7 @@ uniform vec3 u_Specular;
 uniform sampler2D u_SpecularTexture;
 
 uniform float u_Shininess;
+uniform float u_Exposure;
 
 vec3 Calculate(vec3 normal, vec3 viewDir, vec3 lightDir, vec3 reflectDir, vec3 d, vec3 s)
 {
@@ -35,7 +37,7 @@ void main()
     vec3 specular = texture2D(u_SpecularTexture, v_Texcoord).rgb;
 
     //
-    vec3 result = u_GlobalAmbient * diffuse;
+    vec3 result = u_GlobalAmbient * diffuse + u_Emission;
 
     // directional light
     for(int i = 0; i < MAX_DIR_LITS; i++)
@@ -64,5 +66,5 @@ void main()
         result += max(power * attenuation, vec3(0.0, 0.0, 0.0));
     }
 
-    gl_FragColor = vec4(result, 1.0);
+    gl_FragColor = vec4(result * u_Exposure, 1.0);
 }
\ No newline at end of file
@@ -0,0 +1,67 @@
+//! Singleton data shared by the whole world, like delta time or input snapshots.
+//!
+//! Don't confuse it with `WorldResources`, which manages the meshes, textures and
+//! prefabs used by the world.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// A type-keyed map that holds at most one value of every type.
+#[derive(Default)]
+pub struct Resources {
+    values: HashMap<TypeId, Box<Any + Send + Sync>>,
+}
+
+impl Resources {
+    pub fn new() -> Self {
+        Resources {
+            values: HashMap::new(),
+        }
+    }
+
+    /// Inserts a resource, and returns the one of the same type it replaced.
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) -> Option<T> {
+        self.values
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .map(|v| *v.downcast::<T>().unwrap())
+    }
+
+    /// Removes the resource with type `T` and returns it.
+    pub fn remove<T: Any + Send + Sync>(&mut self) -> Option<T> {
+        self.values
+            .remove(&TypeId::of::<T>())
+            .map(|v| *v.downcast::<T>().unwrap())
+    }
+
+    /// Returns true if there is a resource with type `T`.
+    #[inline]
+    pub fn has<T: Any + Send + Sync>(&self) -> bool {
+        self.values.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Gets a reference to the resource with type `T`.
+    #[inline]
+    pub fn fetch<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.values
+            .get(&TypeId::of::<T>())
+            .and_then(|v| v.downcast_ref::<T>())
+    }
+
+    /// Gets a mutable reference to the resource with type `T`.
+    #[inline]
+    pub fn fetch_mut<T: Any + Send + Sync>(&mut self) -> Option<&mut T> {
+        self.values
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|v| v.downcast_mut::<T>())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
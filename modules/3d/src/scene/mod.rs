@@ -12,6 +12,7 @@ use std::iter;
 use crayon::math::{self, One};
 use crayon::utils::hash::{FastHashMap, FastHashSet};
 
+use component::{map_bytes, vec_bytes};
 use Entity;
 
 /// A simple scene graph that used to tore and manipulate the postiion, rotation and scale
@@ -23,6 +24,7 @@ pub struct SceneGraph {
     nodes: Vec<Node>,
     local_transforms: Vec<Transform>,
     world_transforms: Vec<Transform>,
+    enables: Vec<bool>,
 
     pub(crate) roots: FastHashSet<Entity>,
 }
@@ -35,10 +37,45 @@ impl SceneGraph {
             nodes: Vec::new(),
             local_transforms: Vec::new(),
             world_transforms: Vec::new(),
+            enables: Vec::new(),
             roots: FastHashSet::default(),
         }
     }
 
+    /// Reserves capacity for at least `additional` more nodes.
+    pub fn reserve(&mut self, additional: usize) {
+        self.remap.reserve(additional);
+        self.entities.reserve(additional);
+        self.nodes.reserve(additional);
+        self.local_transforms.reserve(additional);
+        self.world_transforms.reserve(additional);
+        self.enables.reserve(additional);
+        self.roots.reserve(additional);
+    }
+
+    /// Gets the number of nodes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns true if there are no nodes.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Gets the approximate memory of this scene graph in bytes.
+    pub fn memory(&self) -> usize {
+        map_bytes(&self.remap)
+            + vec_bytes(&self.entities)
+            + vec_bytes(&self.nodes)
+            + vec_bytes(&self.local_transforms)
+            + vec_bytes(&self.world_transforms)
+            + vec_bytes(&self.enables)
+            + self.roots.capacity() * ::std::mem::size_of::<Entity>()
+    }
+
     /// Adds a node.
     pub fn add(&mut self, ent: Entity) {
         assert!(
@@ -51,6 +88,7 @@ impl SceneGraph {
         self.nodes.push(Node::default());
         self.local_transforms.push(Transform::default());
         self.world_transforms.push(Transform::default());
+        self.enables.push(true);
         self.roots.insert(ent);
     }
 
@@ -67,6 +105,7 @@ impl SceneGraph {
                 self.nodes.swap_remove(index);
                 self.local_transforms.swap_remove(index);
                 self.world_transforms.swap_remove(index);
+                self.enables.swap_remove(index);
 
                 if self.entities.len() != index {
                     *self.remap.get_mut(&self.entities[index]).unwrap() = index;
@@ -118,6 +157,30 @@ impl SceneGraph {
             .unwrap_or(false)
     }
 
+    /// Activates or deactivates the node. A deactivated node hides itself and all of
+    /// its descendants, without changing their own flags.
+    #[inline]
+    pub fn set_enabled(&mut self, ent: Entity, enabled: bool) {
+        if let Some(&index) = self.remap.get(&ent) {
+            self.enables[index] = enabled;
+        }
+    }
+
+    /// Returns true if the node itself is marked as enabled. Notes that it could still
+    /// be inactive if one of its ancestors has been disabled.
+    #[inline]
+    pub fn is_enabled(&self, ent: Entity) -> bool {
+        self.remap
+            .get(&ent)
+            .map(|&index| self.enables[index])
+            .unwrap_or(false)
+    }
+
+    /// Returns true if the node and all of its ancestors are enabled.
+    pub fn is_enabled_in_hierarchy(&self, ent: Entity) -> bool {
+        self.is_enabled(ent) && self.ancestors(ent).all(|v| self.is_enabled(v))
+    }
+
     /// Attachs a new child to parent transform, before existing children.
     pub fn set_parent<T>(&mut self, child: Entity, parent: T, keep_world_pose: bool) -> Result<()>
     where
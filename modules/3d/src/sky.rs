@@ -0,0 +1,382 @@
+//! Procedural clear sky and the day/night cycle.
+//!
+//! The sun is placed by the time of day, the day of year and the latitude, in a world
+//! space whose positive x-axis points east, y-axis points up and z-axis points north.
+//! The colors of sky are approximated with the analytic model of Preetham et al. (1999),
+//! and the sunlight is attenuated by the scattering of air and haze along its path.
+//!
+//! A `TimeOfDay` advances the clock and drives a directional light as the sun. The
+//! `SimpleRenderer` has no ambient probes, so the ambient color is returned for the
+//! global ambient instead:
+//!
+//! ```rust,ignore
+//! let mut time = TimeOfDay::new(8.0);
+//! time.latitude = 40.0;
+//! ...
+//! let sky = time.update(dt, &mut world.scene, &mut world.renderables, sun);
+//! world.renderer.set_global_ambient(sky.ambient);
+//! ```
+
+use std::f32::consts::PI;
+
+use crayon::math;
+use crayon::math::InnerSpace;
+
+use renderers::{Lit, LitSource, Renderable};
+use scene::SceneGraph;
+use Entity;
+
+// The tilt of earth's axis in degrees.
+const AXIAL_TILT: f32 = 23.44;
+const DAYS_PER_YEAR: u32 = 365;
+
+// The elevation of sun in degrees, where the sky fades into night.
+const NIGHT_ELEVATION: f32 = -12.0;
+
+/// Gets the unit direction towards the sun at `hour` of the `day` of year, seen from
+/// `latitude` in degrees.
+pub fn sun_direction(hour: f32, day: u32, latitude: f32) -> math::Vector3<f32> {
+    let declination = -AXIAL_TILT.to_radians()
+        * (2.0 * PI / DAYS_PER_YEAR as f32 * (day as f32 + 10.0)).cos();
+
+    let hour_angle = ((hour - 12.0) * 15.0).to_radians();
+    let (sin_d, cos_d) = declination.sin_cos();
+    let (sin_h, cos_h) = hour_angle.sin_cos();
+    let (sin_l, cos_l) = latitude.to_radians().sin_cos();
+
+    let east = -cos_d * sin_h;
+    let up = sin_l * sin_d + cos_l * cos_d * cos_h;
+    let north = cos_l * sin_d - sin_l * cos_d * cos_h;
+    math::Vector3::new(east, up, north).normalize()
+}
+
+/// The colors of sky and sunlight for a position of sun.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkyState {
+    /// The direction towards the sun.
+    pub sun: math::Vector3<f32>,
+    /// The color of sunlight, whose brightest channel is 1.
+    pub sun_color: math::Color<f32>,
+    pub sun_intensity: f32,
+    /// The average color of the upper hemisphere of sky.
+    pub ambient: math::Color<f32>,
+    /// The color of sky straight up.
+    pub zenith: math::Color<f32>,
+    /// The average color of sky around the horizon.
+    pub horizon: math::Color<f32>,
+}
+
+/// An analytic model of clear sky.
+#[derive(Debug, Clone, Copy)]
+pub struct Sky {
+    /// The haziness of atmosphere, from 2 of a clear day to 10 of a hazy one.
+    pub turbidity: f32,
+    /// The scale from the luminance of sky in kcd/m² to colors.
+    pub exposure: f32,
+    /// The intensity of sunlight at zenith through a clean atmosphere.
+    pub sun_intensity: f32,
+    /// The color of sky at night, which is also the ambient color.
+    pub night: math::Color<f32>,
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Sky {
+            turbidity: 2.5,
+            exposure: 0.03,
+            sun_intensity: 1.0,
+            night: math::Color::new(0.01, 0.012, 0.03, 1.0),
+        }
+    }
+}
+
+impl Sky {
+    /// Gets the color of sky seen along `view`, with the sun towards `sun`. The views
+    /// below horizon get the color of horizon.
+    pub fn radiance(&self, sun: math::Vector3<f32>, view: math::Vector3<f32>) -> math::Color<f32> {
+        let sun = sun.normalize();
+        let view = view.normalize();
+
+        // The model is only valid for the sun above horizon, which is faded into night.
+        let theta_s = sun.y.max(0.0).acos();
+        let theta = view.y.max(0.001).acos();
+        let gamma = sun.dot(view).max(-1.0).min(1.0).acos();
+
+        let t = self.turbidity;
+        let perez = |c: [f32; 5], theta: f32, gamma: f32| {
+            (1.0 + c[0] * (c[1] / theta.cos()).exp())
+                * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * gamma.cos() * gamma.cos())
+        };
+
+        let coeffs = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        let (zx, zy, zl) = zenith(t, theta_s);
+        let mut xyy = [zl, zx, zy];
+        for (v, &c) in xyy.iter_mut().zip(&coeffs) {
+            *v *= perez(c, theta, gamma) / perez(c, 0.0, theta_s);
+        }
+
+        let (l, x, y) = (xyy[0], xyy[1], xyy[2]);
+        let (cx, cy, cz) = (x * l / y, l, (1.0 - x - y) * l / y);
+        let rgb = [
+            3.2406 * cx - 1.5372 * cy - 0.4986 * cz,
+            -0.9689 * cx + 1.8758 * cy + 0.0415 * cz,
+            0.0557 * cx - 0.2040 * cy + 1.0570 * cz,
+        ];
+
+        let day = smoothstep(NIGHT_ELEVATION, 0.0, elevation(sun));
+        let mix = |v: f32, night: f32| v.max(0.0) * self.exposure * day + night * (1.0 - day);
+        math::Color::new(
+            mix(rgb[0], self.night.r),
+            mix(rgb[1], self.night.g),
+            mix(rgb[2], self.night.b),
+            1.0,
+        )
+    }
+
+    /// Gets the color and intensity of sunlight that passes through the atmosphere
+    /// towards `sun`.
+    pub fn sunlight(&self, sun: math::Vector3<f32>) -> (math::Color<f32>, f32) {
+        let sun = sun.normalize();
+        let zenith = 90.0 - elevation(sun).max(0.0);
+
+        // The relative optical air mass of Kasten and Young (1989).
+        let mass = 1.0 / (zenith.to_radians().cos() + 0.50572 * (96.07995 - zenith).powf(-1.6364));
+
+        // The optical depths of Rayleigh and aerosol scattering at 680, 550 and 440 nm.
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let mut transmittance = [0.0; 3];
+        for (v, &lambda) in transmittance.iter_mut().zip(&[0.68f32, 0.55, 0.44]) {
+            let rayleigh = 0.008569 * lambda.powi(-4)
+                * (1.0 + 0.0113 * lambda.powi(-2) + 0.00013 * lambda.powi(-4));
+            let aerosol = beta * lambda.powf(-1.3);
+            *v = (-(rayleigh + aerosol) * mass).exp();
+        }
+
+        let max = transmittance[0].max(transmittance[1]).max(transmittance[2]);
+        let color = math::Color::new(
+            transmittance[0] / max,
+            transmittance[1] / max,
+            transmittance[2] / max,
+            1.0,
+        );
+
+        let luminance = 0.2126 * transmittance[0]
+            + 0.7152 * transmittance[1]
+            + 0.0722 * transmittance[2];
+
+        // The disk of sun sinks below horizon.
+        let visible = smoothstep(-0.5, 0.5, elevation(sun));
+        (color, self.sun_intensity * luminance * visible)
+    }
+
+    /// Evaluates the colors of sky and sunlight with the sun towards `sun`.
+    pub fn evaluate(&self, sun: math::Vector3<f32>) -> SkyState {
+        let sun = sun.normalize();
+        let (sun_color, sun_intensity) = self.sunlight(sun);
+
+        // Samples the zenith, and rings of directions at 45 and 5 degrees of elevation.
+        let zenith = self.radiance(sun, math::Vector3::new(0.0, 1.0, 0.0));
+        let mut ambient = zenith.rgb();
+        let mut horizon = [0.0; 3];
+
+        const RING: usize = 8;
+        for i in 0..RING {
+            let azimuth = 2.0 * PI * i as f32 / RING as f32;
+            let (sin_a, cos_a) = azimuth.sin_cos();
+
+            for &(elevation, horizontal) in &[(45f32, false), (5f32, true)] {
+                let (sin_e, cos_e) = elevation.to_radians().sin_cos();
+                let view = math::Vector3::new(sin_a * cos_e, sin_e, cos_a * cos_e);
+                let color = self.radiance(sun, view);
+
+                accumulate(&mut ambient, color);
+                if horizontal {
+                    accumulate(&mut horizon, color);
+                }
+            }
+        }
+
+        let samples = (RING * 2 + 1) as f32;
+        SkyState {
+            sun: sun,
+            sun_color: sun_color,
+            sun_intensity: sun_intensity,
+            ambient: math::Color::new(
+                ambient[0] / samples,
+                ambient[1] / samples,
+                ambient[2] / samples,
+                1.0,
+            ),
+            zenith: zenith,
+            horizon: math::Color::new(
+                horizon[0] / RING as f32,
+                horizon[1] / RING as f32,
+                horizon[2] / RING as f32,
+                1.0,
+            ),
+        }
+    }
+}
+
+/// The clock of the day/night cycle, which drives a directional light as the sun. It
+/// could be kept in `World` with `World::insert_resource`.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeOfDay {
+    /// The hour of day in [0, 24).
+    pub hour: f32,
+    /// The day of year in [0, 365).
+    pub day: u32,
+    /// The latitude of the world in degrees, positive to the north.
+    pub latitude: f32,
+    /// The seconds of a whole day. The clock stops if it's not positive.
+    pub day_length: f32,
+    pub sky: Sky,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        TimeOfDay::new(12.0)
+    }
+}
+
+impl TimeOfDay {
+    /// Creates a new `TimeOfDay` at `hour` of the spring equinox.
+    pub fn new(hour: f32) -> Self {
+        TimeOfDay {
+            hour: hour,
+            day: 79,
+            latitude: 0.0,
+            day_length: 1200.0,
+            sky: Sky::default(),
+        }
+    }
+
+    /// Advances the clock by `dt` seconds, which wraps into the next days.
+    pub fn advance(&mut self, dt: f32) {
+        if self.day_length <= 0.0 {
+            return;
+        }
+
+        self.hour += dt * 24.0 / self.day_length;
+        while self.hour >= 24.0 {
+            self.hour -= 24.0;
+            self.day = (self.day + 1) % DAYS_PER_YEAR;
+        }
+    }
+
+    /// Gets the direction towards the sun.
+    #[inline]
+    pub fn sun_direction(&self) -> math::Vector3<f32> {
+        sun_direction(self.hour, self.day, self.latitude)
+    }
+
+    /// Evaluates the colors of sky and sunlight at the current time.
+    #[inline]
+    pub fn evaluate(&self) -> SkyState {
+        self.sky.evaluate(self.sun_direction())
+    }
+
+    /// Points the directional light of `sun` along the sunlight, and sets its color and
+    /// intensity. A light is added to `sun` if it has none, and it's disabled at night.
+    pub fn apply(
+        &self,
+        scene: &mut SceneGraph,
+        renderables: &mut Renderable,
+        sun: Entity,
+    ) -> SkyState {
+        let state = self.evaluate();
+
+        // The lights shine along their forward directions.
+        let dir = -state.sun;
+        let up = if dir.y.abs() > 0.999 {
+            math::Vector3::new(0.0, 0.0, 1.0)
+        } else {
+            math::Vector3::new(0.0, 1.0, 0.0)
+        };
+
+        let side = up.cross(dir).normalize();
+        let up = dir.cross(side).normalize();
+        let rotation: math::Quaternion<f32> = math::Matrix3::from_cols(side, up, dir).into();
+        scene.set_rotation(sun, rotation);
+
+        if renderables.lit(sun).is_none() {
+            renderables.add_lit(sun, Lit::default());
+        }
+
+        let lit = renderables.lit_mut(sun).unwrap();
+        lit.source = LitSource::Dir;
+        lit.color = state.sun_color;
+        lit.intensity = state.sun_intensity;
+        lit.enable = state.sun_intensity > 0.0;
+        state
+    }
+
+    /// Advances the clock by `dt` seconds, and applies it to the light of `sun`.
+    pub fn update(
+        &mut self,
+        dt: f32,
+        scene: &mut SceneGraph,
+        renderables: &mut Renderable,
+        sun: Entity,
+    ) -> SkyState {
+        self.advance(dt);
+        self.apply(scene, renderables, sun)
+    }
+}
+
+// Gets the elevation of direction above horizon in degrees.
+fn elevation(v: math::Vector3<f32>) -> f32 {
+    v.y.max(-1.0).min(1.0).asin().to_degrees()
+}
+
+fn accumulate(sum: &mut [f32; 3], color: math::Color<f32>) {
+    sum[0] += color.r;
+    sum[1] += color.g;
+    sum[2] += color.b;
+}
+
+fn smoothstep(from: f32, to: f32, v: f32) -> f32 {
+    let t = ((v - from) / (to - from)).max(0.0).min(1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Gets the chromaticity and luminance in kcd/m² at zenith, with the sun at `theta_s`
+// radians from zenith.
+fn zenith(t: f32, theta_s: f32) -> (f32, f32, f32) {
+    let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+    let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+    let (t2, s) = (t * t, theta_s);
+    let (s2, s3) = (s * s, s * s * s);
+    let x = (0.00166 * s3 - 0.00375 * s2 + 0.00209 * s) * t2
+        + (-0.02903 * s3 + 0.06377 * s2 - 0.03202 * s + 0.00394) * t
+        + (0.11693 * s3 - 0.21196 * s2 + 0.06052 * s + 0.25886);
+    let y = (0.00275 * s3 - 0.00610 * s2 + 0.00317 * s) * t2
+        + (-0.04214 * s3 + 0.08970 * s2 - 0.04153 * s + 0.00516) * t
+        + (0.15346 * s3 - 0.26756 * s2 + 0.06670 * s + 0.26688);
+
+    (x, y, luminance.max(0.0))
+}
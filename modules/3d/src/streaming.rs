@@ -0,0 +1,196 @@
+//! Streams the sectors of a large scene in and out around a viewer.
+//!
+//! A scene is divided into sectors, each of them is a prefab with its bounds in world
+//! space. The `SectorStreamer` loads the prefabs of sectors near the viewer through the
+//! resource system asynchronously, nearest first, and instantiates them once they are
+//! ready. Sectors that go beyond the unload distance are removed from the world and
+//! their prefabs are released.
+
+use std::cmp::Ordering;
+
+use crayon::errors::*;
+use crayon::math;
+use crayon::uuid::Uuid;
+
+use assets::PrefabHandle;
+use renderers::Renderer;
+use world::{Entity, World};
+
+/// A part of scene with bounds.
+#[derive(Debug, Clone, Copy)]
+pub struct Sector {
+    /// The bounds of this sector in world space.
+    pub bounds: math::Aabb3<f32>,
+    /// The uuid of prefab which holds the entities of this sector.
+    pub prefab: Uuid,
+}
+
+/// The streaming state of a sector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectorState {
+    Unloaded,
+    Loading(PrefabHandle),
+    Loaded(PrefabHandle, Entity),
+    /// The prefab could not be loaded, it will not be retried.
+    Failed,
+}
+
+/// The sectors that should be loaded and unloaded at a position.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SectorPlan {
+    /// The indices of unloaded sectors in range, nearest first.
+    pub load: Vec<usize>,
+    /// The indices of loaded sectors out of range.
+    pub unload: Vec<usize>,
+}
+
+pub struct SectorStreamer {
+    sectors: Vec<(Sector, SectorState)>,
+    load_distance: f32,
+    unload_distance: f32,
+    max_loading: usize,
+}
+
+impl SectorStreamer {
+    /// Creates a new `SectorStreamer`. Sectors within `load_distance` are loaded, and
+    /// the loaded ones beyond `unload_distance` are unloaded. The unload distance is
+    /// never less than the load distance, so sectors on the edge don't flicker.
+    pub fn new(load_distance: f32, unload_distance: f32) -> Self {
+        SectorStreamer {
+            sectors: Vec::new(),
+            load_distance: load_distance,
+            unload_distance: unload_distance.max(load_distance),
+            max_loading: 2,
+        }
+    }
+
+    /// Sets the maximum number of sectors being loaded at the same time. Limiting it
+    /// keeps the nearest sectors from waiting behind far ones. Defaults to 2.
+    #[inline]
+    pub fn set_max_loading(&mut self, max_loading: usize) {
+        self.max_loading = max_loading.max(1);
+    }
+
+    /// Adds a sector, and returns its index.
+    pub fn add(&mut self, sector: Sector) -> usize {
+        self.sectors.push((sector, SectorState::Unloaded));
+        self.sectors.len() - 1
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.sectors.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.sectors.is_empty()
+    }
+
+    /// Gets the sector at `index`.
+    #[inline]
+    pub fn sector(&self, index: usize) -> Option<Sector> {
+        self.sectors.get(index).map(|v| v.0)
+    }
+
+    /// Gets the streaming state of sector at `index`.
+    #[inline]
+    pub fn state(&self, index: usize) -> Option<SectorState> {
+        self.sectors.get(index).map(|v| v.1)
+    }
+
+    /// Gets the sectors that should be loaded and unloaded if the viewer is at
+    /// `position`.
+    pub fn plan(&self, position: math::Point3<f32>) -> SectorPlan {
+        let mut plan = SectorPlan::default();
+        let mut candidates = Vec::new();
+
+        for (i, &(ref sector, state)) in self.sectors.iter().enumerate() {
+            let distance = distance(&sector.bounds, position);
+            match state {
+                SectorState::Unloaded if distance <= self.load_distance => {
+                    candidates.push((distance, i));
+                }
+                SectorState::Loaded(_, _) if distance > self.unload_distance => {
+                    plan.unload.push(i);
+                }
+                _ => {}
+            }
+        }
+
+        candidates.sort_by(|lhs, rhs| lhs.partial_cmp(rhs).unwrap_or(Ordering::Equal));
+        plan.load = candidates.into_iter().map(|v| v.1).collect();
+        plan
+    }
+
+    /// Streams sectors around the viewer at `position`. It should be called every
+    /// frame.
+    ///
+    /// Sectors that are still loading when they go out of range finish loading first,
+    /// and get unloaded in later frames. Sectors whose prefabs could not be loaded or
+    /// instantiated are marked as `Failed`, and the others keep streaming.
+    pub fn advance<T: Renderer>(
+        &mut self,
+        world: &mut World<T>,
+        position: math::Point3<f32>,
+    ) -> Result<()> {
+        let plan = self.plan(position);
+
+        for &i in &plan.unload {
+            if let SectorState::Loaded(handle, ent) = self.sectors[i].1 {
+                world.remove(ent);
+                world.res.delete_prefab(handle);
+                self.sectors[i].1 = SectorState::Unloaded;
+            }
+        }
+
+        let mut loading = 0;
+        for v in &mut self.sectors {
+            if let SectorState::Loading(handle) = v.1 {
+                // Waits for the meshes of prefab too, so instantiating never blocks.
+                if world.res.is_prefab_loading(handle) {
+                    loading += 1;
+                } else if world.res.try_prefab(handle).is_some() {
+                    match world.instantiate(handle) {
+                        Ok(ent) => v.1 = SectorState::Loaded(handle, ent),
+                        Err(err) => {
+                            warn!("Failed to instantiate sector {}: {}", v.0.prefab, err);
+                            world.res.delete_prefab(handle);
+                            v.1 = SectorState::Failed;
+                        }
+                    }
+                } else {
+                    world.res.delete_prefab(handle);
+                    v.1 = SectorState::Failed;
+                }
+            }
+        }
+
+        for &i in &plan.load {
+            if loading >= self.max_loading {
+                break;
+            }
+
+            let handle = world
+                .res
+                .create_prefab_from_uuid(self.sectors[i].0.prefab)?;
+            self.sectors[i].1 = SectorState::Loading(handle);
+            loading += 1;
+        }
+
+        Ok(())
+    }
+}
+
+fn distance(bounds: &math::Aabb3<f32>, position: math::Point3<f32>) -> f32 {
+    let dx = (bounds.min.x - position.x)
+        .max(position.x - bounds.max.x)
+        .max(0.0);
+    let dy = (bounds.min.y - position.y)
+        .max(position.y - bounds.max.y)
+        .max(0.0);
+    let dz = (bounds.min.z - position.z)
+        .max(position.z - bounds.max.z)
+        .max(0.0);
+    (dx * dx + dy * dy + dz * dz).sqrt()
+}
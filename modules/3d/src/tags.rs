@@ -1,15 +1,21 @@
+use component::{map_bytes, vec_bytes};
 use {Component, Entity};
 
-use crayon::utils::VariantStr;
+use crayon::utils::hash::FastHashMap;
+use crayon::utils::{HashValue, VariantStr};
 
 pub struct Tags {
     names: Component<VariantStr>,
+    tagged: FastHashMap<HashValue<str>, Vec<Entity>>,
+    tags: FastHashMap<Entity, Vec<HashValue<str>>>,
 }
 
 impl Tags {
     pub fn new() -> Self {
         Tags {
             names: Component::new(),
+            tagged: FastHashMap::default(),
+            tags: FastHashMap::default(),
         }
     }
 
@@ -18,13 +24,132 @@ impl Tags {
         self.names.add(ent, name.as_ref().into());
     }
 
+    /// Removes the name and all the tags of `ent`.
     #[inline]
     pub fn remove(&mut self, ent: Entity) {
         self.names.remove(ent);
+
+        if let Some(tags) = self.tags.remove(&ent) {
+            for v in tags {
+                self.detach(v, ent);
+            }
+        }
     }
 
     #[inline]
     pub fn name(&self, ent: Entity) -> Option<&str> {
         self.names.get(ent).map(|v| v.as_str())
     }
+
+    /// Attaches a tag to `ent`. An entity could have any number of tags, and a tag
+    /// could be shared by any number of entities.
+    pub fn tag<T: AsRef<str>>(&mut self, ent: Entity, tag: T) {
+        self.attach(tag.as_ref().into(), ent);
+    }
+
+    /// Moves the names and tags of `other` into this, with their entities remapped.
+    pub(crate) fn merge(&mut self, other: Tags, remap: &FastHashMap<Entity, Entity>) {
+        self.names.merge(other.names, remap);
+
+        for (ent, tags) in other.tags {
+            if let Some(&ent) = remap.get(&ent) {
+                for v in tags {
+                    self.attach(v, ent);
+                }
+            }
+        }
+    }
+
+    fn attach(&mut self, tag: HashValue<str>, ent: Entity) {
+        let tags = self.tags.entry(ent).or_insert_with(Vec::new);
+
+        if !tags.contains(&tag) {
+            tags.push(tag);
+            self.tagged.entry(tag).or_insert_with(Vec::new).push(ent);
+        }
+    }
+
+    /// Detaches a tag from `ent`.
+    pub fn untag<T: AsRef<str>>(&mut self, ent: Entity, tag: T) {
+        let tag = tag.as_ref().into();
+
+        let removed = if let Some(tags) = self.tags.get_mut(&ent) {
+            let len = tags.len();
+            tags.retain(|&v| v != tag);
+            len != tags.len()
+        } else {
+            false
+        };
+
+        if removed {
+            if self.tags[&ent].is_empty() {
+                self.tags.remove(&ent);
+            }
+
+            self.detach(tag, ent);
+        }
+    }
+
+    /// Returns true if `ent` has the tag.
+    #[inline]
+    pub fn has_tag<T: AsRef<str>>(&self, ent: Entity, tag: T) -> bool {
+        let tag = tag.as_ref().into();
+        self.tags
+            .get(&ent)
+            .map(|v| v.contains(&tag))
+            .unwrap_or(false)
+    }
+
+    /// Finds the first entity with the tag.
+    #[inline]
+    pub fn find_tagged<T: AsRef<str>>(&self, tag: T) -> Option<Entity> {
+        self.tagged(tag).first().cloned()
+    }
+
+    /// Gets all the entities with the tag, in the order they are tagged.
+    #[inline]
+    pub fn tagged<T: AsRef<str>>(&self, tag: T) -> &[Entity] {
+        self.tagged
+            .get(&tag.as_ref().into())
+            .map(|v| v.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Gets the number of named entities.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns true if there are no named entities.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.names.len() == 0
+    }
+
+    /// Gets the number of distinct tags.
+    #[inline]
+    pub fn tags_len(&self) -> usize {
+        self.tagged.len()
+    }
+
+    /// Gets the approximate memory of names and tags in bytes.
+    pub fn memory(&self) -> usize {
+        let tagged: usize = self.tagged.values().map(vec_bytes).sum();
+        let tags: usize = self.tags.values().map(vec_bytes).sum();
+        self.names.memory() + map_bytes(&self.tagged) + map_bytes(&self.tags) + tagged + tags
+    }
+
+    fn detach(&mut self, tag: HashValue<str>, ent: Entity) {
+        let empty = if let Some(entities) = self.tagged.get_mut(&tag) {
+            entities.retain(|&v| v != ent);
+            entities.is_empty()
+        } else {
+            false
+        };
+
+        if empty {
+            self.tagged.remove(&tag);
+        }
+    }
 }
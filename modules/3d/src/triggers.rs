@@ -0,0 +1,208 @@
+//! Trigger volumes which report the tagged entities entering and leaving them.
+//!
+//! Triggers are a lightweight replacement of physics for basic gameplay, e.g. doors that
+//! open when the player comes close, or checkpoints. Every trigger watches the entities
+//! with a tag, which are treated as points at their world positions.
+
+use crayon::math;
+use crayon::math::InnerSpace;
+use crayon::utils::hash::{FastHashMap, FastHashSet};
+use crayon::utils::EventChannel;
+
+use component::Component;
+use scene::SceneGraph;
+use tags::Tags;
+use Entity;
+
+/// The shape of a trigger volume, in the local space of its entity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriggerShape {
+    /// A box with half extents.
+    Box(math::Vector3<f32>),
+    /// A sphere with radius.
+    Sphere(f32),
+    /// A capsule along the local y-axis, with radius and the half height of its segment.
+    Capsule(f32, f32),
+}
+
+impl TriggerShape {
+    /// Returns true if the point in local space is inside this shape.
+    pub fn contains(&self, p: math::Vector3<f32>) -> bool {
+        match *self {
+            TriggerShape::Box(extents) => {
+                p.x.abs() <= extents.x && p.y.abs() <= extents.y && p.z.abs() <= extents.z
+            }
+            TriggerShape::Sphere(radius) => p.magnitude2() <= radius * radius,
+            TriggerShape::Capsule(radius, half_height) => {
+                let y = p.y.max(-half_height).min(half_height);
+                (p - math::Vector3::new(0.0, y, 0.0)).magnitude2() <= radius * radius
+            }
+        }
+    }
+}
+
+/// A trigger volume attached to an entity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trigger {
+    /// Disabled triggers report exits of all their overlaps.
+    pub enable: bool,
+    /// The shape of volume.
+    pub shape: TriggerShape,
+    /// The tag of entities that this trigger watches.
+    pub tag: String,
+}
+
+impl Trigger {
+    /// Creates a new `Trigger` which watches the entities tagged with `tag`.
+    pub fn new<T: Into<String>>(shape: TriggerShape, tag: T) -> Self {
+        Trigger {
+            enable: true,
+            shape: shape,
+            tag: tag.into(),
+        }
+    }
+}
+
+/// The overlap events, with the entity of trigger and the one that overlaps with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TriggerEvent {
+    /// The entity has entered the trigger since last update.
+    Enter(Entity, Entity),
+    /// The entity is still inside the trigger.
+    Stay(Entity, Entity),
+    /// The entity has left the trigger, or one of them has been removed or disabled.
+    Exit(Entity, Entity),
+}
+
+/// The storage of triggers, and the overlaps between them and the watched entities.
+pub struct Triggers {
+    triggers: Component<Trigger>,
+    overlaps: FastHashSet<(Entity, Entity)>,
+    events: EventChannel<TriggerEvent>,
+}
+
+impl Default for Triggers {
+    fn default() -> Self {
+        Triggers::new()
+    }
+}
+
+impl Triggers {
+    pub fn new() -> Self {
+        Triggers {
+            triggers: Component::new(),
+            overlaps: FastHashSet::default(),
+            events: EventChannel::new(),
+        }
+    }
+
+    /// Attaches a trigger to the entity, and returns the old one if there is any.
+    #[inline]
+    pub fn add(&mut self, ent: Entity, trigger: Trigger) -> Option<Trigger> {
+        self.triggers.add(ent, trigger)
+    }
+
+    /// Removes the trigger of entity. Its overlaps exit on the next update.
+    #[inline]
+    pub fn remove(&mut self, ent: Entity) {
+        self.triggers.remove(ent)
+    }
+
+    #[inline]
+    pub fn has(&self, ent: Entity) -> bool {
+        self.triggers.has(ent)
+    }
+
+    #[inline]
+    pub fn get(&self, ent: Entity) -> Option<&Trigger> {
+        self.triggers.get(ent)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, ent: Entity) -> Option<&mut Trigger> {
+        self.triggers.get_mut(ent)
+    }
+
+    /// Gets the number of triggers.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.triggers.len()
+    }
+
+    /// Returns true if there are no triggers.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.triggers.len() == 0
+    }
+
+    /// Returns true if `other` was inside `trigger` on the last update.
+    #[inline]
+    pub fn overlaps(&self, trigger: Entity, other: Entity) -> bool {
+        self.overlaps.contains(&(trigger, other))
+    }
+
+    /// Gets the channel of overlap events, which are written on every update.
+    #[inline]
+    pub fn events(&mut self) -> &mut EventChannel<TriggerEvent> {
+        &mut self.events
+    }
+
+    /// Gets the approximate memory of this storage in bytes.
+    pub fn memory(&self) -> usize {
+        self.triggers.memory()
+            + self.overlaps.capacity() * ::std::mem::size_of::<(Entity, Entity)>()
+    }
+
+    /// Moves the triggers of `other` into this, with their entities remapped.
+    pub fn merge(&mut self, other: Triggers, remap: &FastHashMap<Entity, Entity>) {
+        self.triggers.merge(other.triggers, remap);
+    }
+
+    /// Tests the watched entities against all the triggers, and writes the events of
+    /// overlaps. Entities and triggers that are disabled in hierarchy are ignored.
+    pub fn update(&mut self, scene: &SceneGraph, tags: &Tags) {
+        let mut overlaps = FastHashSet::default();
+        let mut events = Vec::new();
+
+        for (&ent, v) in self.triggers.entities.iter().zip(&self.triggers.data) {
+            if !v.enable || !scene.is_enabled_in_hierarchy(ent) {
+                continue;
+            }
+
+            let inverse = match scene.transform(ent).and_then(|v| v.inverse()) {
+                Some(inverse) => inverse,
+                None => continue,
+            };
+
+            for &other in tags.tagged(&v.tag) {
+                if other == ent || !scene.is_enabled_in_hierarchy(other) {
+                    continue;
+                }
+
+                let inside = scene
+                    .position(other)
+                    .map(|p| v.shape.contains(inverse.transform_point(p)))
+                    .unwrap_or(false);
+
+                if inside && overlaps.insert((ent, other)) {
+                    if self.overlaps.contains(&(ent, other)) {
+                        events.push(TriggerEvent::Stay(ent, other));
+                    } else {
+                        events.push(TriggerEvent::Enter(ent, other));
+                    }
+                }
+            }
+        }
+
+        let mut exits: Vec<_> = self.overlaps.difference(&overlaps).cloned().collect();
+        exits.sort();
+        events.extend(
+            exits
+                .into_iter()
+                .map(|(ent, other)| TriggerEvent::Exit(ent, other)),
+        );
+
+        self.overlaps = overlaps;
+        self.events.iter_write(events);
+    }
+}
@@ -0,0 +1,443 @@
+//! Weather presets with rain and snow around the camera, wind and wet surfaces.
+//!
+//! A `Weather` blends between named `WeatherState`s over time. The precipitation is
+//! simulated in boxes that follow the camera, and wraps around their bounds so there
+//! are always particles nearby. The wetness of surfaces rises while raining and dries
+//! afterwards, which is consumed by `SimpleRenderer::set_global_wetness`:
+//!
+//! ```rust,ignore
+//! weather.transition("storm", 10.0);
+//! ...
+//! weather.update(dt, camera_position);
+//! weather.rain.draw(&mut world.renderer, &world.scene, &world.renderables, camera, drop);
+//! world.renderer.set_global_wetness(weather.wetness());
+//! ```
+
+use crayon::math;
+use crayon::math::InnerSpace;
+use crayon::utils::hash::FastHashMap;
+
+use renderers::{noise, MeshRenderer, Renderable, Renderer};
+use scene::{SceneGraph, Transform};
+use Entity;
+
+/// The wind, which pushes particles around and sways vegetation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wind {
+    /// The direction on the xz-plane.
+    pub direction: math::Vector2<f32>,
+    /// The average speed in units per second.
+    pub speed: f32,
+    /// The relative strength of gusts in [0, 1].
+    pub gustiness: f32,
+    /// The number of gusts per second.
+    pub frequency: f32,
+}
+
+impl Default for Wind {
+    fn default() -> Self {
+        Wind::calm()
+    }
+}
+
+impl Wind {
+    pub fn new(direction: math::Vector2<f32>, speed: f32) -> Self {
+        Wind {
+            direction: direction,
+            speed: speed,
+            gustiness: 0.3,
+            frequency: 0.2,
+        }
+    }
+
+    /// Creates a wind that does not blow.
+    pub fn calm() -> Self {
+        Wind::new(math::Vector2::new(1.0, 0.0), 0.0)
+    }
+
+    /// Gets the velocity of wind at `time` in seconds, including gusts.
+    pub fn sample(&self, time: f32) -> math::Vector3<f32> {
+        if self.direction.magnitude2() <= 0.0 {
+            return math::Vector3::new(0.0, 0.0, 0.0);
+        }
+
+        let direction = self.direction.normalize();
+        let gust = 1.0 + self.gustiness * noise(0, time * self.frequency);
+        let speed = self.speed * gust.max(0.0);
+        math::Vector3::new(direction.x * speed, 0.0, direction.y * speed)
+    }
+
+    /// Linearly interpolates between two winds.
+    pub fn lerp(&self, rhs: &Wind, t: f32) -> Wind {
+        let lhs = self.direction * self.speed;
+        let velocity = lhs + (rhs.direction * rhs.speed - lhs) * t;
+        let speed = velocity.magnitude();
+
+        Wind {
+            direction: if speed > 0.0 {
+                velocity / speed
+            } else {
+                rhs.direction
+            },
+            speed: speed,
+            gustiness: self.gustiness + (rhs.gustiness - self.gustiness) * t,
+            frequency: self.frequency + (rhs.frequency - self.frequency) * t,
+        }
+    }
+}
+
+/// The parameters of a kind of weather.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeatherState {
+    /// The intensity of rain in [0, 1].
+    pub rain: f32,
+    /// The intensity of snow in [0, 1].
+    pub snow: f32,
+    pub wind: Wind,
+}
+
+impl Default for WeatherState {
+    fn default() -> Self {
+        WeatherState::clear()
+    }
+}
+
+impl WeatherState {
+    pub fn clear() -> Self {
+        WeatherState {
+            rain: 0.0,
+            snow: 0.0,
+            wind: Wind::new(math::Vector2::new(1.0, 0.0), 1.0),
+        }
+    }
+
+    pub fn rain() -> Self {
+        WeatherState {
+            rain: 0.5,
+            snow: 0.0,
+            wind: Wind::new(math::Vector2::new(1.0, 0.0), 3.0),
+        }
+    }
+
+    pub fn storm() -> Self {
+        let mut wind = Wind::new(math::Vector2::new(1.0, 0.2), 12.0);
+        wind.gustiness = 0.6;
+        wind.frequency = 0.5;
+
+        WeatherState {
+            rain: 1.0,
+            snow: 0.0,
+            wind: wind,
+        }
+    }
+
+    pub fn snow() -> Self {
+        WeatherState {
+            rain: 0.0,
+            snow: 0.6,
+            wind: Wind::new(math::Vector2::new(1.0, 0.0), 2.0),
+        }
+    }
+
+    /// Linearly interpolates between two states.
+    pub fn lerp(&self, rhs: &WeatherState, t: f32) -> WeatherState {
+        WeatherState {
+            rain: self.rain + (rhs.rain - self.rain) * t,
+            snow: self.snow + (rhs.snow - self.snow) * t,
+            wind: self.wind.lerp(&rhs.wind, t),
+        }
+    }
+}
+
+/// Particles of precipitation in a box around the camera.
+pub struct PrecipitationLayer {
+    /// The half size of box.
+    pub extent: math::Vector3<f32>,
+    /// The number of particles at full intensity.
+    pub capacity: usize,
+    /// The speed of falling in units per second.
+    pub fall_speed: f32,
+    /// How much the particles are carried by wind, in [0, 1].
+    pub drift: f32,
+    /// The scale of particle meshes.
+    pub size: f32,
+
+    particles: Vec<math::Vector3<f32>>,
+    velocity: math::Vector3<f32>,
+    seed: u32,
+    meshes: Vec<MeshRenderer>,
+}
+
+impl PrecipitationLayer {
+    pub fn new(capacity: usize, fall_speed: f32, drift: f32) -> Self {
+        PrecipitationLayer {
+            extent: math::Vector3::new(15.0, 10.0, 15.0),
+            capacity: capacity,
+            fall_speed: fall_speed,
+            drift: drift,
+            size: 1.0,
+            particles: Vec::new(),
+            velocity: math::Vector3::new(0.0, -fall_speed, 0.0),
+            seed: 0x9e37_79b9,
+            meshes: Vec::new(),
+        }
+    }
+
+    /// Creates a layer of fast rain drops, which are barely pushed by wind.
+    pub fn rain() -> Self {
+        PrecipitationLayer::new(4000, 12.0, 0.3)
+    }
+
+    /// Creates a layer of slow snowflakes, which drift along with wind.
+    pub fn snow() -> Self {
+        PrecipitationLayer::new(3000, 1.5, 1.0)
+    }
+
+    /// Gets the positions of the active particles in world space.
+    #[inline]
+    pub fn particles(&self) -> &[math::Vector3<f32>] {
+        &self.particles
+    }
+
+    /// Gets the velocity of particles.
+    #[inline]
+    pub fn velocity(&self) -> math::Vector3<f32> {
+        self.velocity
+    }
+
+    /// Advances the particles by `dt` seconds, and wraps them into the box around
+    /// `center`. The number of active particles follows `intensity`.
+    pub fn update(
+        &mut self,
+        dt: f32,
+        center: math::Vector3<f32>,
+        intensity: f32,
+        wind: math::Vector3<f32>,
+    ) {
+        let active = (intensity.max(0.0).min(1.0) * self.capacity as f32).round() as usize;
+        self.particles.truncate(active);
+
+        while self.particles.len() < active {
+            let offset = math::Vector3::new(
+                self.extent.x * (self.random() * 2.0 - 1.0),
+                self.extent.y * (self.random() * 2.0 - 1.0),
+                self.extent.z * (self.random() * 2.0 - 1.0),
+            );
+
+            self.particles.push(center + offset);
+        }
+
+        self.velocity = math::Vector3::new(0.0, -self.fall_speed, 0.0) + wind * self.drift;
+        let (velocity, extent) = (self.velocity, self.extent);
+        for v in &mut self.particles {
+            let p = *v + velocity * dt;
+            *v = math::Vector3::new(
+                wrap(p.x, center.x, extent.x),
+                wrap(p.y, center.y, extent.y),
+                wrap(p.z, center.z, extent.z),
+            );
+        }
+    }
+
+    /// Draws the particles with the mesh renderer and material of `template`, which
+    /// is usually hidden itself. The particles are stretched along their velocity.
+    pub fn draw<R: Renderer>(
+        &mut self,
+        renderer: &mut R,
+        scene: &SceneGraph,
+        renderables: &Renderable,
+        camera: Entity,
+        template: Entity,
+    ) {
+        let mut view = match renderables.camera(camera) {
+            Some(&view) => view,
+            None => return,
+        };
+
+        match scene.transform(camera) {
+            Some(transform) => view.transform = transform,
+            None => return,
+        }
+
+        let mut mesh = match renderables.mesh(template) {
+            Some(&mesh) => mesh,
+            None => return,
+        };
+
+        // Rotates the y-axis of meshes against the velocity.
+        let rotation = if self.velocity.magnitude2() > 0.0 {
+            math::Quaternion::from_arc(
+                math::Vector3::new(0.0, 1.0, 0.0),
+                -self.velocity.normalize(),
+                None,
+            )
+        } else {
+            math::Quaternion::new(1.0, 0.0, 0.0, 0.0)
+        };
+
+        mesh.ent = template;
+        mesh.visible = true;
+
+        self.meshes.clear();
+        for &v in &self.particles {
+            mesh.transform = Transform {
+                position: v,
+                rotation: rotation,
+                scale: self.size,
+            };
+
+            self.meshes.push(mesh);
+        }
+
+        renderer.submit(&view, renderables.visible_lits(), &self.meshes);
+    }
+
+    // Xorshift, which is good enough for scattering particles.
+    fn random(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        (self.seed >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+/// Blends between weather states, and simulates the precipitation and the wetness of
+/// surfaces. It starts in the "clear" state, and has presets of "rain", "storm" and
+/// "snow".
+pub struct Weather {
+    pub rain: PrecipitationLayer,
+    pub snow: PrecipitationLayer,
+    /// The wetness gained per second in full rain.
+    pub wetting: f32,
+    /// The wetness lost per second without rain.
+    pub drying: f32,
+
+    states: FastHashMap<String, WeatherState>,
+    current: String,
+    from: WeatherState,
+    to: WeatherState,
+    elapsed: f32,
+    duration: f32,
+    time: f32,
+    wetness: f32,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Weather::new()
+    }
+}
+
+impl Weather {
+    pub fn new() -> Self {
+        let mut states = FastHashMap::default();
+        states.insert("clear".to_owned(), WeatherState::clear());
+        states.insert("rain".to_owned(), WeatherState::rain());
+        states.insert("storm".to_owned(), WeatherState::storm());
+        states.insert("snow".to_owned(), WeatherState::snow());
+
+        Weather {
+            rain: PrecipitationLayer::rain(),
+            snow: PrecipitationLayer::snow(),
+            wetting: 0.1,
+            drying: 0.02,
+            states: states,
+            current: "clear".to_owned(),
+            from: WeatherState::clear(),
+            to: WeatherState::clear(),
+            elapsed: 0.0,
+            duration: 0.0,
+            time: 0.0,
+            wetness: 0.0,
+        }
+    }
+
+    /// Adds a named state, replacing the previous one if any.
+    pub fn add_state<T: Into<String>>(&mut self, name: T, state: WeatherState) {
+        self.states.insert(name.into(), state);
+    }
+
+    /// Gets the named state.
+    pub fn state<T: AsRef<str>>(&self, name: T) -> Option<&WeatherState> {
+        self.states.get(name.as_ref())
+    }
+
+    /// Gets the name of the state that is current or being transitioned to.
+    #[inline]
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    /// Returns true if it's blending into the current state.
+    #[inline]
+    pub fn is_transitioning(&self) -> bool {
+        self.elapsed < self.duration
+    }
+
+    /// Starts blending from the present weather into the named state in `duration`
+    /// seconds. Returns false if the state does not exist.
+    pub fn transition<T: AsRef<str>>(&mut self, name: T, duration: f32) -> bool {
+        let to = match self.states.get(name.as_ref()) {
+            Some(&state) => state,
+            None => return false,
+        };
+
+        self.from = self.params();
+        self.to = to;
+        self.elapsed = 0.0;
+        self.duration = duration.max(0.0);
+        self.current = name.as_ref().to_owned();
+        true
+    }
+
+    /// Gets the blended parameters of the present weather.
+    pub fn params(&self) -> WeatherState {
+        if self.is_transitioning() {
+            self.from.lerp(&self.to, self.elapsed / self.duration)
+        } else {
+            self.to
+        }
+    }
+
+    /// Gets the velocity of wind at present, including gusts.
+    #[inline]
+    pub fn wind(&self) -> math::Vector3<f32> {
+        self.params().wind.sample(self.time)
+    }
+
+    /// Gets the wetness of surfaces in [0, 1].
+    #[inline]
+    pub fn wetness(&self) -> f32 {
+        self.wetness
+    }
+
+    /// Advances the weather by `dt` seconds, and moves the precipitation around
+    /// `center`, which is usually the position of camera.
+    pub fn update(&mut self, dt: f32, center: math::Vector3<f32>) {
+        self.time += dt;
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+
+        let params = self.params();
+        let wind = params.wind.sample(self.time);
+        self.rain.update(dt, center, params.rain, wind);
+        self.snow.update(dt, center, params.snow, wind);
+
+        let rate = params.rain * self.wetting - (1.0 - params.rain) * self.drying;
+        self.wetness = (self.wetness + rate * dt).max(0.0).min(1.0);
+    }
+}
+
+// Wraps `v` into [center - extent, center + extent).
+fn wrap(v: f32, center: f32, extent: f32) -> f32 {
+    let size = extent * 2.0;
+    if size <= 0.0 {
+        return center;
+    }
+
+    let mut offset = (v - center + extent) % size;
+    if offset < 0.0 {
+        offset += size;
+    }
+
+    center - extent + offset
+}
@@ -1,52 +1,217 @@
+use std::any::Any;
 use std::sync::Arc;
 
+use crayon::bincode;
 use crayon::errors::*;
-use crayon::utils::HandlePool;
+use crayon::utils::hash::FastHashMap;
+use crayon::utils::{EventChannel, HandlePool, HandlePoolConfig};
+use crayon::video::VideoSystemShared;
 
+use assets::prefab_loader::MAGIC;
 use assets::PrefabHandle;
 use renderers::{MeshRenderer, Renderable, Renderer};
+use resources::Resources;
 use scene::SceneGraph;
 use tags::Tags;
+use triggers::Triggers;
 use WorldResourcesShared;
 
 impl_handle!(Entity);
 
+/// A weak reference to an entity. It resolves to `None` once the entity is removed, and
+/// nulls itself at the same time, so it never aliases a later entity which reuses the
+/// same index.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntityRef(Option<Entity>);
+
+impl EntityRef {
+    /// Creates a reference to `ent`.
+    #[inline]
+    pub fn new(ent: Entity) -> Self {
+        EntityRef(Some(ent))
+    }
+
+    /// Creates a reference to nothing.
+    #[inline]
+    pub fn null() -> Self {
+        EntityRef(None)
+    }
+
+    /// Returns true if this reference has been nulled. A reference which is not null
+    /// might still point to a removed entity until it's resolved.
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Gets the referenced entity without checking if it's still alive.
+    #[inline]
+    pub fn peek(&self) -> Option<Entity> {
+        self.0
+    }
+
+    /// Gets the referenced entity if it's still alive in `entities`, otherwise nulls
+    /// this reference.
+    pub fn resolve(&mut self, entities: &HandlePool<Entity>) -> Option<Entity> {
+        match self.0 {
+            Some(ent) if entities.is_alive(ent) => Some(ent),
+            _ => {
+                self.0 = None;
+                None
+            }
+        }
+    }
+}
+
+impl From<Entity> for EntityRef {
+    fn from(ent: Entity) -> Self {
+        EntityRef::new(ent)
+    }
+}
+
+/// The statistics of a `World`, e.g. for debug overlays and memory budgeting.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorldStats {
+    /// The number of alive entities.
+    pub entities: usize,
+    /// The number of entity indices that have been allocated, alive or not.
+    pub indices: usize,
+    /// The names of components and the number of them.
+    pub components: Vec<(&'static str, usize)>,
+    /// The number of distinct tags.
+    pub tags: usize,
+    /// The number of resources inserted with `insert_resource`.
+    pub resources: usize,
+    /// The approximate memory of storages in bytes, excluding the heap memory owned
+    /// by components.
+    pub memory: usize,
+}
+
 pub struct World<T: Renderer> {
     entities: HandlePool<Entity>,
+    removals: EventChannel<Entity>,
 
     pub tags: Tags,
     pub scene: SceneGraph,
     pub renderables: Renderable,
+    pub triggers: Triggers,
     pub renderer: T,
     pub res: Arc<WorldResourcesShared>,
+    pub resources: Resources,
 }
 
 impl<T: Renderer> World<T> {
     pub fn new(res: Arc<WorldResourcesShared>, renderer: T) -> Self {
         World {
             entities: HandlePool::new(),
+            removals: EventChannel::new(),
             tags: Tags::new(),
             scene: SceneGraph::new(),
             renderables: Renderable::new(),
+            triggers: Triggers::new(),
             renderer: renderer,
             res: res,
+            resources: Resources::new(),
         }
     }
 
+    /// Creates a new and empty world which could hold at least `capacity` entities
+    /// without reallocating, e.g. the number of entities of a level.
+    pub fn with_capacity(res: Arc<WorldResourcesShared>, renderer: T, capacity: usize) -> Self {
+        let mut world = World::new(res, renderer);
+        world.reserve(capacity);
+        world
+    }
+
+    /// Creates a new and empty world with the policy of recycling entity indices. Long
+    /// running servers could delay the reuse of indices, so stale entities are less
+    /// likely to alias living ones.
+    pub fn with_config(
+        res: Arc<WorldResourcesShared>,
+        renderer: T,
+        config: HandlePoolConfig,
+    ) -> Self {
+        let mut world = World::new(res, renderer);
+        world.entities = HandlePool::with_config(config);
+        world
+    }
+
+    /// Reserves capacity for at least `additional` more entities, so loading a large
+    /// level does not grow the storages and rehash repeatedly. The mesh renderers could
+    /// be reserved with `Renderable::reserve_meshes`.
+    pub fn reserve(&mut self, additional: usize) {
+        self.entities.reserve(additional);
+        self.scene.reserve(additional);
+    }
+
     /// Creates a new Entity.
     pub fn create(&mut self) -> Entity {
         world_impl::create(&mut self.entities, &mut self.scene)
     }
 
-    /// Removes a Entity and all of its descendants from this world.
+    /// Creates `n` entities at once. The storages are grown only once, which is
+    /// cheaper than calling `create` repeatedly when spawning lots of particles or tiles.
+    pub fn create_batch(&mut self, n: usize) -> Vec<Entity> {
+        world_impl::create_batch(&mut self.entities, &mut self.scene, n)
+    }
+
+    /// Removes a Entity and all of its descendants from this world. The removed entities
+    /// are written into the channel of `removals`.
     pub fn remove(&mut self, ent: Entity) -> Option<Vec<Entity>> {
-        world_impl::remove(
+        let deletions = world_impl::remove(
             &mut self.entities,
             &mut self.scene,
             &mut self.renderables,
             &mut self.tags,
             ent,
-        )
+        )?;
+
+        for &v in &deletions {
+            self.triggers.remove(v);
+        }
+
+        self.removals.iter_write(deletions.iter().cloned());
+        Some(deletions)
+    }
+
+    /// Returns true if the entity is alive in this world.
+    #[inline]
+    pub fn is_alive(&self, ent: Entity) -> bool {
+        self.entities.is_alive(ent)
+    }
+
+    /// Gets the entity of `r` if it's still alive, otherwise nulls `r`.
+    #[inline]
+    pub fn resolve(&self, r: &mut EntityRef) -> Option<Entity> {
+        r.resolve(&self.entities)
+    }
+
+    /// Gets the channel of removed entities. Systems which keep their own maps keyed by
+    /// entities could register a reader and drop the stale entries.
+    #[inline]
+    pub fn removals(&mut self) -> &mut EventChannel<Entity> {
+        &mut self.removals
+    }
+
+    /// Moves all the entities of `other` into this world, and returns the map from
+    /// their old ids to the new ones. The hierarchy, transforms, names, tags,
+    /// renderable components and triggers are kept, but not the resources of `other`.
+    ///
+    /// This lets chunks of level be built on worker threads, or loaded from files, and
+    /// spliced into the live world at once.
+    pub fn merge<U: Renderer>(&mut self, other: World<U>) -> FastHashMap<Entity, Entity> {
+        let remap = world_impl::merge(
+            &mut self.entities,
+            &mut self.scene,
+            &mut self.renderables,
+            &mut self.tags,
+            &other.scene,
+            other.renderables,
+            other.tags,
+        );
+
+        self.triggers.merge(other.triggers, &remap);
+        remap
     }
 
     /// Finds a Entity by name and returns it.
@@ -58,7 +223,28 @@ impl<T: Renderer> World<T> {
         world_impl::find(&self.scene, &self.tags, name)
     }
 
-    /// Instantiates a prefab into entities of this world.
+    /// Attaches a tag to the entity, so it could be located by `find_tagged` later.
+    #[inline]
+    pub fn tag<N: AsRef<str>>(&mut self, ent: Entity, tag: N) {
+        if self.entities.is_alive(ent) {
+            self.tags.tag(ent, tag);
+        }
+    }
+
+    /// Detaches a tag from the entity.
+    #[inline]
+    pub fn untag<N: AsRef<str>>(&mut self, ent: Entity, tag: N) {
+        self.tags.untag(ent, tag);
+    }
+
+    /// Finds the first Entity with the tag.
+    #[inline]
+    pub fn find_tagged<N: AsRef<str>>(&self, tag: N) -> Option<Entity> {
+        self.tags.find_tagged(tag)
+    }
+
+    /// Instantiates a prefab into entities of this world. It fails if the prefab or any
+    /// of its meshes has failed to load.
     pub fn instantiate(&mut self, handle: PrefabHandle) -> Result<Entity> {
         if let Some(prefab) = self.res.prefab(handle) {
             world_impl::instantiate(
@@ -73,14 +259,73 @@ impl<T: Renderer> World<T> {
         }
     }
 
+    /// Saves all the entities of this world into the bytes of a prefab asset, which
+    /// could be loaded and instantiated as any other prefab later.
+    ///
+    /// Only the names, hierarchy, local transforms and meshes of entities are saved,
+    /// and every mesh must be created from file so it can be referenced by uuid.
+    pub fn save(&self, video: &VideoSystemShared) -> Result<Vec<u8>> {
+        let prefab = world_impl::save(&self.scene, &self.renderables, &self.tags, |v| {
+            video.mesh_uuid(v)
+        })?;
+
+        let mut bytes = Vec::from(&MAGIC[..]);
+        bincode::serialize_into(&mut bytes, &prefab)?;
+        Ok(bytes)
+    }
+
+    /// Inserts a singleton resource into this world, and returns the one of the same
+    /// type it replaced.
+    #[inline]
+    pub fn insert_resource<R: Any + Send + Sync>(&mut self, value: R) -> Option<R> {
+        self.resources.insert(value)
+    }
+
+    /// Gets a reference to the singleton resource with type `R`.
+    #[inline]
+    pub fn fetch_resource<R: Any + Send + Sync>(&self) -> Option<&R> {
+        self.resources.fetch()
+    }
+
+    /// Gets a mutable reference to the singleton resource with type `R`.
+    #[inline]
+    pub fn fetch_resource_mut<R: Any + Send + Sync>(&mut self) -> Option<&mut R> {
+        self.resources.fetch_mut()
+    }
+
+    /// Removes the singleton resource with type `R` from this world.
+    #[inline]
+    pub fn remove_resource<R: Any + Send + Sync>(&mut self) -> Option<R> {
+        self.resources.remove()
+    }
+
+    /// Gets the statistics of this world.
+    pub fn stats(&self) -> WorldStats {
+        let mut stats = world_impl::stats(
+            &self.entities,
+            &self.scene,
+            &self.renderables,
+            &self.tags,
+            &self.resources,
+        );
+
+        stats.components.push(("Trigger", self.triggers.len()));
+        stats.memory += self.triggers.memory();
+        stats
+    }
+
+    /// Updates the overlaps of triggers, and draws the renderables.
     pub fn advance(&mut self) {
+        self.triggers.update(&self.scene, &self.tags);
         self.renderables.draw(&mut self.renderer, &self.scene);
     }
 }
 
 pub mod world_impl {
     use super::*;
-    use assets::Prefab;
+    use assets::prefab::{Prefab, PrefabNode};
+    use crayon::uuid::Uuid;
+    use crayon::video::assets::mesh::MeshHandle;
 
     pub fn create(entities: &mut HandlePool<Entity>, scene: &mut SceneGraph) -> Entity {
         let ent = entities.create().into();
@@ -88,6 +333,70 @@ pub mod world_impl {
         ent
     }
 
+    pub fn create_batch(
+        entities: &mut HandlePool<Entity>,
+        scene: &mut SceneGraph,
+        n: usize,
+    ) -> Vec<Entity> {
+        entities.reserve(n);
+        scene.reserve(n);
+        (0..n).map(|_| create(entities, scene)).collect()
+    }
+
+    pub fn stats(
+        entities: &HandlePool<Entity>,
+        scene: &SceneGraph,
+        renderables: &Renderable,
+        tags: &Tags,
+        resources: &Resources,
+    ) -> WorldStats {
+        let mut components = vec![("Transform", scene.len()), ("Name", tags.len())];
+        components.extend(renderables.components());
+
+        WorldStats {
+            entities: entities.len(),
+            indices: entities.indices(),
+            components: components,
+            tags: tags.tags_len(),
+            resources: resources.len(),
+            memory: entities.memory() + scene.memory() + renderables.memory() + tags.memory(),
+        }
+    }
+
+    pub fn merge(
+        entities: &mut HandlePool<Entity>,
+        scene: &mut SceneGraph,
+        renderables: &mut Renderable,
+        tags: &mut Tags,
+        other_scene: &SceneGraph,
+        other_renderables: Renderable,
+        other_tags: Tags,
+    ) -> FastHashMap<Entity, Entity> {
+        let mut roots: Vec<_> = other_scene.roots.iter().cloned().collect();
+        roots.sort();
+
+        let mut remap = FastHashMap::default();
+        let mut nodes: Vec<_> = roots.into_iter().rev().map(|v| (None, v)).collect();
+
+        while let Some((parent, v)) = nodes.pop() {
+            let e = create(entities, scene);
+            scene.set_local_transform(e, other_scene.local_transform(v).unwrap_or_default());
+            scene.set_enabled(e, other_scene.is_enabled(v));
+
+            if let Some(parent) = parent {
+                scene.set_parent(e, parent, false).unwrap();
+            }
+
+            // Children are attached in reverse order since `set_parent` prepends.
+            nodes.extend(other_scene.children(v).map(|child| (Some(e), child)));
+            remap.insert(v, e);
+        }
+
+        renderables.merge(other_renderables, &remap);
+        tags.merge(other_tags, &remap);
+        remap
+    }
+
     pub fn remove(
         entities: &mut HandlePool<Entity>,
         scene: &mut SceneGraph,
@@ -154,6 +463,93 @@ pub mod world_impl {
         return Ok(root.unwrap());
     }
 
+    /// Saves all the entities into a prefab. Meshes are referenced by the uuids given
+    /// by `uuid`, it fails if any of them has none.
+    pub fn save<F>(
+        scene: &SceneGraph,
+        renderables: &Renderable,
+        tags: &Tags,
+        uuid: F,
+    ) -> Result<Prefab>
+    where
+        F: Fn(MeshHandle) -> Option<Uuid>,
+    {
+        let mut roots: Vec<_> = scene.roots.iter().cloned().collect();
+        roots.sort();
+
+        if roots.is_empty() {
+            bail!("Can not save a world without any entity.");
+        }
+
+        let mut prefab = Prefab {
+            nodes: Vec::new(),
+            universe_meshes: Vec::new(),
+            meshes: Vec::new(),
+        };
+
+        let mut prev: Option<usize> = None;
+        for v in roots {
+            let idx = save_node(scene, renderables, tags, &uuid, &mut prefab, v)?;
+            if let Some(prev) = prev {
+                prefab.nodes[prev].next_sib = Some(idx);
+            }
+
+            prev = Some(idx);
+        }
+
+        Ok(prefab)
+    }
+
+    fn save_node<F>(
+        scene: &SceneGraph,
+        renderables: &Renderable,
+        tags: &Tags,
+        uuid: &F,
+        prefab: &mut Prefab,
+        ent: Entity,
+    ) -> Result<usize>
+    where
+        F: Fn(MeshHandle) -> Option<Uuid>,
+    {
+        let mesh_renderer = if let Some(mr) = renderables.mesh(ent) {
+            match prefab.meshes.iter().position(|&v| v == mr.mesh) {
+                Some(index) => Some(index),
+                None => {
+                    let v = uuid(mr.mesh)
+                        .ok_or_else(|| format_err!("{:?} is not created from file.", mr.mesh))?;
+
+                    prefab.universe_meshes.push(v);
+                    prefab.meshes.push(mr.mesh);
+                    Some(prefab.meshes.len() - 1)
+                }
+            }
+        } else {
+            None
+        };
+
+        let idx = prefab.nodes.len();
+        prefab.nodes.push(PrefabNode {
+            name: tags.name(ent).unwrap_or("").to_owned(),
+            local_transform: scene.local_transform(ent).unwrap_or_default(),
+            first_child: None,
+            next_sib: None,
+            mesh_renderer: mesh_renderer,
+        });
+
+        let mut prev: Option<usize> = None;
+        for child in scene.children(ent) {
+            let v = save_node(scene, renderables, tags, uuid, prefab, child)?;
+            match prev {
+                Some(prev) => prefab.nodes[prev].next_sib = Some(v),
+                None => prefab.nodes[idx].first_child = Some(v),
+            }
+
+            prev = Some(v);
+        }
+
+        Ok(idx)
+    }
+
     pub fn find<N: AsRef<str>>(scene: &SceneGraph, tags: &Tags, name: N) -> Option<Entity> {
         let mut components = name.as_ref().trim_left_matches('/').split('/');
         if let Some(first) = components.next() {
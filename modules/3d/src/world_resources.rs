@@ -4,6 +4,8 @@ use crayon::application::{Context, Engine};
 use crayon::errors::*;
 use crayon::res::location::Location;
 use crayon::res::registry::Registry;
+use crayon::uuid::Uuid;
+use crayon::video::VideoSystemShared;
 
 use assets::mesh_builder::WorldBuiltinMeshes;
 use assets::prefab::{Prefab, PrefabHandle};
@@ -29,6 +31,7 @@ pub type PrefabRegistry = Registry<PrefabHandle, PrefabLoader>;
 
 pub struct WorldResourcesShared {
     prefabs: PrefabRegistry,
+    video: Arc<VideoSystemShared>,
 
     pub meshes: WorldBuiltinMeshes,
     pub textures: WorldBuiltinTextures,
@@ -40,6 +43,7 @@ impl WorldResourcesShared {
 
         let shared = WorldResourcesShared {
             prefabs: PrefabRegistry::new(ctx.res.clone(), register),
+            video: ctx.video.clone(),
             meshes: WorldBuiltinMeshes::new(ctx)?,
             textures: WorldBuiltinTextures::new(ctx)?,
         };
@@ -57,15 +61,59 @@ impl WorldResourcesShared {
     }
 
     #[inline]
+    pub fn create_prefab_from_uuid(&self, uuid: Uuid) -> Result<PrefabHandle> {
+        let handle = self.prefabs.create_from_uuid(uuid)?;
+        Ok(handle)
+    }
+
+    /// Gets the prefab if it has been loaded, without blocking.
+    #[inline]
+    pub fn try_prefab(&self, handle: PrefabHandle) -> Option<Arc<Prefab>> {
+        self.prefabs.get(handle, |v| v.clone())
+    }
+
+    /// Returns true if the prefab is still being loaded.
+    #[inline]
+    pub fn is_prefab_loading(&self, handle: PrefabHandle) -> bool {
+        self.prefabs.is_loading(handle)
+    }
+
+    /// Blocks until the prefab and its meshes are loaded. Returns `None` if any of
+    /// them has failed to load.
     pub fn prefab(&self, handle: PrefabHandle) -> Option<Arc<Prefab>> {
-        self.prefabs
+        let prefab = self
+            .prefabs
             .wait_until(handle)
             .ok()
-            .and_then(|_| self.prefabs.get(handle, |v| v.clone()))
+            .and_then(|_| self.prefabs.get(handle, |v| v.clone()))?;
+
+        for (&mesh, uuid) in prefab.meshes.iter().zip(&prefab.universe_meshes) {
+            if self.video.mesh_aabb(mesh).is_none() {
+                warn!("[WorldResources] {:?} has lost its mesh {}.", handle, uuid);
+                return None;
+            }
+        }
+
+        Some(prefab)
     }
 
     #[inline]
     pub fn delete_prefab(&self, handle: PrefabHandle) {
         self.prefabs.delete(handle);
     }
+
+    #[inline]
+    pub fn retain_prefab(&self, handle: PrefabHandle, holder: &'static str) -> bool {
+        self.prefabs.retain_by(handle, holder)
+    }
+
+    #[inline]
+    pub fn release_prefab(&self, handle: PrefabHandle, holder: &'static str) {
+        self.prefabs.release_by(handle, holder);
+    }
+
+    #[inline]
+    pub fn prefab_holders(&self, handle: PrefabHandle) -> Vec<(&'static str, u32)> {
+        self.prefabs.holders(handle)
+    }
 }
@@ -0,0 +1,153 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::application::{Engine, Settings};
+use crayon::math;
+use crayon_3d::prelude::*;
+use crayon_3d::renderers::DrawOrder;
+
+#[test]
+fn exposure() {
+    let params = PhysicalParams::default();
+    assert!((params.ev100() - 14.643856).abs() < 1e-4);
+
+    let mut camera = Camera::default();
+    assert_eq!(camera.exposure(), 1.0);
+
+    camera.set_physical(params, 1.5);
+    assert!((camera.exposure() - params.exposure()).abs() < 1e-9);
+
+    // Doubles the shutter speed to gain one stop.
+    let mut slower = params;
+    slower.shutter_speed *= 2.0;
+    assert!((slower.ev100() - params.ev100() + 1.0).abs() < 1e-4);
+    assert!((slower.exposure() - params.exposure() * 2.0).abs() < 1e-9);
+
+    camera.set_physical(None, 1.5);
+    assert_eq!(camera.exposure(), 1.0);
+}
+
+#[test]
+fn gate_fit() {
+    let mut params = PhysicalParams::default();
+    params.focal_length = 12.0;
+
+    let deg = |v: math::Rad<f32>| math::Deg::from(v).0;
+
+    // Sensor aspect matches the viewport.
+    params.gate_fit = GateFit::Vertical;
+    let fovy = deg(params.fovy(1.5));
+    assert!((fovy - 90.0).abs() < 1e-3);
+
+    for &fit in &[GateFit::Horizontal, GateFit::Fill, GateFit::Overscan] {
+        params.gate_fit = fit;
+        assert!((deg(params.fovy(1.5)) - fovy).abs() < 1e-3);
+    }
+
+    // A wider viewport crops the sensor vertically with fill, and shows more
+    // horizontally with overscan.
+    params.gate_fit = GateFit::Fill;
+    assert!(deg(params.fovy(3.0)) < fovy);
+    params.gate_fit = GateFit::Overscan;
+    assert!((deg(params.fovy(3.0)) - fovy).abs() < 1e-3);
+
+    let mut camera = Camera::default();
+    camera.set_clip_plane(0.5, 50.0);
+    camera.set_physical(params, 3.0);
+
+    match camera.projection() {
+        math::Projection::Perspective {
+            fovy,
+            aspect,
+            near,
+            far,
+        } => {
+            assert!((deg(fovy) - 90.0).abs() < 1e-3);
+            assert_eq!(aspect, 3.0);
+            assert_eq!(near, 0.5);
+            assert_eq!(far, 50.0);
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn shake() {
+    use crayon::math::{InnerSpace, Zero};
+
+    let mut shake = CameraShake::new(7);
+    shake.max_offset = math::Vector3::new(1.0, 1.0, 1.0);
+
+    // Stays still without trauma.
+    let offset = shake.advance(0.1);
+    assert_eq!(offset.position, math::Vector3::zero());
+
+    shake.add_trauma(0.5);
+    shake.add_trauma(0.8);
+    assert_eq!(shake.trauma(), 1.0);
+
+    let mut moved = false;
+    for _ in 0..10 {
+        let offset = shake.advance(1.0 / 60.0);
+        assert!(offset.position.x.abs() <= 1.0);
+        assert!(offset.position.y.abs() <= 1.0);
+        assert!(offset.position.z.abs() <= 1.0);
+        assert!((offset.rotation.magnitude() - 1.0).abs() < 1e-4);
+        moved |= offset.position != math::Vector3::zero();
+    }
+
+    assert!(moved);
+    assert!(shake.trauma() < 1.0);
+
+    // The same seed produces the same shake.
+    let mut a = CameraShake::new(3);
+    let mut b = CameraShake::new(3);
+    a.add_trauma(1.0);
+    b.add_trauma(1.0);
+    for _ in 0..10 {
+        assert_eq!(a.advance(0.05).rotation, b.advance(0.05).rotation);
+    }
+
+    // Trauma decays to zero.
+    shake.advance(10.0);
+    assert_eq!(shake.trauma(), 0.0);
+    assert_eq!(shake.advance(0.1).position, math::Vector3::zero());
+}
+
+#[test]
+fn depth_prepass() {
+    let mut settings = Settings::default();
+    settings.headless = true;
+    let mut engine = Engine::new_with(&settings).unwrap();
+    let res = WorldResources::new(&mut engine).unwrap();
+    let renderer = SimpleRenderer::new(engine.context(), res.shared()).unwrap();
+
+    let mut camera = Camera::default();
+    let mut mesh = MeshRenderer::default();
+    let passes = renderer.draw_orders(&camera, &mesh);
+    assert_eq!(passes.len(), 1);
+    assert!(passes[0].0.queue().is_some());
+
+    // Opaque objects write depth ahead with the depth-only shader.
+    camera.set_depth_prepass(true);
+    let passes = renderer.draw_orders(&camera, &mesh);
+    assert_eq!(passes.len(), 2);
+
+    let (main, prepass) = (passes[0], passes[1]);
+    assert_eq!(prepass.0.queue(), None);
+    assert_eq!(prepass.1, renderer.depth_shader());
+    assert_ne!(main.1, renderer.depth_shader());
+    assert!(prepass.0 < main.0);
+
+    // Even the farthest pre-pass draw sorts before the main pass.
+    let nearest = DrawOrder::new(RenderQueue::Opaque, 0, main.1);
+    assert!(DrawOrder::prepass(::std::u32::MAX, prepass.1) < nearest);
+
+    // Alpha-tested and blended objects can not write depth ahead.
+    for &queue in &[RenderQueue::AlphaTest, RenderQueue::Transparent] {
+        mesh.queue = queue;
+        let passes = renderer.draw_orders(&camera, &mesh);
+        assert_eq!(passes.len(), 1);
+        assert_eq!(passes[0].0.queue(), Some(queue));
+    }
+}
@@ -0,0 +1,147 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::math;
+use crayon::math::InnerSpace;
+use crayon::utils::handle_pool::HandlePool;
+
+use crayon_3d::collision::*;
+use crayon_3d::prelude::*;
+
+fn v(x: f32, y: f32, z: f32) -> math::Vector3<f32> {
+    math::Vector3::new(x, y, z)
+}
+
+fn near(lhs: math::Vector3<f32>, rhs: math::Vector3<f32>) -> bool {
+    (lhs - rhs).magnitude() < 1e-2
+}
+
+// A floor of 20x20 at y = 0, and a wall at x = 5.
+fn testbed(entities: &mut HandlePool<Entity>) -> (CollisionWorld, Entity, Entity) {
+    let mut world = CollisionWorld::new();
+    let quad = [
+        v(-1.0, 0.0, -1.0),
+        v(1.0, 0.0, -1.0),
+        v(1.0, 0.0, 1.0),
+        v(-1.0, 0.0, 1.0),
+    ];
+    let indices = [0, 1, 2, 0, 2, 3];
+
+    let floor = entities.create();
+    let mut transform = Transform::default();
+    transform.scale = 10.0;
+    world.add_mesh(floor, transform, &quad, &indices).unwrap();
+
+    let wall = entities.create();
+    let mut transform = Transform::default();
+    transform.position = v(5.0, 0.0, 0.0);
+    transform.rotation = math::Quaternion::from(math::Euler::new(
+        math::Deg(0.0),
+        math::Deg(0.0),
+        math::Deg(90.0),
+    ));
+    world.add_mesh(wall, transform, &quad, &indices).unwrap();
+
+    (world, floor, wall)
+}
+
+#[test]
+fn sweep_meshes() {
+    let mut entities = HandlePool::new();
+    let (world, floor, wall) = testbed(&mut entities);
+
+    let shapes = [
+        (Shape::Sphere(0.5), 0.5),
+        (Shape::Capsule(0.5, 1.0), 1.5),
+        (Shape::Aabb(v(0.5, 0.25, 0.5)), 0.25),
+    ];
+
+    for &(shape, height) in &shapes {
+        let contact = world.sweep(shape, v(0.0, 5.0, 0.0), v(0.0, -5.0, 0.0), None);
+        let contact = contact.unwrap();
+        assert_eq!(contact.entity, floor);
+        assert!(near(contact.position, v(0.0, height, 0.0)), "{:?}", contact);
+        assert!(near(contact.normal, v(0.0, 1.0, 0.0)), "{:?}", contact);
+    }
+
+    // Fast and thin bullets do not tunnel through walls.
+    let contact = world.sweep(
+        Shape::Sphere(0.01),
+        v(0.0, 0.5, 0.0),
+        v(100.0, 0.5, 0.0),
+        None,
+    );
+    let contact = contact.unwrap();
+    assert_eq!(contact.entity, wall);
+    assert!(near(contact.position, v(4.99, 0.5, 0.0)));
+    assert!(near(contact.normal, v(-1.0, 0.0, 0.0)));
+
+    // Grazing motions are not skipped either.
+    let contact = world.sweep(
+        Shape::Sphere(0.5),
+        v(0.0, 1.0, -9.0),
+        v(0.0, 0.3, 9.0),
+        None,
+    );
+    let contact = contact.unwrap();
+    assert_eq!(contact.entity, floor);
+    assert!((contact.position.y - 0.5).abs() < 1e-2);
+
+    // Misses everything.
+    let contact = world.sweep(Shape::Sphere(0.5), v(0.0, 5.0, 0.0), v(0.0, 1.0, 0.0), None);
+    assert!(contact.is_none());
+
+    // Starts inside.
+    let contact = world.sweep(Shape::Sphere(0.5), v(0.0, 0.2, 0.0), v(0.0, 5.0, 0.0), None);
+    assert_eq!(contact.unwrap().toi, 0.0);
+}
+
+#[test]
+fn colliders() {
+    let mut entities = HandlePool::new();
+    let (mut world, floor, wall) = testbed(&mut entities);
+
+    let player = entities.create();
+    let monster = entities.create();
+    world.add(player, Shape::Capsule(0.5, 0.5), v(0.0, 1.1, 0.0));
+    world.add(monster, Shape::Aabb(v(0.5, 0.5, 0.5)), v(3.0, 2.0, 0.0));
+
+    assert_eq!(
+        world.overlap(Shape::Sphere(0.5), v(0.0, 1.0, 0.0), Some(player)),
+        vec![]
+    );
+    assert_eq!(
+        world.overlap(Shape::Sphere(1.5), v(0.0, 1.0, 0.0), Some(player)),
+        vec![floor]
+    );
+    assert_eq!(
+        world.overlap(Shape::Sphere(1.5), v(0.0, 1.0, 0.0), None),
+        vec![floor, player]
+    );
+
+    // The player walks into the monster, its own collider is ignored.
+    let (shape, position) = world.get(player).unwrap();
+    let contact = world.sweep(shape, position, position + v(5.0, 0.0, 0.0), Some(player));
+    let contact = contact.unwrap();
+    assert_eq!(contact.entity, monster);
+    assert!(near(contact.position, v(2.0, 1.1, 0.0)));
+    assert!(near(contact.normal, v(-1.0, 0.0, 0.0)));
+
+    // Slides above the floor until it reaches the wall.
+    world.set_position(monster, v(3.0, 5.0, 0.0));
+    let contact = world.sweep(shape, position, position + v(5.0, 0.0, 0.0), Some(player));
+    let contact = contact.unwrap();
+    assert_eq!(contact.entity, wall);
+    assert!(near(contact.position, v(4.5, 1.1, 0.0)));
+
+    world.remove(monster);
+    assert!(world.get(monster).is_none());
+    assert!(world
+        .add_mesh(
+            monster,
+            Transform::default(),
+            &[v(0.0, 0.0, 0.0)],
+            &[0, 0, 1]
+        )
+        .is_err());
+}
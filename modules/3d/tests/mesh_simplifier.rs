@@ -0,0 +1,132 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::video::assets::mesh::*;
+use crayon::video::assets::shader::Attribute;
+use crayon_3d::assets::mesh_simplifier::*;
+
+fn layout() -> VertexLayout {
+    VertexLayout::build()
+        .with(Attribute::Position, VertexFormat::Float, 3, false)
+        .with(Attribute::Texcoord0, VertexFormat::Float, 2, false)
+        .finish()
+}
+
+fn grid(n: usize) -> (MeshParams, MeshData) {
+    let mut verts: Vec<[f32; 5]> = Vec::new();
+    for z in 0..(n + 1) {
+        for x in 0..(n + 1) {
+            let (u, v) = (x as f32 / n as f32, z as f32 / n as f32);
+            verts.push([u, 0.0, v, u, v]);
+        }
+    }
+
+    let mut idxes: Vec<u16> = Vec::new();
+    for z in 0..n {
+        for x in 0..n {
+            let i = (z * (n + 1) + x) as u16;
+            let j = i + (n + 1) as u16;
+            idxes.extend_from_slice(&[i, j, i + 1, i + 1, j, j + 1]);
+        }
+    }
+
+    let mut params = MeshParams::default();
+    params.layout = layout();
+    params.num_verts = verts.len();
+    params.num_idxes = idxes.len();
+
+    let data = MeshData {
+        vptr: IndexFormat::encode(&verts).into(),
+        iptr: IndexFormat::encode(&idxes).into(),
+    };
+
+    (params, data)
+}
+
+fn vertices(params: &MeshParams, data: &MeshData) -> Vec<[f32; 5]> {
+    let stride = params.layout.stride() as usize;
+    (0..params.num_verts)
+        .map(|i| unsafe {
+            let ptr = data.vptr[(i * stride)..].as_ptr() as *const [f32; 5];
+            ::std::ptr::read_unaligned(ptr)
+        }).collect()
+}
+
+#[test]
+fn simplify_plane() {
+    let (params, data) = grid(8);
+    let (lod, lod_data) = simplify(&params, &data, 0.25).unwrap();
+
+    assert!(lod.validate(Some(&lod_data)).is_ok());
+    assert!(lod.num_idxes / 3 <= 32);
+    assert!(lod.num_idxes > 0);
+    assert_eq!(lod_data.iptr.len(), lod.num_idxes * 2);
+
+    // Vertices are kept as they are, and the borders are never moved.
+    let src = vertices(&params, &data);
+    let dst = vertices(&lod, &lod_data);
+    assert!(dst.iter().all(|v| src.contains(v)));
+
+    let borders = src
+        .iter()
+        .filter(|v| v[0] == 0.0 || v[0] == 1.0 || v[2] == 0.0 || v[2] == 1.0);
+
+    for v in borders {
+        assert!(dst.contains(v));
+    }
+}
+
+#[test]
+fn seams() {
+    // Two quads that share an edge with different texcoords.
+    let verts: [[f32; 5]; 8] = [
+        [0.0, 0.0, 0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0, 1.0, 0.0],
+        [1.0, 0.0, 1.0, 1.0, 1.0],
+        [0.0, 0.0, 1.0, 0.0, 1.0],
+        [1.0, 0.0, 0.0, 0.0, 0.0],
+        [2.0, 0.0, 0.0, 1.0, 0.0],
+        [2.0, 0.0, 1.0, 1.0, 1.0],
+        [1.0, 0.0, 1.0, 0.0, 1.0],
+    ];
+
+    let idxes: [u32; 12] = [0, 3, 1, 1, 3, 2, 4, 7, 5, 5, 7, 6];
+
+    let mut params = MeshParams::default();
+    params.layout = layout();
+    params.index_format = IndexFormat::U32;
+    params.num_verts = verts.len();
+    params.num_idxes = idxes.len();
+    params.sub_mesh_offsets = vec![0, 6];
+
+    let data = MeshData {
+        vptr: IndexFormat::encode(&verts).into(),
+        iptr: IndexFormat::encode(&idxes).into(),
+    };
+
+    let (lod, lod_data) = simplify(&params, &data, 0.0).unwrap();
+    assert_eq!(lod.num_verts, 8);
+    assert_eq!(lod.num_idxes, 12);
+    assert_eq!(lod.sub_mesh_offsets, vec![0, 6]);
+    assert_eq!(&lod_data.iptr[..], &data.iptr[..]);
+}
+
+#[test]
+fn lods() {
+    let (params, data) = grid(8);
+
+    let lods = generate_lods(&params, &data, &[0.5, 0.25]).unwrap();
+    assert_eq!(lods.len(), 2);
+    assert!(lods[0].0.num_idxes / 3 <= 64);
+    assert!(lods[1].0.num_idxes <= lods[0].0.num_idxes);
+
+    assert!(generate_lods(&params, &data, &[0.25, 0.5]).is_err());
+
+    let mut lines = params.clone();
+    lines.primitive = MeshPrimitive::Lines;
+    assert!(simplify(&lines, &data, 0.5).is_err());
+
+    let mut broken = params.clone();
+    broken.sub_mesh_offsets = vec![0, broken.num_idxes + 3];
+    assert!(simplify(&broken, &data, 0.5).is_err());
+}
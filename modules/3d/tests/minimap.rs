@@ -0,0 +1,100 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::math;
+use crayon::utils::handle_pool::HandlePool;
+use crayon::video::prelude::*;
+
+use crayon_3d::minimap::*;
+use crayon_3d::prelude::*;
+use crayon_3d::renderers::{Lit, Renderable, Renderer};
+use crayon_3d::tags::Tags;
+
+#[derive(Default)]
+struct Recorder(Vec<MeshHandle>, Option<Camera>);
+
+impl Renderer for Recorder {
+    fn submit(&mut self, camera: &Camera, _: &[Lit], meshes: &[MeshRenderer]) {
+        self.0 = meshes.iter().map(|v| v.mesh).collect();
+        self.1 = Some(*camera);
+    }
+}
+
+fn mesh(video: &VideoSystemShared, size: f32) -> MeshHandle {
+    let mut params = MeshParams::default();
+    params.aabb = math::Aabb3::new(
+        math::Point3::new(-size * 0.5, 0.0, -size * 0.5),
+        math::Point3::new(size * 0.5, size, size * 0.5),
+    );
+
+    video.create_mesh(params, None).unwrap()
+}
+
+#[test]
+fn uv() {
+    let video = VideoSystem::headless(None).shared();
+    let mut minimap = Minimap::new(video, 128).unwrap();
+    minimap.center = math::Vector2::new(10.0, -10.0);
+    minimap.extent = 20.0;
+
+    let uv = minimap.world_to_uv([10.0, 5.0, -10.0]);
+    assert_eq!(uv, math::Vector2::new(0.5, 0.5));
+
+    let uv = minimap.world_to_uv([-10.0, 0.0, 10.0]);
+    assert_eq!(uv, math::Vector2::new(0.0, 1.0));
+    assert!(minimap.contains([-10.0, 0.0, 10.0]));
+    assert!(!minimap.contains([31.0, 0.0, 0.0]));
+
+    let p = minimap.uv_to_world(math::Vector2::new(0.75, 0.25));
+    assert_eq!(p, math::Vector2::new(20.0, -20.0));
+    assert_eq!(minimap.units_per_pixel(), 40.0 / 128.0);
+}
+
+#[test]
+fn icons() {
+    let video = VideoSystem::headless(None).shared();
+    let mut minimap = Minimap::new(video.clone(), 100).unwrap();
+    minimap.extent = 50.0;
+    minimap.icon_threshold = 4.0;
+
+    let mut entities: HandlePool<Entity> = HandlePool::new();
+    let mut scene = SceneGraph::new();
+    let mut tags = Tags::new();
+    let mut renderables = Renderable::new();
+
+    let house = mesh(&video, 10.0);
+    let crate_ = mesh(&video, 1.0);
+    let arrow = mesh(&video, 1.0);
+    let tree = mesh(&video, 2.0);
+
+    let mut spawn = |mesh, tag: &str| {
+        let ent = entities.create();
+        scene.add(ent);
+        tags.tag(ent, tag);
+        renderables.add_mesh(ent, mesh);
+        ent
+    };
+
+    spawn(house, "building");
+    let player = spawn(crate_, "player");
+    spawn(tree, "tree");
+
+    minimap.watch("building");
+    minimap.set_icon("player", MinimapIcon::new(arrow, 8.0));
+
+    let mut recorder = Recorder::default();
+    minimap.draw(&mut recorder, &scene, &tags, &renderables);
+    assert_eq!(recorder.0, vec![house, arrow]);
+
+    let camera = recorder.1.unwrap();
+    assert_eq!(camera.surface(), Some(minimap.camera().surface().unwrap()));
+
+    // Large objects are drawn as they are, even if there is an icon of their tag.
+    scene.set_scale(player, 10.0);
+    minimap.draw(&mut recorder, &scene, &tags, &renderables);
+    assert_eq!(recorder.0, vec![house, crate_]);
+
+    minimap.unwatch("player");
+    minimap.draw(&mut recorder, &scene, &tags, &renderables);
+    assert_eq!(recorder.0, vec![house]);
+}
@@ -0,0 +1,85 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::math;
+use crayon::math::Spline;
+use crayon::utils::handle_pool::HandlePool;
+
+use crayon_3d::path::*;
+use crayon_3d::prelude::*;
+
+fn line() -> Spline {
+    let points = vec![
+        math::Vector3::new(0.0, 0.0, 0.0),
+        math::Vector3::new(1.0, 0.0, 0.0),
+        math::Vector3::new(3.0, 0.0, 0.0),
+    ];
+
+    Spline::new(points, false)
+}
+
+#[test]
+fn waypoints() {
+    let mut follower = PathFollower::new(line(), 1.0);
+    assert!((follower.length() - 3.0).abs() < 1e-3);
+
+    follower.advance(0.5);
+    assert!((follower.position().x - 0.5).abs() < 1e-2);
+    assert!(follower.reached().is_empty());
+
+    follower.advance(1.0);
+    assert_eq!(follower.reached(), &[1]);
+
+    follower.advance(10.0);
+    assert_eq!(follower.reached(), &[2]);
+    assert!(follower.is_finished());
+
+    follower.advance(1.0);
+    assert!(follower.reached().is_empty());
+}
+
+#[test]
+fn wraps() {
+    let mut follower = PathFollower::new(line(), 1.0);
+    follower.wrap = PathWrap::PingPong;
+    follower.advance(4.0);
+    assert_eq!(follower.reached(), &[1, 2]);
+    assert!((follower.distance() - 2.0).abs() < 1e-2);
+    assert!(follower.direction().x < 0.0);
+
+    follower.advance(2.5);
+    assert_eq!(follower.reached(), &[1, 0]);
+    assert!(!follower.is_finished());
+
+    let points = vec![
+        math::Vector3::new(0.0, 0.0, 0.0),
+        math::Vector3::new(1.0, 0.0, 0.0),
+        math::Vector3::new(1.0, 0.0, 1.0),
+        math::Vector3::new(0.0, 0.0, 1.0),
+    ];
+
+    let mut follower = PathFollower::new(Spline::new(points, true), 1.0);
+    follower.wrap = PathWrap::Loop;
+    let length = follower.length();
+    follower.advance(length * 1.4);
+    assert_eq!(follower.reached(), &[1, 2, 3, 0, 1]);
+}
+
+#[test]
+fn update() {
+    let mut entities: HandlePool<Entity> = HandlePool::new();
+    let mut scene = SceneGraph::new();
+    let e1 = entities.create();
+    scene.add(e1);
+
+    let mut follower = PathFollower::new(line(), 1.0);
+    follower.orientation = PathOrientation::Tangent;
+    follower.update(&mut scene, e1, 2.0);
+
+    let position = scene.position(e1).unwrap();
+    assert!((position.x - 2.0).abs() < 1e-2);
+
+    // Faces the direction of travel.
+    let forward = scene.rotation(e1).unwrap() * math::Vector3::new(0.0, 0.0, 1.0);
+    assert!((forward.x - 1.0).abs() < 1e-3);
+}
@@ -0,0 +1,80 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::application::settings::EngineParams;
+use crayon::application::time::TimeSystemShared;
+use crayon::math;
+use crayon::utils::handle_pool::HandlePool;
+
+use crayon_3d::photo::*;
+use crayon_3d::prelude::*;
+
+fn approx(lhs: math::Vector3<f32>, rhs: math::Vector3<f32>) -> bool {
+    (lhs.x - rhs.x).abs() < 1e-4 && (lhs.y - rhs.y).abs() < 1e-4 && (lhs.z - rhs.z).abs() < 1e-4
+}
+
+#[test]
+fn fly() {
+    let mut camera = FlyCamera::new();
+    camera.speed = 2.0;
+
+    let mut transform = Transform::default();
+    let mut controls = FlyInput::default();
+    controls.movement = math::Vector3::new(0.0, 0.0, 1.0);
+    camera.advance(&mut transform, controls, 0.5);
+    assert!(approx(transform.position, math::Vector3::new(0.0, 0.0, 1.0)));
+
+    // Turns right by 90 degrees, and moves along the positive x-axis.
+    controls.look = math::Vector2::new(90.0 / camera.sensitivity, 0.0);
+    controls.boost = true;
+    camera.advance(&mut transform, controls, 0.5);
+    assert!(approx(transform.forward(), math::Vector3::new(1.0, 0.0, 0.0)));
+    assert!(approx(transform.position, math::Vector3::new(4.0, 0.0, 1.0)));
+
+    // The pitch is clamped.
+    controls = FlyInput::default();
+    controls.look = math::Vector2::new(0.0, 1000.0);
+    camera.advance(&mut transform, controls, 0.0);
+    assert_eq!(camera.angles().1, 89.0);
+    assert!(transform.forward().y > 0.99);
+
+    camera.reset(&Transform::default());
+    assert_eq!(camera.angles(), (0.0, 0.0));
+}
+
+#[test]
+fn enter_and_exit() {
+    let mut entities: HandlePool<Entity> = HandlePool::new();
+    let mut scene = SceneGraph::new();
+    let time = TimeSystemShared::new(EngineParams::default());
+
+    let camera = entities.create();
+    scene.add(camera);
+    scene.set_position(camera, [1.0, 2.0, 3.0]);
+
+    let mut photo = PhotoMode::new();
+    photo.update_with(&mut scene, FlyInput::default(), 1.0);
+    assert!(!photo.is_active());
+    assert!(!photo.enter(&time, &scene, entities.create()));
+
+    assert!(photo.enter(&time, &scene, camera));
+    assert!(photo.is_active());
+    assert!(time.is_paused());
+    assert!(!photo.enter(&time, &scene, camera));
+
+    let mut controls = FlyInput::default();
+    controls.movement = math::Vector3::new(0.0, 1.0, 0.0);
+    photo.update_with(&mut scene, controls, 1.0);
+    assert!(approx(scene.position(camera).unwrap(), math::Vector3::new(1.0, 7.0, 3.0)));
+
+    photo.exit(&time, &mut scene);
+    assert!(!photo.is_active());
+    assert!(!time.is_paused());
+    assert!(approx(scene.position(camera).unwrap(), math::Vector3::new(1.0, 2.0, 3.0)));
+
+    // Keeps the game paused if it was paused before.
+    time.set_paused(true);
+    photo.enter(&time, &scene, camera);
+    photo.exit(&time, &mut scene);
+    assert!(time.is_paused());
+}
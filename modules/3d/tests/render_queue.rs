@@ -0,0 +1,102 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::utils::handle::Handle;
+use crayon::video::assets::shader::ShaderHandle;
+
+use crayon_3d::renderers::{DrawOrder, RenderQueue, SortPolicy};
+
+fn shader(index: u32) -> ShaderHandle {
+    Handle::new(index, 1).into()
+}
+
+#[test]
+fn queues() {
+    let queues = [
+        RenderQueue::Opaque,
+        RenderQueue::AlphaTest,
+        RenderQueue::Transparent,
+        RenderQueue::Overlay,
+    ];
+
+    for (i, &v) in queues.iter().enumerate() {
+        assert_eq!(v.index(), i);
+    }
+
+    assert!(queues.windows(2).all(|v| v[0] < v[1]));
+    assert_eq!(RenderQueue::default(), RenderQueue::Opaque);
+
+    assert_eq!(
+        SortPolicy::default_of(RenderQueue::Opaque),
+        SortPolicy::FrontToBack
+    );
+    assert_eq!(
+        SortPolicy::default_of(RenderQueue::AlphaTest),
+        SortPolicy::FrontToBack
+    );
+    assert_eq!(
+        SortPolicy::default_of(RenderQueue::Transparent),
+        SortPolicy::BackToFront
+    );
+    assert_eq!(
+        SortPolicy::default_of(RenderQueue::Overlay),
+        SortPolicy::Unsorted
+    );
+}
+
+#[test]
+fn sort_policies() {
+    let near = 4.0;
+    let far = 400.0;
+
+    let policy = SortPolicy::FrontToBack;
+    assert!(policy.zorder(near) < policy.zorder(far));
+
+    let policy = SortPolicy::BackToFront;
+    assert!(policy.zorder(far) < policy.zorder(near));
+
+    let policy = SortPolicy::Unsorted;
+    assert_eq!(policy.zorder(near), policy.zorder(far));
+
+    // Distances beyond the range of z-order saturate.
+    let policy = SortPolicy::FrontToBack;
+    assert_eq!(policy.zorder(1e20), ::std::u32::MAX);
+    assert_eq!(SortPolicy::BackToFront.zorder(1e20), 0);
+}
+
+#[test]
+fn draw_orders() {
+    let (s0, s1) = (shader(0), shader(1));
+
+    // Queues come first, then z-orders, then shaders.
+    let opaque = DrawOrder::new(RenderQueue::Opaque, 8, s1);
+    assert!(opaque < DrawOrder::new(RenderQueue::Opaque, 9, s0));
+    assert!(opaque > DrawOrder::new(RenderQueue::Opaque, 8, s0));
+    assert!(opaque < DrawOrder::new(RenderQueue::AlphaTest, 0, s0));
+    assert!(DrawOrder::prepass(::std::u32::MAX, s1) < DrawOrder::new(RenderQueue::Opaque, 0, s0));
+
+    assert_eq!(opaque.queue(), Some(RenderQueue::Opaque));
+    assert_eq!(opaque.zorder(), 8);
+    assert_eq!(DrawOrder::prepass(8, s0).queue(), None);
+
+    // The largest z-order does not overflow into the bits of queue.
+    let max = ::std::u32::MAX;
+    let queues = [
+        RenderQueue::Opaque,
+        RenderQueue::AlphaTest,
+        RenderQueue::Transparent,
+        RenderQueue::Overlay,
+    ];
+
+    for v in queues.windows(2) {
+        let last = DrawOrder::new(v[0], max, shader(0xFF_FFFF));
+        assert_eq!(last.queue(), Some(v[0]));
+        assert_eq!(last.zorder(), max);
+        assert!(last < DrawOrder::new(v[1], 0, s0));
+    }
+
+    // Shaders beyond 24 bits do not overflow into the bits of z-order.
+    let order = DrawOrder::new(RenderQueue::Transparent, 7, shader(0x100_0000));
+    assert_eq!(order.zorder(), 7);
+    assert_eq!(order.queue(), Some(RenderQueue::Transparent));
+}
@@ -281,3 +281,40 @@ fn random_iteration() {
     let len = testbed.descendants(constructed[0]).count();
     assert_eq!(len, 254);
 }
+
+#[test]
+pub fn enable() {
+    let mut testbed = Testbed::new();
+    let e1 = testbed.create();
+    let e2 = testbed.create();
+    let e3 = testbed.create();
+    let e4 = testbed.create();
+
+    testbed.set_parent(e2, e1, false).unwrap();
+    testbed.set_parent(e3, e2, false).unwrap();
+
+    assert!(testbed.is_enabled(e3));
+    assert!(testbed.is_enabled_in_hierarchy(e3));
+
+    testbed.set_enabled(e1, false);
+    assert!(!testbed.is_enabled(e1));
+    assert!(testbed.is_enabled(e2));
+    assert!(testbed.is_enabled(e3));
+    assert!(!testbed.is_enabled_in_hierarchy(e2));
+    assert!(!testbed.is_enabled_in_hierarchy(e3));
+    assert!(testbed.is_enabled_in_hierarchy(e4));
+
+    testbed.remove_from_parent(e2, false).unwrap();
+    assert!(testbed.is_enabled_in_hierarchy(e2));
+    assert!(testbed.is_enabled_in_hierarchy(e3));
+
+    testbed.set_parent(e2, e1, false).unwrap();
+    testbed.set_enabled(e1, true);
+    testbed.set_enabled(e3, false);
+    assert!(testbed.is_enabled_in_hierarchy(e2));
+    assert!(!testbed.is_enabled_in_hierarchy(e3));
+
+    testbed.remove(e3);
+    assert!(!testbed.is_enabled(e3));
+    assert!(!testbed.is_enabled_in_hierarchy(e3));
+}
@@ -0,0 +1,125 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::application::{Engine, Settings};
+use crayon::math;
+use crayon::utils::handle_pool::HandlePool;
+
+use crayon_3d::prelude::*;
+
+struct Testbed {
+    renderer: SimpleRenderer,
+    entities: HandlePool<Entity>,
+    _res: WorldResources,
+    _engine: Engine,
+}
+
+impl Testbed {
+    fn new() -> Self {
+        let mut settings = Settings::default();
+        settings.headless = true;
+        let mut engine = Engine::new_with(&settings).unwrap();
+        let res = WorldResources::new(&mut engine).unwrap();
+        let renderer = SimpleRenderer::new(engine.context(), res.shared()).unwrap();
+
+        Testbed {
+            renderer: renderer,
+            entities: HandlePool::new(),
+            _res: res,
+            _engine: engine,
+        }
+    }
+}
+
+#[test]
+fn outlines() {
+    let mut testbed = Testbed::new();
+    let e1 = testbed.entities.create();
+    let e2 = testbed.entities.create();
+    let renderer = &mut testbed.renderer;
+
+    assert!(renderer.outline(e1).is_none());
+    assert!(renderer.outline_mut(e1).is_none());
+
+    let mut outline = SimpleOutline::default();
+    outline.width = 0.1;
+    assert!(renderer.set_outline(e1, outline).is_none());
+    assert_eq!(renderer.outline(e1).unwrap().width, 0.1);
+    assert!(renderer.outline(e2).is_none());
+
+    // Updates in place, or replaces the previous settings.
+    renderer.outline_mut(e1).unwrap().color = math::Color::red();
+    assert_eq!(renderer.outline(e1).unwrap().color, math::Color::red());
+
+    outline.width = 0.2;
+    let prev = renderer.set_outline(e1, outline).unwrap();
+    assert_eq!(prev.width, 0.1);
+    assert_eq!(prev.color, math::Color::red());
+    assert_eq!(renderer.outline(e1).unwrap().width, 0.2);
+
+    renderer.set_outline(e2, outline);
+    renderer.remove_outline(e1);
+    assert!(renderer.outline(e1).is_none());
+    assert!(renderer.outline_mut(e1).is_none());
+    assert!(renderer.outline(e2).is_some());
+
+    // Removing the entity drops its outline too.
+    renderer.remove(e2);
+    assert!(renderer.outline(e2).is_none());
+
+    // Removing it again takes no effect.
+    renderer.remove_outline(e1);
+    assert!(renderer.outline(e1).is_none());
+}
+
+#[test]
+fn property_blocks() {
+    let mut testbed = Testbed::new();
+    let e1 = testbed.entities.create();
+    let e2 = testbed.entities.create();
+    let e3 = testbed.entities.create();
+    let renderer = &mut testbed.renderer;
+
+    // Entities without material or property block fall back to the defaults.
+    let (mat, emission) = renderer.shading(e3);
+    let default = SimpleMaterial::default();
+    assert_eq!(mat.ambient, default.ambient);
+    assert_eq!(mat.diffuse, default.diffuse);
+    assert_eq!(emission, math::Color::black());
+
+    let mut material = SimpleMaterial::default();
+    material.ambient = math::Color::new(0.2, 0.4, 0.6, 1.0);
+    material.diffuse = math::Color::new(1.0, 0.5, 0.25, 1.0);
+    material.specular = math::Color::new(0.1, 0.1, 0.1, 1.0);
+    renderer.add(e1, material);
+    renderer.add(e2, material);
+
+    let mut block = SimplePropertyBlock::default();
+    block.tint = math::Color::new(0.5, 0.5, 2.0, 1.0);
+    block.emission = math::Color::new(0.0, 1.0, 0.0, 1.0);
+    assert!(renderer.set_property_block(e1, block).is_none());
+
+    // The block overrides its own instance only.
+    let (mat, emission) = renderer.shading(e1);
+    assert_eq!(mat.ambient, math::Color::new(0.1, 0.2, 1.2, 1.0));
+    assert_eq!(mat.diffuse, math::Color::new(0.5, 0.25, 0.5, 1.0));
+    assert_eq!(mat.specular, material.specular);
+    assert_eq!(emission, block.emission);
+
+    let (mat, emission) = renderer.shading(e2);
+    assert_eq!(mat.ambient, material.ambient);
+    assert_eq!(mat.diffuse, material.diffuse);
+    assert_eq!(emission, math::Color::black());
+
+    // Updates in place, and falls back to the material once removed.
+    renderer.property_block_mut(e1).unwrap().tint = math::Color::white();
+    let (mat, emission) = renderer.shading(e1);
+    assert_eq!(mat.diffuse, material.diffuse);
+    assert_eq!(emission, block.emission);
+
+    renderer.remove_property_block(e1);
+    assert!(renderer.property_block(e1).is_none());
+    let (mat, emission) = renderer.shading(e1);
+    assert_eq!(mat.ambient, material.ambient);
+    assert_eq!(emission, math::Color::black());
+}
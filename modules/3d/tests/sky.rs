@@ -0,0 +1,87 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::math;
+use crayon::math::InnerSpace;
+use crayon::utils::handle_pool::HandlePool;
+
+use crayon_3d::prelude::*;
+use crayon_3d::renderers::Renderable;
+use crayon_3d::sky::*;
+
+fn approx(lhs: math::Vector3<f32>, rhs: math::Vector3<f32>, epsilon: f32) -> bool {
+    (lhs - rhs).magnitude() < epsilon
+}
+
+#[test]
+fn sun_position() {
+    // The sun rises in the east, and culminates at zenith over the equator on equinox.
+    let east = math::Vector3::new(1.0, 0.0, 0.0);
+    assert!(approx(sun_direction(6.0, 79, 0.0), east, 0.02));
+    assert!(approx(sun_direction(12.0, 79, 0.0), math::Vector3::new(0.0, 1.0, 0.0), 0.02));
+    assert!(approx(sun_direction(18.0, 79, 0.0), -east, 0.02));
+    assert!(sun_direction(0.0, 79, 0.0).y < -0.99);
+
+    // It culminates at 53.4 degrees in the south on the summer solstice of latitude 60.
+    let noon = sun_direction(12.0, 171, 60.0);
+    assert!((noon.y.asin().to_degrees() - 53.44).abs() < 0.1);
+    assert!(noon.z < 0.0 && noon.x.abs() < 1e-4);
+
+    // And it never sets near the north pole.
+    assert!(sun_direction(0.0, 171, 80.0).y > 0.0);
+}
+
+#[test]
+fn sky_colors() {
+    let sky = Sky::default();
+    let noon = sky.evaluate(math::Vector3::new(0.0, 1.0, 0.0));
+    assert!(noon.zenith.b > noon.zenith.r);
+    assert!(noon.zenith.b < 1.5);
+    assert!(noon.sun_color.b > 0.7 && noon.sun_intensity > 0.7);
+
+    // The sunlight gets dimmer and redder when the sun is low.
+    let dusk = sky.evaluate(math::Vector3::new(0.0, 0.05, 1.0));
+    assert!(dusk.sun_intensity < noon.sun_intensity);
+    assert_eq!(dusk.sun_color.r, 1.0);
+    assert!(dusk.sun_color.b < 0.5);
+    assert!(dusk.ambient.g < noon.ambient.g);
+
+    let night = sky.evaluate(math::Vector3::new(0.0, -1.0, 0.0));
+    assert_eq!(night.sun_intensity, 0.0);
+    assert!((night.ambient.g - sky.night.g).abs() < 1e-6);
+    assert_eq!(night.zenith, sky.night);
+}
+
+#[test]
+fn day_night_cycle() {
+    let mut entities: HandlePool<Entity> = HandlePool::new();
+    let mut scene = SceneGraph::new();
+    let mut renderables = Renderable::new();
+
+    let sun = entities.create();
+    scene.add(sun);
+
+    let mut time = TimeOfDay::new(6.0);
+    time.day_length = 24.0;
+    let state = time.update(6.0, &mut scene, &mut renderables, sun);
+    assert_eq!(time.hour, 12.0);
+    assert_eq!(state, time.evaluate());
+
+    // The light shines down from the sun.
+    let transform = scene.transform(sun).unwrap();
+    assert!(approx(transform.forward(), -state.sun, 1e-4));
+
+    let lit = *renderables.lit(sun).unwrap();
+    assert!(lit.enable);
+    assert_eq!(lit.intensity, state.sun_intensity);
+    assert_eq!(lit.color, state.sun_color);
+
+    // Wraps into the next day, and turns the light off at night.
+    time.update(13.0, &mut scene, &mut renderables, sun);
+    assert_eq!((time.hour, time.day), (1.0, 80));
+    assert!(!renderables.lit(sun).unwrap().enable);
+
+    time.day_length = 0.0;
+    time.advance(100.0);
+    assert_eq!(time.hour, 1.0);
+}
@@ -0,0 +1,149 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use std::thread;
+use std::time::Duration;
+
+use crayon::application::{Engine, Settings};
+use crayon::bincode;
+use crayon::math;
+use crayon::res::vfs::MemoryFilesystem;
+use crayon::uuid::Uuid;
+
+use crayon_3d::assets::prefab::PrefabNode;
+use crayon_3d::assets::prefab_loader::MAGIC;
+use crayon_3d::prelude::*;
+use crayon_3d::renderers::{Lit, Renderer};
+use crayon_3d::streaming::*;
+
+struct Headless;
+
+impl Renderer for Headless {
+    fn submit(&mut self, _: &Camera, _: &[Lit], _: &[MeshRenderer]) {}
+}
+
+fn sector(x: f32) -> Sector {
+    sector_with(x, Uuid::nil())
+}
+
+fn sector_with(x: f32, prefab: Uuid) -> Sector {
+    Sector {
+        bounds: math::Aabb3::new(
+            math::Point3::new(x, 0.0, 0.0),
+            math::Point3::new(x + 10.0, 10.0, 10.0),
+        ),
+        prefab: prefab,
+    }
+}
+
+// A prefab with one node, which renders the first of `meshes` if there is any.
+fn prefab(meshes: Vec<Uuid>) -> Vec<u8> {
+    let node = PrefabNode {
+        name: "sector".into(),
+        local_transform: Transform::default(),
+        first_child: None,
+        next_sib: None,
+        mesh_renderer: if meshes.is_empty() { None } else { Some(0) },
+    };
+
+    let prefab = Prefab {
+        nodes: vec![node],
+        universe_meshes: meshes,
+        meshes: Vec::new(),
+    };
+
+    let mut bytes = MAGIC.to_vec();
+    bincode::serialize_into(&mut bytes, &prefab).unwrap();
+    bytes
+}
+
+#[test]
+fn plan() {
+    let mut streamer = SectorStreamer::new(20.0, 30.0);
+    assert!(streamer.is_empty());
+
+    let s0 = streamer.add(sector(0.0));
+    let s1 = streamer.add(sector(20.0));
+    let s2 = streamer.add(sector(-20.0));
+    let s3 = streamer.add(sector(100.0));
+    assert_eq!(streamer.len(), 4);
+    assert_eq!(streamer.state(s3), Some(SectorState::Unloaded));
+    assert_eq!(streamer.sector(s1).unwrap().bounds.min.x, 20.0);
+
+    // Nearest sectors come first.
+    let plan = streamer.plan(math::Point3::new(8.0, 5.0, 5.0));
+    assert_eq!(plan.load, vec![s0, s1, s2]);
+    assert!(plan.unload.is_empty());
+
+    let plan = streamer.plan(math::Point3::new(105.0, 5.0, 5.0));
+    assert_eq!(plan.load, vec![s3]);
+
+    let plan = streamer.plan(math::Point3::new(500.0, 5.0, 5.0));
+    assert_eq!(plan, SectorPlan::default());
+}
+
+#[test]
+fn advance() {
+    let mut settings = Settings::default();
+    settings.headless = true;
+    let mut engine = Engine::new_with(&settings).unwrap();
+
+    let uuids: Vec<_> = (0..4).map(|i| Uuid::from_bytes([i + 1; 16])).collect();
+    let mut fs = MemoryFilesystem::new();
+    fs.add_resource("loaded.prefab", uuids[0], prefab(Vec::new()));
+    fs.add_resource("broken.prefab", uuids[1], &b"broken"[..]);
+    fs.add_resource("lost.prefab", uuids[2], prefab(vec![uuids[3]]));
+    fs.add_resource("lost.obj", uuids[3], &b"broken"[..]);
+    engine.res.mount("sectors", fs).unwrap();
+
+    let res = WorldResources::new(&mut engine).unwrap();
+    let mut world = World::new(res.shared(), Headless);
+
+    let mut streamer = SectorStreamer::new(20.0, 30.0);
+    streamer.set_max_loading(4);
+    let loaded = streamer.add(sector_with(0.0, uuids[0]));
+    let broken = streamer.add(sector_with(10.0, uuids[1]));
+    let lost = streamer.add(sector_with(-10.0, uuids[2]));
+
+    let origin = math::Point3::new(5.0, 5.0, 5.0);
+    streamer.advance(&mut world, origin).unwrap();
+    for i in 0..3 {
+        match streamer.state(i) {
+            Some(SectorState::Loading(_)) => {}
+            v => panic!("Sector {} should be loading, but it's {:?}.", i, v),
+        }
+    }
+
+    for _ in 0..1000 {
+        let loading = (0..3).any(|i| match streamer.state(i) {
+            Some(SectorState::Loading(_)) => true,
+            _ => false,
+        });
+
+        if !loading {
+            break;
+        }
+
+        thread::sleep(Duration::from_millis(5));
+        streamer.advance(&mut world, origin).unwrap();
+    }
+
+    // The prefab of `lost` loads, but it can't be instantiated without its mesh.
+    let ent = match streamer.state(loaded) {
+        Some(SectorState::Loaded(_, ent)) => ent,
+        v => panic!("Sector should be loaded, but it's {:?}.", v),
+    };
+
+    assert!(world.is_alive(ent));
+    assert_eq!(world.find("sector"), Some(ent));
+    assert_eq!(streamer.state(broken), Some(SectorState::Failed));
+    assert_eq!(streamer.state(lost), Some(SectorState::Failed));
+
+    // Failed sectors are not retried, and loaded ones are removed out of range.
+    streamer
+        .advance(&mut world, math::Point3::new(500.0, 5.0, 5.0))
+        .unwrap();
+    assert_eq!(streamer.state(loaded), Some(SectorState::Unloaded));
+    assert_eq!(streamer.state(broken), Some(SectorState::Failed));
+    assert!(!world.is_alive(ent));
+}
@@ -0,0 +1,80 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::math;
+use crayon::utils::handle_pool::HandlePool;
+
+use crayon_3d::prelude::*;
+use crayon_3d::tags::Tags;
+use crayon_3d::triggers::*;
+
+#[test]
+fn shapes() {
+    let v = |x, y, z| math::Vector3::new(x, y, z);
+
+    let shape = TriggerShape::Box(v(1.0, 2.0, 3.0));
+    assert!(shape.contains(v(-1.0, 2.0, 0.0)));
+    assert!(!shape.contains(v(0.0, 0.0, 3.5)));
+
+    let shape = TriggerShape::Sphere(2.0);
+    assert!(shape.contains(v(0.0, -2.0, 0.0)));
+    assert!(!shape.contains(v(1.5, 1.5, 0.0)));
+
+    let shape = TriggerShape::Capsule(1.0, 2.0);
+    assert!(shape.contains(v(1.0, 2.0, 0.0)));
+    assert!(shape.contains(v(0.0, -2.9, 0.0)));
+    assert!(!shape.contains(v(0.0, 3.1, 0.0)));
+    assert!(!shape.contains(v(1.1, 0.0, 0.0)));
+}
+
+#[test]
+fn events() {
+    let mut entities: HandlePool<Entity> = HandlePool::new();
+    let mut scene = SceneGraph::new();
+    let mut tags = Tags::new();
+    let mut triggers = Triggers::new();
+    let reader = triggers.events().register_reader();
+
+    let door = entities.create();
+    let player = entities.create();
+    let monster = entities.create();
+    for &v in &[door, player, monster] {
+        scene.add(v);
+    }
+
+    scene.set_position(door, [10.0, 0.0, 0.0]);
+    scene.set_scale(door, 2.0);
+    tags.tag(player, "player");
+    triggers.add(door, Trigger::new(TriggerShape::Sphere(1.0), "player"));
+
+    // The volume is scaled with its entity, and only tagged entities are watched.
+    scene.set_position(player, [8.5, 0.0, 0.0]);
+    scene.set_position(monster, [10.0, 0.0, 0.0]);
+    triggers.update(&scene, &tags);
+    let events: Vec<_> = triggers.events().read(reader).cloned().collect();
+    assert_eq!(events, vec![TriggerEvent::Enter(door, player)]);
+    assert!(triggers.overlaps(door, player));
+
+    triggers.update(&scene, &tags);
+    let events: Vec<_> = triggers.events().read(reader).cloned().collect();
+    assert_eq!(events, vec![TriggerEvent::Stay(door, player)]);
+
+    scene.set_position(player, [7.0, 0.0, 0.0]);
+    triggers.update(&scene, &tags);
+    let events: Vec<_> = triggers.events().read(reader).cloned().collect();
+    assert_eq!(events, vec![TriggerEvent::Exit(door, player)]);
+
+    // Disabled entities exit at once.
+    scene.set_position(player, [10.0, 0.0, 0.0]);
+    triggers.update(&scene, &tags);
+    scene.set_enabled(player, false);
+    triggers.update(&scene, &tags);
+    let events: Vec<_> = triggers.events().read(reader).cloned().collect();
+    assert_eq!(
+        events,
+        vec![
+            TriggerEvent::Enter(door, player),
+            TriggerEvent::Exit(door, player),
+        ]
+    );
+}
@@ -0,0 +1,130 @@
+extern crate crayon;
+extern crate crayon_3d;
+
+use crayon::math;
+use crayon::utils::handle_pool::HandlePool;
+use crayon::video::prelude::*;
+
+use crayon_3d::prelude::*;
+use crayon_3d::renderers::{Lit, Renderable, Renderer};
+use crayon_3d::weather::*;
+
+#[derive(Default)]
+struct Recorder(Vec<MeshRenderer>);
+
+impl Renderer for Recorder {
+    fn submit(&mut self, _: &Camera, _: &[Lit], meshes: &[MeshRenderer]) {
+        self.0 = meshes.to_vec();
+    }
+}
+
+#[test]
+fn transitions() {
+    let mut weather = Weather::new();
+    assert_eq!(weather.current(), "clear");
+    assert!(!weather.transition("fog", 1.0));
+
+    assert!(weather.transition("storm", 10.0));
+    assert!(weather.is_transitioning());
+    weather.update(5.0, math::Vector3::new(0.0, 0.0, 0.0));
+    assert_eq!(weather.params().rain, 0.5);
+
+    // Interrupted transitions start from the blended weather.
+    weather.add_state("drizzle", WeatherState::rain());
+    assert!(weather.transition("drizzle", 2.0));
+    assert_eq!(weather.params().rain, 0.5);
+    weather.update(5.0, math::Vector3::new(0.0, 0.0, 0.0));
+    assert!(!weather.is_transitioning());
+    assert_eq!(weather.current(), "drizzle");
+    assert_eq!(weather.params(), WeatherState::rain());
+}
+
+#[test]
+fn wetness() {
+    let mut weather = Weather::new();
+    weather.wetting = 0.25;
+    weather.drying = 0.1;
+    weather.transition("storm", 0.0);
+
+    let center = math::Vector3::new(0.0, 0.0, 0.0);
+    weather.update(2.0, center);
+    assert_eq!(weather.wetness(), 0.5);
+    weather.update(10.0, center);
+    assert_eq!(weather.wetness(), 1.0);
+
+    weather.transition("clear", 0.0);
+    weather.update(5.0, center);
+    assert_eq!(weather.wetness(), 0.5);
+
+    let material = SimpleMaterial::default();
+    assert_eq!(material.wet(0.0).shininess, material.shininess);
+    let wet = material.wet(1.0);
+    assert!(wet.diffuse.r < material.diffuse.r);
+    assert!(wet.specular.r > material.specular.r);
+    assert!(wet.shininess > material.shininess);
+}
+
+#[test]
+fn precipitation() {
+    let mut rain = PrecipitationLayer::new(100, 10.0, 0.5);
+    rain.extent = math::Vector3::new(5.0, 5.0, 5.0);
+
+    let center = math::Vector3::new(100.0, 0.0, 0.0);
+    let wind = math::Vector3::new(4.0, 0.0, 0.0);
+    rain.update(0.0, center, 0.5, wind);
+    assert_eq!(rain.particles().len(), 50);
+    assert_eq!(rain.velocity(), math::Vector3::new(2.0, -10.0, 0.0));
+
+    // Particles are wrapped into the box that follows the camera.
+    let center = math::Vector3::new(-50.0, 20.0, 7.0);
+    for _ in 0..10 {
+        rain.update(0.37, center, 1.0, wind);
+    }
+
+    assert_eq!(rain.particles().len(), 100);
+    for v in rain.particles() {
+        assert!((v.x - center.x).abs() <= 5.0);
+        assert!((v.y - center.y).abs() <= 5.0);
+        assert!((v.z - center.z).abs() <= 5.0);
+    }
+
+    rain.update(0.1, center, 0.0, wind);
+    assert!(rain.particles().is_empty());
+
+    let mut calm = Wind::calm();
+    assert_eq!(calm.sample(1.0), math::Vector3::new(0.0, 0.0, 0.0));
+    calm.speed = 2.0;
+    calm.gustiness = 0.0;
+    assert_eq!(calm.sample(1.0), math::Vector3::new(2.0, 0.0, 0.0));
+}
+
+#[test]
+fn draw() {
+    let mut entities: HandlePool<Entity> = HandlePool::new();
+    let mut scene = SceneGraph::new();
+    let mut renderables = Renderable::new();
+
+    let camera = entities.create();
+    scene.add(camera);
+    renderables.add_camera(camera, Camera::ortho(10.0, 10.0, 0.1, 100.0));
+
+    let template = entities.create();
+    scene.add(template);
+    let video = VideoSystem::headless(None).shared();
+    let mesh = video.create_mesh(MeshParams::default(), None).unwrap();
+    let mut drop = MeshRenderer::from(mesh);
+    drop.visible = false;
+    renderables.add_mesh(template, drop);
+
+    let mut snow = PrecipitationLayer::snow();
+    snow.capacity = 10;
+    snow.update(0.1, math::Vector3::new(0.0, 0.0, 0.0), 1.0, math::Vector3::new(0.0, 0.0, 0.0));
+
+    let mut recorder = Recorder::default();
+    snow.draw(&mut recorder, &scene, &renderables, template, template);
+    assert!(recorder.0.is_empty());
+
+    snow.draw(&mut recorder, &scene, &renderables, camera, template);
+    assert_eq!(recorder.0.len(), 10);
+    assert!(recorder.0.iter().all(|v| v.visible && v.mesh == drop.mesh));
+}
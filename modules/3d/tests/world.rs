@@ -2,6 +2,7 @@ extern crate crayon;
 extern crate crayon_3d;
 
 use crayon::utils::handle_pool::HandlePool;
+use crayon::uuid::Uuid;
 
 use crayon_3d::prelude::*;
 use crayon_3d::renderers::Renderable;
@@ -44,6 +45,12 @@ impl Testbed {
             prefab,
         ).ok()
     }
+
+    fn save(&self) -> Option<Prefab> {
+        world_impl::save(&self.scene, &self.renderables, &self.tags, |_| {
+            Some(Uuid::nil())
+        }).ok()
+    }
 }
 
 #[test]
@@ -122,3 +129,267 @@ fn instantiate() {
     assert_eq!(testbed.find("room.obj"), Some(e1));
     assert!(testbed.find("room.obj/floor/tallBox").is_some());
 }
+
+#[test]
+fn save() {
+    use crayon::math;
+    use crayon::utils::handle::Handle;
+    use crayon::video::assets::mesh::MeshHandle;
+
+    let mut testbed = Testbed::new();
+    assert!(testbed.save().is_none());
+
+    let e1 = testbed.create("room.obj");
+    let e2 = testbed.create("floor");
+    let e3 = testbed.create("tallBox");
+    let e4 = testbed.create("shortBox");
+    let e5 = testbed.create("light");
+
+    testbed.scene.set_parent(e2, e1, false).unwrap();
+    testbed.scene.set_parent(e3, e1, false).unwrap();
+    testbed.scene.set_parent(e4, e3, false).unwrap();
+    testbed.scene.set_local_position(e4, [1.0, 2.0, 3.0]);
+    testbed.scene.set_local_scale(e3, 2.0);
+
+    let mesh: MeshHandle = Handle::new(1, 1).into();
+    testbed.renderables.add_mesh(e2, mesh);
+    testbed.renderables.add_mesh(e4, mesh);
+
+    let prefab = testbed.save().unwrap();
+    assert_eq!(prefab.nodes.len(), 5);
+    assert_eq!(prefab.universe_meshes, vec![Uuid::nil()]);
+    assert_eq!(prefab.meshes, vec![mesh]);
+
+    let mut other = Testbed::new();
+    other.instantiate(&prefab).unwrap();
+    assert_eq!(other.entities.len(), 5);
+    assert!(other.find("room.obj/floor").is_some());
+    assert!(other.find("light").is_some());
+
+    let v = other.find("room.obj/tallBox/shortBox").unwrap();
+    let position = other.scene.local_position(v).unwrap();
+    assert_eq!(position, math::Vector3::new(1.0, 2.0, 3.0));
+    assert_eq!(other.renderables.mesh(v).unwrap().mesh, mesh);
+
+    let v = other.find("room.obj/tallBox").unwrap();
+    assert_eq!(other.scene.local_scale(v), Some(2.0));
+    assert!(other.renderables.mesh(v).is_none());
+
+    // Meshes which are not created from file can not be saved.
+    let scene = &testbed.scene;
+    let result = world_impl::save(scene, &testbed.renderables, &testbed.tags, |_| None);
+    assert!(result.is_err());
+    assert!(testbed.scene.is_root(e5));
+}
+
+#[test]
+fn tags() {
+    let mut testbed = Testbed::new();
+    let e1 = testbed.create("player");
+    let e2 = testbed.create("goblin");
+    let e3 = testbed.create("orc");
+
+    testbed.tags.tag(e1, "player");
+    testbed.tags.tag(e2, "enemy");
+    testbed.tags.tag(e3, "enemy");
+    testbed.tags.tag(e3, "enemy");
+    testbed.tags.tag(e3, "boss");
+
+    assert_eq!(testbed.tags.find_tagged("player"), Some(e1));
+    assert_eq!(testbed.tags.tagged("enemy"), &[e2, e3]);
+    assert!(testbed.tags.has_tag(e3, "boss"));
+    assert!(!testbed.tags.has_tag(e1, "boss"));
+    assert_eq!(testbed.tags.find_tagged("npc"), None);
+
+    testbed.tags.untag(e3, "boss");
+    assert!(!testbed.tags.has_tag(e3, "boss"));
+    assert_eq!(testbed.tags.find_tagged("boss"), None);
+
+    // Tags are removed with entities.
+    testbed.scene.set_parent(e3, e2, false).unwrap();
+    world_impl::remove(
+        &mut testbed.entities,
+        &mut testbed.scene,
+        &mut testbed.renderables,
+        &mut testbed.tags,
+        e2,
+    );
+
+    assert!(testbed.tags.tagged("enemy").is_empty());
+    assert!(!testbed.tags.has_tag(e3, "enemy"));
+    assert_eq!(testbed.tags.find_tagged("player"), Some(e1));
+}
+
+#[test]
+fn resources() {
+    use crayon_3d::resources::Resources;
+
+    #[derive(Debug, PartialEq)]
+    struct Time(f32);
+
+    let mut resources = Resources::new();
+    assert!(resources.fetch::<Time>().is_none());
+
+    assert_eq!(resources.insert(Time(0.1)), None);
+    assert_eq!(resources.insert(1u32), None);
+    assert_eq!(resources.len(), 2);
+    assert!(resources.has::<Time>());
+
+    resources.fetch_mut::<Time>().unwrap().0 += 0.1;
+    assert_eq!(resources.fetch::<u32>(), Some(&1));
+    assert_eq!(resources.insert(Time(1.0)), Some(Time(0.2)));
+
+    assert_eq!(resources.remove::<Time>(), Some(Time(1.0)));
+    assert_eq!(resources.remove::<Time>(), None);
+    assert!(!resources.has::<Time>());
+    assert_eq!(resources.len(), 1);
+}
+
+#[test]
+fn create_batch() {
+    let mut testbed = Testbed::new();
+    let e1 = testbed.create("e1");
+    world_impl::remove(
+        &mut testbed.entities,
+        &mut testbed.scene,
+        &mut testbed.renderables,
+        &mut testbed.tags,
+        e1,
+    );
+
+    let v = world_impl::create_batch(&mut testbed.entities, &mut testbed.scene, 16);
+    assert_eq!(v.len(), 16);
+    assert_eq!(testbed.entities.len(), 16);
+
+    for &ent in &v {
+        assert!(testbed.entities.is_alive(ent));
+        assert!(testbed.scene.is_root(ent));
+    }
+
+    // The freed index is reused.
+    assert!(v.iter().any(|&ent| ent.index() == e1.index()));
+}
+
+#[test]
+fn merge() {
+    let mut testbed = Testbed::new();
+    let e1 = testbed.create("player");
+
+    let mut chunk = Testbed::new();
+    let c1 = chunk.create("room");
+    let c2 = chunk.create("floor");
+    let c3 = chunk.create("wall");
+    let c4 = chunk.create("lamp");
+
+    chunk.scene.set_parent(c2, c1, false).unwrap();
+    chunk.scene.set_parent(c3, c1, false).unwrap();
+    chunk.scene.set_local_position(c2, [1.0, 0.0, 0.0]);
+    chunk.scene.set_enabled(c3, false);
+    chunk.tags.tag(c4, "lit");
+    chunk.renderables.add_mesh(c2, MeshRenderer::default());
+
+    let remap = world_impl::merge(
+        &mut testbed.entities,
+        &mut testbed.scene,
+        &mut testbed.renderables,
+        &mut testbed.tags,
+        &chunk.scene,
+        chunk.renderables,
+        chunk.tags,
+    );
+
+    assert_eq!(remap.len(), 4);
+    assert_eq!(testbed.entities.len(), 5);
+    assert_eq!(testbed.find("player"), Some(e1));
+
+    let floor = testbed.find("room/floor").unwrap();
+    assert_eq!(floor, remap[&c2]);
+    assert_eq!(testbed.scene.parent(floor), Some(remap[&c1]));
+    assert_eq!(
+        testbed.scene.local_position(floor),
+        Some([1.0, 0.0, 0.0].into())
+    );
+
+    assert!(testbed.renderables.mesh(floor).is_some());
+    assert!(!testbed.scene.is_enabled(remap[&c3]));
+    assert_eq!(testbed.tags.find_tagged("lit"), Some(remap[&c4]));
+
+    // The order of children is kept.
+    let children: Vec<_> = testbed.scene.children(remap[&c1]).collect();
+    let expected: Vec<_> = chunk.scene.children(c1).map(|v| remap[&v]).collect();
+    assert_eq!(children, expected);
+}
+
+#[test]
+fn stats() {
+    let mut testbed = Testbed::new();
+    let e1 = testbed.create("player");
+    let e2 = testbed.create("enemy");
+    testbed.create("camera");
+    testbed.tags.tag(e1, "alive");
+    testbed.tags.tag(e2, "alive");
+    testbed.renderables.add_mesh(e2, MeshRenderer::default());
+
+    world_impl::remove(
+        &mut testbed.entities,
+        &mut testbed.scene,
+        &mut testbed.renderables,
+        &mut testbed.tags,
+        e1,
+    );
+
+    let resources = crayon_3d::resources::Resources::new();
+    let stats = world_impl::stats(
+        &testbed.entities,
+        &testbed.scene,
+        &testbed.renderables,
+        &testbed.tags,
+        &resources,
+    );
+
+    assert_eq!(stats.entities, 2);
+    assert_eq!(stats.indices, 3);
+    assert_eq!(stats.tags, 1);
+    assert_eq!(stats.resources, 0);
+    assert!(stats.memory > 0);
+
+    let names: Vec<_> = stats.components.iter().map(|v| v.0).collect();
+    assert_eq!(
+        names,
+        ["Transform", "Name", "Camera", "Lit", "MeshRenderer"]
+    );
+    assert_eq!(stats.components[0].1, 2);
+    assert_eq!(stats.components[4].1, 1);
+}
+
+#[test]
+fn entity_ref() {
+    let mut testbed = Testbed::new();
+    let e1 = testbed.create("e1");
+    let e2 = testbed.create("e2");
+    testbed.scene.set_parent(e2, e1, false).unwrap();
+
+    let mut r1 = EntityRef::new(e2);
+    let mut r2 = r1;
+    assert_eq!(r1.resolve(&testbed.entities), Some(e2));
+
+    world_impl::remove(
+        &mut testbed.entities,
+        &mut testbed.scene,
+        &mut testbed.renderables,
+        &mut testbed.tags,
+        e1,
+    ).unwrap();
+
+    // The index of removed entity is reused, but the reference does not alias it.
+    let e3 = testbed.create("e3");
+    assert_eq!(r1.peek(), Some(e2));
+    assert_eq!(r1.resolve(&testbed.entities), None);
+    assert!(r1.is_null());
+    assert!(!r2.is_null());
+    assert_eq!(r2.resolve(&testbed.entities), None);
+
+    let mut r3: EntityRef = e3.into();
+    assert_eq!(r3.resolve(&testbed.entities), Some(e3));
+    assert_eq!(EntityRef::null().resolve(&testbed.entities), None);
+}
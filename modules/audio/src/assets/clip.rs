@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 impl_handle!(AudioClipHandle);
 
 #[derive(Debug, Clone)]
@@ -6,3 +8,13 @@ pub struct AudioClip {
     pub channels: u8,
     pub sample_rate: u32,
 }
+
+impl AudioClip {
+    /// Gets the duration of this clip when it's played with pitch 1.
+    pub fn duration(&self) -> Duration {
+        let frames = self.pcm.len() as u64 / u64::from(self.channels.max(1));
+        let rate = u64::from(self.sample_rate.max(1));
+        Duration::from_secs(frames / rate)
+            + Duration::from_nanos((frames % rate) * 1_000_000_000 / rate)
+    }
+}
@@ -1,6 +1,7 @@
 use std::io::Cursor;
 use std::sync::Arc;
 
+use crayon::res::utils;
 use lewton::inside_ogg::OggStreamReader;
 
 use super::super::Result;
@@ -23,12 +24,12 @@ impl ::crayon::res::registry::Register for AudioClipLoader {
     type Value = Arc<AudioClip>;
 
     fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
-        if &bytes[0..8] != MAGIC {
+        if !utils::check_magic(bytes, &MAGIC) {
             bail!("[AudioClipLoader] MAGIC number not match.");
         }
 
         let cursor = Cursor::new(&bytes[8..]);
-        let mut stream_reader = OggStreamReader::new(cursor).unwrap();
+        let mut stream_reader = OggStreamReader::new(cursor)?;
 
         let mut clip = AudioClip {
             channels: stream_reader.ident_hdr.audio_channels,
@@ -56,4 +57,8 @@ impl ::crayon::res::registry::Register for AudioClipLoader {
     }
 
     fn detach(&self, _: Self::Handle, _: Self::Value) {}
+
+    fn memory(&self, value: &Self::Value) -> usize {
+        value.pcm.len() * ::std::mem::size_of::<i16>()
+    }
 }
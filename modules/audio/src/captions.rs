@@ -0,0 +1,274 @@
+//! Subtitles and closed captions of dialogues and sound effects.
+//!
+//! Caption tracks are attached to audio clips, and start whenever their clips are played
+//! through `Captions`. The visible lines are drawn by the UI of game every frame, e.g.
+//! with `crayon-imgui`:
+//!
+//! ```rust,ignore
+//! captions.update(&audio, dt);
+//! for line in captions.lines() {
+//!     let c = line.style.color;
+//!     ui.text_colored((c.r, c.g, c.b, c.a), im_str!("{}", line));
+//! }
+//! ```
+//!
+//! Captions are an accessibility option, which is stored in the preference
+//! `CAPTIONS_PREFERENCE` once `Captions::bind_preferences` is called.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crayon::application::Preferences;
+use crayon::math::Color;
+use crayon::utils::FastHashMap;
+
+use source::{AudioSource, AudioSourceHandle};
+use {AudioClipHandle, AudioSystemShared, Result};
+
+/// The name of preference which enables captions.
+pub const CAPTIONS_PREFERENCE: &'static str = "audio.captions";
+
+/// The display style of captions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptionStyle {
+    pub color: Color<f32>,
+    /// Usually used for narrations, and the speakers that are out of screen.
+    pub italic: bool,
+}
+
+impl Default for CaptionStyle {
+    fn default() -> Self {
+        CaptionStyle {
+            color: Color::white(),
+            italic: false,
+        }
+    }
+}
+
+/// A timed line of caption.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Caption {
+    /// The seconds since the start of clip.
+    pub start: f32,
+    pub end: f32,
+    pub speaker: Option<String>,
+    pub text: String,
+    /// Overrides the style of speaker.
+    pub style: Option<CaptionStyle>,
+}
+
+/// The captions of an audio clip.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CaptionTrack {
+    pub captions: Vec<Caption>,
+}
+
+impl CaptionTrack {
+    pub fn new() -> Self {
+        CaptionTrack::default()
+    }
+
+    /// Appends a line which is shown between `start` and `end` seconds.
+    pub fn push<T: Into<String>>(&mut self, start: f32, end: f32, text: T) -> &mut Caption {
+        self.captions.push(Caption {
+            start: start,
+            end: end,
+            speaker: None,
+            text: text.into(),
+            style: None,
+        });
+
+        self.captions.last_mut().unwrap()
+    }
+
+    /// Appends a line of speaker which is shown between `start` and `end` seconds.
+    pub fn say<S, T>(&mut self, start: f32, end: f32, speaker: S, text: T) -> &mut Caption
+    where
+        S: Into<String>,
+        T: Into<String>,
+    {
+        let caption = self.push(start, end, text);
+        caption.speaker = Some(speaker.into());
+        caption
+    }
+
+    /// Gets the end of the last line.
+    pub fn duration(&self) -> f32 {
+        self.captions.iter().fold(0.0, |acc, v| acc.max(v.end))
+    }
+}
+
+/// A visible line of captions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptionLine<'a> {
+    pub speaker: Option<&'a str>,
+    pub text: &'a str,
+    pub style: CaptionStyle,
+}
+
+impl<'a> fmt::Display for CaptionLine<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.speaker {
+            Some(speaker) => write!(f, "{}: {}", speaker, self.text),
+            None => write!(f, "{}", self.text),
+        }
+    }
+}
+
+struct Playing {
+    track: Arc<CaptionTrack>,
+    source: Option<AudioSourceHandle>,
+    elapsed: f32,
+}
+
+/// The captions attached to audio clips, and the ones that are playing.
+pub struct Captions {
+    tracks: FastHashMap<AudioClipHandle, Arc<CaptionTrack>>,
+    speakers: FastHashMap<String, CaptionStyle>,
+    playing: Vec<Playing>,
+    preferences: Option<Arc<Preferences>>,
+    enable: bool,
+}
+
+impl Default for Captions {
+    fn default() -> Self {
+        Captions::new()
+    }
+}
+
+impl Captions {
+    pub fn new() -> Self {
+        Captions {
+            tracks: FastHashMap::default(),
+            speakers: FastHashMap::default(),
+            playing: Vec::new(),
+            preferences: None,
+            enable: false,
+        }
+    }
+
+    /// Stores the toggle of captions in `preferences`, it's registered with captions
+    /// disabled if it does not exist.
+    pub fn bind_preferences(&mut self, preferences: Arc<Preferences>) -> Result<()> {
+        if !preferences.has(CAPTIONS_PREFERENCE) {
+            preferences.register(CAPTIONS_PREFERENCE, self.enable)?;
+        }
+
+        self.preferences = Some(preferences);
+        Ok(())
+    }
+
+    /// Returns true if captions are shown.
+    pub fn is_enabled(&self) -> bool {
+        self.preferences
+            .as_ref()
+            .and_then(|v| v.get(CAPTIONS_PREFERENCE))
+            .unwrap_or(self.enable)
+    }
+
+    /// Shows or hides captions.
+    pub fn set_enabled(&mut self, enable: bool) -> Result<()> {
+        self.enable = enable;
+        if let Some(ref preferences) = self.preferences {
+            preferences.set(CAPTIONS_PREFERENCE, enable)?;
+        }
+
+        Ok(())
+    }
+
+    /// Attaches a caption track to the clip.
+    pub fn attach(&mut self, clip: AudioClipHandle, track: CaptionTrack) {
+        self.tracks.insert(clip, Arc::new(track));
+    }
+
+    /// Detaches the caption track of clip.
+    pub fn detach(&mut self, clip: AudioClipHandle) {
+        self.tracks.remove(&clip);
+    }
+
+    /// Sets the default style of speaker.
+    pub fn set_speaker_style<T: Into<String>>(&mut self, speaker: T, style: CaptionStyle) {
+        self.speakers.insert(speaker.into(), style);
+    }
+
+    /// Plays a audio source, and starts the captions of its clip. The captions are
+    /// stopped with the source.
+    pub fn play<T>(&mut self, audio: &AudioSystemShared, params: T) -> Result<AudioSourceHandle>
+    where
+        T: Into<AudioSource>,
+    {
+        let params = params.into();
+        let handle = audio.play(params)?;
+        self.start_with(params.clip, Some(handle));
+        Ok(handle)
+    }
+
+    /// Starts the captions of clip without a source, e.g. for one-shots. Returns false
+    /// if there are no captions attached to it.
+    pub fn start(&mut self, clip: AudioClipHandle) -> bool {
+        self.start_with(clip, None)
+    }
+
+    fn start_with(&mut self, clip: AudioClipHandle, source: Option<AudioSourceHandle>) -> bool {
+        match self.tracks.get(&clip) {
+            Some(track) => {
+                self.playing.push(Playing {
+                    track: track.clone(),
+                    source: source,
+                    elapsed: 0.0,
+                });
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stops all the playing captions.
+    pub fn clear(&mut self) {
+        self.playing.clear();
+    }
+
+    /// Advances the playing captions by `dt` seconds, and drops the finished ones.
+    pub fn update(&mut self, audio: &AudioSystemShared, dt: f32) {
+        for v in &mut self.playing {
+            v.elapsed += dt;
+        }
+
+        self.playing.retain(|v| {
+            let stopped = v.source.map(|h| !audio.is_playing(h)).unwrap_or(false);
+            !stopped && v.elapsed < v.track.duration()
+        });
+    }
+
+    /// Gets the visible lines in the order they were started, or nothing if captions
+    /// are disabled.
+    pub fn lines(&self) -> Vec<CaptionLine> {
+        if !self.is_enabled() {
+            return Vec::new();
+        }
+
+        let mut lines = Vec::new();
+        for v in &self.playing {
+            for caption in &v.track.captions {
+                if v.elapsed < caption.start || v.elapsed >= caption.end {
+                    continue;
+                }
+
+                let speaker = caption.speaker.as_ref().map(|v| v.as_str());
+                let style = caption
+                    .style
+                    .or_else(|| speaker.and_then(|v| self.speakers.get(v).cloned()))
+                    .unwrap_or_default();
+
+                lines.push(CaptionLine {
+                    speaker: speaker,
+                    text: &caption.text,
+                    style: style,
+                });
+            }
+        }
+
+        lines
+    }
+}
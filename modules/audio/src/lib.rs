@@ -6,10 +6,13 @@ extern crate crayon;
 extern crate failure;
 
 pub mod assets;
+pub mod captions;
 mod mixer;
+pub mod music;
 pub mod source;
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use crayon::math::Vector3;
 use crayon::res::prelude::{Location, ResourceSystemShared};
@@ -17,11 +20,13 @@ use crayon::res::registry::Registry;
 
 use self::assets::{AudioClipHandle, AudioClipLoader};
 use self::mixer::MixerController;
-use self::source::{AudioSource, AudioSourceHandle};
+use self::source::{AudioSource, AudioSourceHandle, AudioSourceSpatial};
 
 pub mod prelude {
     pub use super::{AudioSystem, AudioSystemShared};
     pub use assets::AudioClipHandle;
+    pub use captions::{CaptionStyle, CaptionTrack, Captions};
+    pub use music::{MusicPlayer, MusicStem, MusicTrack};
     pub use source::{AudioSource, AudioSourceHandle, AudioSourceSpatial, AudioSourceWrap};
 }
 
@@ -113,6 +118,25 @@ impl AudioSystemShared {
         self.clips.delete(handle);
     }
 
+    /// Gets the duration of a `AudioClip` resource, blocks until it has been loaded.
+    pub fn clip_duration(&self, handle: AudioClipHandle) -> Option<Duration> {
+        self.clips.wait_until(handle).ok()?;
+        self.clips.get(handle, |v| v.duration())
+    }
+
+    /// Retains a `AudioClip` resource on behalf of `holder`, returns false if it has
+    /// been deleted.
+    #[inline]
+    pub fn retain_clip(&self, handle: AudioClipHandle, holder: &'static str) -> bool {
+        self.clips.retain_by(handle, holder)
+    }
+
+    /// Drops a reference of `AudioClip` resource that was retained by `holder`.
+    #[inline]
+    pub fn release_clip(&self, handle: AudioClipHandle, holder: &'static str) {
+        self.clips.release_by(handle, holder);
+    }
+
     /// Plays a audio source, returning a `AudioSourceHandle` for it.
     #[inline]
     pub fn play<T>(&self, params: T) -> Result<AudioSourceHandle>
@@ -122,6 +146,50 @@ impl AudioSystemShared {
         self.mixer.create_source(params.into())
     }
 
+    /// Plays several audio sources that are started at the same sample, e.g. the layered
+    /// stems of music.
+    pub fn play_synced<T>(&self, params: &[T]) -> Result<Vec<AudioSourceHandle>>
+    where
+        T: Into<AudioSource> + Copy,
+    {
+        let params: Vec<_> = params.iter().map(|&v| v.into()).collect();
+        self.mixer.create_sources(&params)
+    }
+
+    /// Returns true if the audio source is still playing.
+    #[inline]
+    pub fn is_playing(&self, handle: AudioSourceHandle) -> bool {
+        self.mixer.is_playing(handle)
+    }
+
+    /// Plays a audio source once without keeping its handle, which is handy for sound
+    /// effects like footsteps and impacts. The one-shot sources are recycled once they
+    /// are finished, and the oldest one is stopped if there are too many of them.
+    #[inline]
+    pub fn play_once<T>(&self, params: T) -> Result<()>
+    where
+        T: Into<AudioSource>,
+    {
+        self.mixer.create_one_shot(params.into())
+    }
+
+    /// Plays a clip once at `position` with the 3D parameters `spatial`.
+    pub fn play_once_at<T>(
+        &self,
+        clip: AudioClipHandle,
+        position: T,
+        mut spatial: AudioSourceSpatial,
+    ) -> Result<()>
+    where
+        T: Into<Vector3<f32>>,
+    {
+        spatial.position = position.into();
+
+        let mut params = AudioSource::from(clip);
+        params.spatial = Some(spatial);
+        self.mixer.create_one_shot(params)
+    }
+
     /// Stops a played audio source.
     #[inline]
     pub fn stop(&self, handle: AudioSourceHandle) {
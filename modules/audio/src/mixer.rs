@@ -1,4 +1,5 @@
-use std::sync::{Arc, RwLock};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread::Builder;
 
 use cpal::{self, EventLoop, StreamData, UnknownTypeOutputBuffer};
@@ -9,6 +10,9 @@ use assets::AudioClip;
 use source::{AudioSource, AudioSourceHandle, AudioSourceSpatial, AudioSourceWrap};
 use {AudioClipRegistry, Result};
 
+// The maximum number of one-shot sources that are playing at the same time.
+const ONE_SHOT_VOICES: usize = 32;
+
 pub fn mixer(clips: Arc<AudioClipRegistry>) -> Result<MixerController> {
     let device = cpal::default_output_device()
         .ok_or_else(|| format_err!("No avaiable audio output device"))?;
@@ -21,6 +25,7 @@ pub fn mixer(clips: Arc<AudioClipRegistry>) -> Result<MixerController> {
     info!("Created audio mixer. [{:?}] {:?}.", device.name(), format);
 
     let cmds = Arc::new(RwLock::new(Vec::new()));
+    let finished = Arc::new(RwLock::new(Vec::new()));
     let mut mixer = Mixer {
         channels: format.channels as u8,
         channels_iter: 0,
@@ -29,6 +34,7 @@ pub fn mixer(clips: Arc<AudioClipRegistry>) -> Result<MixerController> {
         sources: Vec::new(),
         rx: cmds.clone(),
         bufs: Vec::new(),
+        finished: finished.clone(),
     };
 
     Builder::new()
@@ -46,7 +52,9 @@ pub fn mixer(clips: Arc<AudioClipRegistry>) -> Result<MixerController> {
     Ok(MixerController {
         clips: clips,
         sources: RwLock::new(HandlePool::new()),
+        one_shots: Mutex::new(VecDeque::new()),
         tx: cmds,
+        finished: finished,
     })
 }
 
@@ -55,33 +63,105 @@ pub fn headless(clips: Arc<AudioClipRegistry>) -> Result<MixerController> {
     Ok(MixerController {
         clips: clips,
         sources: RwLock::new(HandlePool::new()),
+        one_shots: Mutex::new(VecDeque::new()),
         tx: cmds,
+        finished: Arc::new(RwLock::new(Vec::new())),
     })
 }
 
 pub struct MixerController {
     clips: Arc<AudioClipRegistry>,
     sources: RwLock<HandlePool<AudioSourceHandle>>,
+    one_shots: Mutex<VecDeque<AudioSourceHandle>>,
     tx: Arc<RwLock<Vec<Command>>>,
+    // The sources that have been played to the end by mixer.
+    finished: Arc<RwLock<Vec<AudioSourceHandle>>>,
 }
 
 impl MixerController {
     #[inline]
     pub fn create_source(&self, params: AudioSource) -> Result<AudioSourceHandle> {
-        if let Some(clip) = self
-            .clips
-            .wait_until(params.clip)
-            .ok()
-            .and_then(|_| self.clips.get(params.clip, |v| v.clone()))
-        {
-            let handle = self.sources.write().unwrap().create();
-            self.tx
-                .write()
-                .unwrap()
-                .push(Command::CreateSource(handle, params, clip));
-            Ok(handle)
-        } else {
-            bail!("The AudioClip {:?} is not available.", params.clip);
+        self.create_sources(&[params]).map(|v| v[0])
+    }
+
+    /// Creates sources that are started at the same sample.
+    pub fn create_sources(&self, params: &[AudioSource]) -> Result<Vec<AudioSourceHandle>> {
+        self.collect();
+
+        let mut clips = Vec::with_capacity(params.len());
+        for v in params {
+            if let Some(clip) = self
+                .clips
+                .wait_until(v.clip)
+                .ok()
+                .and_then(|_| self.clips.get(v.clip, |v| v.clone()))
+            {
+                clips.push(clip);
+            } else {
+                bail!("The AudioClip {:?} is not available.", v.clip);
+            }
+        }
+
+        let handles: Vec<_> = {
+            let mut sources = self.sources.write().unwrap();
+            params.iter().map(|_| sources.create()).collect()
+        };
+
+        // The commands pushed at once are always executed in the same mix.
+        let mut tx = self.tx.write().unwrap();
+        for ((&handle, &v), clip) in handles.iter().zip(params).zip(clips) {
+            tx.push(Command::CreateSource(handle, v, clip));
+        }
+
+        Ok(handles)
+    }
+
+    /// Creates a source that is played once and then recycled. The oldest one-shot
+    /// source is stopped if there are too many of them.
+    pub fn create_one_shot(&self, mut params: AudioSource) -> Result<()> {
+        if let AudioSourceWrap::Infinite = params.loops {
+            params.loops = AudioSourceWrap::Repeat(1);
+        }
+
+        let handle = self.create_source(params)?;
+
+        let oldest = {
+            let mut one_shots = self.one_shots.lock().unwrap();
+            one_shots.push_back(handle);
+
+            if one_shots.len() > ONE_SHOT_VOICES {
+                one_shots.pop_front()
+            } else {
+                None
+            }
+        };
+
+        if let Some(oldest) = oldest {
+            self.delete_source(oldest);
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the source is still playing.
+    pub fn is_playing(&self, handle: AudioSourceHandle) -> bool {
+        self.collect();
+        self.sources.read().unwrap().is_alive(handle)
+    }
+
+    /// Frees the handles of sources that have been played to the end.
+    fn collect(&self) {
+        let finished: Vec<_> = self.finished.write().unwrap().drain(..).collect();
+        if finished.is_empty() {
+            return;
+        }
+
+        let mut sources = self.sources.write().unwrap();
+        let mut one_shots = self.one_shots.lock().unwrap();
+        for handle in finished {
+            if sources.free(handle) {
+                one_shots.retain(|&v| v != handle);
+            }
         }
     }
 
@@ -95,7 +175,9 @@ impl MixerController {
 
     #[inline]
     pub fn delete_source(&self, handle: AudioSourceHandle) {
-        self.tx.write().unwrap().push(Command::DeleteSource(handle));
+        if self.sources.write().unwrap().free(handle) {
+            self.tx.write().unwrap().push(Command::DeleteSource(handle));
+        }
     }
 
     #[inline]
@@ -142,6 +224,7 @@ struct Mixer {
     sources: Vec<Option<AudioSourceInstance>>,
     rx: Arc<RwLock<Vec<Command>>>,
     bufs: Vec<Command>,
+    finished: Arc<RwLock<Vec<AudioSourceHandle>>>,
 }
 
 impl Mixer {
@@ -182,7 +265,8 @@ impl Mixer {
                     .unwrap_or(false);
 
                 if free {
-                    *v = None;
+                    let handle = v.take().unwrap().handle;
+                    self.finished.write().unwrap().push(handle);
                 }
             }
         }
@@ -213,29 +297,25 @@ impl Mixer {
                         self.sources.resize(index + 1, None);
                     }
 
-                    self.sources[index] = Some(AudioSourceInstance::new(clip, source));
+                    self.sources[index] = Some(AudioSourceInstance::new(handle, clip, source));
                 }
                 Command::DeleteSource(handle) => {
-                    let index = handle.index() as usize;
-                    if self.sources.len() > index {
-                        self.sources[index] = None;
+                    if source_mut(&mut self.sources, handle).is_some() {
+                        self.sources[handle.index() as usize] = None;
                     }
                 }
                 Command::UpdateSourcePitch(handle, pitch) => {
-                    let index = handle.index() as usize;
-                    if let Some(v) = self.sources.get_mut(index).and_then(|v| v.as_mut()) {
+                    if let Some(v) = source_mut(&mut self.sources, handle) {
                         v.pitch = pitch;
                     }
                 }
                 Command::UpdateSourceVolume(handle, volume) => {
-                    let index = handle.index() as usize;
-                    if let Some(v) = self.sources.get_mut(index).and_then(|v| v.as_mut()) {
+                    if let Some(v) = source_mut(&mut self.sources, handle) {
                         v.volume = volume;
                     }
                 }
                 Command::UpdateSourcePosition(handle, emitter) => {
-                    let index = handle.index() as usize;
-                    if let Some(v) = self.sources.get_mut(index).and_then(|v| v.as_mut()) {
+                    if let Some(v) = source_mut(&mut self.sources, handle) {
                         if let Some(ref mut v) = v.spatial {
                             v.position = emitter;
                         }
@@ -246,8 +326,21 @@ impl Mixer {
     }
 }
 
+// Gets the playing source of handle, the slots might have been reused by others since
+// handles are recycled.
+fn source_mut(
+    sources: &mut [Option<AudioSourceInstance>],
+    handle: AudioSourceHandle,
+) -> Option<&mut AudioSourceInstance> {
+    sources
+        .get_mut(handle.index() as usize)
+        .and_then(|v| v.as_mut())
+        .filter(|v| v.handle == handle)
+}
+
 #[derive(Clone)]
 struct AudioSourceInstance {
+    handle: AudioSourceHandle,
     clip: Arc<AudioClip>,
     volume: f32,
     pitch: f32,
@@ -257,8 +350,9 @@ struct AudioSourceInstance {
 }
 
 impl AudioSourceInstance {
-    fn new(clip: Arc<AudioClip>, source: AudioSource) -> Self {
+    fn new(handle: AudioSourceHandle, clip: Arc<AudioClip>, source: AudioSource) -> Self {
         AudioSourceInstance {
+            handle: handle,
             clip: clip,
             volume: source.volume,
             pitch: source.pitch,
@@ -0,0 +1,283 @@
+//! Playlists of music with crossfade, and adaptive music with layered stems.
+//!
+//! A `MusicTrack` is made of one or more stems, which are clips of the same length that
+//! are started at the same sample. Every stem fades in over a range of the gameplay
+//! driven intensity, e.g. percussions are added in combat:
+//!
+//! ```rust,ignore
+//! let mut music = MusicPlayer::new();
+//! music.push(MusicTrack::with_stems(vec![
+//!     MusicStem::base(ambient),
+//!     MusicStem::new(drums, 0.3, 0.6),
+//! ]));
+//!
+//! music.play(&audio, 0)?;
+//! ...
+//! music.set_intensity(if in_combat { 1.0 } else { 0.0 });
+//! music.update(&audio, dt)?;
+//! ```
+
+use source::{AudioSource, AudioSourceHandle, AudioSourceWrap};
+use {AudioClipHandle, AudioSystemShared, Result};
+
+/// A clip of a track, which is heard when the intensity is in its range.
+#[derive(Debug, Clone, Copy)]
+pub struct MusicStem {
+    pub clip: AudioClipHandle,
+    /// The intensity where this stem starts to fade in.
+    pub from: f32,
+    /// The intensity where this stem reaches full volume.
+    pub to: f32,
+}
+
+impl MusicStem {
+    pub fn new(clip: AudioClipHandle, from: f32, to: f32) -> Self {
+        MusicStem {
+            clip: clip,
+            from: from,
+            to: to,
+        }
+    }
+
+    /// Creates a stem which is always heard.
+    pub fn base(clip: AudioClipHandle) -> Self {
+        MusicStem::new(clip, 0.0, 0.0)
+    }
+
+    /// Gets the volume of this stem at `intensity`.
+    pub fn volume(&self, intensity: f32) -> f32 {
+        if intensity >= self.to {
+            1.0
+        } else if intensity <= self.from {
+            0.0
+        } else {
+            (intensity - self.from) / (self.to - self.from)
+        }
+    }
+}
+
+/// A track of playlist, with its synchronized stems.
+#[derive(Debug, Clone)]
+pub struct MusicTrack {
+    pub stems: Vec<MusicStem>,
+}
+
+impl MusicTrack {
+    /// Creates a track with a single clip.
+    pub fn new(clip: AudioClipHandle) -> Self {
+        MusicTrack::with_stems(vec![MusicStem::base(clip)])
+    }
+
+    pub fn with_stems(stems: Vec<MusicStem>) -> Self {
+        MusicTrack { stems: stems }
+    }
+}
+
+// A playing track, with its stems in the same order.
+struct Voice {
+    track: usize,
+    sources: Vec<AudioSourceHandle>,
+    duration: f32,
+    elapsed: f32,
+    gain: f32,
+    fading_out: bool,
+}
+
+/// Plays the tracks of a playlist one after another, and crossfades between them. It
+/// should be updated every frame.
+pub struct MusicPlayer {
+    /// The master volume of music.
+    pub volume: f32,
+    /// The duration of crossfades in seconds, which is also used to fade stems in and out
+    /// when the intensity changes.
+    pub crossfade: f32,
+    /// Starts from the first track again after the last one.
+    pub loops: bool,
+
+    tracks: Vec<MusicTrack>,
+    voices: Vec<Voice>,
+    current: Option<usize>,
+    intensity: f32,
+    target: f32,
+}
+
+impl Default for MusicPlayer {
+    fn default() -> Self {
+        MusicPlayer::new()
+    }
+}
+
+impl MusicPlayer {
+    pub fn new() -> Self {
+        MusicPlayer {
+            volume: 1.0,
+            crossfade: 2.0,
+            loops: true,
+            tracks: Vec::new(),
+            voices: Vec::new(),
+            current: None,
+            intensity: 0.0,
+            target: 0.0,
+        }
+    }
+
+    /// Appends a track to the playlist, and returns its index.
+    pub fn push(&mut self, track: MusicTrack) -> usize {
+        self.tracks.push(track);
+        self.tracks.len() - 1
+    }
+
+    /// Gets the tracks of playlist.
+    #[inline]
+    pub fn tracks(&self) -> &[MusicTrack] {
+        &self.tracks
+    }
+
+    /// Gets the index of the track that is playing, excluding the fading out ones.
+    #[inline]
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// Gets the current intensity, which follows the one that was set smoothly.
+    #[inline]
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// Sets the intensity, which is usually in `[0, 1]`.
+    #[inline]
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.target = intensity;
+    }
+
+    /// Crossfades into the track at `index`.
+    pub fn play(&mut self, audio: &AudioSystemShared, index: usize) -> Result<()> {
+        let track = match self.tracks.get(index) {
+            Some(track) => track,
+            None => bail!("The track {} is out of the playlist.", index),
+        };
+
+        let params: Vec<_> = track
+            .stems
+            .iter()
+            .map(|v| {
+                let mut params = AudioSource::from(v.clip);
+                params.volume = 0.0;
+                params.loops = AudioSourceWrap::Repeat(1);
+                params
+            }).collect();
+
+        let duration = track
+            .stems
+            .first()
+            .and_then(|v| audio.clip_duration(v.clip))
+            .map(|v| v.as_secs() as f32 + v.subsec_nanos() as f32 * 1e-9)
+            .unwrap_or(0.0);
+
+        let sources = audio.play_synced(&params)?;
+        self.fade_out();
+        self.voices.push(Voice {
+            track: index,
+            sources: sources,
+            duration: duration,
+            elapsed: 0.0,
+            gain: 0.0,
+            fading_out: false,
+        });
+
+        self.current = Some(index);
+        Ok(())
+    }
+
+    /// Crossfades into the next track of playlist. It stops if there are no more tracks
+    /// and `loops` is false.
+    pub fn next(&mut self, audio: &AudioSystemShared) -> Result<()> {
+        let next = self.current.map(|v| v + 1).unwrap_or(0);
+        if next < self.tracks.len() {
+            self.play(audio, next)
+        } else if self.loops && !self.tracks.is_empty() {
+            self.play(audio, 0)
+        } else {
+            self.stop();
+            Ok(())
+        }
+    }
+
+    /// Fades out the playing tracks.
+    pub fn stop(&mut self) {
+        self.fade_out();
+        self.current = None;
+    }
+
+    /// Advances fades by `dt` seconds, and starts the next track when the current one
+    /// is about to end.
+    pub fn update(&mut self, audio: &AudioSystemShared, dt: f32) -> Result<()> {
+        let step = if self.crossfade > 0.0 {
+            dt / self.crossfade
+        } else {
+            1.0
+        };
+
+        let delta = (self.target - self.intensity).max(-step).min(step);
+        self.intensity += delta;
+
+        let crossfade = self.crossfade;
+        let ending = self.voices.iter().any(|v| {
+            let remaining = v.duration - v.elapsed - dt;
+            !v.fading_out && v.duration > 0.0 && remaining <= crossfade
+        });
+
+        if ending {
+            self.next(audio)?;
+        }
+
+        for v in &mut self.voices {
+            v.elapsed += dt;
+            v.gain = if v.fading_out {
+                (v.gain - step).max(0.0)
+            } else {
+                (v.gain + step).min(1.0)
+            };
+        }
+
+        // Drops the tracks that are silent or finished.
+        let mut index = 0;
+        while index < self.voices.len() {
+            let finished = {
+                let v = &self.voices[index];
+                let silent = v.fading_out && v.gain <= 0.0;
+                silent || !v.sources.iter().any(|&h| audio.is_playing(h))
+            };
+
+            if finished {
+                let v = self.voices.remove(index);
+                for handle in v.sources {
+                    audio.stop(handle);
+                }
+
+                if self.current == Some(v.track) && !v.fading_out {
+                    self.current = None;
+                }
+            } else {
+                index += 1;
+            }
+        }
+
+        for v in &self.voices {
+            let track = &self.tracks[v.track];
+            for (stem, &handle) in track.stems.iter().zip(&v.sources) {
+                let volume = self.volume * v.gain * stem.volume(self.intensity);
+                audio.set_volume(handle, volume);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fade_out(&mut self) {
+        for v in &mut self.voices {
+            v.fading_out = true;
+        }
+    }
+}
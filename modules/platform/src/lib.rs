@@ -0,0 +1,86 @@
+//! The services of platforms that applications are published on, e.g. achievements,
+//! rich presence and cloud saves of a store. They are defined as traits, so gameplay
+//! code never talks with the SDK of a platform directly, and runs unchanged on the
+//! platforms without them.
+//!
+//! `NullPlatform` is the fallback when there is no platform, e.g. in development
+//! builds. Backends of actual platforms implement `PlatformServices` in their own
+//! crates, since their SDKs could not be shipped with crayon.
+//!
+//! ```rust,ignore
+//! let mut platform: Box<dyn PlatformServices> = Box::new(NullPlatform::new());
+//!
+//! // Once a frame.
+//! platform.advance();
+//! if !platform.is_overlay_active() {
+//!     self.update_gameplay(platform.as_mut())?;
+//! }
+//! ```
+
+#[macro_use]
+extern crate crayon;
+#[macro_use]
+extern crate failure;
+
+pub mod null;
+pub use self::null::NullPlatform;
+
+pub mod prelude {
+    pub use super::{Achievements, CloudSaves, NullPlatform, Overlay, PlatformServices, RichPresence};
+}
+
+pub type Result<T> = ::std::result::Result<T, ::failure::Error>;
+
+/// The achievements of platform.
+pub trait Achievements {
+    /// Unlocks an achievement. Unlocking it again takes no effect.
+    fn unlock_achievement(&mut self, id: &str) -> Result<()>;
+
+    /// Returns true if the achievement has been unlocked.
+    fn is_achievement_unlocked(&self, id: &str) -> bool;
+
+    /// Reports the progress of an achievement, e.g. 3 of 10 items collected. It's
+    /// unlocked once `current` reaches `max`.
+    fn set_achievement_progress(&mut self, id: &str, current: u32, max: u32) -> Result<()>;
+}
+
+/// The rich presence shown to friends, e.g. the level that the player is in.
+pub trait RichPresence {
+    /// Sets a key of rich presence, e.g. `("status", "In the forest")`.
+    fn set_presence(&mut self, key: &str, value: &str);
+
+    /// Clears all the keys of rich presence.
+    fn clear_presence(&mut self);
+}
+
+/// The files that are synchronized across the devices of player.
+pub trait CloudSaves {
+    /// Returns true if cloud saves are available. Applications should keep their
+    /// saves locally if not, e.g. with `UserDirFilesystem`.
+    fn is_cloud_enabled(&self) -> bool;
+
+    /// Writes the whole file `name`, replacing it if it exists already.
+    fn write_cloud(&mut self, name: &str, bytes: &[u8]) -> Result<()>;
+
+    /// Reads the whole file `name`.
+    fn read_cloud(&self, name: &str) -> Result<Vec<u8>>;
+
+    /// Removes the file `name`.
+    fn remove_cloud(&mut self, name: &str) -> Result<()>;
+}
+
+/// The overlay that platform shows over the application, e.g. a store page or chats.
+pub trait Overlay {
+    /// Returns true if the overlay is shown. Applications usually pause until it's
+    /// closed, since the inputs go to the overlay.
+    fn is_overlay_active(&self) -> bool;
+}
+
+/// All the services of a platform.
+pub trait PlatformServices: Achievements + RichPresence + CloudSaves + Overlay + Send {
+    /// Gets the name of platform.
+    fn name(&self) -> &str;
+
+    /// Handles the callbacks of platform, it should be called once a frame.
+    fn advance(&mut self) {}
+}
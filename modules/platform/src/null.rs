@@ -0,0 +1,88 @@
+use crayon::utils::{FastHashMap, FastHashSet};
+
+use super::*;
+
+/// The services when there is no platform. Achievements and rich presence are kept
+/// for the session only, cloud saves are not available and the overlay is never shown.
+#[derive(Debug, Default)]
+pub struct NullPlatform {
+    unlocked: FastHashSet<String>,
+    presence: FastHashMap<String, String>,
+}
+
+impl NullPlatform {
+    pub fn new() -> Self {
+        NullPlatform::default()
+    }
+
+    /// Gets a key of rich presence.
+    pub fn presence(&self, key: &str) -> Option<&str> {
+        self.presence.get(key).map(|v| v.as_str())
+    }
+}
+
+impl Achievements for NullPlatform {
+    fn unlock_achievement(&mut self, id: &str) -> Result<()> {
+        if self.unlocked.insert(id.to_owned()) {
+            info!("[NullPlatform] unlocks achievement {}.", id);
+        }
+
+        Ok(())
+    }
+
+    fn is_achievement_unlocked(&self, id: &str) -> bool {
+        self.unlocked.contains(id)
+    }
+
+    fn set_achievement_progress(&mut self, id: &str, current: u32, max: u32) -> Result<()> {
+        if max == 0 {
+            bail!("The progress of achievement {} has no maximum.", id);
+        }
+
+        if current >= max {
+            self.unlock_achievement(id)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl RichPresence for NullPlatform {
+    fn set_presence(&mut self, key: &str, value: &str) {
+        self.presence.insert(key.to_owned(), value.to_owned());
+    }
+
+    fn clear_presence(&mut self) {
+        self.presence.clear();
+    }
+}
+
+impl CloudSaves for NullPlatform {
+    fn is_cloud_enabled(&self) -> bool {
+        false
+    }
+
+    fn write_cloud(&mut self, name: &str, _: &[u8]) -> Result<()> {
+        bail!("Cloud saves are not available to write {}.", name);
+    }
+
+    fn read_cloud(&self, name: &str) -> Result<Vec<u8>> {
+        bail!("Cloud saves are not available to read {}.", name);
+    }
+
+    fn remove_cloud(&mut self, name: &str) -> Result<()> {
+        bail!("Cloud saves are not available to remove {}.", name);
+    }
+}
+
+impl Overlay for NullPlatform {
+    fn is_overlay_active(&self) -> bool {
+        false
+    }
+}
+
+impl PlatformServices for NullPlatform {
+    fn name(&self) -> &str {
+        "null"
+    }
+}
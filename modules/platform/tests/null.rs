@@ -0,0 +1,36 @@
+extern crate crayon_platform;
+
+use crayon_platform::prelude::*;
+
+// Runs gameplay code against the traits only.
+fn finish_level(platform: &mut dyn PlatformServices, collected: u32) {
+    platform.set_presence("status", "Level 2");
+    platform.unlock_achievement("level-1").unwrap();
+    platform
+        .set_achievement_progress("collector", collected, 10)
+        .unwrap();
+}
+
+#[test]
+fn null_platform() {
+    let mut platform = NullPlatform::new();
+    finish_level(&mut platform, 3);
+
+    assert_eq!(platform.name(), "null");
+    assert!(platform.is_achievement_unlocked("level-1"));
+    assert!(!platform.is_achievement_unlocked("collector"));
+    assert_eq!(platform.presence("status"), Some("Level 2"));
+
+    finish_level(&mut platform, 10);
+    assert!(platform.is_achievement_unlocked("collector"));
+    assert!(platform.set_achievement_progress("broken", 0, 0).is_err());
+
+    platform.clear_presence();
+    assert_eq!(platform.presence("status"), None);
+
+    // Saves should be kept locally without cloud.
+    assert!(!platform.is_cloud_enabled());
+    assert!(platform.write_cloud("1.sav", b"level 2").is_err());
+    assert!(platform.read_cloud("1.sav").is_err());
+    assert!(!platform.is_overlay_active());
+}
@@ -1,10 +1,12 @@
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 
+use super::module::{Module, ModuleRegistry, ModuleSwitch};
 use super::*;
 use input;
 use res;
 use sched;
+use sched::unwind;
 use video;
 
 type Result<T> = ::std::result::Result<T, ::failure::Error>;
@@ -23,6 +25,8 @@ pub struct Context {
     pub video: Arc<video::VideoSystemShared>,
     pub window: Arc<window::WindowShared>,
     pub sched: Arc<sched::ScheduleSystemShared>,
+    pub preferences: Arc<Preferences>,
+    pub timeline: Arc<Timeline>,
 
     data: Arc<RwLock<ContextData>>,
 }
@@ -51,6 +55,7 @@ pub struct Engine {
     pub sched: sched::ScheduleSystem,
 
     context: Context,
+    modules: Arc<RwLock<ModuleRegistry>>,
     headless: bool,
 }
 
@@ -62,7 +67,8 @@ impl Engine {
 
     /// Setup engine with specified settings.
     pub fn new_with(settings: &Settings) -> Result<Self> {
-        let sched = sched::ScheduleSystem::new(6, None, None);
+        let workers = settings.engine.workers.max(1);
+        let sched = sched::ScheduleSystem::new(workers, None, None);
         let sched_shared = sched.shared();
 
         let input = input::InputSystem::new(settings.input);
@@ -88,6 +94,11 @@ impl Engine {
         let time = time::TimeSystem::new(settings.engine);
         let time_shared = time.shared();
 
+        let preferences = match settings.preferences {
+            Some(ref path) => Preferences::load_from(path)?,
+            None => Preferences::new(),
+        };
+
         let context = Context {
             res: res_shared,
             input: input_shared,
@@ -95,6 +106,8 @@ impl Engine {
             video: video_shared,
             window: window.shared(),
             sched: sched_shared,
+            preferences: Arc::new(preferences),
+            timeline: Arc::new(Timeline::new()),
             data: Arc::new(RwLock::new(ContextData::default())),
         };
 
@@ -107,6 +120,7 @@ impl Engine {
             sched: sched,
 
             context: context,
+            modules: Arc::new(RwLock::new(ModuleRegistry::new())),
             headless: settings.headless,
         })
     }
@@ -115,6 +129,26 @@ impl Engine {
         &self.context
     }
 
+    /// Registers a module that hooks into the main loop. The hooks of `module` will
+    /// always be invoked after the modules named in `dependencies`.
+    ///
+    /// The returned `ModuleSwitch` could be kept to pause and resume the module.
+    pub fn register_module<T, M>(
+        &mut self,
+        name: T,
+        dependencies: &[&str],
+        module: M,
+    ) -> Result<ModuleSwitch>
+    where
+        T: Into<String>,
+        M: Module,
+    {
+        self.modules
+            .write()
+            .unwrap()
+            .register(name, dependencies, module)
+    }
+
     /// Run the main loop of `Engine`, this will block the working
     /// thread until we finished.
     pub fn run<T>(mut self, application: T) -> Result<Self>
@@ -126,8 +160,16 @@ impl Engine {
         let dir = ::std::env::current_dir()?;
         info!("CWD: {:?}.", dir);
 
+        // Reports unresolved module dependencies before running any frame.
+        self.modules.write().unwrap().order()?;
+
         let latch = Arc::new(sched::latch::LockLatch::new());
-        Self::execute_frame(&self.context, latch.clone(), application.clone());
+        Self::execute_frame(
+            &self.context,
+            latch.clone(),
+            application.clone(),
+            self.modules.clone(),
+        );
 
         let mut alive = true;
         while alive {
@@ -157,6 +199,7 @@ impl Engine {
             }
 
             self.time.advance();
+            self.res.advance();
             self.video.swap_frames();
 
             let (video_info, duration) = {
@@ -164,7 +207,13 @@ impl Engine {
 
                 // Perform update and render submitting for frame [x], and drawing
                 // frame [x-1] at the same time.
-                Self::execute_frame(&self.context, latch.clone(), application.clone());
+                Self::execute_frame(
+                    &self.context,
+                    latch.clone(),
+                    application.clone(),
+                    self.modules.clone(),
+                );
+
                 // This will block the main-thread until all the video commands is finished by GPU.
                 let video_info = self.video.advance(&self.window)?;
                 (video_info, duration)
@@ -179,20 +228,51 @@ impl Engine {
                     fps: self.time.shared().get_fps(),
                 };
 
+                let mut modules = self.modules.write().unwrap();
+                modules.on_post_update(&self.context, &info)?;
+
                 let mut application = application.write().unwrap();
                 application.on_post_update(&self.context, &info)?;
+
+                if self.context.timeline.is_recording() {
+                    let loads = self.context.res.load_stats();
+                    let timeline = &self.context.timeline;
+                    timeline.record(&info, &modules.timings()?, loads);
+                }
+
+                modules.reset_timings();
             }
 
             alive = alive && !self.context.is_shutdown() && !self.headless;
         }
 
-        {
+        // Tears down the sub-systems in order. The frame in flight is finished first, then
+        // the pending loads, so nothing is attached after the modules released their
+        // resources.
+        latch.wait_and_take()?;
+        self.res.shutdown();
+
+        // Modules tear down even if the application failed to, and the first error is
+        // reported after the sub-systems are shut down.
+        let result = {
             let mut application = application.write().unwrap();
-            application.on_exit(&self.context)?;
-        }
+            let rsp = application.on_exit(&self.context);
+
+            let mut modules = self.modules.write().unwrap();
+            let modules_rsp = modules.on_exit(&self.context);
+            rsp.and(modules_rsp)
+        };
+
+        // Executes the deletions of video objects submitted in `on_exit`.
+        self.video.swap_frames();
+        self.video.advance(&self.window)?;
+
+        self.context.preferences.save()?;
 
         self.sched.terminate();
         self.sched.wait_until_terminated();
+
+        result?;
         Ok(self)
     }
 
@@ -200,20 +280,77 @@ impl Engine {
         ctx: &Context,
         latch: Arc<sched::latch::LockLatch<Result<Duration>>>,
         app: Arc<RwLock<T>>,
+        modules: Arc<RwLock<ModuleRegistry>>,
     ) where
         T: Application + Send + Sync + 'static,
     {
-        let run = |ctx, app: Arc<RwLock<T>>| {
+        let run = |ctx: Context, app: Arc<RwLock<T>>, modules: Arc<RwLock<ModuleRegistry>>| {
             let ts = Instant::now();
 
+            let mut modules = modules.write().unwrap();
             let mut application = app.write().unwrap();
+            modules.on_pre_update(&ctx)?;
+
+            for _ in 0..ctx.time.fixed_steps() {
+                modules.on_fixed_update(&ctx)?;
+                application.on_fixed_update(&ctx)?;
+            }
+
             application.on_update(&ctx)?;
+            modules.on_pre_render(&ctx)?;
             application.on_render(&ctx)?;
 
             Ok(Instant::now() - ts)
         };
 
+        // Delivers panics of hooks to the main thread as errors, so the engine could
+        // still exit gracefully.
         let ctx_clone = ctx.clone();
-        ctx.sched.spawn(move || latch.set(run(ctx_clone, app)));
+        ctx.sched.spawn(move || {
+            let rsp = unwind::halt_unwinding(|| run(ctx_clone, app, modules));
+            latch.set(rsp.unwrap_or_else(|err| {
+                Err(format_err!("Panicked: {}", unwind::panic_message(&*err)))
+            }))
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Failure;
+
+    impl Application for Failure {
+        fn on_exit(&mut self, _: &Context) -> Result<()> {
+            bail!("Application failed to exit.");
+        }
+    }
+
+    struct Counter(Arc<AtomicUsize>);
+
+    impl Module for Counter {
+        fn on_exit(&mut self, _: &Context) -> Result<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn exit() {
+        let mut settings = Settings::default();
+        settings.headless = true;
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let mut engine = Engine::new_with(&settings).unwrap();
+        engine
+            .register_module("counter", &[], Counter(counter.clone()))
+            .unwrap();
+
+        // Modules tear down even if the application failed to.
+        let err = engine.run(Failure).err().unwrap();
+        assert_eq!(err.to_string(), "Application failed to exit.");
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
     }
 }
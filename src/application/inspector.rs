@@ -0,0 +1,524 @@
+//! A TCP server which lets external tools inspect a running application. It's enabled
+//! with the `inspector` feature.
+//!
+//! The protocol is plain text, so `telnet` or `nc` is enough to talk with it. Every
+//! request is a line of command and its arguments separated by whitespaces, and every
+//! response ends with an empty line. Messages that are not replies of requests could be
+//! pushed to all the clients with `Inspector::broadcast`, and the logs are streamed to
+//! them once `InspectorLogger` is installed:
+//!
+//! ```rust,ignore
+//! let inspector = Inspector::bind("0.0.0.0:7001")?;
+//! inspector.logger().install(LevelFilter::Info)?;
+//! ```
+//!
+//! The `Inspector` does not run by itself. Applications poll it every frame and answer
+//! the requests they know, which lets them expose states that are only reachable from
+//! the main thread:
+//!
+//! ```rust,ignore
+//! fn on_update(&mut self, ctx: &Context) -> Result<()> {
+//!     for req in self.inspector.poll() {
+//!         let rsp = match req.command.as_str() {
+//!             "score" => format!("{}", self.score),
+//!             _ => inspector::builtin(ctx, &req)
+//!                 .unwrap_or_else(|| format!("Unknown command {}.", req.command)),
+//!         };
+//!
+//!         self.inspector.reply(&req, &rsp);
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use errors::*;
+use res::location::Location;
+
+use super::Context;
+
+/// The maximum length of a request line. Clients that send longer lines are dropped.
+pub const MAX_REQUEST_LEN: usize = 4 * 1024;
+/// The maximum bytes of pending output of a client. Clients that don't read their
+/// responses fast enough are dropped once their output exceeds it.
+pub const MAX_PENDING_OUTPUT: usize = 1024 * 1024;
+/// The maximum number of log lines kept until next poll, the oldest ones are dropped
+/// first.
+pub const MAX_PENDING_LOGS: usize = 1024;
+
+/// A request from a client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    client: usize,
+    /// The first word of request.
+    pub command: String,
+    /// The words after command.
+    pub args: Vec<String>,
+}
+
+struct Client {
+    id: usize,
+    stream: TcpStream,
+    buf: Vec<u8>,
+    out: Vec<u8>,
+    alive: bool,
+}
+
+impl Client {
+    fn write(&mut self, text: &str) {
+        if !self.alive {
+            return;
+        }
+
+        if self.out.len() + text.len() > MAX_PENDING_OUTPUT {
+            warn!("Inspector drops client {} which does not read its output.", self.id);
+            self.alive = false;
+            return;
+        }
+
+        self.out.extend_from_slice(text.as_bytes());
+        self.flush();
+    }
+
+    // Writes as much as possible without blocking, the rest is sent on next poll.
+    fn flush(&mut self) {
+        while !self.out.is_empty() {
+            match self.stream.write(&self.out) {
+                Ok(0) => self.alive = false,
+                Ok(len) => {
+                    self.out.drain(0..len);
+                    continue;
+                }
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => {}
+                Err(_) => self.alive = false,
+            }
+
+            break;
+        }
+    }
+}
+
+/// The debug server which accepts clients and collects their requests.
+pub struct Inspector {
+    listener: TcpListener,
+    clients: Vec<Client>,
+    counter: usize,
+    logs: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl Inspector {
+    /// Creates a new `Inspector` listening on `addr`.
+    pub fn bind<T: ToSocketAddrs>(addr: T) -> Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        info!("Inspector listens on {}.", listener.local_addr()?);
+
+        Ok(Inspector {
+            listener: listener,
+            clients: Vec::new(),
+            counter: 0,
+            logs: Arc::new(Mutex::new(VecDeque::new())),
+        })
+    }
+
+    /// Creates a logger which streams the logs to the clients of this inspector.
+    pub fn logger(&self) -> InspectorLogger {
+        InspectorLogger {
+            logs: self.logs.clone(),
+            next: None,
+        }
+    }
+
+    /// Gets the local address that this inspector listens on.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Gets the number of connected clients.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Returns true if there are no connected clients.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Accepts the new clients, and returns the requests received since last poll.
+    /// This never blocks.
+    pub fn poll(&mut self) -> Vec<Request> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    if stream.set_nonblocking(true).is_ok() {
+                        info!("Inspector accepts client {}.", addr);
+                        self.counter += 1;
+                        self.clients.push(Client {
+                            id: self.counter,
+                            stream: stream,
+                            buf: Vec::new(),
+                            out: Vec::new(),
+                            alive: true,
+                        });
+                    }
+                }
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    warn!("Inspector failed to accept client: {}.", err);
+                    break;
+                }
+            }
+        }
+
+        // Takes the logs out first, the broadcast might log too.
+        let logs: Vec<_> = self.logs.lock().unwrap().drain(..).collect();
+        for v in logs {
+            self.broadcast(&v);
+        }
+
+        let mut requests = Vec::new();
+        let mut bytes = [0; 1024];
+
+        for v in &mut self.clients {
+            v.flush();
+
+            // The bytes beyond a few lines are left in the socket until next poll.
+            while v.alive && v.buf.len() <= MAX_REQUEST_LEN {
+                match v.stream.read(&mut bytes) {
+                    Ok(0) => {
+                        v.alive = false;
+                        break;
+                    }
+                    Ok(len) => v.buf.extend_from_slice(&bytes[0..len]),
+                    Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+                    Err(ref err) if err.kind() == ErrorKind::Interrupted => {}
+                    Err(_) => {
+                        v.alive = false;
+                        break;
+                    }
+                }
+            }
+
+            while let Some(n) = v.buf.iter().position(|&c| c == b'\n') {
+                let line: Vec<_> = v.buf.drain(0..(n + 1)).collect();
+                let line = String::from_utf8_lossy(&line);
+                let mut words = line.split_whitespace().map(|w| w.to_owned());
+
+                if let Some(command) = words.next() {
+                    requests.push(Request {
+                        client: v.id,
+                        command: command,
+                        args: words.collect(),
+                    });
+                }
+            }
+
+            if v.buf.len() > MAX_REQUEST_LEN {
+                warn!("Inspector drops client {} with a request line too long.", v.id);
+                v.alive = false;
+            }
+        }
+
+        self.clients.retain(|v| v.alive);
+        requests
+    }
+
+    /// Replies a request. It's dropped if the client has disconnected.
+    pub fn reply(&mut self, request: &Request, text: &str) {
+        if let Some(v) = self.clients.iter_mut().find(|v| v.id == request.client) {
+            v.write(text);
+            v.write(if text.ends_with('\n') { "\n" } else { "\n\n" });
+        }
+    }
+
+    /// Sends a line to all the clients.
+    pub fn broadcast(&mut self, text: &str) {
+        for v in &mut self.clients {
+            v.write(text);
+            v.write("\n");
+        }
+    }
+}
+
+/// A `log::Log` which streams the logs to the clients of `Inspector` on its next poll,
+/// and passes them on to another logger if there is one.
+pub struct InspectorLogger {
+    logs: Arc<Mutex<VecDeque<String>>>,
+    next: Option<Box<dyn Log>>,
+}
+
+impl InspectorLogger {
+    /// Passes the logs on to `logger` too, e.g. the one printing to console.
+    pub fn chain<T: Log + 'static>(mut self, logger: T) -> Self {
+        self.next = Some(Box::new(logger));
+        self
+    }
+
+    /// Installs this as the global logger, which could be done only once. The logs
+    /// above `level` are discarded.
+    pub fn install(self, level: LevelFilter) -> Result<()> {
+        if let Err(err) = ::log::set_logger(Box::leak(Box::new(self))) {
+            bail!("{}", err);
+        }
+
+        ::log::set_max_level(level);
+        Ok(())
+    }
+}
+
+impl Log for InspectorLogger {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let line = format!("[{}] {}: {}", record.level(), record.target(), record.args());
+
+        {
+            let mut logs = self.logs.lock().unwrap();
+            if logs.len() >= MAX_PENDING_LOGS {
+                logs.pop_front();
+            }
+
+            logs.push_back(line);
+        }
+
+        if let Some(ref next) = self.next {
+            if next.enabled(record.metadata()) {
+                next.log(record);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(ref next) = self.next {
+            next.flush();
+        }
+    }
+}
+
+/// Answers the built-in commands with the states of engine.
+///
+/// * `stats` prints the frame rate and the statistics of resource loading.
+/// * `resources` prints the resources created from files, and the memory of caches.
+/// * `dependencies [location...]` prints the dependency graph in DOT format, starting
+/// from the resources at `location`s, or all the resources if none is given.
+pub fn builtin(ctx: &Context, request: &Request) -> Option<String> {
+    match request.command.as_str() {
+        "stats" => {
+            let loads = ctx.res.load_stats();
+            Some(format!(
+                "fps: {}\nframe: {:?}\nloads: {} pending, {} finished, {} failed",
+                ctx.time.get_fps(),
+                ctx.time.frame_delta(),
+                loads.pending,
+                loads.finished,
+                loads.failed
+            ))
+        }
+        "resources" => {
+            let mut entries: Vec<_> = ctx
+                .res
+                .entries()
+                .into_iter()
+                .map(|v| (ctx.res.filename(v.uuid).unwrap_or_default(), v))
+                .collect();
+
+            entries.sort_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
+
+            let mut text = match ctx.res.memory_budget() {
+                Some(budget) => format!("cached: {} / {} bytes\n", ctx.res.cached_bytes(), budget),
+                None => format!("cached: {} bytes\n", ctx.res.cached_bytes()),
+            };
+
+            for (filename, v) in entries {
+                let state = if v.loading {
+                    "loading"
+                } else if v.failed {
+                    "failed"
+                } else if v.rc == 0 {
+                    "cached"
+                } else {
+                    "loaded"
+                };
+
+                text.push_str(&format!(
+                    "{} ({}): {}, rc: {}, {} bytes\n",
+                    filename, v.uuid, state, v.rc, v.bytes
+                ));
+            }
+
+            Some(text)
+        }
+        "dependencies" => {
+            let roots: Vec<_> = if request.args.is_empty() {
+                ctx.res.entries().iter().map(|v| v.uuid).collect()
+            } else {
+                let mut roots = Vec::new();
+                for v in &request.args {
+                    match Location::new(v).ok().and_then(|v| ctx.res.redirect(v)) {
+                        Some(uuid) => roots.push(uuid),
+                        None => return Some(format!("Undefined resource {}.", v)),
+                    }
+                }
+
+                roots
+            };
+
+            let mut graph = Vec::new();
+            ctx.res.write_dependency_graph(&roots, &mut graph).ok()?;
+            Some(String::from_utf8_lossy(&graph).into_owned())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::thread;
+    use std::time::Duration;
+
+    fn poll(inspector: &mut Inspector, n: usize) -> Vec<Request> {
+        let mut requests = Vec::new();
+        for _ in 0..500 {
+            requests.extend(inspector.poll());
+            if requests.len() >= n {
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(2));
+        }
+
+        requests
+    }
+
+    #[test]
+    fn requests() {
+        let mut inspector = Inspector::bind("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(inspector.local_addr().unwrap()).unwrap();
+
+        stream.write_all(b"entities  root \n\nstats").unwrap();
+        stream.write_all(b"\n").unwrap();
+
+        let requests = poll(&mut inspector, 2);
+        assert_eq!(inspector.len(), 1);
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].command, "entities");
+        assert_eq!(requests[0].args, vec!["root".to_owned()]);
+        assert_eq!(requests[1].command, "stats");
+        assert!(requests[1].args.is_empty());
+
+        inspector.reply(&requests[0], "e1\ne2");
+        inspector.broadcast("log");
+
+        let mut reader = BufReader::new(stream);
+        let lines: Vec<_> = (0..4)
+            .map(|_| {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                line
+            }).collect();
+
+        assert_eq!(lines, ["e1\n", "e2\n", "\n", "log\n"]);
+
+        drop(reader);
+        for _ in 0..500 {
+            inspector.poll();
+            if inspector.is_empty() {
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(2));
+        }
+
+        assert!(inspector.is_empty());
+    }
+
+    #[test]
+    fn logs() {
+        let mut inspector = Inspector::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(inspector.local_addr().unwrap()).unwrap();
+        for _ in 0..500 {
+            inspector.poll();
+            if !inspector.is_empty() {
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(2));
+        }
+
+        let logger = inspector.logger();
+        for i in 0..(MAX_PENDING_LOGS + 1) {
+            logger.log(
+                &Record::builder()
+                    .args(format_args!("{}", i))
+                    .level(::log::Level::Warn)
+                    .target("app")
+                    .build(),
+            );
+        }
+
+        // The oldest one is dropped.
+        inspector.poll();
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "[WARN] app: 1\n");
+    }
+
+    fn wait_until_empty(inspector: &mut Inspector) {
+        for _ in 0..500 {
+            inspector.poll();
+            if inspector.is_empty() {
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(2));
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let mut inspector = Inspector::bind("127.0.0.1:0").unwrap();
+        let addr = inspector.local_addr().unwrap();
+
+        // A line without end is dropped, after the requests before it.
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"stats\n").unwrap();
+        stream.write_all(&[b'a'; MAX_REQUEST_LEN + 1]).unwrap();
+        assert_eq!(poll(&mut inspector, 1).len(), 1);
+        wait_until_empty(&mut inspector);
+        assert!(inspector.is_empty());
+
+        // A client that never reads is dropped once its output piles up.
+        let _stream = TcpStream::connect(addr).unwrap();
+        for _ in 0..500 {
+            if inspector.poll().is_empty() && !inspector.is_empty() {
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(2));
+        }
+
+        assert_eq!(inspector.len(), 1);
+        let text = "x".repeat(64 * 1024);
+        for _ in 0..1024 {
+            inspector.broadcast(&text);
+        }
+
+        inspector.poll();
+        assert!(inspector.is_empty());
+    }
+}
@@ -27,10 +27,17 @@
 //!
 
 pub mod events;
+#[cfg(feature = "inspector")]
+pub mod inspector;
+pub mod module;
+pub mod preferences;
 pub mod settings;
 pub mod time;
+pub mod timeline;
 pub mod window;
+pub use self::preferences::Preferences;
 pub use self::settings::Settings;
+pub use self::timeline::Timeline;
 
 mod engine;
 pub use self::engine::{Context, Engine};
@@ -38,8 +45,9 @@ pub use self::engine::{Context, Engine};
 mod backends;
 
 pub mod prelude {
+    pub use super::module::{Module, ModuleSwitch};
     pub use super::FrameInfo;
-    pub use super::{Application, Context, Engine, Settings};
+    pub use super::{Application, Context, Engine, Preferences, Settings, Timeline};
     pub use errors::Result;
 }
 
@@ -65,6 +73,14 @@ pub trait Application {
         Ok(())
     }
 
+    /// `Application::on_fixed_update` is called every fixed timestep before
+    /// `Application::on_update`, which makes it suitable for things like physics that
+    /// should advance at a deterministic rate. It might be called several times or not
+    /// at all in one frame.
+    fn on_fixed_update(&mut self, _: &Context) -> Result<()> {
+        Ok(())
+    }
+
     /// `Application::on_render` is called before we starts rendering the scene.
     fn on_render(&mut self, _: &Context) -> Result<()> {
         Ok(())
@@ -0,0 +1,396 @@
+//! A registry of user modules that hooks into the lifecycle of `Engine`.
+//!
+//! Modules are registered with a unique name and the names of the modules that
+//! they depend on. The hooks are invoked in dependency order, so a module always
+//! runs after all of its dependencies (and in reverse order when exiting).
+//!
+//! Registering a module returns a `ModuleSwitch`, which could be used to pause the
+//! module (e.g. gameplay modules during a pause menu) without unregistering it.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::{Context, FrameInfo};
+use errors::*;
+use utils::FastHashMap;
+
+/// The lifecycle hooks of a module. All of them are optional.
+pub trait Module: Send + Sync + 'static {
+    /// Called every frame before `Application::on_update`.
+    fn on_pre_update(&mut self, _: &Context) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called every fixed timestep after `on_pre_update`, but before
+    /// `Application::on_fixed_update`. It might be called several times or not at
+    /// all in one frame.
+    fn on_fixed_update(&mut self, _: &Context) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called every frame after `Application::on_update`, but before
+    /// `Application::on_render`.
+    fn on_pre_render(&mut self, _: &Context) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called every frame after the scene has been rendered, but before
+    /// `Application::on_post_update`.
+    fn on_post_update(&mut self, _: &Context, _: &FrameInfo) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called when exiting, after `Application::on_exit`.
+    fn on_exit(&mut self, _: &Context) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Enables or disables a registered module. It's cheap to clone and could be shared
+/// between threads.
+#[derive(Clone)]
+pub struct ModuleSwitch {
+    enabled: Arc<AtomicBool>,
+}
+
+impl ModuleSwitch {
+    /// Enables or disables the module. The hooks of a disabled module are skipped,
+    /// except `on_exit` which is always invoked.
+    #[inline]
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Returns true if the module is enabled.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+}
+
+struct ModuleEntry {
+    name: String,
+    dependencies: Vec<String>,
+    module: Box<dyn Module>,
+    elapsed: Duration,
+    switch: ModuleSwitch,
+}
+
+/// The registry of modules.
+pub struct ModuleRegistry {
+    entries: Vec<ModuleEntry>,
+    order: Option<Vec<usize>>,
+}
+
+impl ModuleRegistry {
+    pub fn new() -> Self {
+        ModuleRegistry {
+            entries: Vec::new(),
+            order: None,
+        }
+    }
+
+    /// Registers a module with its name and the names of its dependencies. The module
+    /// is enabled by default.
+    pub fn register<T, M>(
+        &mut self,
+        name: T,
+        dependencies: &[&str],
+        module: M,
+    ) -> Result<ModuleSwitch>
+    where
+        T: Into<String>,
+        M: Module,
+    {
+        let name = name.into();
+        if self.contains(&name) {
+            bail!("Module {} has been registered already.", name);
+        }
+
+        let switch = ModuleSwitch {
+            enabled: Arc::new(AtomicBool::new(true)),
+        };
+
+        self.entries.push(ModuleEntry {
+            name: name,
+            dependencies: dependencies.iter().map(|v| v.to_string()).collect(),
+            module: Box::new(module),
+            elapsed: Duration::default(),
+            switch: switch.clone(),
+        });
+
+        self.order = None;
+        Ok(switch)
+    }
+
+    /// Returns true if there is a module registered with `name`.
+    pub fn contains<T: AsRef<str>>(&self, name: T) -> bool {
+        self.entries.iter().any(|v| v.name == name.as_ref())
+    }
+
+    /// Gets the number of registered modules.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there is no registered module.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Gets the module names in the order that their hooks will be invoked.
+    pub fn order(&mut self) -> Result<Vec<&str>> {
+        self.resolve()?;
+
+        let entries = &self.entries;
+        let order = self.order.as_ref().unwrap();
+        Ok(order.iter().map(|&v| entries[v].name.as_str()).collect())
+    }
+
+    /// Gets the time spent in the hooks of every module since last `reset_timings`,
+    /// in the order that their hooks are invoked.
+    pub fn timings(&mut self) -> Result<Vec<(&str, Duration)>> {
+        self.resolve()?;
+
+        let entries = &self.entries;
+        let order = self.order.as_ref().unwrap();
+        Ok(order
+            .iter()
+            .map(|&v| (entries[v].name.as_str(), entries[v].elapsed))
+            .collect())
+    }
+
+    /// Resets the time spent in hooks.
+    pub fn reset_timings(&mut self) {
+        for v in &mut self.entries {
+            v.elapsed = Duration::default();
+        }
+    }
+
+    pub(crate) fn on_pre_update(&mut self, ctx: &Context) -> Result<()> {
+        self.foreach(false, |v| v.on_pre_update(ctx))
+    }
+
+    pub(crate) fn on_fixed_update(&mut self, ctx: &Context) -> Result<()> {
+        self.foreach(false, |v| v.on_fixed_update(ctx))
+    }
+
+    pub(crate) fn on_pre_render(&mut self, ctx: &Context) -> Result<()> {
+        self.foreach(false, |v| v.on_pre_render(ctx))
+    }
+
+    pub(crate) fn on_post_update(&mut self, ctx: &Context, info: &FrameInfo) -> Result<()> {
+        self.foreach(false, |v| v.on_post_update(ctx, info))
+    }
+
+    pub(crate) fn on_exit(&mut self, ctx: &Context) -> Result<()> {
+        self.foreach(true, |v| v.on_exit(ctx))
+    }
+
+    fn foreach<F>(&mut self, reverse: bool, mut func: F) -> Result<()>
+    where
+        F: FnMut(&mut dyn Module) -> Result<()>,
+    {
+        self.resolve()?;
+
+        let order = self.order.as_ref().unwrap();
+        let mut invoke = |entry: &mut ModuleEntry| {
+            // Only `on_exit` runs in reverse, and it's never skipped so disabled modules
+            // could still tear down.
+            if !reverse && !entry.switch.is_enabled() {
+                return Ok(());
+            }
+
+            let ts = Instant::now();
+            let rsp = func(entry.module.as_mut());
+            entry.elapsed += Instant::now() - ts;
+            rsp
+        };
+
+        if reverse {
+            // Every module tears down even if the ones before it failed, and the first
+            // error is reported.
+            let mut result = Ok(());
+            for &v in order.iter().rev() {
+                let rsp = invoke(&mut self.entries[v]);
+                if result.is_ok() {
+                    result = rsp;
+                }
+            }
+
+            result
+        } else {
+            for &v in order {
+                invoke(&mut self.entries[v])?;
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Sorts modules topologically, keeping the registration order between modules
+    /// that do not depend on each other.
+    fn resolve(&mut self) -> Result<()> {
+        if self.order.is_some() {
+            return Ok(());
+        }
+
+        let mut remap = FastHashMap::default();
+        for (i, v) in self.entries.iter().enumerate() {
+            remap.insert(v.name.as_str(), i);
+        }
+
+        let mut dependencies = Vec::with_capacity(self.entries.len());
+        for v in &self.entries {
+            let mut indices = Vec::new();
+            for name in &v.dependencies {
+                match remap.get(name.as_str()) {
+                    Some(&index) => indices.push(index),
+                    None => bail!("Module {} depends on unregistered module {}.", v.name, name),
+                }
+            }
+
+            dependencies.push(indices);
+        }
+
+        let mut order = Vec::with_capacity(self.entries.len());
+        let mut visited = vec![false; self.entries.len()];
+
+        while order.len() < self.entries.len() {
+            let next = (0..self.entries.len())
+                .find(|&i| !visited[i] && dependencies[i].iter().all(|&d| visited[d]));
+
+            match next {
+                Some(index) => {
+                    visited[index] = true;
+                    order.push(index);
+                }
+                None => {
+                    let names: Vec<_> = (0..self.entries.len())
+                        .filter(|&i| !visited[i])
+                        .map(|i| self.entries[i].name.as_str())
+                        .collect();
+
+                    bail!("Circular dependencies found between modules {:?}.", names);
+                }
+            }
+        }
+
+        self.order = Some(order);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Dummy;
+    impl Module for Dummy {}
+
+    #[test]
+    fn order() {
+        let mut modules = ModuleRegistry::new();
+        modules.register("3d", &["video", "res"], Dummy).unwrap();
+        modules.register("video", &["res"], Dummy).unwrap();
+        modules.register("audio", &["res"], Dummy).unwrap();
+        modules.register("res", &[], Dummy).unwrap();
+        assert!(modules.register("res", &[], Dummy).is_err());
+
+        assert_eq!(modules.len(), 4);
+        assert!(modules.contains("video"));
+        assert!(!modules.contains("physics"));
+        assert_eq!(modules.order().unwrap(), ["res", "video", "3d", "audio"]);
+
+        modules.register("ui", &["video"], Dummy).unwrap();
+        assert_eq!(
+            modules.order().unwrap(),
+            ["res", "video", "3d", "audio", "ui"]
+        );
+    }
+
+    #[test]
+    fn timings() {
+        let mut modules = ModuleRegistry::new();
+        modules.register("video", &[], Dummy).unwrap();
+        modules.register("3d", &["video"], Dummy).unwrap();
+
+        modules
+            .foreach(false, |_| {
+                ::std::thread::sleep(Duration::from_millis(1));
+                Ok(())
+            }).unwrap();
+
+        let timings = modules.timings().unwrap();
+        assert_eq!(timings[0].0, "video");
+        assert!(timings.iter().all(|v| v.1 >= Duration::from_millis(1)));
+
+        modules.reset_timings();
+        let timings = modules.timings().unwrap();
+        assert!(timings.iter().all(|v| v.1 == Duration::default()));
+    }
+
+    #[test]
+    fn switch() {
+        let mut modules = ModuleRegistry::new();
+        let video = modules.register("video", &[], Dummy).unwrap();
+        modules.register("3d", &["video"], Dummy).unwrap();
+
+        let mut count = 0;
+        video.set_enabled(false);
+        assert!(!video.is_enabled());
+
+        modules
+            .foreach(false, |_| {
+                count += 1;
+                Ok(())
+            }).unwrap();
+        assert_eq!(count, 1);
+
+        modules
+            .foreach(true, |_| {
+                count += 1;
+                Ok(())
+            }).unwrap();
+        assert_eq!(count, 3);
+
+        video.clone().set_enabled(true);
+        modules
+            .foreach(false, |_| {
+                count += 1;
+                Ok(())
+            }).unwrap();
+        assert_eq!(count, 5);
+    }
+
+    #[test]
+    fn exit() {
+        let mut modules = ModuleRegistry::new();
+        modules.register("video", &[], Dummy).unwrap();
+        modules.register("3d", &["video"], Dummy).unwrap();
+        modules.register("ui", &["video"], Dummy).unwrap();
+
+        // Failures do not skip the following modules.
+        let mut count = 0;
+        let rsp = modules.foreach(true, |_| {
+            count += 1;
+            bail!("Failure {}.", count);
+        });
+
+        assert_eq!(count, 3);
+        assert_eq!(rsp.unwrap_err().to_string(), "Failure 1.");
+    }
+
+    #[test]
+    fn unresolved() {
+        let mut modules = ModuleRegistry::new();
+        modules.register("3d", &["video"], Dummy).unwrap();
+        assert!(modules.order().is_err());
+
+        modules.register("video", &["3d"], Dummy).unwrap();
+        assert!(modules.order().is_err());
+    }
+}
@@ -0,0 +1,569 @@
+//! A typed registry of user preferences, e.g. graphics quality, audio volumes or key
+//! bindings, which are persisted into the user config file.
+//!
+//! Engine modules and game code register their preferences with default values, and
+//! optionally valid ranges. Changes are validated, broadcasted to subscribers, and
+//! written back when `Preferences::save` is called, or when the engine exits.
+//!
+//! The config file is made of `name = value` lines, and lines starting with `#` are
+//! ignored. Values of unregistered names are kept as they are, so preferences of other
+//! modules (or other versions of game) are not lost after saving.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use errors::*;
+use utils::object_pool::ObjectPool;
+use utils::FastHashMap;
+
+impl_handle!(PreferenceListener);
+
+/// The value of a preference.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Preference {
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    Str(String),
+}
+
+impl Preference {
+    fn parse(&self, text: &str) -> Option<Preference> {
+        match *self {
+            Preference::Bool(_) => text.parse().ok().map(Preference::Bool),
+            Preference::Int(_) => text.parse().ok().map(Preference::Int),
+            Preference::Float(_) => text.parse().ok().map(Preference::Float),
+            Preference::Str(_) => Some(Preference::Str(text.to_owned())),
+        }
+    }
+
+    fn is_same_type(&self, rhs: &Preference) -> bool {
+        match (self, rhs) {
+            (&Preference::Bool(_), &Preference::Bool(_)) => true,
+            (&Preference::Int(_), &Preference::Int(_)) => true,
+            (&Preference::Float(_), &Preference::Float(_)) => true,
+            (&Preference::Str(_), &Preference::Str(_)) => true,
+            _ => false,
+        }
+    }
+
+    fn within(&self, min: &Preference, max: &Preference) -> bool {
+        match (self, min, max) {
+            (&Preference::Int(v), &Preference::Int(min), &Preference::Int(max)) => {
+                v >= min && v <= max
+            }
+            (&Preference::Float(v), &Preference::Float(min), &Preference::Float(max)) => {
+                v >= min && v <= max
+            }
+            _ => true,
+        }
+    }
+}
+
+impl ::std::fmt::Display for Preference {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match *self {
+            Preference::Bool(v) => write!(f, "{}", v),
+            Preference::Int(v) => write!(f, "{}", v),
+            Preference::Float(v) => write!(f, "{:?}", v),
+            Preference::Str(ref v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// The types that could be stored as preferences.
+pub trait PreferenceType: Sized {
+    fn into_preference(self) -> Preference;
+    fn from_preference(v: &Preference) -> Option<Self>;
+}
+
+macro_rules! impl_preference_type {
+    ($ty:ty, $variant:ident) => {
+        impl PreferenceType for $ty {
+            fn into_preference(self) -> Preference {
+                Preference::$variant(self)
+            }
+
+            fn from_preference(v: &Preference) -> Option<Self> {
+                match *v {
+                    Preference::$variant(ref v) => Some(v.clone()),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+impl_preference_type!(bool, Bool);
+impl_preference_type!(i32, Int);
+impl_preference_type!(f32, Float);
+impl_preference_type!(String, Str);
+
+type Callback = Arc<dyn Fn(&str, &Preference) + Send + Sync>;
+
+struct Entry {
+    value: Preference,
+    default: Preference,
+    range: Option<(Preference, Preference)>,
+}
+
+struct PreferencesData {
+    entries: FastHashMap<String, Entry>,
+    unregistered: FastHashMap<String, String>,
+    listeners: ObjectPool<PreferenceListener, (String, Callback)>,
+    dirty: bool,
+}
+
+impl PreferencesData {
+    fn new() -> Self {
+        PreferencesData {
+            entries: FastHashMap::default(),
+            unregistered: FastHashMap::default(),
+            listeners: ObjectPool::new(),
+            dirty: false,
+        }
+    }
+}
+
+/// A thread-safe registry of preferences.
+pub struct Preferences {
+    path: Option<PathBuf>,
+    data: RwLock<PreferencesData>,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Preferences::new()
+    }
+}
+
+impl Preferences {
+    /// Creates a new `Preferences` which is not backed by any file.
+    pub fn new() -> Self {
+        Preferences {
+            path: None,
+            data: RwLock::new(PreferencesData::new()),
+        }
+    }
+
+    /// Creates a new `Preferences` backed by the config file at `path`. Values in the
+    /// file are applied when preferences get registered. It's fine if the file does
+    /// not exist yet.
+    pub fn load_from<T: AsRef<Path>>(path: T) -> Result<Self> {
+        let path = path.as_ref();
+        let preferences = Preferences {
+            path: Some(path.to_owned()),
+            data: RwLock::new(PreferencesData::new()),
+        };
+
+        if path.exists() {
+            let text = fs::read_to_string(path)?;
+            let mut data = preferences.data.write().unwrap();
+
+            for line in text.lines() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                if let Some(pos) = line.find('=') {
+                    let name = line[..pos].trim();
+                    let value = line[pos + 1..].trim();
+                    data.unregistered.insert(name.to_owned(), value.to_owned());
+                } else {
+                    warn!(
+                        "[Preferences] Ignores malformed line '{}' in {:?}.",
+                        line, path
+                    );
+                }
+            }
+        }
+
+        Ok(preferences)
+    }
+
+    /// Gets the path of the config file.
+    #[inline]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_ref().map(|v| v.as_ref())
+    }
+
+    /// Registers a preference with its default value. The value in config file is used
+    /// if there is a valid one.
+    pub fn register<T, N>(&self, name: N, default: T) -> Result<()>
+    where
+        T: PreferenceType,
+        N: Into<String>,
+    {
+        self.register_entry(name.into(), default.into_preference(), None)
+    }
+
+    /// Registers a numeric preference with its default value and valid range, values
+    /// out of the range are rejected.
+    pub fn register_range<T, N>(&self, name: N, default: T, min: T, max: T) -> Result<()>
+    where
+        T: PreferenceType + PartialOrd,
+        N: Into<String>,
+    {
+        if default < min || default > max {
+            bail!("The default value of preference is out of range.");
+        }
+
+        let range = (min.into_preference(), max.into_preference());
+        self.register_entry(name.into(), default.into_preference(), Some(range))
+    }
+
+    fn register_entry(
+        &self,
+        name: String,
+        default: Preference,
+        range: Option<(Preference, Preference)>,
+    ) -> Result<()> {
+        // The name would be parsed as something else from the config file.
+        if name.is_empty()
+            || name.trim() != name
+            || name.contains(&['=', '#', '\n'][..])
+        {
+            bail!("Preference '{}' is not a valid name.", name);
+        }
+
+        let mut data = self.data.write().unwrap();
+
+        if data.entries.contains_key(&name) {
+            bail!("Preference {} has been registered already.", name);
+        }
+
+        let mut value = default.clone();
+        if let Some(text) = data.unregistered.remove(&name) {
+            match default.parse(&text) {
+                Some(ref v) if range.as_ref().map(|r| v.within(&r.0, &r.1)).unwrap_or(true) => {
+                    value = v.clone();
+                }
+                _ => {
+                    warn!(
+                        "[Preferences] Ignores invalid value '{}' of {}.",
+                        text, name
+                    );
+                }
+            }
+        }
+
+        let entry = Entry {
+            value: value,
+            default: default,
+            range: range,
+        };
+
+        data.entries.insert(name, entry);
+        Ok(())
+    }
+
+    /// Returns true if the preference has been registered.
+    #[inline]
+    pub fn has<N: AsRef<str>>(&self, name: N) -> bool {
+        self.data
+            .read()
+            .unwrap()
+            .entries
+            .contains_key(name.as_ref())
+    }
+
+    /// Gets the value of preference, returns `None` if it does not exist or it is not
+    /// a `T`.
+    pub fn get<T, N>(&self, name: N) -> Option<T>
+    where
+        T: PreferenceType,
+        N: AsRef<str>,
+    {
+        self.data
+            .read()
+            .unwrap()
+            .entries
+            .get(name.as_ref())
+            .and_then(|v| T::from_preference(&v.value))
+    }
+
+    /// Sets the value of preference, and notifies the subscribers if it has changed.
+    pub fn set<T, N>(&self, name: N, value: T) -> Result<()>
+    where
+        T: PreferenceType,
+        N: AsRef<str>,
+    {
+        let name = name.as_ref();
+        let value = value.into_preference();
+
+        if let Preference::Str(ref v) = value {
+            if v.contains('\n') || v.trim() != v {
+                bail!(
+                    "Preference {} can not have surrounding spaces or newlines.",
+                    name
+                );
+            }
+        }
+
+        let callbacks = {
+            let mut data = self.data.write().unwrap();
+
+            {
+                let entry = data
+                    .entries
+                    .get_mut(name)
+                    .ok_or_else(|| format_err!("Preference {} is not registered.", name))?;
+
+                if !entry.default.is_same_type(&value) {
+                    bail!("Preference {} can not be set with {:?}.", name, value);
+                }
+
+                if let Some((ref min, ref max)) = entry.range {
+                    if !value.within(min, max) {
+                        bail!(
+                            "{} is out of the range [{}, {}] of {}.",
+                            value,
+                            min,
+                            max,
+                            name
+                        );
+                    }
+                }
+
+                if entry.value == value {
+                    return Ok(());
+                }
+
+                entry.value = value.clone();
+            }
+
+            data.dirty = true;
+            Self::callbacks(&data, name)
+        };
+
+        for v in callbacks {
+            v(name, &value);
+        }
+
+        Ok(())
+    }
+
+    /// Resets the preference to its default value.
+    pub fn reset<N: AsRef<str>>(&self, name: N) -> Result<()> {
+        let name = name.as_ref();
+
+        let (value, callbacks) = {
+            let mut data = self.data.write().unwrap();
+
+            let value = {
+                let entry = data
+                    .entries
+                    .get_mut(name)
+                    .ok_or_else(|| format_err!("Preference {} is not registered.", name))?;
+
+                if entry.value == entry.default {
+                    return Ok(());
+                }
+
+                entry.value = entry.default.clone();
+                entry.value.clone()
+            };
+
+            data.dirty = true;
+            (value, Self::callbacks(&data, name))
+        };
+
+        for v in callbacks {
+            v(name, &value);
+        }
+
+        Ok(())
+    }
+
+    fn callbacks(data: &PreferencesData, name: &str) -> Vec<Callback> {
+        data.listeners
+            .iter()
+            .filter_map(|v| data.listeners.get(v))
+            .filter(|v| v.0 == name)
+            .map(|v| v.1.clone())
+            .collect()
+    }
+
+    /// Subscribes the changes of preference. The callback is invoked with the name and
+    /// new value, after the value has been changed.
+    pub fn subscribe<N, F>(&self, name: N, callback: F) -> PreferenceListener
+    where
+        N: Into<String>,
+        F: Fn(&str, &Preference) + Send + Sync + 'static,
+    {
+        let callback: Callback = Arc::new(callback);
+        let mut data = self.data.write().unwrap();
+        data.listeners.create((name.into(), callback))
+    }
+
+    /// Stops receiving the changes.
+    pub fn unsubscribe(&self, listener: PreferenceListener) {
+        self.data.write().unwrap().listeners.free(listener);
+    }
+
+    /// Returns true if there are any changes not saved yet.
+    #[inline]
+    pub fn is_dirty(&self) -> bool {
+        self.data.read().unwrap().dirty
+    }
+
+    /// Saves the preferences into config file if there are any changes. It does nothing
+    /// if this is not backed by any file.
+    pub fn save(&self) -> Result<()> {
+        if let Some(ref path) = self.path {
+            // Takes the snapshot and clears the flag under the same lock, so changes made
+            // while writing are saved next time.
+            let lines = {
+                let mut data = self.data.write().unwrap();
+                if !data.dirty {
+                    return Ok(());
+                }
+
+                data.dirty = false;
+                Self::lines(&data)
+            };
+
+            if let Err(err) = Self::write(path, &lines) {
+                self.data.write().unwrap().dirty = true;
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes all the preferences into file at `path`.
+    pub fn save_to<T: AsRef<Path>>(&self, path: T) -> Result<()> {
+        let lines = Self::lines(&self.data.read().unwrap());
+        Self::write(path.as_ref(), &lines)
+    }
+
+    fn lines(data: &PreferencesData) -> Vec<String> {
+        let mut lines: Vec<_> = data
+            .entries
+            .iter()
+            .map(|(k, v)| format!("{} = {}", k, v.value))
+            .chain(
+                data.unregistered
+                    .iter()
+                    .map(|(k, v)| format!("{} = {}", k, v)),
+            ).collect();
+
+        lines.sort();
+        lines
+    }
+
+    fn write(path: &Path, lines: &[String]) -> Result<()> {
+        let mut file = fs::File::create(path)?;
+        for v in lines {
+            writeln!(file, "{}", v)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn basic() {
+        let prefs = Preferences::new();
+        prefs.register("video.vsync", true).unwrap();
+        prefs
+            .register_range("audio.volume", 0.8f32, 0.0, 1.0)
+            .unwrap();
+        prefs.register("input.jump", "Space".to_owned()).unwrap();
+
+        assert!(prefs.register("video.vsync", false).is_err());
+        assert!(prefs.register("video.vsync = false", true).is_err());
+        assert!(prefs.register("#video.vsync", true).is_err());
+        assert!(prefs.register("video\nvsync", true).is_err());
+        assert!(prefs.register(" video.fps", 60).is_err());
+        assert!(prefs.register_range("video.quality", 5, 0, 3).is_err());
+
+        assert_eq!(prefs.get("video.vsync"), Some(true));
+        assert_eq!(prefs.get::<i32, _>("video.vsync"), None);
+        assert_eq!(prefs.get("audio.volume"), Some(0.8f32));
+        assert!(!prefs.is_dirty());
+
+        assert!(prefs.set("audio.volume", 1.5f32).is_err());
+        assert!(prefs.set("audio.volume", 1).is_err());
+        assert!(prefs.set("audio.pitch", 1.0f32).is_err());
+        assert!(prefs.set("input.jump", " J".to_owned()).is_err());
+        assert!(!prefs.is_dirty());
+
+        prefs.set("audio.volume", 0.5f32).unwrap();
+        assert_eq!(prefs.get("audio.volume"), Some(0.5f32));
+        assert!(prefs.is_dirty());
+
+        prefs.reset("audio.volume").unwrap();
+        assert_eq!(prefs.get("audio.volume"), Some(0.8f32));
+    }
+
+    #[test]
+    fn subscribe() {
+        let prefs = Preferences::new();
+        prefs.register_range("video.quality", 2, 0, 3).unwrap();
+
+        let counter = Arc::new(AtomicUsize::new(0));
+        let c = counter.clone();
+        let listener = prefs.subscribe("video.quality", move |name, v| {
+            assert_eq!(name, "video.quality");
+            c.store(
+                match *v {
+                    Preference::Int(v) => v as usize,
+                    _ => 0,
+                },
+                Ordering::SeqCst,
+            );
+        });
+
+        prefs.set("video.quality", 3).unwrap();
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+
+        prefs.set("video.quality", 1).unwrap();
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+
+        prefs.unsubscribe(listener);
+        prefs.set("video.quality", 0).unwrap();
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn persistence() {
+        let path = ::std::env::temp_dir().join("crayon_preferences_test.cfg");
+        fs::write(
+            &path,
+            "# comments\naudio.volume = 0.25\nvideo.quality = 9\nfuture.option = 42\n",
+        ).unwrap();
+
+        let prefs = Preferences::load_from(&path).unwrap();
+        prefs
+            .register_range("audio.volume", 0.8f32, 0.0, 1.0)
+            .unwrap();
+        prefs.register_range("video.quality", 2, 0, 3).unwrap();
+        prefs.register("input.jump", "Space".to_owned()).unwrap();
+
+        // Invalid values fall back to defaults.
+        assert_eq!(prefs.get("audio.volume"), Some(0.25f32));
+        assert_eq!(prefs.get("video.quality"), Some(2));
+
+        prefs.set("input.jump", "Left Shift".to_owned()).unwrap();
+        prefs.save().unwrap();
+        assert!(!prefs.is_dirty());
+
+        let prefs = Preferences::load_from(&path).unwrap();
+        prefs.register("input.jump", "Space".to_owned()).unwrap();
+        prefs.register("future.option", 0).unwrap();
+        assert_eq!(prefs.get("input.jump"), Some("Left Shift".to_owned()));
+        assert_eq!(prefs.get("future.option"), Some(42));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
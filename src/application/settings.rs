@@ -1,5 +1,8 @@
 //! Functions for loading game settings.
 
+use std::path::PathBuf;
+use std::time::Duration;
+
 use input;
 use math;
 
@@ -12,6 +15,9 @@ pub struct Settings {
     pub window: WindowParams,
     pub input: input::InputParams,
     pub headless: bool,
+    /// The user config file that preferences are loaded from and saved into. Preferences
+    /// are kept in memory only if it's `None`.
+    pub preferences: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -20,6 +26,14 @@ pub struct EngineParams {
     pub max_fps: u32,
     pub max_inactive_fps: u32,
     pub time_smooth_step: u32,
+    /// The duration of every fixed update step.
+    pub fixed_timestep: Duration,
+    /// The maximum fixed update steps in one frame. The time left is dropped when it
+    /// gets exceeded, so a slow frame does not make the following ones even slower.
+    pub max_fixed_steps: u32,
+    /// The number of worker threads, which load resources and run the spawned jobs
+    /// concurrently.
+    pub workers: u32,
 }
 
 impl Default for EngineParams {
@@ -29,6 +43,9 @@ impl Default for EngineParams {
             max_fps: 30,
             max_inactive_fps: 0,
             time_smooth_step: 0,
+            fixed_timestep: Duration::from_nanos(1_000_000_000 / 60),
+            max_fixed_steps: 5,
+            workers: 6,
         }
     }
 }
@@ -13,8 +13,12 @@ pub struct TimeSystem {
     max_fps: u32,
     max_inactive_fps: u32,
     smoothing_step: usize,
+    fixed_timestep: Duration,
+    max_fixed_steps: u32,
+    time_scale: f32,
 
     timestep: Duration,
+    accumulator: Duration,
     previous_timesteps: VecDeque<Duration>,
     last_frame_timepoint: Instant,
     shared: Arc<TimeSystemShared>,
@@ -29,8 +33,12 @@ impl TimeSystem {
             max_fps: setup.max_fps,
             max_inactive_fps: setup.max_inactive_fps,
             smoothing_step: setup.time_smooth_step as usize,
+            fixed_timestep: setup.fixed_timestep,
+            max_fixed_steps: setup.max_fixed_steps,
+            time_scale: 1.0,
             previous_timesteps: VecDeque::new(),
             timestep: Duration::new(0, 0),
+            accumulator: Duration::new(0, 0),
             last_frame_timepoint: Instant::now(),
             shared: Arc::new(shared),
         }
@@ -47,6 +55,9 @@ impl TimeSystem {
         self.max_fps = *self.shared.max_fps.read().unwrap();
         self.max_inactive_fps = *self.shared.max_inactive_fps.read().unwrap();
         self.smoothing_step = *self.shared.smoothing_step.read().unwrap();
+        self.fixed_timestep = *self.shared.fixed_timestep.read().unwrap();
+        self.max_fixed_steps = *self.shared.max_fixed_steps.read().unwrap();
+        self.time_scale = self.shared.effective_time_scale();
 
         // Perform waiting loop if maximum fps set, cooperatively gives up
         // a timeslice to the OS scheduler.
@@ -90,9 +101,46 @@ impl TimeSystem {
             self.timestep = elapsed;
         }
 
+        let scaled = scale(self.timestep, self.time_scale);
+        let steps = self.accumulate(scaled);
+        *self.shared.fixed_steps.write().unwrap() = steps;
+        *self.shared.scaled_timestep.write().unwrap() = scaled;
         *self.shared.timestep.write().unwrap() = self.timestep;
         self.timestep
     }
+
+    /// Accumulates the elapsed time, and returns the number of fixed steps to run.
+    fn accumulate(&mut self, elapsed: Duration) -> u32 {
+        if self.fixed_timestep == Duration::new(0, 0) {
+            return 0;
+        }
+
+        self.accumulator += elapsed;
+
+        let mut steps = 0;
+        while self.accumulator >= self.fixed_timestep {
+            if steps >= self.max_fixed_steps {
+                self.accumulator = Duration::new(0, 0);
+                break;
+            }
+
+            self.accumulator -= self.fixed_timestep;
+            steps += 1;
+        }
+
+        steps
+    }
+}
+
+/// Scales the duration by a non-negative factor.
+fn scale(duration: Duration, factor: f32) -> Duration {
+    if factor <= 0.0 {
+        return Duration::new(0, 0);
+    }
+
+    let nanos = duration.as_secs() as f64 * 1e9 + f64::from(duration.subsec_nanos());
+    let nanos = (nanos * f64::from(factor)).round() as u64;
+    Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32)
 }
 
 /// The multi-thread friendly parts of `TimeSystem`.
@@ -101,7 +149,13 @@ pub struct TimeSystemShared {
     max_fps: RwLock<u32>,
     max_inactive_fps: RwLock<u32>,
     smoothing_step: RwLock<usize>,
+    fixed_timestep: RwLock<Duration>,
+    max_fixed_steps: RwLock<u32>,
+    fixed_steps: RwLock<u32>,
     timestep: RwLock<Duration>,
+    time_scale: RwLock<f32>,
+    paused: RwLock<bool>,
+    scaled_timestep: RwLock<Duration>,
 }
 
 impl TimeSystemShared {
@@ -111,7 +165,13 @@ impl TimeSystemShared {
             max_fps: RwLock::new(setup.max_fps),
             max_inactive_fps: RwLock::new(setup.max_inactive_fps),
             smoothing_step: RwLock::new(setup.time_smooth_step as usize),
+            fixed_timestep: RwLock::new(setup.fixed_timestep),
+            max_fixed_steps: RwLock::new(setup.max_fixed_steps),
+            fixed_steps: RwLock::new(0),
             timestep: RwLock::new(Duration::new(0, 0)),
+            time_scale: RwLock::new(1.0),
+            paused: RwLock::new(false),
+            scaled_timestep: RwLock::new(Duration::new(0, 0)),
         }
     }
 
@@ -143,6 +203,31 @@ impl TimeSystemShared {
         *self.smoothing_step.write().unwrap() = step as usize;
     }
 
+    /// Set the duration of every fixed update step. Fixed updates are disabled if it's
+    /// zero.
+    #[inline]
+    pub fn set_fixed_timestep(&self, timestep: Duration) {
+        *self.fixed_timestep.write().unwrap() = timestep;
+    }
+
+    /// Gets the duration of every fixed update step.
+    #[inline]
+    pub fn fixed_timestep(&self) -> Duration {
+        *self.fixed_timestep.read().unwrap()
+    }
+
+    /// Set the maximum fixed update steps in one frame.
+    #[inline]
+    pub fn set_max_fixed_steps(&self, steps: u32) {
+        *self.max_fixed_steps.write().unwrap() = steps;
+    }
+
+    /// Gets the number of fixed update steps in current frame.
+    #[inline]
+    pub fn fixed_steps(&self) -> u32 {
+        *self.fixed_steps.read().unwrap()
+    }
+
     /// Gets current fps.
     #[inline]
     pub fn get_fps(&self) -> u32 {
@@ -159,4 +244,90 @@ impl TimeSystemShared {
     pub fn frame_delta(&self) -> Duration {
         *self.timestep.read().unwrap()
     }
+
+    /// Gets the duration of simulation during last frame, which is `frame_delta` scaled
+    /// by the time scale, or zero if paused. Gameplay should advance with it, while UI
+    /// and free cameras keep using `frame_delta`.
+    #[inline]
+    pub fn scaled_frame_delta(&self) -> Duration {
+        *self.scaled_timestep.read().unwrap()
+    }
+
+    /// Set the speed of simulation, e.g. 0.5 for slow motion. It scales fixed updates
+    /// and `scaled_frame_delta`, and takes effect from the next frame.
+    #[inline]
+    pub fn set_time_scale(&self, scale: f32) {
+        *self.time_scale.write().unwrap() = scale.max(0.0);
+    }
+
+    /// Gets the speed of simulation, regardless of pausing.
+    #[inline]
+    pub fn time_scale(&self) -> f32 {
+        *self.time_scale.read().unwrap()
+    }
+
+    /// Pauses or resumes the simulation. There are no fixed updates while paused, but
+    /// frames are still rendered and input is still processed.
+    #[inline]
+    pub fn set_paused(&self, paused: bool) {
+        *self.paused.write().unwrap() = paused;
+    }
+
+    /// Returns true if the simulation is paused.
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        *self.paused.read().unwrap()
+    }
+
+    fn effective_time_scale(&self) -> f32 {
+        if self.is_paused() {
+            0.0
+        } else {
+            self.time_scale()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fixed_steps() {
+        let mut params = EngineParams::default();
+        params.fixed_timestep = Duration::from_millis(10);
+        params.max_fixed_steps = 3;
+
+        let mut time = TimeSystem::new(params);
+        assert_eq!(time.accumulate(Duration::from_millis(4)), 0);
+        assert_eq!(time.accumulate(Duration::from_millis(7)), 1);
+        assert_eq!(time.accumulate(Duration::from_millis(29)), 3);
+
+        // The time left is dropped if it takes too many steps.
+        assert_eq!(time.accumulate(Duration::from_millis(100)), 3);
+        assert_eq!(time.accumulate(Duration::from_millis(5)), 0);
+
+        time.fixed_timestep = Duration::new(0, 0);
+        assert_eq!(time.accumulate(Duration::from_millis(100)), 0);
+    }
+
+    #[test]
+    fn time_scale() {
+        let ms = Duration::from_millis;
+        assert_eq!(scale(ms(100), 1.0), ms(100));
+        assert_eq!(scale(ms(1500), 2.0), ms(3000));
+        assert_eq!(scale(ms(100), 0.0), ms(0));
+
+        let shared = TimeSystemShared::new(EngineParams::default());
+        shared.set_time_scale(0.5);
+        assert_eq!(shared.effective_time_scale(), 0.5);
+
+        shared.set_paused(true);
+        assert!(shared.is_paused());
+        assert_eq!(shared.effective_time_scale(), 0.0);
+        assert_eq!(shared.time_scale(), 0.5);
+
+        shared.set_paused(false);
+        assert_eq!(shared.effective_time_scale(), 0.5);
+    }
 }
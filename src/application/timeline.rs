@@ -0,0 +1,276 @@
+//! Records the statistics of frames during a session, and exports them as a trace.
+//!
+//! The trace is written in the JSON format of Chrome's trace event profiler, which
+//! could be opened with `chrome://tracing` or [Perfetto](https://ui.perfetto.dev) for
+//! post-hoc analysis of long sessions.
+
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use errors::*;
+use res::LoadStats;
+
+use super::FrameInfo;
+
+/// The statistics of a frame.
+#[derive(Debug, Clone)]
+pub struct TimelineFrame {
+    /// The time when this frame ends, since the start of recording.
+    pub timestamp: Duration,
+    /// The collected information of this frame.
+    pub info: FrameInfo,
+    /// The time spent in the hooks of modules.
+    pub modules: Vec<(String, Duration)>,
+    /// The statistics of resource loading when this frame ends.
+    pub loads: LoadStats,
+}
+
+/// Records the statistics of frames. It's disabled by default.
+pub struct Timeline {
+    enabled: AtomicBool,
+    frames: Mutex<(Option<Instant>, Vec<TimelineFrame>)>,
+}
+
+impl Timeline {
+    pub(crate) fn new() -> Self {
+        Timeline {
+            enabled: AtomicBool::new(false),
+            frames: Mutex::new((None, Vec::new())),
+        }
+    }
+
+    /// Starts to record frames.
+    pub fn start(&self) {
+        self.enabled.store(true, Ordering::SeqCst);
+    }
+
+    /// Stops recording. The frames are kept until `clear` is called.
+    pub fn stop(&self) {
+        self.enabled.store(false, Ordering::SeqCst);
+    }
+
+    /// Returns true if the timeline is recording.
+    #[inline]
+    pub fn is_recording(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Removes all the frames.
+    pub fn clear(&self) {
+        let mut frames = self.frames.lock().unwrap();
+        frames.0 = None;
+        frames.1.clear();
+    }
+
+    /// Gets the number of recorded frames.
+    pub fn len(&self) -> usize {
+        self.frames.lock().unwrap().1.len()
+    }
+
+    /// Returns true if there are no recorded frames.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the recorded frames.
+    pub fn frames(&self) -> Vec<TimelineFrame> {
+        self.frames.lock().unwrap().1.clone()
+    }
+
+    /// Writes the recorded frames as a JSON trace. Every frame is written as a span
+    /// and the statistics of frame as counters.
+    pub fn write_json(&self, w: &mut dyn Write) -> Result<()> {
+        let frames = self.frames.lock().unwrap();
+
+        writeln!(w, "{{\"traceEvents\":[")?;
+
+        let mut last = Duration::default();
+        for (i, v) in frames.1.iter().enumerate() {
+            let ts = micros(v.timestamp);
+            let dur = micros(v.timestamp - last);
+            last = v.timestamp;
+
+            if i > 0 {
+                writeln!(w, ",")?;
+            }
+
+            write!(
+                w,
+                "{{\"name\":\"Frame\",\"ph\":\"X\",\"pid\":0,\"tid\":0,\"ts\":{},\"dur\":{},\
+                 \"args\":{{\"index\":{},\"fps\":{}}}}}",
+                ts - dur,
+                dur,
+                i,
+                v.info.fps
+            )?;
+
+            counter(
+                w,
+                "CPU/GPU (us)",
+                ts,
+                &[
+                    ("update", micros(v.info.duration)),
+                    ("render", micros(v.info.video.duration)),
+                ],
+            )?;
+
+            let modules: Vec<_> = v
+                .modules
+                .iter()
+                .map(|&(ref name, duration)| (name.as_str(), micros(duration)))
+                .collect();
+            counter(w, "Modules (us)", ts, &modules)?;
+
+            let video = &v.info.video;
+            counter(
+                w,
+                "Draw",
+                ts,
+                &[
+                    ("drawcall", u64::from(video.drawcall)),
+                    ("triangles", u64::from(video.triangles)),
+                ],
+            )?;
+
+            counter(
+                w,
+                "Video Objects",
+                ts,
+                &[
+                    ("surfaces", u64::from(video.alive_surfaces)),
+                    ("shaders", u64::from(video.alive_shaders)),
+                    ("meshes", u64::from(video.alive_meshes)),
+                    ("textures", u64::from(video.alive_textures)),
+                ],
+            )?;
+
+            counter(
+                w,
+                "Loads",
+                ts,
+                &[
+                    ("pending", v.loads.pending as u64),
+                    ("finished", v.loads.finished as u64),
+                    ("failed", v.loads.failed as u64),
+                ],
+            )?;
+        }
+
+        writeln!(w, "\n]}}")?;
+        Ok(())
+    }
+
+    pub(crate) fn record(&self, info: &FrameInfo, modules: &[(&str, Duration)], loads: LoadStats) {
+        if !self.is_recording() {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut frames = self.frames.lock().unwrap();
+        let start = *frames.0.get_or_insert(now);
+
+        frames.1.push(TimelineFrame {
+            timestamp: now - start,
+            info: *info,
+            modules: modules
+                .iter()
+                .map(|&(name, duration)| (name.to_owned(), duration))
+                .collect(),
+            loads: loads,
+        });
+    }
+}
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros())
+}
+
+fn counter(w: &mut dyn Write, name: &str, ts: u64, values: &[(&str, u64)]) -> Result<()> {
+    if values.is_empty() {
+        return Ok(());
+    }
+
+    write!(
+        w,
+        ",\n{{\"name\":\"{}\",\"ph\":\"C\",\"pid\":0,\"ts\":{},\"args\":{{",
+        escape(name),
+        ts
+    )?;
+
+    for (i, &(k, v)) in values.iter().enumerate() {
+        if i > 0 {
+            write!(w, ",")?;
+        }
+
+        write!(w, "\"{}\":{}", escape(k), v)?;
+    }
+
+    write!(w, "}}}}")?;
+    Ok(())
+}
+
+fn escape(v: &str) -> String {
+    let mut escaped = String::with_capacity(v.len());
+    for c in v.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let timeline = Timeline::new();
+        let info = FrameInfo::default();
+        let loads = LoadStats::default();
+        let ms = Duration::from_millis(1);
+
+        timeline.record(&info, &[], loads);
+        assert!(timeline.is_empty());
+
+        timeline.start();
+        timeline.record(&info, &[("3d", ms * 2)], loads);
+        timeline.record(&info, &[("3d", ms)], loads);
+        timeline.stop();
+        timeline.record(&info, &[], loads);
+
+        let frames = timeline.frames();
+        assert_eq!(frames.len(), 2);
+        assert!(frames[0].timestamp <= frames[1].timestamp);
+        assert_eq!(frames[1].modules, vec![("3d".to_owned(), ms)]);
+
+        timeline.clear();
+        assert!(timeline.is_empty());
+    }
+
+    #[test]
+    fn json() {
+        let timeline = Timeline::new();
+        timeline.start();
+
+        let info = FrameInfo::default();
+        let modules = [("\"quoted\"", Duration::from_millis(2))];
+        timeline.record(&info, &modules, LoadStats::default());
+
+        let mut json = Vec::new();
+        timeline.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+
+        assert!(json.starts_with("{\"traceEvents\":["));
+        assert!(json.trim_end().ends_with("]}"));
+        assert!(json.contains("\"name\":\"Frame\",\"ph\":\"X\""));
+        assert!(json.contains("\"\\\"quoted\\\"\":2000"));
+        assert!(json.contains("\"failed\":0"));
+    }
+}
@@ -38,6 +38,9 @@ extern crate log;
 extern crate serde;
 pub extern crate bincode;
 pub extern crate uuid;
+extern crate zip;
+#[cfg(feature = "http")]
+extern crate ureq;
 
 #[doc(hidden)]
 pub use cgmath::*;
@@ -13,3 +13,9 @@ pub use self::frustum::{Frustum, FrustumPoints, Projection};
 
 pub mod color;
 pub use self::color::Color;
+
+pub mod spring;
+pub use self::spring::Spring;
+
+pub mod spline;
+pub use self::spline::{ArcLengths, Spline};
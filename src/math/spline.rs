@@ -0,0 +1,226 @@
+//! Catmull-Rom splines and their arc-length parameterization.
+
+use cgmath::{InnerSpace, Vector3};
+
+/// A Catmull-Rom spline which passes through all of its control points. The segment
+/// `i` starts at `points[i]`, so the parameter `i as f32` is always at the control point
+/// `i`, which makes the control points handy as waypoints.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Spline {
+    /// The control points.
+    pub points: Vec<Vector3<f32>>,
+    /// Whether the last point connects back to the first one.
+    pub closed: bool,
+}
+
+impl Spline {
+    /// Creates a new `Spline` through `points`.
+    pub fn new(points: Vec<Vector3<f32>>, closed: bool) -> Self {
+        Spline {
+            points: points,
+            closed: closed,
+        }
+    }
+
+    /// Gets the number of segments, the valid parameters are in `[0, segments]`.
+    pub fn segments(&self) -> usize {
+        match self.points.len() {
+            0 | 1 => 0,
+            n if self.closed => n,
+            n => n - 1,
+        }
+    }
+
+    /// Gets the position at parameter `t`.
+    pub fn position(&self, t: f32) -> Vector3<f32> {
+        match self.points.len() {
+            0 => Vector3::new(0.0, 0.0, 0.0),
+            1 => self.points[0],
+            _ => {
+                let (p0, p1, p2, p3, t) = self.segment(t);
+                let t2 = t * t;
+                let t3 = t2 * t;
+
+                (p1 * 2.0
+                    + (p2 - p0) * t
+                    + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+                    + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+                    * 0.5
+            }
+        }
+    }
+
+    /// Gets the derivative at parameter `t`, which is not normalized.
+    pub fn tangent(&self, t: f32) -> Vector3<f32> {
+        if self.points.len() < 2 {
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+
+        let (p0, p1, p2, p3, t) = self.segment(t);
+        ((p2 - p0)
+            + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * (2.0 * t)
+            + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * (3.0 * t * t))
+            * 0.5
+    }
+
+    // Gets the 4 control points of the segment at `t`, and the local parameter in it.
+    fn segment(&self, t: f32) -> (Vector3<f32>, Vector3<f32>, Vector3<f32>, Vector3<f32>, f32) {
+        let segments = self.segments();
+        let t = t.max(0.0).min(segments as f32);
+        let i = (t.floor() as usize).min(segments - 1);
+
+        let n = self.points.len() as isize;
+        let closed = self.closed;
+        let p = |i: isize| {
+            let i = if closed {
+                ((i % n) + n) % n
+            } else {
+                i.max(0).min(n - 1)
+            };
+
+            self.points[i as usize]
+        };
+
+        let i = i as isize;
+        (p(i - 1), p(i), p(i + 1), p(i + 2), t - i as f32)
+    }
+}
+
+/// A table which maps the distances along a spline to its parameters, so things could
+/// travel along the spline at constant speed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArcLengths {
+    samples: usize,
+    distances: Vec<f32>,
+}
+
+impl ArcLengths {
+    /// Builds the table by sampling every segment of `spline` at `samples` points. More
+    /// samples are more accurate on sharp turns.
+    pub fn new(spline: &Spline, samples: usize) -> Self {
+        let samples = samples.max(1);
+        let n = spline.segments() * samples;
+
+        let mut distances = Vec::with_capacity(n + 1);
+        distances.push(0.0);
+
+        let mut last = spline.position(0.0);
+        for i in 1..(n + 1) {
+            let v = spline.position(i as f32 / samples as f32);
+            let d = distances[i - 1] + (v - last).magnitude();
+            distances.push(d);
+            last = v;
+        }
+
+        ArcLengths {
+            samples: samples,
+            distances: distances,
+        }
+    }
+
+    /// Gets the total length of spline.
+    #[inline]
+    pub fn length(&self) -> f32 {
+        self.distances[self.distances.len() - 1]
+    }
+
+    /// Gets the distance from the start to parameter `t`.
+    pub fn distance(&self, t: f32) -> f32 {
+        let last = self.distances.len() - 1;
+        let x = (t * self.samples as f32).max(0.0).min(last as f32);
+        let i = (x.floor() as usize).min(last.saturating_sub(1));
+
+        if last == 0 {
+            0.0
+        } else {
+            let f = x - i as f32;
+            self.distances[i] + (self.distances[i + 1] - self.distances[i]) * f
+        }
+    }
+
+    /// Gets the parameter at `distance` from the start.
+    pub fn param(&self, distance: f32) -> f32 {
+        let last = self.distances.len() - 1;
+        if last == 0 || distance <= 0.0 {
+            return 0.0;
+        }
+
+        if distance >= self.length() {
+            return last as f32 / self.samples as f32;
+        }
+
+        // The first sample that is further than `distance`.
+        let i = match self
+            .distances
+            .binary_search_by(|v| v.partial_cmp(&distance).unwrap())
+        {
+            Ok(i) => return i as f32 / self.samples as f32,
+            Err(i) => i,
+        };
+
+        let (d0, d1) = (self.distances[i - 1], self.distances[i]);
+        let f = if d1 > d0 {
+            (distance - d0) / (d1 - d0)
+        } else {
+            0.0
+        };
+
+        ((i - 1) as f32 + f) / self.samples as f32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_near(lhs: Vector3<f32>, rhs: Vector3<f32>) {
+        assert!((lhs - rhs).magnitude() < 1e-4, "{:?} != {:?}", lhs, rhs);
+    }
+
+    #[test]
+    fn control_points() {
+        let points = vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 0.0),
+        ];
+
+        let spline = Spline::new(points.clone(), false);
+        assert_eq!(spline.segments(), 2);
+        for (i, &v) in points.iter().enumerate() {
+            assert_near(spline.position(i as f32), v);
+        }
+
+        let spline = Spline::new(points.clone(), true);
+        assert_eq!(spline.segments(), 3);
+        assert_near(spline.position(3.0), points[0]);
+        assert_near(spline.position(-1.0), points[0]);
+    }
+
+    #[test]
+    fn arc_lengths() {
+        // Control points with uneven spacing still move at constant speed.
+        let points = vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(4.0, 0.0, 0.0),
+        ];
+
+        let spline = Spline::new(points, false);
+        let table = ArcLengths::new(&spline, 32);
+        assert!((table.length() - 4.0).abs() < 1e-3);
+        assert!((table.distance(1.0) - 1.0).abs() < 1e-3);
+        assert_eq!(table.param(-1.0), 0.0);
+        assert_eq!(table.param(10.0), 2.0);
+
+        for i in 0..9 {
+            let d = i as f32 * 0.5;
+            let v = spline.position(table.param(d));
+            assert!((v.x - d).abs() < 1e-2, "{} != {}", v.x, d);
+        }
+
+        let table = ArcLengths::new(&Spline::new(Vec::new(), false), 8);
+        assert_eq!(table.length(), 0.0);
+        assert_eq!(table.param(1.0), 0.0);
+    }
+}
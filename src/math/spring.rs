@@ -0,0 +1,88 @@
+//! Critically damped springs that make a value follow its target smoothly.
+
+use std::ops::{Add, Mul, Sub};
+
+use cgmath::Zero;
+
+/// A `Spring` drags a value towards its target, like a critically damped spring. It
+/// never overshoots, and stays stable no matter how large the time step is, which
+/// makes it handy for cameras and UI that follows something.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spring<T> {
+    /// The current value.
+    pub value: T,
+    /// The current velocity of value.
+    pub velocity: T,
+    /// The angular frequency of spring. Higher frequency follows the target faster,
+    /// and it takes about `4.0 / frequency` seconds to reach it.
+    pub frequency: f32,
+}
+
+impl<T> Spring<T>
+where
+    T: Copy + Zero + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+{
+    /// Creates a new `Spring` resting at `value`.
+    pub fn new(value: T, frequency: f32) -> Self {
+        Spring {
+            value: value,
+            velocity: T::zero(),
+            frequency: frequency,
+        }
+    }
+
+    /// Moves the value towards `target` by `dt` seconds, and returns the new value.
+    pub fn advance(&mut self, target: T, dt: f32) -> T {
+        let omega = self.frequency.max(0.0);
+        let exp = (-omega * dt).exp();
+
+        let change = self.value - target;
+        let temp = (self.velocity + change * omega) * dt;
+
+        self.velocity = (self.velocity - temp * omega) * exp;
+        self.value = target + (change + temp) * exp;
+        self.value
+    }
+
+    /// Places the value at `value` and stops it immediately.
+    pub fn reset(&mut self, value: T) {
+        self.value = value;
+        self.velocity = T::zero();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cgmath::Vector2;
+
+    #[test]
+    fn follow() {
+        let mut spring = Spring::new(0.0f32, 10.0);
+
+        let mut last = 0.0;
+        for _ in 0..60 {
+            let v = spring.advance(1.0, 1.0 / 60.0);
+            assert!(v >= last && v <= 1.0);
+            last = v;
+        }
+
+        assert!((last - 1.0).abs() < 1e-2);
+
+        // Large time steps are stable.
+        spring.reset(0.0);
+        assert_eq!(spring.velocity, 0.0);
+        assert!((spring.advance(1.0, 100.0) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn vector() {
+        let mut spring = Spring::new(Vector2::new(0.0f32, 0.0), 8.0);
+        for _ in 0..120 {
+            spring.advance(Vector2::new(2.0, -1.0), 1.0 / 60.0);
+        }
+
+        assert!((spring.value.x - 2.0).abs() < 1e-3);
+        assert!((spring.value.y + 1.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,94 @@
+//! Groups of resources that report their loading progress together.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use uuid::Uuid;
+
+use utils::FastHashMap;
+
+use super::promise::Promise;
+use super::reachable;
+use super::vfs::VFSDriver;
+
+/// A group of resources and their dependencies, which is created by
+/// `ResourceSystemShared::load_group`.
+#[derive(Clone)]
+pub struct LoadGroup {
+    pub(crate) uuids: Vec<Uuid>,
+    pub(crate) driver: Arc<RwLock<VFSDriver>>,
+    pub(crate) declared: Arc<RwLock<FastHashMap<Uuid, Vec<Uuid>>>>,
+    pub(crate) promises: Arc<RwLock<FastHashMap<Uuid, Arc<Promise>>>>,
+}
+
+impl LoadGroup {
+    /// Gets the resources of this group, excluding their dependencies.
+    #[inline]
+    pub fn uuids(&self) -> &[Uuid] {
+        &self.uuids
+    }
+
+    /// Adds a resource into this group.
+    pub fn add(&mut self, uuid: Uuid) {
+        if !self.uuids.contains(&uuid) {
+            self.uuids.push(uuid);
+        }
+    }
+
+    /// Gets the loading progress in [0, 1], which is the ratio of loaded resources in
+    /// this group and all their dependencies. An empty group is always finished.
+    pub fn progress(&self) -> f32 {
+        let tree = reachable(&self.driver, &self.declared, &self.uuids);
+        if tree.is_empty() {
+            return 1.0;
+        }
+
+        let promises = self.promises.read().unwrap();
+        let loaded = tree.iter().filter(|v| !promises.contains_key(v)).count();
+        loaded as f32 / tree.len() as f32
+    }
+
+    /// Returns true if all the resources in this group have been loaded.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.pending().is_empty()
+    }
+
+    /// Gets a future which resolves once all the resources in this group have been
+    /// loaded. Failed loads are reported by the owners of resources, instead of this.
+    pub fn future(&self) -> LoadGroupFuture {
+        LoadGroupFuture(self.clone())
+    }
+
+    fn pending(&self) -> Vec<Arc<Promise>> {
+        let tree = reachable(&self.driver, &self.declared, &self.uuids);
+        let promises = self.promises.read().unwrap();
+        tree.iter().filter_map(|v| promises.get(v).cloned()).collect()
+    }
+}
+
+/// A `Future` which resolves once all the resources in `LoadGroup` have been loaded.
+pub struct LoadGroupFuture(LoadGroup);
+
+impl Future for LoadGroupFuture {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        // Waits for one pending load at a time, dependencies might be added once their
+        // dependents are loaded.
+        for v in self.0.pending() {
+            if v.register(cx.waker()) {
+                return Poll::Pending;
+            }
+        }
+
+        if self.0.is_finished() {
+            Poll::Ready(())
+        } else {
+            // The pending loads have been finished in the meantime.
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
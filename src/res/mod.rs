@@ -56,8 +56,18 @@
 //! filesystem, or other places if extended by pluggable `VFS`.
 //!
 //! The `VFS` trait has a pretty simple interface, since it should focus on games that load
-//! data asynchronously. A trival `Directory` is provided to supports local host filesystem.
-//! And it should be easy to add features like compression and encrpytion.
+//! data asynchronously. A trival `Directory` is provided to supports local host filesystem,
+//! and `ZipFilesystem` reads a single zip archive, which is handy for shipped builds. With the
+//! `http` feature, `HttpFilesystem` fetches files from web servers with a local disk cache. And
+//! it should be easy to add features like encrpytion.
+//!
+//! Tests and single-binary distributions could embed resources with `include_bytes!`, and mount
+//! them with `MemoryFilesystem`, or `ZipFilesystem::from_bytes` for a whole archive.
+//!
+//! Save files and screenshots are written through a `WritableFilesystem`, e.g. the
+//! `UserDirFilesystem` in the platform directory of user data. They are mounted with
+//! `ResourceSystem::mount_writable` without manifests, and accessed with
+//! `ResourceSystemShared::write` and `ResourceSystemShared::read`.
 //!
 //! ## Manifest
 //!
@@ -92,6 +102,22 @@
 //! drop the ownership of the resource. And when the last ownership to a given resource is dropped,
 //! the corresponding resource is also destroyed.
 //!
+//! Systems that share a resource created by others could `retain_by` it with a name, and
+//! `release_by` it with the same name when they are done. The `holders` of resource then tells
+//! which systems are still keeping it alive, which makes leaks easy to track down.
+//!
+//! ## Hot Reload
+//!
+//! With `ResourceSystemShared::set_hot_reload`, the files of loaded resources are polled for
+//! modifications every `HOT_RELOAD_INTERVAL`. The modified ones are loaded again by their
+//! registries, and the new values replace the old ones under the same handles, so shaders and
+//! textures could be iterated on without restarting the application. The modification times of
+//! files are recorded for every load, so the resources loaded before it's enabled are watched
+//! too.
+//!
+
+pub mod group;
+use self::group::LoadGroup;
 
 pub mod location;
 use self::location::Location;
@@ -99,29 +125,44 @@ use self::location::Location;
 pub mod promise;
 use self::promise::Promise;
 
+pub mod recorder;
+use self::recorder::AccessRecorder;
+
 pub mod registry;
+pub mod utils;
 pub mod vfs;
 
 pub mod prelude {
+    pub use super::group::{LoadGroup, LoadGroupFuture};
     pub use super::location::Location;
-    pub use super::promise::Promise;
-    pub use super::vfs::Directory;
-    pub use super::{ResourceSystem, ResourceSystemShared};
+    pub use super::promise::{block_on, Promise, PromiseFuture};
+    pub use super::recorder::{AccessRecord, AccessRecorder};
+    pub use super::vfs::{Directory, MemoryFilesystem, UserDirFilesystem, ZipFilesystem};
+    pub use super::{LoadStats, ResourceEntry, ResourceSystem, ResourceSystemShared};
 }
 
-use std::sync::{Arc, RwLock};
+use std::io::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
 use uuid::Uuid;
 
-use self::vfs::{VFSDriver, VFS};
+use self::vfs::{VFSDriver, WritableFilesystem, VFS};
 
 use errors::*;
+use sched::unwind;
 use sched::ScheduleSystemShared;
-use utils::FastHashMap;
+use utils::{FastHashMap, FastHashSet};
+
+/// The interval of polling modified files when hot reload is enabled.
+pub const HOT_RELOAD_INTERVAL: Duration = Duration::from_millis(500);
 
 /// The `ResourceSystem` Takes care of loading data asynchronously through pluggable filesystems.
 pub struct ResourceSystem {
     driver: Arc<RwLock<VFSDriver>>,
     shared: Arc<ResourceSystemShared>,
+    polled: Instant,
 }
 
 impl ResourceSystem {
@@ -134,11 +175,22 @@ impl ResourceSystem {
             sched: sched,
             bufs: Arc::new(RwLock::new(Vec::new())),
             promises: Arc::new(RwLock::new(FastHashMap::default())),
+            declared: Arc::new(RwLock::new(FastHashMap::default())),
+            queue: Arc::new(Mutex::new(Vec::new())),
+            recorder: Arc::new(AccessRecorder::new()),
+            closed: AtomicBool::new(false),
+            stats: Arc::new(LoadCounters::default()),
+            hot_reload: AtomicBool::new(false),
+            timestamps: Arc::new(RwLock::new(FastHashMap::default())),
+            reloaders: RwLock::new(Vec::new()),
+            caches: RwLock::new(Vec::new()),
+            memory_budget: RwLock::new(None),
         });
 
         Ok(ResourceSystem {
             driver: driver,
             shared: shared,
+            polled: Instant::now(),
         })
     }
 
@@ -153,14 +205,106 @@ impl ResourceSystem {
         self.driver.write().unwrap().mount(name, vfs)
     }
 
+    /// Mount a writable file-system drive with identifier, e.g. a `UserDirFilesystem`
+    /// for save files. Its files are accessed with `ResourceSystemShared::write` and
+    /// `ResourceSystemShared::read`.
+    pub fn mount_writable<T, F>(&mut self, name: T, fs: F) -> Result<()>
+    where
+        T: AsRef<str>,
+        F: WritableFilesystem + 'static,
+    {
+        let name = name.as_ref();
+        info!("Mounts writable virtual file system {}.", name);
+        self.driver.write().unwrap().mount_writable(name, fs)
+    }
+
     /// Returns the multi-thread friendly parts of `ResourceSystem`.
     pub fn shared(&self) -> Arc<ResourceSystemShared> {
         self.shared.clone()
     }
+
+    /// Reloads the modified resources if hot reload is enabled, and the last poll is
+    /// older than `HOT_RELOAD_INTERVAL`. The caches are trimmed to the memory budget.
+    pub fn advance(&mut self) {
+        self.shared.trim_caches();
+
+        if self.shared.is_hot_reload() && self.polled.elapsed() >= HOT_RELOAD_INTERVAL {
+            self.polled = Instant::now();
+            self.shared.reload_modified();
+        }
+    }
+
+    /// Stops accepting new loads, and blocks current thread until all the pending loads
+    /// are finished.
+    pub fn shutdown(&self) {
+        self.shared.closed.store(true, Ordering::SeqCst);
+
+        loop {
+            let promises: Vec<_> = self
+                .shared
+                .promises
+                .read()
+                .unwrap()
+                .values()
+                .cloned()
+                .collect();
+            if promises.is_empty() {
+                break;
+            }
+
+            for v in promises {
+                self.shared.sched.wait_until(v.as_ref());
+            }
+        }
+    }
 }
 
 pub trait Loader: Send + Sync + 'static {
     fn load(&self, file: &[u8]) -> Result<()>;
+
+    /// Called instead of `load` if the load is cancelled, or its file could not be read.
+    fn fail(&self, _: &::failure::Error) {}
+}
+
+/// Loads the modified resources again, e.g. a `Registry`.
+pub trait Reloader: Send + Sync + 'static {
+    /// Reloads the resource `uuid` if it's managed by this reloader.
+    fn reload(&self, res: &ResourceSystemShared, uuid: Uuid);
+
+    /// Returns false once the owner has been dropped, and this reloader could be removed.
+    fn is_alive(&self) -> bool;
+}
+
+/// The unused resources that are kept for reuse, e.g. by a `Registry`.
+pub trait Cache: Send + Sync + 'static {
+    /// Gets the approximate memory of cached resources in bytes.
+    fn cached_bytes(&self) -> usize;
+
+    /// Unloads a cached resource chosen by the policy of cache. Returns false if it's
+    /// empty.
+    fn evict(&self) -> bool;
+
+    /// Returns false once the owner has been dropped, and this cache could be removed.
+    fn is_alive(&self) -> bool;
+
+    /// Gets the resources created from files, including the cached ones.
+    fn entries(&self) -> Vec<ResourceEntry> {
+        Vec::new()
+    }
+}
+
+/// A resource created from file, which is reported by `ResourceSystemShared::entries`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ResourceEntry {
+    pub uuid: Uuid,
+    /// The number of references, it's zero if the resource is cached for reuse.
+    pub rc: u32,
+    /// The approximate memory in bytes.
+    pub bytes: usize,
+    /// True if it's still being loaded.
+    pub loading: bool,
+    /// True if it has failed to load.
+    pub failed: bool,
 }
 
 pub struct ResourceSystemShared {
@@ -169,6 +313,110 @@ pub struct ResourceSystemShared {
 
     bufs: Arc<RwLock<Vec<Vec<u8>>>>,
     promises: Arc<RwLock<FastHashMap<Uuid, Arc<Promise>>>>,
+    declared: Arc<RwLock<FastHashMap<Uuid, Vec<Uuid>>>>,
+    queue: Arc<Mutex<Vec<QueuedLoad>>>,
+    recorder: Arc<AccessRecorder>,
+    closed: AtomicBool,
+    stats: Arc<LoadCounters>,
+
+    hot_reload: AtomicBool,
+    timestamps: Arc<RwLock<FastHashMap<Uuid, SystemTime>>>,
+    reloaders: RwLock<Vec<Box<dyn Reloader>>>,
+
+    caches: RwLock<Vec<Box<dyn Cache>>>,
+    memory_budget: RwLock<Option<usize>>,
+}
+
+/// The statistics of resource loading.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct LoadStats {
+    /// The number of loads in progress.
+    pub pending: usize,
+    /// The number of loads that have finished successfully.
+    pub finished: usize,
+    /// The number of loads that have failed.
+    pub failed: usize,
+    /// The number of bytes read from files.
+    pub bytes: usize,
+}
+
+// A load that waits for a worker, with the flag of cancellation.
+type QueuedLoad = (Arc<Promise>, Box<dyn FnOnce(bool) + Send>);
+
+// Takes the pending load with the highest priority, the earliest one goes first if there
+// are several of them.
+fn dequeue(queue: &Mutex<Vec<QueuedLoad>>) -> Option<QueuedLoad> {
+    let mut queue = queue.lock().unwrap();
+
+    let mut index = None;
+    for (i, v) in queue.iter().enumerate() {
+        if index.map(|j: usize| v.0.priority() > queue[j].0.priority()).unwrap_or(true) {
+            index = Some(i);
+        }
+    }
+
+    index.map(|i| queue.remove(i))
+}
+
+// Declares the runtime dependencies of `uuid`, an empty list clears them.
+fn declare(declared: &RwLock<FastHashMap<Uuid, Vec<Uuid>>>, uuid: Uuid, dependencies: Vec<Uuid>) {
+    let mut declared = declared.write().unwrap();
+    if dependencies.is_empty() {
+        declared.remove(&uuid);
+    } else {
+        declared.insert(uuid, dependencies);
+    }
+}
+
+// Gets the dependencies of `uuid` in manifest, followed by the declared ones.
+fn dependencies(
+    driver: &RwLock<VFSDriver>,
+    declared: &RwLock<FastHashMap<Uuid, Vec<Uuid>>>,
+    uuid: Uuid,
+) -> Vec<Uuid> {
+    let mut dependencies: Vec<Uuid> = driver
+        .read()
+        .unwrap()
+        .vfs_from_uuid(uuid)
+        .and_then(|vfs| vfs.dependencies(uuid).map(|v| v.collect()))
+        .unwrap_or_default();
+
+    if let Some(declared) = declared.read().unwrap().get(&uuid) {
+        for &v in declared {
+            if !dependencies.contains(&v) {
+                dependencies.push(v);
+            }
+        }
+    }
+
+    dependencies
+}
+
+// Gets `roots` and all the resources they depend on directly or indirectly.
+fn reachable(
+    driver: &RwLock<VFSDriver>,
+    declared: &RwLock<FastHashMap<Uuid, Vec<Uuid>>>,
+    roots: &[Uuid],
+) -> Vec<Uuid> {
+    let mut visited = FastHashSet::default();
+    let mut tree = Vec::new();
+    let mut stack: Vec<_> = roots.iter().rev().cloned().collect();
+
+    while let Some(v) = stack.pop() {
+        if visited.insert(v) {
+            tree.push(v);
+            stack.extend(dependencies(driver, declared, v));
+        }
+    }
+
+    tree
+}
+
+#[derive(Default)]
+struct LoadCounters {
+    finished: AtomicUsize,
+    failed: AtomicUsize,
+    bytes: AtomicUsize,
 }
 
 impl ResourceSystemShared {
@@ -181,8 +429,360 @@ impl ResourceSystemShared {
             .and_then(|vfs| vfs.redirect(location.filename()))
     }
 
+    /// Writes the whole file at `location` of a writable file system, e.g.
+    /// `"saves:slot1.sav"`. It blocks current thread until the file is written.
+    pub fn write<'a, T>(&self, location: T, bytes: &[u8]) -> Result<()>
+    where
+        T: Into<Location<'a>>,
+    {
+        let location = location.into();
+        let fs = self.writable(location)?;
+        fs.write(location.filename().as_ref(), bytes)
+    }
+
+    /// Reads the whole file at `location` of a writable file system.
+    pub fn read<'a, T>(&self, location: T) -> Result<Vec<u8>>
+    where
+        T: Into<Location<'a>>,
+    {
+        let location = location.into();
+        let fs = self.writable(location)?;
+
+        let mut buf = Vec::new();
+        fs.read_to_end(location.filename().as_ref(), &mut buf)?;
+        Ok(buf)
+    }
+
+    /// Removes the file or directory at `location` of a writable file system.
+    pub fn remove<'a, T>(&self, location: T) -> Result<()>
+    where
+        T: Into<Location<'a>>,
+    {
+        let location = location.into();
+        let fs = self.writable(location)?;
+        fs.remove(location.filename().as_ref())
+    }
+
+    /// Returns true if the file exists at `location` of a writable file system.
+    pub fn exists<'a, T>(&self, location: T) -> bool
+    where
+        T: Into<Location<'a>>,
+    {
+        let location = location.into();
+        self.writable(location)
+            .map(|fs| fs.exists(location.filename().as_ref()))
+            .unwrap_or(false)
+    }
+
+    fn writable(&self, location: Location) -> Result<Arc<dyn WritableFilesystem>> {
+        self.driver
+            .read()
+            .unwrap()
+            .writable(location.vfs())
+            .ok_or_else(|| {
+                format_err!(
+                    "Undefined writable virtual filesystem with identifier {}.",
+                    location.vfs()
+                )
+            })
+    }
+
+    /// Gets the statistics of resource loading since the start.
+    pub fn load_stats(&self) -> LoadStats {
+        LoadStats {
+            pending: self.promises.read().unwrap().len(),
+            finished: self.stats.finished.load(Ordering::SeqCst),
+            failed: self.stats.failed.load(Ordering::SeqCst),
+            bytes: self.stats.bytes.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Gets the recorder of resource accesses.
+    #[inline]
+    pub fn recorder(&self) -> &AccessRecorder {
+        &self.recorder
+    }
+
+    /// Gets the readable identifier of resource in manifest.
+    pub fn filename(&self, uuid: Uuid) -> Option<String> {
+        self.driver
+            .read()
+            .unwrap()
+            .vfs_from_uuid(uuid)
+            .and_then(|vfs| vfs.filename(uuid).map(|v| v.to_owned()))
+    }
+
+    /// Gets the resources that `uuid` depends on directly, e.g. the meshes and textures
+    /// of a prefab. It includes the ones in manifest, and the ones declared at runtime.
+    pub fn dependencies(&self, uuid: Uuid) -> Vec<Uuid> {
+        dependencies(&self.driver, &self.declared, uuid)
+    }
+
+    /// Declares the resources that `uuid` depends on at runtime, e.g. the ones created
+    /// by its loader. They are included in the `progress` of `uuid`, and an empty list
+    /// clears the declaration.
+    ///
+    /// The `Registry` declares the dependencies of its `Register` automatically.
+    pub fn declare_dependencies(&self, uuid: Uuid, dependencies: Vec<Uuid>) {
+        declare(&self.declared, uuid, dependencies);
+    }
+
+    /// Gets the loading progress in [0, 1] of resource `uuid` and all the resources it
+    /// depends on directly or indirectly. It's the ratio of the ones that are not being
+    /// loaded, so it only reaches 1 once the whole tree is loaded.
+    pub fn progress(&self, uuid: Uuid) -> f32 {
+        let tree = reachable(&self.driver, &self.declared, &[uuid]);
+        let promises = self.promises.read().unwrap();
+        let loaded = tree.iter().filter(|v| !promises.contains_key(v)).count();
+        loaded as f32 / tree.len() as f32
+    }
+
+    /// Groups the resources at `locations`, so the progress of them could be reported
+    /// together, e.g. by loading screens. The resources should have been created with
+    /// their registries, since the ones that are not being loaded are treated as loaded.
+    ///
+    /// ```rust,ignore
+    /// let texture = video.create_texture_from("res:crate.bmp")?;
+    /// let mesh = video.create_mesh_from("res:cube.obj")?;
+    /// let group = res.load_group(&["res:crate.bmp", "res:cube.obj"])?;
+    /// ...
+    /// draw_progress_bar(group.progress());
+    /// ```
+    pub fn load_group<'a, T>(&self, locations: &[T]) -> Result<LoadGroup>
+    where
+        T: Into<Location<'a>> + Copy,
+    {
+        let mut uuids = Vec::new();
+        for &v in locations {
+            let location = v.into();
+            let uuid = self.redirect(location).ok_or_else(|| {
+                format_err!("Undefined resource at {}:{}.", location.vfs(), location.filename())
+            })?;
+
+            uuids.push(uuid);
+        }
+
+        Ok(self.load_group_from_uuids(&uuids))
+    }
+
+    /// Groups the resources with uuids, see `load_group` for details.
+    pub fn load_group_from_uuids(&self, uuids: &[Uuid]) -> LoadGroup {
+        LoadGroup {
+            uuids: uuids.to_vec(),
+            driver: self.driver.clone(),
+            declared: self.declared.clone(),
+            promises: self.promises.clone(),
+        }
+    }
+
+    /// Blocks current thread until resource `uuid` and all the resources it depends on
+    /// are loaded. Unlike `wait_until`, the results of loads are left to their owners.
+    pub fn wait_until_dependencies(&self, uuid: Uuid) {
+        loop {
+            // Dependencies are declared once their dependents are loaded, so the tree
+            // is walked again until nothing is pending.
+            let pending: Vec<_> = reachable(&self.driver, &self.declared, &[uuid])
+                .into_iter()
+                .filter_map(|v| self.promise(v))
+                .collect();
+
+            if pending.is_empty() {
+                break;
+            }
+
+            for v in pending {
+                self.sched.wait_until(v.as_ref());
+            }
+        }
+    }
+
+    /// Writes the dependency graph of resources that are reachable from `roots` in
+    /// DOT format, which could be rendered with Graphviz. It helps to find out why a
+    /// resource is resident, e.g. with the roots from `AccessRecorder`.
+    pub fn write_dependency_graph(&self, roots: &[Uuid], w: &mut dyn Write) -> Result<()> {
+        let mut visited = FastHashSet::default();
+        let mut stack: Vec<_> = roots.iter().rev().cloned().collect();
+
+        writeln!(w, "digraph resources {{")?;
+
+        while let Some(uuid) = stack.pop() {
+            if !visited.insert(uuid) {
+                continue;
+            }
+
+            let filename = self.filename(uuid).unwrap_or_else(|| format!("{}", uuid));
+            let filename = filename.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(w, "    \"{}\" [label=\"{}\"];", uuid, filename)?;
+
+            let dependencies = self.dependencies(uuid);
+            for v in &dependencies {
+                writeln!(w, "    \"{}\" -> \"{}\";", uuid, v)?;
+            }
+
+            stack.extend(dependencies.into_iter().rev());
+        }
+
+        writeln!(w, "}}")?;
+        Ok(())
+    }
+
+    /// Enables or disables polling the modified files in `ResourceSystem::advance`. It's
+    /// disabled by default.
+    pub fn set_hot_reload(&self, enabled: bool) {
+        self.hot_reload.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Returns true if hot reload is enabled.
+    #[inline]
+    pub fn is_hot_reload(&self) -> bool {
+        self.hot_reload.load(Ordering::SeqCst)
+    }
+
+    /// Adds a reloader which is notified with the modified resources.
+    pub fn watch(&self, reloader: Box<dyn Reloader>) {
+        self.reloaders.write().unwrap().push(reloader);
+    }
+
+    /// Adds a cache whose memory is limited by the memory budget.
+    pub fn add_cache(&self, cache: Box<dyn Cache>) {
+        self.caches.write().unwrap().push(cache);
+    }
+
+    /// Sets the budget of memory of the unused resources in all the caches, e.g. the
+    /// ones kept by registries with `Registry::set_cache_policy`. It's unlimited with
+    /// `None` by default.
+    pub fn set_memory_budget<T: Into<Option<usize>>>(&self, bytes: T) {
+        *self.memory_budget.write().unwrap() = bytes.into();
+    }
+
+    /// Gets the budget of memory of the unused resources.
+    #[inline]
+    pub fn memory_budget(&self) -> Option<usize> {
+        *self.memory_budget.read().unwrap()
+    }
+
+    /// Gets the approximate memory of the unused resources in all the caches.
+    pub fn cached_bytes(&self) -> usize {
+        self.caches
+            .read()
+            .unwrap()
+            .iter()
+            .map(|v| v.cached_bytes())
+            .sum()
+    }
+
+    /// Gets the resources created from files in all the caches, e.g. the registries.
+    pub fn entries(&self) -> Vec<ResourceEntry> {
+        self.caches
+            .read()
+            .unwrap()
+            .iter()
+            .flat_map(|v| v.entries())
+            .collect()
+    }
+
+    /// Evicts unused resources until the caches fit into the memory budget, starting
+    /// from the largest cache. Returns the number of evicted resources.
+    ///
+    /// This is called by `ResourceSystem::advance` every frame.
+    pub fn trim_caches(&self) -> usize {
+        let budget = match self.memory_budget() {
+            Some(budget) => budget,
+            None => return 0,
+        };
+
+        let mut caches = self.caches.write().unwrap();
+        caches.retain(|v| v.is_alive());
+
+        let mut evicted = 0;
+        loop {
+            let bytes: Vec<_> = caches.iter().map(|v| v.cached_bytes()).collect();
+            if bytes.iter().sum::<usize>() <= budget {
+                break;
+            }
+
+            match (0..bytes.len()).max_by_key(|&i| bytes[i]) {
+                Some(i) if caches[i].evict() => evicted += 1,
+                _ => break,
+            }
+        }
+
+        evicted
+    }
+
+    /// Returns true if the file of resource has been modified since it was loaded last time.
+    pub fn is_modified(&self, uuid: Uuid) -> bool {
+        let ts = match self.timestamps.read().unwrap().get(&uuid) {
+            Some(&ts) => ts,
+            None => return false,
+        };
+
+        self.driver
+            .read()
+            .unwrap()
+            .vfs_from_uuid(uuid)
+            .and_then(|vfs| vfs.locate(uuid).map(|uri| vfs.modified_since(&uri, ts)))
+            .unwrap_or(false)
+    }
+
+    /// Checks the files of all the loaded resources, and reloads the modified ones with
+    /// the reloaders. Returns the modified resources.
+    ///
+    /// This is called by `ResourceSystem::advance` periodically when hot reload is enabled.
+    pub fn reload_modified(&self) -> Vec<Uuid> {
+        let uuids: Vec<_> = self.timestamps.read().unwrap().keys().cloned().collect();
+        let modified: Vec<_> = uuids
+            .into_iter()
+            .filter(|&v| self.is_modified(v) && !self.promises.read().unwrap().contains_key(&v))
+            .collect();
+
+        if modified.is_empty() {
+            return modified;
+        }
+
+        {
+            // Resources that no one reloads are not reported again until next modification.
+            let now = SystemTime::now();
+            let mut timestamps = self.timestamps.write().unwrap();
+            for &v in &modified {
+                timestamps.insert(v, now);
+            }
+        }
+
+        let mut reloaders = self.reloaders.write().unwrap();
+        reloaders.retain(|v| v.is_alive());
+
+        for &uuid in &modified {
+            info!("Reloads modified resource {}.", uuid);
+            for v in reloaders.iter() {
+                v.reload(self, uuid);
+            }
+        }
+
+        modified
+    }
+
     /// Loads a resource at readable location asynchronously.
+    #[inline]
     pub fn load_from<T: Loader>(&self, loader: T, location: Location) -> Result<Arc<Promise>> {
+        self.load_from_with_priority(loader, location, 0)
+    }
+
+    /// Loads a resource with uuid asynchronously.
+    #[inline]
+    pub fn load_from_uuid<T: Loader>(&self, loader: T, uuid: Uuid) -> Result<Arc<Promise>> {
+        self.load_from_uuid_with_priority(loader, uuid, 0)
+    }
+
+    /// Loads a resource at readable location asynchronously, with the priority among
+    /// the loads that are waiting for workers. The load could be re-prioritized or
+    /// cancelled with the returned `Promise` before it's started.
+    pub fn load_from_with_priority<T: Loader>(
+        &self,
+        loader: T,
+        location: Location,
+        priority: i32,
+    ) -> Result<Arc<Promise>> {
         let uuid = self.redirect(location).ok_or_else(|| {
             format_err!(
                 "Undefined virtual filesystem with identifier {}.",
@@ -190,11 +790,21 @@ impl ResourceSystemShared {
             )
         })?;
 
-        self.load_from_uuid(loader, uuid)
+        self.load_from_uuid_with_priority(loader, uuid, priority)
     }
 
-    /// Loads a resource with uuid asynchronously.
-    pub fn load_from_uuid<T: Loader>(&self, loader: T, uuid: Uuid) -> Result<Arc<Promise>> {
+    /// Loads a resource with uuid asynchronously, with the priority among the loads that
+    /// are waiting for workers.
+    pub fn load_from_uuid_with_priority<T: Loader>(
+        &self,
+        loader: T,
+        uuid: Uuid,
+        priority: i32,
+    ) -> Result<Arc<Promise>> {
+        if self.closed.load(Ordering::SeqCst) {
+            bail!("Can not load resource {} after shutdown.", uuid);
+        }
+
         let vfs = self
             .driver
             .read()
@@ -209,6 +819,7 @@ impl ResourceSystemShared {
             }
 
             let latch = Arc::new(Promise::new());
+            latch.set_priority(priority);
             promises.insert(uuid, latch.clone());
             latch
         };
@@ -216,31 +827,92 @@ impl ResourceSystemShared {
         let tx = latch.clone();
         let bufs = self.bufs.clone();
         let promises = self.promises.clone();
+        let recorder = self.recorder.clone();
+        let stats = self.stats.clone();
+        let timestamps = self.timestamps.clone();
 
-        self.sched.spawn(move || {
+        let load = move |cancelled: bool| {
             let mut bytes = bufs.write().unwrap().pop().unwrap_or(Vec::new());
-            let uri = vfs.locate(uuid).unwrap();
 
-            if let Err(err) = vfs.read_to_end(&uri, &mut bytes) {
-                tx.set(Err(err));
-            } else {
-                tx.set(loader.load(&bytes));
-            }
+            // A panicking loader fails this load only, instead of aborting the workers.
+            let rsp = panic::catch_unwind(AssertUnwindSafe(|| {
+                let read = if cancelled {
+                    Err(format_err!("Cancelled."))
+                } else {
+                    vfs.locate(uuid)
+                        .ok_or_else(|| format_err!("Undefined uuid with {}", uuid))
+                        .and_then(|uri| {
+                            // Takes the time before reading, so modifications during the
+                            // read are not missed.
+                            let ts = vfs.modified(&uri).unwrap_or_else(SystemTime::now);
+                            let len = vfs.read_to_end(&uri, &mut bytes)?;
+                            timestamps.write().unwrap().insert(uuid, ts);
+                            stats.bytes.fetch_add(len, Ordering::SeqCst);
+                            recorder.record(uuid, vfs.filename(uuid), &uri);
+                            Ok(())
+                        })
+                };
+
+                match read {
+                    Ok(_) => loader.load(&bytes),
+                    Err(err) => {
+                        loader.fail(&err);
+                        Err(err)
+                    }
+                }
+            }));
+
+            let rsp = rsp.unwrap_or_else(|err| {
+                Err(format_err!("Panicked: {}", unwind::panic_message(&*err)))
+            });
 
-            promises.write().unwrap().remove(&uuid);
             bytes.clear();
             bufs.write().unwrap().push(bytes);
+
+            match rsp {
+                Ok(_) => stats.finished.fetch_add(1, Ordering::SeqCst),
+                Err(_) => stats.failed.fetch_add(1, Ordering::SeqCst),
+            };
+
+            // Removes the promise before it's set, so the resource could be loaded again
+            // as soon as the waiters are notified.
+            promises.write().unwrap().remove(&uuid);
+
+            // Keeps the underlying error as cause, and tells which resource failed.
+            tx.set(rsp.map_err(|err| {
+                let filename = vfs.filename(uuid).unwrap_or("<unknown>");
+                err.context(format!("Failed to load {} ({}).", filename, uuid))
+                    .into()
+            }));
+        };
+
+        // Every spawned job takes the most important load when it's executed, instead of
+        // the one it was spawned with.
+        self.queue
+            .lock()
+            .unwrap()
+            .push((latch.clone(), Box::new(load)));
+
+        let queue = self.queue.clone();
+        self.sched.spawn(move || {
+            if let Some((promise, load)) = dequeue(&queue) {
+                load(promise.is_cancelled());
+            }
         });
 
         Ok(latch)
     }
 
+    /// Gets the promise of resource `uuid` if it's being loaded.
+    pub fn promise(&self, uuid: Uuid) -> Option<Arc<Promise>> {
+        self.promises.read().unwrap().get(&uuid).cloned()
+    }
+
     /// Blocks current thread until the loading process of resource `uuid` finished.
     pub fn wait_until(&self, uuid: Uuid) -> Result<()> {
-        let promise = self.promises.read().unwrap().get(&uuid).cloned();
-        if let Some(promise) = promise {
+        if let Some(promise) = self.promise(uuid) {
             self.sched.wait_until(promise.as_ref());
-            promise.take()
+            promise.result()
         } else {
             Ok(())
         }
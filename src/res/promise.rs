@@ -1,16 +1,43 @@
-use std::sync::{Condvar, Mutex};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+
+use failure::Fail;
 
 use errors::*;
 use sched::latch::{LatchProbe, LatchWaitProbe};
 
 enum PromiseState {
     NotReady,
-    Ok(Result<()>),
+    Ok(::std::result::Result<(), SharedError>),
+}
+
+// The error of a failed load, which is shared by everyone waiting on the promise.
+#[derive(Debug, Clone)]
+struct SharedError(Arc<::failure::Error>);
+
+impl fmt::Display for SharedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Fail for SharedError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.0.as_fail().cause()
+    }
 }
 
 pub struct Promise {
     m: Mutex<PromiseState>,
     v: Condvar,
+    wakers: Mutex<Vec<Waker>>,
+    priority: AtomicIsize,
+    cancelled: AtomicBool,
 }
 
 impl Promise {
@@ -19,24 +46,71 @@ impl Promise {
         Promise {
             m: Mutex::new(PromiseState::NotReady),
             v: Condvar::new(),
+            wakers: Mutex::new(Vec::new()),
+            priority: AtomicIsize::new(0),
+            cancelled: AtomicBool::new(false),
         }
     }
 
+    /// Gets the priority of this load.
+    #[inline]
+    pub fn priority(&self) -> i32 {
+        self.priority.load(Ordering::Relaxed) as i32
+    }
+
+    /// Sets the priority of this load, the pending loads with higher priority are
+    /// started first. It takes no effect once the load has been started.
+    #[inline]
+    pub fn set_priority(&self, priority: i32) {
+        self.priority.store(priority as isize, Ordering::Relaxed);
+    }
+
+    /// Cancels this load if it has not been started yet, it would fail instead of being
+    /// read and loaded. Loads in progress are finished as usual.
+    #[inline]
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true if this load has been cancelled.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
     #[inline]
     pub(crate) fn set(&self, v: Result<()>) {
         {
             let mut guard = self.m.lock().unwrap();
-            *guard = PromiseState::Ok(v);
+            *guard = PromiseState::Ok(v.map_err(|err| SharedError(Arc::new(err))));
         }
 
         self.v.notify_all();
+
+        for v in self.wakers.lock().unwrap().drain(..) {
+            v.wake();
+        }
     }
 
+    // Registers a waker which is woken once this promise is set, the state is locked
+    // so it can't miss the `set`. Returns false if it has been set already.
+    pub(crate) fn register(&self, waker: &Waker) -> bool {
+        let guard = self.m.lock().unwrap();
+        if let PromiseState::NotReady = *guard {
+            self.wakers.lock().unwrap().push(waker.clone());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Gets the result once this promise is set. It could be read any number of times,
+    /// so everyone waiting on a failed load gets the error.
     #[inline]
-    pub fn take(&self) -> Result<()> {
-        let mut guard = self.m.lock().unwrap();
-        if let PromiseState::Ok(v) = ::std::mem::replace(&mut *guard, PromiseState::Ok(Ok(()))) {
-            v
+    pub fn result(&self) -> Result<()> {
+        let guard = self.m.lock().unwrap();
+        if let PromiseState::Ok(ref v) = *guard {
+            v.clone().map_err(|err| err.into())
         } else {
             unreachable!();
         }
@@ -62,3 +136,59 @@ impl LatchWaitProbe for Promise {
         }
     }
 }
+
+/// A `Future` which resolves once the `Promise` is set, so loads could be awaited in
+/// async runtimes. An empty future resolves immediately.
+pub struct PromiseFuture(Option<Arc<Promise>>);
+
+impl PromiseFuture {
+    pub fn new<T: Into<Option<Arc<Promise>>>>(promise: T) -> Self {
+        PromiseFuture(promise.into())
+    }
+}
+
+impl From<Arc<Promise>> for PromiseFuture {
+    fn from(promise: Arc<Promise>) -> Self {
+        PromiseFuture(Some(promise))
+    }
+}
+
+impl Future for PromiseFuture {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let promise = match self.0 {
+            Some(ref promise) => promise,
+            None => return Poll::Ready(Ok(())),
+        };
+
+        if promise.register(cx.waker()) {
+            Poll::Pending
+        } else {
+            Poll::Ready(promise.result())
+        }
+    }
+}
+
+struct ThreadWaker(thread::Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Blocks current thread until the future resolves, which is handy to use futures in
+/// synchronous code.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(v) => return v,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
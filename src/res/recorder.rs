@@ -0,0 +1,140 @@
+//! Records the resources that have been loaded during a session.
+//!
+//! The report lists every resource in the order of its first access, and could be
+//! used by build pipelines to strip the resources that are never used from shipped
+//! archives, or to lay out archives in loading order.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use uuid::Uuid;
+
+use errors::*;
+use utils::FastHashSet;
+
+/// The record of a loaded resource.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessRecord {
+    /// The universe-uniqued identifier of resource.
+    pub uuid: Uuid,
+    /// The readable identifier of resource in manifest.
+    pub filename: Option<String>,
+    /// The actual path of resource in its virtual filesystem.
+    pub path: PathBuf,
+}
+
+/// Records the resources that have been loaded. It's disabled by default.
+pub struct AccessRecorder {
+    enabled: AtomicBool,
+    records: Mutex<(FastHashSet<Uuid>, Vec<AccessRecord>)>,
+}
+
+impl AccessRecorder {
+    pub(crate) fn new() -> Self {
+        AccessRecorder {
+            enabled: AtomicBool::new(false),
+            records: Mutex::new((FastHashSet::default(), Vec::new())),
+        }
+    }
+
+    /// Starts to record resource accesses.
+    pub fn start(&self) {
+        self.enabled.store(true, Ordering::SeqCst);
+    }
+
+    /// Stops recording. The records are kept until `clear` is called.
+    pub fn stop(&self) {
+        self.enabled.store(false, Ordering::SeqCst);
+    }
+
+    /// Returns true if the recorder is recording.
+    #[inline]
+    pub fn is_recording(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Removes all the records.
+    pub fn clear(&self) {
+        let mut records = self.records.lock().unwrap();
+        records.0.clear();
+        records.1.clear();
+    }
+
+    /// Gets the records in the order of first access.
+    pub fn records(&self) -> Vec<AccessRecord> {
+        self.records.lock().unwrap().1.clone()
+    }
+
+    /// Writes the records as plain text, one resource per line with its uuid, actual
+    /// path and readable identifier separated by tabs.
+    pub fn write_report(&self, w: &mut dyn Write) -> Result<()> {
+        writeln!(w, "# uuid\tpath\tfilename")?;
+        for v in &self.records.lock().unwrap().1 {
+            writeln!(
+                w,
+                "{:X}\t{}\t{}",
+                v.uuid.to_simple(),
+                v.path.display(),
+                v.filename.as_ref().map(|v| v.as_str()).unwrap_or("")
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn record(&self, uuid: Uuid, filename: Option<&str>, path: &Path) {
+        if !self.is_recording() {
+            return;
+        }
+
+        let mut records = self.records.lock().unwrap();
+        if records.0.insert(uuid) {
+            records.1.push(AccessRecord {
+                uuid: uuid,
+                filename: filename.map(|v| v.to_owned()),
+                path: path.to_owned(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn record() {
+        let recorder = AccessRecorder::new();
+        let u1 = Uuid::from_bytes([1; 16]);
+        let u2 = Uuid::from_bytes([2; 16]);
+
+        recorder.record(u1, Some("a.png"), Path::new("A"));
+        assert!(recorder.records().is_empty());
+
+        recorder.start();
+        recorder.record(u2, None, Path::new("B"));
+        recorder.record(u1, Some("a.png"), Path::new("A"));
+        recorder.record(u2, None, Path::new("B"));
+        recorder.stop();
+        recorder.record(Uuid::from_bytes([3; 16]), None, Path::new("C"));
+
+        let records = recorder.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].uuid, u2);
+        assert_eq!(records[0].filename, None);
+        assert_eq!(records[1].uuid, u1);
+        assert_eq!(records[1].filename, Some("a.png".to_owned()));
+
+        let mut report = Vec::new();
+        recorder.write_report(&mut report).unwrap();
+        let report = String::from_utf8(report).unwrap();
+        let lines: Vec<_> = report.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[2], format!("{:X}\tA\ta.png", u1.to_simple()));
+
+        recorder.clear();
+        assert!(recorder.records().is_empty());
+    }
+}
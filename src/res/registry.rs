@@ -1,13 +1,17 @@
 //! The `Registry` is a standardized resources manager that defines a set of interface for creation,
 //! destruction, sharing and lifetime management. It is used in all the built-in crayon modules.
 
-use std::sync::{Arc, RwLock};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, RwLock, Weak};
+use std::time::SystemTime;
 use uuid::Uuid;
 
 use errors::*;
+use sched::unwind;
 use utils::{FastHashMap, HandleLike, ObjectPool};
 
-use super::{Loader, Location, ResourceSystemShared};
+use super::promise::{Promise, PromiseFuture};
+use super::{declare, Cache, Loader, Location, Reloader, ResourceEntry, ResourceSystemShared};
 
 pub trait Register: Send + Sync {
     type Handle: Send + Sync;
@@ -17,12 +21,80 @@ pub trait Register: Send + Sync {
     fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate>;
     fn attach(&self, handle: Self::Handle, item: Self::Intermediate) -> Result<Self::Value>;
     fn detach(&self, handle: Self::Handle, value: Self::Value);
+
+    /// Gets the resources that `item` depends on, e.g. the textures of a material. They
+    /// are usually created in `load` and deleted in `detach`, so they are kept alive by
+    /// the resource. `Registry::wait_until` waits for them, and they are reported in
+    /// the `progress` of resource.
+    fn dependencies(&self, _: &Self::Intermediate) -> Vec<Uuid> {
+        Vec::new()
+    }
+
+    /// Gets the approximate memory of value in bytes, which is used by the limits of
+    /// caches and the memory budget.
+    fn memory(&self, _: &Self::Value) -> usize {
+        0
+    }
+
+    /// Replaces the `old` value of a reloaded resource with `item`. If it fails, the old
+    /// value is handed back with the error, and the resource keeps using it.
+    ///
+    /// The new value is attached before the old one is detached by default. Registers that
+    /// key their objects with handles should override it, and detach the old value once
+    /// `item` is known to be attached successfully.
+    fn reattach(
+        &self,
+        handle: Self::Handle,
+        old: Self::Value,
+        item: Self::Intermediate,
+    ) -> ::std::result::Result<Self::Value, (Self::Value, ::failure::Error)>
+    where
+        Self::Handle: Copy,
+    {
+        match self.attach(handle, item) {
+            Ok(value) => {
+                self.detach(handle, old);
+                Ok(value)
+            }
+            Err(err) => Err((old, err)),
+        }
+    }
+}
+
+/// Which unused resource is evicted first when the cache is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Eviction {
+    /// The least recently used one.
+    Lru,
+    /// The least frequently used one, which is created or retained the least times.
+    Lfu,
+}
+
+/// The limits of unused resources that are kept by a `Registry` for reuse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+    /// The maximum number of unused resources.
+    pub max_items: usize,
+    /// The maximum memory of unused resources in bytes.
+    pub max_bytes: usize,
+    pub eviction: Eviction,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        CachePolicy {
+            max_items: ::std::usize::MAX,
+            max_bytes: ::std::usize::MAX,
+            eviction: Eviction::Lru,
+        }
+    }
 }
 
 // The `Registry` is a standardized resources manager that defines a set of interface for creation,
 // destruction, sharing and lifetime management. It is used in all the built-in crayon modules.
 pub struct Registry<H: HandleLike + 'static, R: Register<Handle = H> + Clone + 'static> {
     res: Arc<ResourceSystemShared>,
+    records: Records,
     payload: Arc<RwLock<Payload<H, R>>>,
     register: R,
 }
@@ -33,11 +105,27 @@ impl<H: HandleLike + 'static, R: Register<Handle = H> + Clone + 'static> Registr
         let payload = Payload {
             items: ObjectPool::new(),
             redirects: FastHashMap::default(),
+            cache: Vec::new(),
+            policy: None,
         };
 
+        let payload = Arc::new(RwLock::new(payload));
+        let records = Records::new(&res);
+        res.watch(Box::new(RegistryReloader {
+            register: register.clone(),
+            payload: Arc::downgrade(&payload),
+        }));
+
+        res.add_cache(Box::new(RegistryCache {
+            register: register.clone(),
+            payload: Arc::downgrade(&payload),
+            records: records.clone(),
+        }));
+
         Registry {
             res: res,
-            payload: Arc::new(RwLock::new(payload)),
+            records: records,
+            payload: payload,
             register: register,
         }
     }
@@ -49,14 +137,19 @@ impl<H: HandleLike + 'static, R: Register<Handle = H> + Clone + 'static> Registr
         let entry = Entry {
             rc: 1,
             uuid: None,
+            holders: Vec::new(),
             state: AsyncState::NotReady,
+            hits: 1,
+            bytes: 0,
         };
 
         let mut payload = self.payload.write().unwrap();
 
         let handle = payload.items.create(entry);
         let value = self.register.attach(handle, params)?;
-        payload.items.get_mut(handle).unwrap().state = AsyncState::Ok(value);
+        let entry = payload.items.get_mut(handle).unwrap();
+        entry.bytes = self.register.memory(&value);
+        entry.state = AsyncState::Ok(value);
 
         Ok(handle)
     }
@@ -84,14 +177,17 @@ impl<H: HandleLike + 'static, R: Register<Handle = H> + Clone + 'static> Registr
             let mut payload = self.payload.write().unwrap();
 
             if let Some(&handle) = payload.redirects.get(&uuid) {
-                payload.items.get_mut(handle).unwrap().rc += 1;
+                acquire(&mut payload, handle);
                 return Ok(handle);
             }
 
             let entry = Entry {
                 rc: 1,
                 uuid: Some(uuid),
+                holders: Vec::new(),
                 state: AsyncState::NotReady,
+                hits: 1,
+                bytes: 0,
             };
 
             let handle = payload.items.create(entry);
@@ -101,16 +197,17 @@ impl<H: HandleLike + 'static, R: Register<Handle = H> + Clone + 'static> Registr
 
         let loader = RegistryLoader {
             handle: handle,
+            uuid: uuid,
             register: self.register.clone(),
             payload: self.payload.clone(),
+            records: self.records.clone(),
         };
 
-        match self.res.load_from_uuid(loader, uuid) {
-            Err(err) => {
-                self.payload.write().unwrap().items.free(handle).unwrap();
-                return Err(err);
-            }
-            _ => {}
+        if let Err(err) = self.res.load_from_uuid(loader, uuid) {
+            let mut payload = self.payload.write().unwrap();
+            payload.items.free(handle);
+            payload.redirects.remove(&uuid);
+            return Err(err);
         }
 
         Ok(handle)
@@ -118,12 +215,98 @@ impl<H: HandleLike + 'static, R: Register<Handle = H> + Clone + 'static> Registr
 
     /// Deletes a resource from registery.
     pub fn delete(&self, handle: H) {
+        self.release(handle, None);
+    }
+
+    /// Loads the resource from its file again. The old value is kept until the new one
+    /// is loaded, and then replaced under the same handle.
+    pub fn reload(&self, handle: H) -> Result<()> {
+        let uuid = self
+            .uuid(handle)
+            .ok_or_else(|| format_err!("Resource is not created from file."))?;
+
+        reload(&self.res, &self.payload, &self.register, handle, uuid)
+    }
+
+    /// Increases the reference count of a living resource on behalf of `holder`. The
+    /// reference should be dropped with `release_by` later.
+    ///
+    /// Returns false if the resource has been destroyed.
+    pub fn retain_by(&self, handle: H, holder: &'static str) -> bool {
+        let mut payload = self.payload.write().unwrap();
+        if !acquire(&mut payload, handle) {
+            return false;
+        }
+
+        let entry = payload.items.get_mut(handle).unwrap();
+        match entry.holders.iter().position(|v| v.0 == holder) {
+            Some(i) => entry.holders[i].1 += 1,
+            None => entry.holders.push((holder, 1)),
+        }
+
+        true
+    }
+
+    /// Drops a reference that was retained by `holder`.
+    pub fn release_by(&self, handle: H, holder: &'static str) {
+        self.release(handle, Some(holder));
+    }
+
+    /// Gets the reference count of resource.
+    #[inline]
+    pub fn rc(&self, handle: H) -> Option<u32> {
+        self.payload.read().unwrap().items.get(handle).map(|v| v.rc)
+    }
+
+    /// Gets the holders and the number of references they retained. References from
+    /// `create*` are not listed, which is useful to find out which system leaks a
+    /// resource.
+    pub fn holders(&self, handle: H) -> Vec<(&'static str, u32)> {
+        self.payload
+            .read()
+            .unwrap()
+            .items
+            .get(handle)
+            .map(|v| v.holders.clone())
+            .unwrap_or_default()
+    }
+
+    fn release(&self, handle: H, holder: Option<&'static str>) {
         let mut payload = self.payload.write().unwrap();
 
         let disposed = payload
             .items
             .get_mut(handle)
             .map(|entry| {
+                // Cached resources are kept with no reference, and could be deleted again
+                // by mistake.
+                if entry.rc == 0 {
+                    warn!(
+                        "Resource ({}, {}) is released without any reference.",
+                        handle.index(),
+                        handle.version()
+                    );
+                    return false;
+                }
+
+                if let Some(holder) = holder {
+                    match entry.holders.iter().position(|v| v.0 == holder) {
+                        Some(i) if entry.holders[i].1 > 1 => entry.holders[i].1 -= 1,
+                        Some(i) => {
+                            entry.holders.remove(i);
+                        }
+                        None => {
+                            warn!(
+                                "Resource ({}, {}) is released by {} without retaining.",
+                                handle.index(),
+                                handle.version(),
+                                holder
+                            );
+                            return false;
+                        }
+                    }
+                }
+
                 entry.rc -= 1;
                 match entry.state {
                     AsyncState::Ok(_) | AsyncState::Err => entry.rc == 0,
@@ -132,18 +315,61 @@ impl<H: HandleLike + 'static, R: Register<Handle = H> + Clone + 'static> Registr
             }).unwrap_or(false);
 
         if disposed {
-            let entry = payload.items.free(handle).unwrap();
-
-            if let Some(uuid) = entry.uuid {
-                payload.redirects.remove(&uuid);
-            }
+            // Resources loaded from files could be cached, and created again without
+            // loading. The failed ones are disposed, so they are loaded again next time.
+            let cacheable = payload.policy.is_some() && payload
+                .items
+                .get(handle)
+                .map(|v| match v.state {
+                    AsyncState::Ok(_) => v.uuid.is_some(),
+                    _ => false,
+                }).unwrap_or(false);
 
-            if let AsyncState::Ok(value) = entry.state {
-                self.register.detach(handle, value);
+            if cacheable {
+                payload.cache.push(handle);
+                trim(&mut payload, &self.register, &self.records);
+            } else {
+                dispose(&mut payload, &self.register, &self.records, handle);
             }
         }
     }
 
+    /// Sets the policy of caching unused resources that are created from files, which
+    /// is disabled with `None` by default. Cached resources keep their handles, and are
+    /// reused without loading when they are created again.
+    ///
+    /// Unused resources are evicted once the limits of policy are exceeded, or by the
+    /// memory budget of `ResourceSystemShared`.
+    pub fn set_cache_policy<T: Into<Option<CachePolicy>>>(&self, policy: T) {
+        let mut payload = self.payload.write().unwrap();
+        payload.policy = policy.into();
+        trim(&mut payload, &self.register, &self.records);
+    }
+
+    /// Gets the policy of caching unused resources.
+    #[inline]
+    pub fn cache_policy(&self) -> Option<CachePolicy> {
+        self.payload.read().unwrap().policy
+    }
+
+    /// Gets the number of unused resources in cache.
+    #[inline]
+    pub fn cached_len(&self) -> usize {
+        self.payload.read().unwrap().cache.len()
+    }
+
+    /// Gets the approximate memory of unused resources in cache.
+    #[inline]
+    pub fn cached_bytes(&self) -> usize {
+        self.payload.read().unwrap().cached_bytes()
+    }
+
+    /// Unloads all the unused resources in cache.
+    pub fn clear_cache(&self) {
+        let mut payload = self.payload.write().unwrap();
+        while evict(&mut payload, &self.register, &self.records) {}
+    }
+
     /// Gets the underlying `uuid` of handle.
     #[inline]
     pub fn uuid(&self, handle: H) -> Option<Uuid> {
@@ -155,13 +381,73 @@ impl<H: HandleLike + 'static, R: Register<Handle = H> + Clone + 'static> Registr
             .and_then(|v| v.uuid)
     }
 
-    /// Blocks current thread until the loading process of resource finished.
+    /// Blocks current thread until the loading process of resource and its dependencies
+    /// finished. The result is the one of resource itself.
     pub fn wait_until(&self, handle: H) -> Result<()> {
         if let Some(uuid) = self.uuid(handle) {
-            self.res.wait_until(uuid)
-        } else {
-            Ok(())
+            self.res.wait_until(uuid)?;
+            self.res.wait_until_dependencies(uuid);
         }
+
+        self.check(handle)
+    }
+
+    /// Gets a future which resolves once the loading process of resource finished, e.g.
+    /// `registry.future(handle).await`. Resources that have failed to load resolve with
+    /// an error immediately.
+    pub fn future(&self, handle: H) -> PromiseFuture {
+        if let Some(promise) = self.uuid(handle).and_then(|uuid| self.res.promise(uuid)) {
+            return promise.into();
+        }
+
+        match self.check(handle) {
+            Ok(_) => PromiseFuture::new(None),
+            Err(err) => {
+                let promise = Promise::new();
+                promise.set(Err(err));
+                Arc::new(promise).into()
+            }
+        }
+    }
+
+    // Returns an error if the resource has failed to load.
+    fn check(&self, handle: H) -> Result<()> {
+        let payload = self.payload.read().unwrap();
+        if let Some(&AsyncState::Err) = payload.items.get(handle).map(|v| &v.state) {
+            bail!(
+                "Resource ({}, {}) has failed to load.",
+                handle.index(),
+                handle.version()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if the resource associated with `handle`, or any of its dependencies
+    /// is still being loaded.
+    #[inline]
+    pub fn is_loading(&self, handle: H) -> bool {
+        let loading = self
+            .payload
+            .read()
+            .unwrap()
+            .items
+            .get(handle)
+            .map(|v| match v.state {
+                AsyncState::NotReady => true,
+                _ => false,
+            }).unwrap_or(false);
+
+        loading || self.progress(handle) < 1.0
+    }
+
+    /// Gets the loading progress in [0, 1] of resource and its dependencies. Resources
+    /// that are not created from files are always loaded.
+    pub fn progress(&self, handle: H) -> f32 {
+        self.uuid(handle)
+            .map(|v| self.res.progress(v))
+            .unwrap_or(1.0)
     }
 
     /// Gets the length of this `Registry`.
@@ -194,6 +480,19 @@ impl<H: HandleLike + 'static, R: Register<Handle = H> + Clone + 'static> Registr
 struct Payload<H: HandleLike, R: Register<Handle = H>> {
     items: ObjectPool<H, Entry<R::Value>>,
     redirects: FastHashMap<Uuid, H>,
+    // The unused resources, in the order of being released.
+    cache: Vec<H>,
+    policy: Option<CachePolicy>,
+}
+
+impl<H: HandleLike, R: Register<Handle = H>> Payload<H, R> {
+    fn cached_bytes(&self) -> usize {
+        self.cache
+            .iter()
+            .filter_map(|&v| self.items.get(v))
+            .map(|v| v.bytes)
+            .sum()
+    }
 }
 
 enum AsyncState<T> {
@@ -205,55 +504,389 @@ enum AsyncState<T> {
 struct Entry<T> {
     rc: u32,
     uuid: Option<Uuid>,
+    holders: Vec<(&'static str, u32)>,
     state: AsyncState<T>,
+    // The number of times it has been created or retained.
+    hits: u32,
+    bytes: usize,
+}
+
+// Increases the reference count of resource, and takes it out of cache if it was unused.
+// Returns false if the resource has been destroyed.
+fn acquire<H, R>(payload: &mut Payload<H, R>, handle: H) -> bool
+where
+    H: HandleLike,
+    R: Register<Handle = H>,
+{
+    let revived = match payload.items.get_mut(handle) {
+        Some(entry) => {
+            entry.rc += 1;
+            entry.hits += 1;
+            entry.rc == 1
+        }
+        None => return false,
+    };
+
+    if revived {
+        payload.cache.retain(|v| v.index() != handle.index());
+    }
+
+    true
+}
+
+// Frees the resource, and detaches its value if it has been loaded.
+fn dispose<H, R>(
+    payload: &mut Payload<H, R>,
+    register: &R,
+    records: &Records,
+    handle: H,
+) where
+    H: HandleLike,
+    R: Register<Handle = H>,
+{
+    let entry = payload.items.free(handle).unwrap();
+
+    if let Some(uuid) = entry.uuid {
+        payload.redirects.remove(&uuid);
+        records.forget(uuid);
+    }
+
+    if let AsyncState::Ok(value) = entry.state {
+        register.detach(handle, value);
+    }
+}
+
+// Disposes an unused resource chosen by the cache policy. Returns false if the cache is
+// empty.
+fn evict<H, R>(
+    payload: &mut Payload<H, R>,
+    register: &R,
+    records: &Records,
+) -> bool
+where
+    H: HandleLike,
+    R: Register<Handle = H>,
+{
+    let eviction = payload.policy.map(|v| v.eviction).unwrap_or(Eviction::Lru);
+    let index = match eviction {
+        Eviction::Lru => if payload.cache.is_empty() {
+            None
+        } else {
+            Some(0)
+        },
+        Eviction::Lfu => {
+            // The oldest one goes first if there are ties.
+            let items = &payload.items;
+            let hits = |v: H| items.get(v).map(|v| v.hits).unwrap_or(0);
+            payload
+                .cache
+                .iter()
+                .enumerate()
+                .min_by_key(|&(i, &v)| (hits(v), i))
+                .map(|(i, _)| i)
+        }
+    };
+
+    match index {
+        Some(i) => {
+            let handle = payload.cache.remove(i);
+            dispose(payload, register, records, handle);
+            true
+        }
+        None => false,
+    }
+}
+
+// Evicts unused resources until the cache fits into its policy.
+fn trim<H, R>(
+    payload: &mut Payload<H, R>,
+    register: &R,
+    records: &Records,
+) where
+    H: HandleLike,
+    R: Register<Handle = H>,
+{
+    loop {
+        let exceeded = match payload.policy {
+            Some(policy) => {
+                payload.cache.len() > policy.max_items || payload.cached_bytes() > policy.max_bytes
+            }
+            None => !payload.cache.is_empty(),
+        };
+
+        if !exceeded || !evict(payload, register, records) {
+            break;
+        }
+    }
+}
+
+fn reload<H, R>(
+    res: &ResourceSystemShared,
+    payload: &Arc<RwLock<Payload<H, R>>>,
+    register: &R,
+    handle: H,
+    uuid: Uuid,
+) -> Result<()>
+where
+    H: HandleLike + 'static,
+    R: Register<Handle = H> + Clone + 'static,
+{
+    if let Some(v) = payload.read().unwrap().items.get(handle) {
+        if let AsyncState::NotReady = v.state {
+            bail!("Can not reload resource {} while it's being loaded.", uuid);
+        }
+    }
+
+    let loader = RegistryReloadLoader {
+        handle: handle,
+        uuid: uuid,
+        register: register.clone(),
+        payload: payload.clone(),
+        declared: res.declared.clone(),
+    };
+
+    res.load_from_uuid(loader, uuid)?;
+    Ok(())
+}
+
+struct RegistryReloader<H: HandleLike, R: Register<Handle = H>> {
+    register: R,
+    payload: Weak<RwLock<Payload<H, R>>>,
+}
+
+impl<H, R> Reloader for RegistryReloader<H, R>
+where
+    H: HandleLike + 'static,
+    R: Register<Handle = H> + Clone + 'static,
+{
+    fn reload(&self, res: &ResourceSystemShared, uuid: Uuid) {
+        if let Some(payload) = self.payload.upgrade() {
+            let handle = payload.read().unwrap().redirects.get(&uuid).cloned();
+            if let Some(handle) = handle {
+                if let Err(err) = reload(res, &payload, &self.register, handle, uuid) {
+                    warn!("{:?}", err);
+                }
+            }
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        self.payload.upgrade().is_some()
+    }
+}
+
+struct RegistryCache<H: HandleLike, R: Register<Handle = H>> {
+    register: R,
+    payload: Weak<RwLock<Payload<H, R>>>,
+    records: Records,
+}
+
+impl<H, R> Cache for RegistryCache<H, R>
+where
+    H: HandleLike + 'static,
+    R: Register<Handle = H> + Clone + 'static,
+{
+    fn cached_bytes(&self) -> usize {
+        self.payload
+            .upgrade()
+            .map(|v| v.read().unwrap().cached_bytes())
+            .unwrap_or(0)
+    }
+
+    fn evict(&self) -> bool {
+        match self.payload.upgrade() {
+            Some(payload) => evict(&mut payload.write().unwrap(), &self.register, &self.records),
+            None => false,
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        self.payload.upgrade().is_some()
+    }
+
+    fn entries(&self) -> Vec<ResourceEntry> {
+        let payload = match self.payload.upgrade() {
+            Some(payload) => payload,
+            None => return Vec::new(),
+        };
+
+        let payload = payload.read().unwrap();
+        payload
+            .items
+            .iter()
+            .filter_map(|v| payload.items.get(v))
+            .filter_map(|v| {
+                v.uuid.map(|uuid| ResourceEntry {
+                    uuid: uuid,
+                    rc: v.rc,
+                    bytes: v.bytes,
+                    loading: match v.state {
+                        AsyncState::NotReady => true,
+                        _ => false,
+                    },
+                    failed: match v.state {
+                        AsyncState::Err => true,
+                        _ => false,
+                    },
+                })
+            }).collect()
+    }
+}
+
+struct RegistryReloadLoader<H: HandleLike, R: Register<Handle = H>> {
+    handle: H,
+    uuid: Uuid,
+    register: R,
+    payload: Arc<RwLock<Payload<H, R>>>,
+    declared: Arc<RwLock<FastHashMap<Uuid, Vec<Uuid>>>>,
+}
+
+impl<H: HandleLike + 'static, R: Register<Handle = H> + 'static> Loader
+    for RegistryReloadLoader<H, R>
+{
+    fn load(&self, bytes: &[u8]) -> Result<()> {
+        // Keeps the old value if the new file is broken, e.g. saved in the middle of edit.
+        let rsp = panic::catch_unwind(AssertUnwindSafe(|| self.register.load(self.handle, bytes)));
+        let item = rsp
+            .unwrap_or_else(|err| Err(format_err!("Panicked: {}", unwind::panic_message(&*err))))?;
+
+        declare(&self.declared, self.uuid, self.register.dependencies(&item));
+
+        let mut payload = self.payload.write().unwrap();
+        let entry = match payload.items.get_mut(self.handle) {
+            Some(entry) => entry,
+            // The resource has been deleted during reloading.
+            None => return Ok(()),
+        };
+
+        // The old value takes over again if the new one could not be attached. It's lost
+        // only if the register panics.
+        let state = ::std::mem::replace(&mut entry.state, AsyncState::Err);
+        let rsp = panic::catch_unwind(AssertUnwindSafe(|| match state {
+            AsyncState::Ok(old) => self
+                .register
+                .reattach(self.handle, old, item)
+                .map_err(|(old, err)| (Some(old), err)),
+            _ => self
+                .register
+                .attach(self.handle, item)
+                .map_err(|err| (None, err)),
+        }));
+
+        let rsp = rsp.unwrap_or_else(|err| {
+            let err = format_err!("Panicked: {}", unwind::panic_message(&*err));
+            Err((None, err))
+        });
+
+        match rsp {
+            Ok(value) => {
+                entry.bytes = self.register.memory(&value);
+                entry.state = AsyncState::Ok(value);
+                Ok(())
+            }
+            Err((old, err)) => {
+                if let Some(old) = old {
+                    entry.state = AsyncState::Ok(old);
+                }
+
+                warn!("{:?}", err);
+                Err(err)
+            }
+        }
+    }
 }
 
 struct RegistryLoader<H: HandleLike, R: Register<Handle = H>> {
     handle: H,
+    uuid: Uuid,
     register: R,
     payload: Arc<RwLock<Payload<H, R>>>,
+    records: Records,
 }
 
 impl<H: HandleLike + 'static, R: Register<Handle = H> + 'static> Loader for RegistryLoader<H, R> {
     fn load(&self, bytes: &[u8]) -> Result<()> {
-        let rsp = self.register.load(self.handle, bytes);
+        // Attaches outside of the lock, so a panicking register can't poison the payload
+        // shared with other loads. The panic is recorded as a failed load.
+        let rsp = panic::catch_unwind(AssertUnwindSafe(|| {
+            self.register.load(self.handle, bytes).and_then(|item| {
+                declare(&self.records.declared, self.uuid, self.register.dependencies(&item));
+                self.register.attach(self.handle, item)
+            })
+        }));
+
+        let rsp = rsp
+            .unwrap_or_else(|err| Err(format_err!("Panicked: {}", unwind::panic_message(&*err))));
 
-        {
-            let mut payload = self.payload.write().unwrap();
-            let disposed = payload.items.get(self.handle).unwrap().rc <= 0;
+        let mut payload = self.payload.write().unwrap();
+        let disposed = payload.items.get(self.handle).unwrap().rc <= 0;
 
-            if disposed {
-                let entry = payload.items.free(self.handle).unwrap();
+        if disposed {
+            let entry = payload.items.free(self.handle).unwrap();
 
-                if let Some(uuid) = entry.uuid {
-                    payload.redirects.remove(&uuid);
-                }
+            if let Some(uuid) = entry.uuid {
+                payload.redirects.remove(&uuid);
+                self.records.forget(uuid);
+            }
 
-                if let AsyncState::Ok(value) = entry.state {
-                    self.register.detach(self.handle, value);
-                }
-            } else {
-                match rsp {
-                    Ok(item) => match self.register.attach(self.handle, item) {
-                        Ok(value) => {
-                            payload.items.get_mut(self.handle).unwrap().state =
-                                AsyncState::Ok(value);
-                        }
-                        Err(err) => {
-                            warn!("{:?}", err);
-                            payload.items.get_mut(self.handle).unwrap().state = AsyncState::Err;
-                            return Err(err);
-                        }
-                    },
-                    Err(err) => {
-                        warn!("{:?}", err);
-                        payload.items.get_mut(self.handle).unwrap().state = AsyncState::Err;
-                        return Err(err);
-                    }
-                }
+            if let Ok(value) = rsp {
+                self.register.detach(self.handle, value);
             }
+
+            return Ok(());
         }
 
-        Ok(())
+        match rsp {
+            Ok(value) => {
+                let entry = payload.items.get_mut(self.handle).unwrap();
+                entry.bytes = self.register.memory(&value);
+                entry.state = AsyncState::Ok(value);
+                Ok(())
+            }
+            Err(err) => {
+                warn!("{:?}", err);
+                payload.items.get_mut(self.handle).unwrap().state = AsyncState::Err;
+                Err(err)
+            }
+        }
+    }
+
+    fn fail(&self, _: &::failure::Error) {
+        let mut payload = self.payload.write().unwrap();
+        let disposed = payload.items.get(self.handle).unwrap().rc <= 0;
+
+        if disposed {
+            let entry = payload.items.free(self.handle).unwrap();
+
+            if let Some(uuid) = entry.uuid {
+                payload.redirects.remove(&uuid);
+                self.records.forget(uuid);
+            }
+        } else {
+            payload.items.get_mut(self.handle).unwrap().state = AsyncState::Err;
+        }
+    }
+}
+
+// The records of resource system about the loaded resources, which are kept until the
+// resources are unloaded.
+#[derive(Clone)]
+struct Records {
+    declared: Arc<RwLock<FastHashMap<Uuid, Vec<Uuid>>>>,
+    timestamps: Arc<RwLock<FastHashMap<Uuid, SystemTime>>>,
+}
+
+impl Records {
+    fn new(res: &ResourceSystemShared) -> Self {
+        Records {
+            declared: res.declared.clone(),
+            timestamps: res.timestamps.clone(),
+        }
+    }
+
+    // Forgets the declared dependencies of resource, and stops watching its file.
+    fn forget(&self, uuid: Uuid) {
+        declare(&self.declared, uuid, Vec::new());
+        self.timestamps.write().unwrap().remove(&uuid);
     }
 }
@@ -0,0 +1,29 @@
+//! Helpers for loaders that parse untrusted bytes.
+
+use std::io::Read;
+
+use bincode;
+use serde::de::DeserializeOwned;
+
+use errors::*;
+
+/// Checks if `bytes` starts with the `magic` number.
+#[inline]
+pub fn check_magic(bytes: &[u8], magic: &[u8]) -> bool {
+    bytes.len() >= magic.len() && &bytes[0..magic.len()] == magic
+}
+
+/// Deserializes a value with bincode, reading at most `limit` bytes from `reader`.
+///
+/// Corrupted resources may claim arbitrary lengths for their sequences, which makes
+/// plain `bincode::deserialize_from` try to allocate all that memory up front. With
+/// the limit of remaining bytes, such inputs fail with errors instead.
+#[allow(deprecated)]
+pub fn deserialize_from<R, T>(reader: R, limit: u64) -> Result<T>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    let v = bincode::config().limit(limit).deserialize_from(reader)?;
+    Ok(v)
+}
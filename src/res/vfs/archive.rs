@@ -0,0 +1,120 @@
+use std::borrow::Cow;
+use std::fs;
+use std::io::{Cursor, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use zip::ZipArchive;
+
+use errors::*;
+use utils::{FastHashMap, FastHashSet};
+
+use super::VFS;
+
+trait Reader: Read + Seek + Send {}
+impl<T: Read + Seek + Send> Reader for T {}
+
+/// A read-only file system backed by a zip archive, e.g. a `data.pak` which is shipped
+/// instead of loose directories. The entries are indexed once when it's created, and
+/// could be either stored or deflated.
+///
+/// The archive could be read from memory as well with `ZipFilesystem::from_bytes`, e.g.
+/// a `data.pak` embedded with `include_bytes!`.
+pub struct ZipFilesystem {
+    archive: Mutex<ZipArchive<Box<dyn Reader>>>,
+    files: FastHashMap<PathBuf, usize>,
+    dirs: FastHashSet<PathBuf>,
+}
+
+impl ZipFilesystem {
+    pub fn new<T: Into<PathBuf>>(path: T) -> Result<Self> {
+        let path = path.into();
+        info!(
+            "Creates zip archive based virtual file system at {:?}.",
+            path
+        );
+
+        ZipFilesystem::from_reader(Box::new(fs::File::open(&path)?))
+    }
+
+    /// Creates a file system from the bytes of zip archive in memory.
+    pub fn from_bytes<T: Into<Cow<'static, [u8]>>>(bytes: T) -> Result<Self> {
+        let bytes = bytes.into();
+        info!(
+            "Creates zip archive based virtual file system from {} bytes in memory.",
+            bytes.len()
+        );
+
+        ZipFilesystem::from_reader(Box::new(Cursor::new(bytes)))
+    }
+
+    fn from_reader(reader: Box<dyn Reader>) -> Result<Self> {
+        let mut archive = ZipArchive::new(reader)?;
+        let mut files = FastHashMap::default();
+        let mut dirs = FastHashSet::default();
+
+        for i in 0..archive.len() {
+            let file = archive.by_index(i)?;
+            let location = PathBuf::from(file.name().trim_end_matches('/'));
+
+            // Directories might be implied by the names of files only.
+            for v in location.ancestors().skip(1) {
+                if v != Path::new("") {
+                    dirs.insert(v.to_owned());
+                }
+            }
+
+            if file.is_dir() {
+                dirs.insert(location);
+            } else {
+                files.insert(location, i);
+            }
+        }
+
+        Ok(ZipFilesystem {
+            archive: Mutex::new(archive),
+            files: files,
+            dirs: dirs,
+        })
+    }
+
+    /// Gets the number of files in the archive.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns true if there is no file in the archive.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+impl VFS for ZipFilesystem {
+    fn read_to_end(&self, location: &Path, buf: &mut Vec<u8>) -> Result<usize> {
+        let index = match self.files.get(location) {
+            Some(&index) => index,
+            None => bail!("{:?} does not exist in the zip archive.", location),
+        };
+
+        let mut archive = self.archive.lock().unwrap();
+        let mut file = archive.by_index(index)?;
+        let len = file.read_to_end(buf)?;
+        Ok(len)
+    }
+
+    fn is_dir(&self, location: &Path) -> bool {
+        self.dirs.contains(location)
+    }
+
+    fn exists(&self, location: &Path) -> bool {
+        self.files.contains_key(location) || self.dirs.contains(location)
+    }
+
+    /// Archives are immutable once indexed, so nothing would be reloaded from them.
+    fn modified_since(&self, _: &Path, _: SystemTime) -> bool {
+        false
+    }
+}
@@ -42,7 +42,14 @@ impl VFS for Directory {
     }
 
     fn modified_since(&self, location: &Path, ts: SystemTime) -> bool {
-        let metadata = self.root.join(location).metadata().unwrap();
-        ts == metadata.modified().unwrap()
+        self.modified(location).map(|v| v > ts).unwrap_or(false)
+    }
+
+    fn modified(&self, location: &Path) -> Option<SystemTime> {
+        self.root
+            .join(location)
+            .metadata()
+            .and_then(|v| v.modified())
+            .ok()
     }
 }
@@ -0,0 +1,196 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+use ureq;
+
+use errors::*;
+
+use super::VFS;
+
+// The timeout of connecting to server in milliseconds.
+const CONNECT_TIMEOUT: u64 = 10_000;
+// The timeout of reading from server in milliseconds, so a stalled server never blocks
+// the workers forever.
+const READ_TIMEOUT: u64 = 30_000;
+
+/// A read-only file system which fetches files from a web server or CDN over HTTP(S),
+/// e.g. for web builds and patchable desktop builds. Files are fetched on the workers
+/// of `ResourceSystem`, so blocking requests never stall the main thread.
+///
+/// With a local cache, fetched files are saved on disk and revalidated with their
+/// `ETag`s, so unchanged files are not downloaded again. The cached files are also used
+/// when the server is unreachable.
+pub struct HttpFilesystem {
+    url: String,
+    cache: Option<PathBuf>,
+    agent: ureq::Agent,
+}
+
+impl HttpFilesystem {
+    /// Creates a file system with files under `url`, e.g. `https://cdn.example.com/data`.
+    pub fn new<T: Into<String>>(url: T) -> Result<Self> {
+        let mut url = url.into();
+        info!("Creates HTTP based virtual file system at {:?}.", url);
+
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            bail!("HTTP file-system must be associated with a HTTP(S) url.");
+        }
+
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+
+        Ok(HttpFilesystem {
+            url: url,
+            cache: None,
+            agent: ureq::Agent::new(),
+        })
+    }
+
+    /// Caches the fetched files in local directory `dir`, which is created if it does
+    /// not exist.
+    pub fn with_cache<T: Into<PathBuf>>(mut self, dir: T) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        self.cache = Some(dir);
+        Ok(self)
+    }
+
+    // Rejects the locations that could escape from the cache directory, e.g. the ones
+    // with `..` in the manifest provided by server.
+    fn check(location: &Path) -> Result<()> {
+        for v in location.components() {
+            match v {
+                Component::Normal(_) | Component::CurDir => {}
+                _ => bail!("{:?} is out of the HTTP file system.", location),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn url(&self, location: &Path) -> String {
+        let components: Vec<_> = location
+            .components()
+            .filter_map(|v| match v {
+                Component::Normal(v) => v.to_str(),
+                _ => None,
+            })
+            .collect();
+
+        format!("{}{}", self.url, components.join("/"))
+    }
+
+    fn save(&self, path: &Path, bytes: &[u8], etag: Option<String>) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, bytes)?;
+        match etag {
+            Some(etag) => fs::write(etag_path(path), etag)?,
+            None => {
+                let _ = fs::remove_file(etag_path(path));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn etag_path(path: &Path) -> PathBuf {
+    let mut v = OsString::from(path);
+    v.push(".etag");
+    v.into()
+}
+
+fn read_cached(path: &Path, buf: &mut Vec<u8>) -> Result<usize> {
+    let mut file = fs::File::open(path)?;
+    let len = file.read_to_end(buf)?;
+    Ok(len)
+}
+
+impl VFS for HttpFilesystem {
+    fn read_to_end(&self, location: &Path, buf: &mut Vec<u8>) -> Result<usize> {
+        HttpFilesystem::check(location)?;
+
+        let url = self.url(location);
+        let cached = self
+            .cache
+            .as_ref()
+            .map(|v| v.join(location))
+            .filter(|v| v.is_file());
+
+        let mut request = self.agent.get(&url);
+        request.timeout_connect(CONNECT_TIMEOUT);
+        request.timeout_read(READ_TIMEOUT);
+
+        if let Some(ref path) = cached {
+            if let Ok(etag) = fs::read_to_string(etag_path(path)) {
+                request.set("If-None-Match", &etag);
+            }
+        }
+
+        let response = request.call();
+        if let Some(ref path) = cached {
+            if response.status() == 304 {
+                return read_cached(path, buf);
+            }
+
+            if response.synthetic() {
+                warn!("Failed to fetch {}, uses the cached one instead.", url);
+                return read_cached(path, buf);
+            }
+        }
+
+        if response.synthetic() || !response.ok() {
+            bail!("Failed to fetch {}: {}.", url, response.status_line());
+        }
+
+        let etag = response.header("ETag").map(|v| v.to_owned());
+        let start = buf.len();
+        let len = response.into_reader().read_to_end(buf)?;
+
+        if let Some(ref cache) = self.cache {
+            let path = cache.join(location);
+            if let Err(err) = self.save(&path, &buf[start..], etag) {
+                warn!("Failed to cache {} at {:?}: {}", url, path, err);
+            }
+        }
+
+        Ok(len)
+    }
+
+    /// There are no directories in HTTP.
+    fn is_dir(&self, _: &Path) -> bool {
+        false
+    }
+
+    fn exists(&self, location: &Path) -> bool {
+        if HttpFilesystem::check(location).is_err() {
+            return false;
+        }
+
+        let mut request = self.agent.head(&self.url(location));
+        request.timeout_connect(CONNECT_TIMEOUT);
+        request.timeout_read(READ_TIMEOUT);
+
+        let response = request.call();
+        if response.synthetic() {
+            self.cache
+                .as_ref()
+                .map(|v| v.join(location).is_file())
+                .unwrap_or(false)
+        } else {
+            response.ok()
+        }
+    }
+
+    /// Remote files are revalidated on every read instead.
+    fn modified_since(&self, _: &Path, _: SystemTime) -> bool {
+        false
+    }
+}
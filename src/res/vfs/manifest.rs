@@ -3,10 +3,10 @@
 use std::io::Read;
 use std::path::PathBuf;
 
-use bincode;
 use uuid::Uuid;
 
 use errors::*;
+use res::utils;
 use utils::{DataBuffer, DataBufferPtr, FastHashMap, HashValue};
 
 pub const NAME: &'static str = ".MANIFEST";
@@ -44,16 +44,18 @@ impl Manifest {
         }
     }
 
-    pub fn load_from(mut file: &mut dyn Read) -> Result<Manifest> {
-        let mut buf = [0; 16];
-        file.read_exact(&mut buf[0..8])?;
+    pub fn load_from(file: &mut dyn Read) -> Result<Manifest> {
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
 
         // MAGIC: [u8; 8]
-        if &buf[0..8] != &MAGIC[..] {
+        if !utils::check_magic(&bytes, &MAGIC) {
             bail!("[ManifestLoader] MAGIC number not match.");
         }
 
-        let mut manifest: Manifest = bincode::deserialize_from(&mut file)?;
+        let limit = bytes.len() as u64;
+        let mut manifest: Manifest = utils::deserialize_from(&bytes[8..], limit)?;
+        manifest.validate()?;
 
         manifest.uuids.clear();
         manifest.filenames.clear();
@@ -70,6 +72,33 @@ impl Manifest {
 }
 
 impl Manifest {
+    /// Checks that the filenames and dependencies of items point into the buffer, so
+    /// a corrupted manifest is rejected instead of panicking on access.
+    fn validate(&self) -> Result<()> {
+        for v in &self.items {
+            if !self.buf.contains(v.filename)
+                || ::std::str::from_utf8(self.buf.as_bytes(v.filename)).is_err()
+            {
+                bail!("[ManifestLoader] {} has invalid filename.", v.uuid);
+            }
+
+            let size = ::std::mem::size_of::<usize>();
+            if !self.buf.contains(v.dependencies)
+                || self.buf.as_bytes(v.dependencies).len() % size != 0
+            {
+                bail!("[ManifestLoader] {} has invalid dependencies.", v.uuid);
+            }
+
+            for chunk in self.buf.as_bytes(v.dependencies).chunks(size) {
+                if dependency(chunk) >= self.items.len() {
+                    bail!("[ManifestLoader] {} depends on undefined items.", v.uuid);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     #[inline]
     pub fn redirect<T>(&self, filename: T) -> Option<Uuid>
     where
@@ -94,19 +123,36 @@ impl Manifest {
         self.uuids.contains_key(&uuid)
     }
 
+    /// Gets the readable identifier of resource.
+    #[inline]
+    pub fn filename(&self, uuid: Uuid) -> Option<&str> {
+        self.uuids
+            .get(&uuid)
+            .map(|&index| self.buf.as_str(self.items[index].filename))
+    }
+
     #[inline]
     pub fn dependencies(&self, uuid: Uuid) -> Option<Dependencies> {
         self.uuids.get(&uuid).map(|&index| Dependencies {
-            index: 0,
-            dependencies: self.buf.as_slice(self.items[index].dependencies),
+            chunks: self
+                .buf
+                .as_bytes(self.items[index].dependencies)
+                .chunks(::std::mem::size_of::<usize>()),
             items: self.items.as_ref(),
         })
     }
 }
 
+// The dependencies are not guaranteed to be aligned in buffer.
+fn dependency(chunk: &[u8]) -> usize {
+    let mut index = [0; ::std::mem::size_of::<usize>()];
+    index.copy_from_slice(chunk);
+    usize::from_ne_bytes(index)
+}
+
+/// Iterates the uuids of the resources that a resource depends on.
 pub struct Dependencies<'a> {
-    index: usize,
-    dependencies: &'a [usize],
+    chunks: ::std::slice::Chunks<'a, u8>,
     items: &'a [ManifestItem],
 }
 
@@ -114,11 +160,7 @@ impl<'a> Iterator for Dependencies<'a> {
     type Item = Uuid;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.index < self.dependencies.len() {
-            self.index += 1;
-            Some(self.items[self.index - 1].uuid)
-        } else {
-            None
-        }
+        let items = self.items;
+        self.chunks.next().map(|v| items[dependency(v)].uuid)
     }
 }
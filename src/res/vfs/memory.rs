@@ -0,0 +1,160 @@
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use bincode;
+use uuid::Uuid;
+
+use errors::*;
+use utils::{FastHashMap, FastHashSet};
+
+use super::manifest::{self, Manifest, ManifestItem};
+use super::VFS;
+
+/// A read-only file system in memory, which is populated from byte slices, e.g. the
+/// resources that are embedded into the executable with `include_bytes!`. It's handy
+/// for tests and single-binary distributions.
+///
+/// The outputs of `crayon-cli` could be inserted as they are, including the manifest.
+/// Or resources could be added with their readable identifiers and UUIDs, which are
+/// listed in a manifest generated by this file system.
+///
+/// ```rust,ignore
+/// let mut fs = MemoryFilesystem::new();
+/// fs.add_resource("crate.png", uuid, &include_bytes!("crate.png")[..]);
+/// res.mount("mem", fs)?;
+/// ```
+#[derive(Default)]
+pub struct MemoryFilesystem {
+    files: FastHashMap<PathBuf, Cow<'static, [u8]>>,
+    dirs: FastHashSet<PathBuf>,
+    resources: Vec<(String, Uuid, Vec<Uuid>)>,
+}
+
+impl MemoryFilesystem {
+    pub fn new() -> Self {
+        MemoryFilesystem::default()
+    }
+
+    /// Inserts a file at location, replacing the previous one if any.
+    pub fn insert<P, T>(&mut self, location: P, bytes: T)
+    where
+        P: Into<PathBuf>,
+        T: Into<Cow<'static, [u8]>>,
+    {
+        let location = location.into();
+
+        // Directories are implied by the locations of files.
+        for v in location.ancestors().skip(1) {
+            if v != Path::new("") {
+                self.dirs.insert(v.to_owned());
+            }
+        }
+
+        self.files.insert(location, bytes.into());
+    }
+
+    /// Adds a resource with its readable identifier `filename` into the generated
+    /// manifest, and inserts its bytes at the location of `uuid`.
+    pub fn add_resource<F, T>(&mut self, filename: F, uuid: Uuid, bytes: T)
+    where
+        F: Into<String>,
+        T: Into<Cow<'static, [u8]>>,
+    {
+        self.add_resource_with_dependencies(filename, uuid, bytes, &[]);
+    }
+
+    /// Adds a resource which depends on the resources of `dependencies`, which should be
+    /// added into this file system too.
+    pub fn add_resource_with_dependencies<F, T>(
+        &mut self,
+        filename: F,
+        uuid: Uuid,
+        bytes: T,
+        dependencies: &[Uuid],
+    ) where
+        F: Into<String>,
+        T: Into<Cow<'static, [u8]>>,
+    {
+        self.resources.retain(|v| v.1 != uuid);
+        self.resources
+            .push((filename.into(), uuid, dependencies.to_vec()));
+
+        self.insert(format!("{:X}", uuid.to_simple()), bytes);
+    }
+
+    /// Gets the number of files.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns true if there is no file.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    // Generates the manifest of the added resources.
+    fn manifest(&self) -> Result<Vec<u8>> {
+        let indices: FastHashMap<_, _> = self
+            .resources
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (v.1, i))
+            .collect();
+
+        let mut manifest = Manifest::new();
+        for &(ref filename, uuid, ref dependencies) in &self.resources {
+            let mut deps = Vec::new();
+            for v in dependencies {
+                match indices.get(v) {
+                    Some(&index) => deps.push(index),
+                    None => bail!("{} depends on undefined resource {}.", filename, v),
+                }
+            }
+
+            let filename = manifest.buf.extend_from_str(filename.as_str());
+            let dependencies = manifest.buf.extend_from_slice(&deps);
+            manifest.items.push(ManifestItem {
+                filename: filename,
+                dependencies: dependencies,
+                uuid: uuid,
+            });
+        }
+
+        let mut bytes = manifest::MAGIC.to_vec();
+        bytes.extend(bincode::serialize(&manifest)?);
+        Ok(bytes)
+    }
+}
+
+impl VFS for MemoryFilesystem {
+    fn read_to_end(&self, location: &Path, buf: &mut Vec<u8>) -> Result<usize> {
+        if let Some(bytes) = self.files.get(location) {
+            buf.extend_from_slice(bytes);
+            return Ok(bytes.len());
+        }
+
+        if location == Path::new(manifest::NAME) {
+            let bytes = self.manifest()?;
+            buf.extend_from_slice(&bytes);
+            return Ok(bytes.len());
+        }
+
+        bail!("{:?} does not exist in the memory file system.", location);
+    }
+
+    fn is_dir(&self, location: &Path) -> bool {
+        self.dirs.contains(location)
+    }
+
+    fn exists(&self, location: &Path) -> bool {
+        self.files.contains_key(location) || self.dirs.contains(location)
+    }
+
+    /// The files could not be modified once it's mounted.
+    fn modified_since(&self, _: &Path, _: SystemTime) -> bool {
+        false
+    }
+}
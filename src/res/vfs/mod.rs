@@ -4,6 +4,20 @@ pub use self::directory::Directory;
 pub mod manifest;
 pub use self::manifest::Manifest;
 
+pub mod archive;
+pub use self::archive::ZipFilesystem;
+
+pub mod memory;
+pub use self::memory::MemoryFilesystem;
+
+pub mod user_dir;
+pub use self::user_dir::UserDirFilesystem;
+
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "http")]
+pub use self::http::HttpFilesystem;
+
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -26,6 +40,21 @@ pub trait VFS: Send + Sync + 'static {
 
     /// Returns true if the file has been modified since `ts`.
     fn modified_since(&self, location: &Path, ts: SystemTime) -> bool;
+
+    /// Gets the last modification time of the file, if it's known.
+    fn modified(&self, _: &Path) -> Option<SystemTime> {
+        None
+    }
+}
+
+/// A file system that could be written, e.g. for save files. It's mounted without a
+/// manifest, and the files are located with their actual paths.
+pub trait WritableFilesystem: VFS {
+    /// Writes the whole file at location, replacing it if it exists already.
+    fn write(&self, location: &Path, bytes: &[u8]) -> Result<()>;
+
+    /// Removes the file or directory at location.
+    fn remove(&self, location: &Path) -> Result<()>;
 }
 
 pub struct VFSInstance {
@@ -63,6 +92,16 @@ impl VFSInstance {
     pub fn contains(&self, uuid: Uuid) -> bool {
         self.manifest.contains(uuid)
     }
+
+    #[inline]
+    pub fn filename(&self, uuid: Uuid) -> Option<&str> {
+        self.manifest.filename(uuid)
+    }
+
+    #[inline]
+    pub fn dependencies(&self, uuid: Uuid) -> Option<manifest::Dependencies> {
+        self.manifest.dependencies(uuid)
+    }
 }
 
 impl VFS for VFSInstance {
@@ -85,10 +124,16 @@ impl VFS for VFSInstance {
     fn modified_since(&self, location: &Path, ts: SystemTime) -> bool {
         self.vfs.modified_since(location, ts)
     }
+
+    #[inline]
+    fn modified(&self, location: &Path) -> Option<SystemTime> {
+        self.vfs.modified(location)
+    }
 }
 
 pub struct VFSDriver {
     mounts: FastHashMap<HashValue<str>, Arc<VFSInstance>>,
+    writables: FastHashMap<HashValue<str>, Arc<dyn WritableFilesystem>>,
 }
 
 impl VFSDriver {
@@ -96,6 +141,7 @@ impl VFSDriver {
     pub fn new() -> Self {
         VFSDriver {
             mounts: FastHashMap::default(),
+            writables: FastHashMap::default(),
         }
     }
 
@@ -106,14 +152,39 @@ impl VFSDriver {
         F: VFS + 'static,
     {
         let hash = name.into();
-        if self.mounts.get(&hash).is_some() {
+        self.check_unmounted(hash)?;
+        self.mounts.insert(hash, Arc::new(VFSInstance::new(vfs)?));
+        Ok(())
+    }
+
+    /// Mount a writable file-system drive with identifier.
+    pub fn mount_writable<T, F>(&mut self, name: T, fs: F) -> Result<()>
+    where
+        T: Into<HashValue<str>>,
+        F: WritableFilesystem + 'static,
+    {
+        let hash = name.into();
+        self.check_unmounted(hash)?;
+        self.writables.insert(hash, Arc::new(fs));
+        Ok(())
+    }
+
+    /// Gets writable vfs with specified identifier `fs`.
+    pub fn writable<T>(&self, fs: T) -> Option<Arc<dyn WritableFilesystem>>
+    where
+        T: Into<HashValue<str>>,
+    {
+        self.writables.get(&fs.into()).cloned()
+    }
+
+    fn check_unmounted(&self, hash: HashValue<str>) -> Result<()> {
+        if self.mounts.contains_key(&hash) || self.writables.contains_key(&hash) {
             bail!(
                 "Virtual file system with identifier {:?} has been mounted already.",
                 hash
             );
         }
 
-        self.mounts.insert(hash, Arc::new(VFSInstance::new(vfs)?));
         Ok(())
     }
 
@@ -0,0 +1,130 @@
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+use errors::*;
+
+use super::{WritableFilesystem, VFS};
+
+/// Gets the platform specific directory of user data, which is `$XDG_DATA_HOME` or
+/// `~/.local/share` on Linux, `~/Library/Application Support` on macOS, `%APPDATA%` on
+/// Windows and `~/Documents` on iOS.
+pub fn user_dir() -> Option<PathBuf> {
+    let home = || env::var_os("HOME").map(PathBuf::from);
+
+    if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|v| v.join("Library").join("Application Support"))
+    } else if cfg!(target_os = "ios") {
+        home().map(|v| v.join("Documents"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|v| v.is_absolute())
+            .or_else(|| home().map(|v| v.join(".local").join("share")))
+    }
+}
+
+/// A writable file system in the directory of user data, which is used to persist
+/// save files, settings and screenshots.
+pub struct UserDirFilesystem {
+    root: PathBuf,
+}
+
+impl UserDirFilesystem {
+    /// Creates a file system at the subdirectory `app` of `user_dir`.
+    pub fn new<T: AsRef<Path>>(app: T) -> Result<Self> {
+        let dir = user_dir().ok_or_else(|| format_err!("Could not find the user directory."))?;
+        UserDirFilesystem::with_root(dir.join(app))
+    }
+
+    /// Creates a file system at `root`, which is created if it does not exist.
+    pub fn with_root<T: Into<PathBuf>>(root: T) -> Result<Self> {
+        let root = root.into();
+        info!("Creates writable virtual file system at {:?}.", root);
+
+        fs::create_dir_all(&root)?;
+        Ok(UserDirFilesystem { root: root })
+    }
+
+    /// Gets the actual directory of this file system.
+    #[inline]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    // Joins the location to root, which must not escape from it.
+    fn resolve(&self, location: &Path) -> Result<PathBuf> {
+        for v in location.components() {
+            match v {
+                Component::Normal(_) | Component::CurDir => {}
+                _ => bail!("{:?} is out of the writable file system.", location),
+            }
+        }
+
+        Ok(self.root.join(location))
+    }
+}
+
+impl VFS for UserDirFilesystem {
+    fn read_to_end(&self, location: &Path, buf: &mut Vec<u8>) -> Result<usize> {
+        let mut file = fs::File::open(self.resolve(location)?)?;
+        let len = file.read_to_end(buf)?;
+        Ok(len)
+    }
+
+    fn is_dir(&self, location: &Path) -> bool {
+        self.resolve(location).map(|v| v.is_dir()).unwrap_or(false)
+    }
+
+    fn exists(&self, location: &Path) -> bool {
+        self.resolve(location).map(|v| v.exists()).unwrap_or(false)
+    }
+
+    fn modified_since(&self, location: &Path, ts: SystemTime) -> bool {
+        self.modified(location).map(|v| v > ts).unwrap_or(false)
+    }
+
+    fn modified(&self, location: &Path) -> Option<SystemTime> {
+        self.resolve(location)
+            .ok()
+            .and_then(|v| v.metadata().and_then(|v| v.modified()).ok())
+    }
+}
+
+impl WritableFilesystem for UserDirFilesystem {
+    fn write(&self, location: &Path, bytes: &[u8]) -> Result<()> {
+        let path = self.resolve(location)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Writes into a temporary file first, so a crash would never leave a truncated
+        // save file behind.
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+
+        {
+            let mut file = fs::File::create(&tmp)?;
+            file.write_all(bytes)?;
+            file.sync_all()?;
+        }
+
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn remove(&self, location: &Path) -> Result<()> {
+        let path = self.resolve(location)?;
+        if path.is_dir() {
+            fs::remove_dir_all(path)?;
+        } else {
+            fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+}
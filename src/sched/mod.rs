@@ -3,7 +3,7 @@ pub mod scope;
 
 mod job;
 mod scheduler;
-mod unwind;
+pub(crate) mod unwind;
 
 use std::sync::Arc;
 
@@ -16,6 +16,17 @@ where
     panic::catch_unwind(AssertUnwindSafe(func))
 }
 
+/// Gets the message of a panic payload, which is a string in most cases.
+pub fn panic_message(payload: &(Any + Send)) -> &str {
+    if let Some(v) = payload.downcast_ref::<&str>() {
+        v
+    } else if let Some(v) = payload.downcast_ref::<String>() {
+        v
+    } else {
+        "Box<Any>"
+    }
+}
+
 pub fn resume_unwinding(payload: Box<Any + Send>) -> ! {
     panic::resume_unwind(payload)
 }
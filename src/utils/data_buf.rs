@@ -96,6 +96,15 @@ impl DataBuffer {
         str::from_utf8(self.as_bytes(ptr)).unwrap()
     }
 
+    /// Returns true if the range indicated by `DataBufferPtr` is inside the buffer.
+    #[inline]
+    pub fn contains<T>(&self, ptr: DataBufferPtr<T>) -> bool
+    where
+        T: ?Sized,
+    {
+        (ptr.position as usize + ptr.size as usize) <= self.0.len()
+    }
+
     #[inline]
     pub fn as_bytes<T>(&self, slice: DataBufferPtr<T>) -> &[u8]
     where
@@ -0,0 +1,203 @@
+//! A broadcast queue of events with independent readers.
+
+use std::collections::vec_deque::{self, VecDeque};
+
+use super::object_pool::ObjectPool;
+
+impl_handle!(ReaderId);
+
+/// An `EventChannel` broadcasts events to every registered reader. Each reader keeps
+/// its own cursor, so it receives every event written after its registration exactly
+/// once, no matter when it reads. Events received by all the readers are dropped on
+/// the next write or read.
+///
+/// ```rust
+/// use crayon::utils::EventChannel;
+///
+/// let mut channel = EventChannel::new();
+/// let reader = channel.register_reader();
+///
+/// channel.single_write("collided");
+/// channel.single_write("spawned");
+///
+/// let events: Vec<_> = channel.read(reader).cloned().collect();
+/// assert_eq!(events, vec!["collided", "spawned"]);
+/// assert_eq!(channel.read(reader).count(), 0);
+/// ```
+pub struct EventChannel<T> {
+    events: VecDeque<T>,
+    head: usize,
+    readers: ObjectPool<ReaderId, usize>,
+}
+
+impl<T> Default for EventChannel<T> {
+    fn default() -> Self {
+        EventChannel::new()
+    }
+}
+
+impl<T> EventChannel<T> {
+    /// Creates a new and empty `EventChannel`.
+    pub fn new() -> Self {
+        EventChannel {
+            events: VecDeque::new(),
+            head: 0,
+            readers: ObjectPool::new(),
+        }
+    }
+
+    /// Registers a reader which receives the events written from now on.
+    pub fn register_reader(&mut self) -> ReaderId {
+        let tail = self.head + self.events.len();
+        self.readers.create(tail)
+    }
+
+    /// Removes a reader, the events that only it has not read yet are dropped.
+    pub fn unregister_reader(&mut self, reader: ReaderId) {
+        if self.readers.free(reader).is_some() {
+            self.trim();
+        }
+    }
+
+    /// Writes an event. It's dropped at once if there are no readers.
+    pub fn single_write(&mut self, event: T) {
+        self.trim();
+        if !self.readers.is_empty() {
+            self.events.push_back(event);
+        }
+    }
+
+    /// Writes events from an iterator.
+    pub fn iter_write<I>(&mut self, events: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.trim();
+        if !self.readers.is_empty() {
+            self.events.extend(events);
+        }
+    }
+
+    /// Reads the events which are not received by `reader` yet. Nothing is returned
+    /// if `reader` is not registered.
+    pub fn read<'a>(&'a mut self, reader: ReaderId) -> EventIter<'a, T> {
+        let tail = self.head + self.events.len();
+        let from = match self.readers.get_mut(reader) {
+            Some(cursor) => ::std::mem::replace(cursor, tail),
+            None => tail,
+        };
+
+        // Events before every cursor have been received by all the readers.
+        let min = self
+            .readers
+            .iter()
+            .filter_map(|v| self.readers.get(v))
+            .cloned()
+            .min()
+            .unwrap_or(tail);
+
+        let received = from.min(min) - self.head;
+        let skip = from - self.head - received;
+
+        self.events.drain(..received);
+        self.head += received;
+
+        EventIter {
+            iter: self.events.range(skip..),
+        }
+    }
+
+    /// Gets the number of events buffered in channel.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns true if there are no events buffered in channel.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    fn trim(&mut self) {
+        let tail = self.head + self.events.len();
+        let min = self
+            .readers
+            .iter()
+            .filter_map(|v| self.readers.get(v))
+            .cloned()
+            .min()
+            .unwrap_or(tail);
+
+        self.events.drain(..(min - self.head));
+        self.head = min;
+    }
+}
+
+/// Iterates the events received by a reader.
+pub struct EventIter<'a, T: 'a> {
+    iter: vec_deque::Iter<'a, T>,
+}
+
+impl<'a, T: 'a> Iterator for EventIter<'a, T> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basic() {
+        let mut channel = EventChannel::new();
+        channel.single_write(0);
+        assert!(channel.is_empty());
+
+        let r1 = channel.register_reader();
+        channel.iter_write(vec![1, 2]);
+
+        let r2 = channel.register_reader();
+        channel.single_write(3);
+
+        assert_eq!(channel.read(r1).cloned().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        // Only the event which has not been received by r2 is kept.
+        channel.single_write(4);
+        assert_eq!(channel.len(), 2);
+
+        assert_eq!(channel.read(r2).cloned().collect::<Vec<_>>(), vec![3, 4]);
+        assert_eq!(channel.read(r2).count(), 0);
+        assert_eq!(channel.read(r1).cloned().collect::<Vec<_>>(), vec![4]);
+
+        channel.iter_write(None);
+        assert!(channel.is_empty());
+    }
+
+    #[test]
+    fn unregister() {
+        let mut channel = EventChannel::new();
+        let r1 = channel.register_reader();
+        let r2 = channel.register_reader();
+
+        channel.iter_write(vec![1, 2, 3]);
+        assert_eq!(channel.read(r1).count(), 3);
+        assert_eq!(channel.len(), 3);
+
+        channel.unregister_reader(r2);
+        assert!(channel.is_empty());
+        assert_eq!(channel.read(r2).count(), 0);
+
+        channel.single_write(4);
+        assert_eq!(channel.read(r1).cloned().collect::<Vec<_>>(), vec![4]);
+    }
+}
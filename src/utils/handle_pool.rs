@@ -1,5 +1,6 @@
 use std::cmp::Ordering;
 use std::collections::binary_heap::BinaryHeap;
+use std::collections::VecDeque;
 use std::marker::PhantomData;
 
 use super::handle::{HandleIndex, HandleLike};
@@ -19,6 +20,29 @@ impl Ord for InverseHandleIndex {
     }
 }
 
+/// The policy of recycling the indices of freed handles.
+///
+/// A stale handle aliases a living one if its index is reused until the version wraps
+/// around. Long-running applications could trade memory for a lower chance of that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HandlePoolConfig {
+    /// The number of freed indices that are kept before they could be reused, so a
+    /// freed index is not reused until `min_frees` other handles are freed after it.
+    pub min_frees: usize,
+    /// The number of bits of versions, ranges from 2 to 32. An index is retired
+    /// forever instead of wrapping its version around.
+    pub version_bits: u32,
+}
+
+impl Default for HandlePoolConfig {
+    fn default() -> Self {
+        HandlePoolConfig {
+            min_frees: 0,
+            version_bits: 32,
+        }
+    }
+}
+
 /// `HandlePool` manages the manipulations of a `Handle` collection, which are
 /// created with a continuous `index` field. It also have the ability to find
 /// out the current status of a specified `Handle`.
@@ -26,34 +50,49 @@ impl Ord for InverseHandleIndex {
 pub struct HandlePool<T: HandleLike> {
     versions: Vec<HandleIndex>,
     frees: BinaryHeap<InverseHandleIndex>,
+    pendings: VecDeque<HandleIndex>,
+    retired: usize,
+    config: HandlePoolConfig,
     _marker: PhantomData<T>,
 }
 
 impl<T: HandleLike> HandlePool<T> {
     /// Constructs a new, empty `HandlePool`.
     pub fn new() -> HandlePool<T> {
-        HandlePool {
-            versions: Vec::new(),
-            frees: BinaryHeap::new(),
-            _marker: PhantomData::default(),
-        }
+        HandlePool::with_config(HandlePoolConfig::default())
     }
 
     /// Constructs a new `HandlePool` with the specified capacity.
     pub fn with_capacity(capacity: usize) -> HandlePool<T> {
-        let versions = Vec::with_capacity(capacity);
-        let mut frees = BinaryHeap::with_capacity(capacity);
-        for i in 0..versions.len() {
-            frees.push(InverseHandleIndex(i as HandleIndex));
-        }
+        let mut pool = HandlePool::new();
+        pool.versions.reserve(capacity);
+        pool.frees.reserve(capacity);
+        pool
+    }
+
+    /// Constructs a new, empty `HandlePool` with the policy of recycling indices.
+    pub fn with_config(config: HandlePoolConfig) -> HandlePool<T> {
+        assert!(
+            config.version_bits >= 2 && config.version_bits <= 32,
+            "The number of version bits must be in [2, 32]."
+        );
 
         HandlePool {
-            versions: versions,
-            frees: frees,
+            versions: Vec::new(),
+            frees: BinaryHeap::new(),
+            pendings: VecDeque::new(),
+            retired: 0,
+            config: config,
             _marker: PhantomData::default(),
         }
     }
 
+    /// Gets the policy of recycling indices.
+    #[inline]
+    pub fn config(&self) -> HandlePoolConfig {
+        self.config
+    }
+
     /// Creates a unused `Handle`.
     pub fn create(&mut self) -> T {
         if !self.frees.is_empty() {
@@ -68,6 +107,15 @@ impl<T: HandleLike> HandlePool<T> {
         }
     }
 
+    /// Reserves capacity for at least `additional` more handles to be created
+    /// without reallocation.
+    pub fn reserve(&mut self, additional: usize) {
+        let frees = self.frees.len();
+        if additional > frees {
+            self.versions.reserve(additional - frees);
+        }
+    }
+
     /// Returns true if this `Handle` was created by `HandlePool`, and has not been
     /// freed yet.
     pub fn is_alive(&self, handle: T) -> bool {
@@ -85,8 +133,7 @@ impl<T: HandleLike> HandlePool<T> {
         if !self.is_alive(handle) {
             false
         } else {
-            self.versions[handle.index() as usize] += 1;
-            self.frees.push(InverseHandleIndex(handle.index()));
+            self.recycle(handle.index() as usize);
             true
         }
     }
@@ -96,9 +143,27 @@ impl<T: HandleLike> HandlePool<T> {
         if !self.is_alive_at(index) {
             None
         } else {
-            self.versions[index] += 1;
-            self.frees.push(InverseHandleIndex(index as HandleIndex));
-            Some(T::new(index as HandleIndex, self.versions[index] - 1))
+            let version = self.versions[index];
+            self.recycle(index);
+            Some(T::new(index as HandleIndex, version))
+        }
+    }
+
+    fn recycle(&mut self, index: usize) {
+        let version = self.versions[index];
+        self.versions[index] = version.wrapping_add(1);
+
+        // Retires the index once its version reaches the maximum of version bits.
+        let max = ::std::u32::MAX >> (32 - self.config.version_bits);
+        if version == max {
+            self.retired += 1;
+            return;
+        }
+
+        self.pendings.push_back(index as HandleIndex);
+        while self.pendings.len() > self.config.min_frees {
+            let index = self.pendings.pop_front().unwrap();
+            self.frees.push(InverseHandleIndex(index));
         }
     }
 
@@ -106,13 +171,28 @@ impl<T: HandleLike> HandlePool<T> {
     /// for reuse.
     pub fn clear(&mut self) {
         self.frees.clear();
+        self.pendings.clear();
+        self.retired = 0;
         self.versions.clear();
     }
 
     /// Returns the total number of alive handle in this `HandlePool`.
     #[inline]
     pub fn len(&self) -> usize {
-        self.versions.len() - self.frees.len()
+        self.versions.len() - self.frees.len() - self.pendings.len() - self.retired
+    }
+
+    /// Returns the number of indices that have been allocated, including the freed
+    /// ones.
+    #[inline]
+    pub fn indices(&self) -> usize {
+        self.versions.len()
+    }
+
+    /// Gets the approximate memory of this pool in bytes.
+    pub fn memory(&self) -> usize {
+        let size = ::std::mem::size_of::<HandleIndex>();
+        (self.versions.capacity() + self.frees.capacity() + self.pendings.capacity()) * size
     }
 
     /// Checks if the pool is empty.
@@ -3,6 +3,7 @@
 #[macro_use]
 pub mod handle;
 pub mod data_buf;
+pub mod event_channel;
 pub mod handle_pool;
 pub mod hash;
 pub mod hash_value;
@@ -11,8 +12,9 @@ pub mod variant_str;
 pub mod variant_vec;
 
 pub use self::data_buf::{DataBuffer, DataBufferPtr};
+pub use self::event_channel::{EventChannel, ReaderId};
 pub use self::handle::{Handle, HandleIndex, HandleLike};
-pub use self::handle_pool::HandlePool;
+pub use self::handle_pool::{HandlePool, HandlePoolConfig};
 pub use self::hash::{FastHashMap, FastHashSet};
 pub use self::hash_value::HashValue;
 pub use self::object_pool::ObjectPool;
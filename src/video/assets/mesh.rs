@@ -427,8 +427,10 @@ pub mod macros {
     macro_rules! offset_of {
         ($ty:ty, $field:ident) => {{
             use std;
-            let ptr: *const $ty = std::ptr::null();
-            unsafe { &(*ptr).$field as *const _ as usize }
+            // Takes the address of field without dereferencing a null pointer.
+            let v = std::mem::MaybeUninit::<$ty>::uninit();
+            let ptr = v.as_ptr();
+            unsafe { std::ptr::addr_of!((*ptr).$field) as usize - ptr as usize }
         }};
     }
 
@@ -1,8 +1,8 @@
-use bincode;
 use std::io::Cursor;
 use std::sync::Arc;
 
 use errors::*;
+use res::utils;
 
 use super::super::backends::frame::Command;
 use super::super::DoubleFrame;
@@ -21,6 +21,15 @@ impl MeshLoader {
     pub(crate) fn new(frames: Arc<DoubleFrame>) -> Self {
         MeshLoader { frames: frames }
     }
+
+    // Creates the object under `handle`, the parameters must have been validated.
+    fn create(&self, handle: MeshHandle, item: (MeshParams, Option<MeshData>)) -> MeshParams {
+        let mut frame = self.frames.front();
+        let task = Command::CreateMesh(handle, item.0.clone(), item.1);
+        frame.cmds.push(task);
+
+        item.0
+    }
 }
 
 impl ::res::registry::Register for MeshLoader {
@@ -29,13 +38,14 @@ impl ::res::registry::Register for MeshLoader {
     type Value = MeshParams;
 
     fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
-        if &bytes[0..8] != &MAGIC[..] {
+        if !utils::check_magic(bytes, &MAGIC) {
             bail!("[MeshLoader] MAGIC number not match.");
         }
 
+        let limit = bytes.len() as u64;
         let mut file = Cursor::new(&bytes[8..]);
-        let params: MeshParams = bincode::deserialize_from(&mut file)?;
-        let data = bincode::deserialize_from(&mut file)?;
+        let params: MeshParams = utils::deserialize_from(&mut file, limit)?;
+        let data = utils::deserialize_from(&mut file, limit)?;
 
         info!(
             "[MeshLoader] loads {:?}. (Verts: {}, Indxes: {})",
@@ -47,16 +57,31 @@ impl ::res::registry::Register for MeshLoader {
 
     fn attach(&self, handle: Self::Handle, item: Self::Intermediate) -> Result<Self::Value> {
         item.0.validate(item.1.as_ref())?;
+        Ok(self.create(handle, item))
+    }
 
-        let mut frame = self.frames.front();
-        let task = Command::CreateMesh(handle, item.0.clone(), item.1);
-        frame.cmds.push(task);
+    /// The new mesh is created under the same handle, so the old one is deleted first,
+    /// once the new one is known to be valid.
+    fn reattach(
+        &self,
+        handle: Self::Handle,
+        old: Self::Value,
+        item: Self::Intermediate,
+    ) -> ::std::result::Result<Self::Value, (Self::Value, ::failure::Error)> {
+        if let Err(err) = item.0.validate(item.1.as_ref()) {
+            return Err((old, err.into()));
+        }
 
-        Ok(item.0)
+        self.detach(handle, old);
+        Ok(self.create(handle, item))
     }
 
     fn detach(&self, handle: Self::Handle, _: Self::Value) {
         let cmd = Command::DeleteMesh(handle);
         self.frames.front().cmds.push(cmd);
     }
+
+    fn memory(&self, value: &Self::Value) -> usize {
+        value.vertex_buffer_len() + value.index_buffer_len()
+    }
 }
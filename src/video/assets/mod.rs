@@ -1,6 +1,8 @@
 pub mod shader;
 pub mod surface;
 pub mod texture;
+pub mod texture_container;
+pub mod texture_hdr;
 pub mod texture_loader;
 #[macro_use]
 pub mod mesh;
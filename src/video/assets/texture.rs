@@ -57,7 +57,7 @@ pub struct TextureData {
 /// be used as a render target. If the `sampler` field is true, it can also be ther
 /// source of a texture access from a __shader__.
 ///
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct RenderTextureParams {
     pub format: RenderTextureFormat,
     pub wrap: TextureWrap,
@@ -0,0 +1,398 @@
+//! Decoders of DDS and KTX2 texture containers.
+//!
+//! The payloads are passed to the video device as they are, including compressed
+//! blocks and the full mipmap chains. Since the texture API only supports single
+//! 2D textures for now, containers with array layers, cubemap faces or depth
+//! slices are rejected.
+
+use errors::*;
+
+use super::texture::*;
+use math;
+
+const DDS_MAGIC: [u8; 4] = [b'D', b'D', b'S', b' '];
+const KTX2_MAGIC: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+/// Returns true if `bytes` starts like a DDS file.
+pub fn is_dds(bytes: &[u8]) -> bool {
+    bytes.starts_with(&DDS_MAGIC)
+}
+
+/// Returns true if `bytes` starts like a KTX2 file.
+pub fn is_ktx2(bytes: &[u8]) -> bool {
+    bytes.starts_with(&KTX2_MAGIC)
+}
+
+/// Decodes a DDS file with all of its mipmaps.
+///
+/// DXT1, DXT5 and 32-bit RGBA/BGRA pixels are supported, as well as the BC1, BC3,
+/// RGBA8, RGBA16F and RGBA32F formats of the DX10 extended header. BGRA pixels are
+/// swizzled into RGBA.
+pub fn decode_dds(bytes: &[u8]) -> Result<(TextureParams, TextureData)> {
+    if !is_dds(bytes) {
+        bail!("[DDS] MAGIC number not match.");
+    }
+
+    if bytes.len() < 128 || u32_at(bytes, 4)? != 124 {
+        bail!("[DDS] Invalid header.");
+    }
+
+    let flags = u32_at(bytes, 8)?;
+    let height = u32_at(bytes, 12)?;
+    let width = u32_at(bytes, 16)?;
+    let levels = if flags & 0x2_0000 != 0 {
+        u32_at(bytes, 28)?.max(1)
+    } else {
+        1
+    };
+
+    let pf_flags = u32_at(bytes, 80)?;
+    let four_cc = &bytes[84..88];
+    let caps2 = u32_at(bytes, 112)?;
+
+    if caps2 & 0x200 != 0 {
+        bail!("[DDS] Cubemaps are not supported.");
+    }
+
+    if caps2 & 0x20_0000 != 0 {
+        bail!("[DDS] Volume textures are not supported.");
+    }
+
+    let mut offset = 128;
+    let mut swizzle = false;
+
+    let format = if pf_flags & 0x4 != 0 {
+        match four_cc {
+            b"DXT1" => TextureFormat::S3tcDxt1RGB4BPP,
+            b"DXT5" => TextureFormat::S3tcDxt5RGBA8BPP,
+            b"DX10" => {
+                let format = match u32_at(bytes, 128)? {
+                    2 => TextureFormat::RGBA32F,
+                    10 => TextureFormat::RGBA16F,
+                    28 | 29 => TextureFormat::RGBA8,
+                    71 | 72 => TextureFormat::S3tcDxt1RGB4BPP,
+                    77 | 78 => TextureFormat::S3tcDxt5RGBA8BPP,
+                    v => bail!("[DDS] Unsupported DXGI format {}.", v),
+                };
+
+                if u32_at(bytes, 128 + 8)? & 0x4 != 0 {
+                    bail!("[DDS] Cubemaps are not supported.");
+                }
+
+                if u32_at(bytes, 128 + 12)? > 1 {
+                    bail!("[DDS] Texture arrays are not supported.");
+                }
+
+                offset += 20;
+                format
+            }
+            v => bail!("[DDS] Unsupported FourCC {:?}.", String::from_utf8_lossy(v)),
+        }
+    } else {
+        let masks = (
+            u32_at(bytes, 88)?,
+            u32_at(bytes, 92)?,
+            u32_at(bytes, 96)?,
+            u32_at(bytes, 100)?,
+        );
+
+        match masks {
+            (32, 0xFF, 0xFF00, 0xFF_0000) => TextureFormat::RGBA8,
+            (32, 0xFF_0000, 0xFF00, 0xFF) => {
+                swizzle = true;
+                TextureFormat::RGBA8
+            }
+            _ => bail!("[DDS] Unsupported pixel format {:?}.", masks),
+        }
+    };
+
+    check_levels(width, height, levels)?;
+    let mut levels = read_levels(bytes, offset, format, width, height, levels)?;
+    if swizzle {
+        for level in &mut levels {
+            for v in level.chunks_mut(4) {
+                v.swap(0, 2);
+            }
+        }
+    }
+
+    Ok(texture(format, width, height, levels))
+}
+
+/// Decodes a KTX2 file with all of its mipmaps.
+///
+/// Supercompressed and Basis Universal payloads are rejected, since they need to
+/// be transcoded.
+pub fn decode_ktx2(bytes: &[u8]) -> Result<(TextureParams, TextureData)> {
+    if !is_ktx2(bytes) {
+        bail!("[KTX2] MAGIC number not match.");
+    }
+
+    let vk_format = u32_at(bytes, 12)?;
+    let width = u32_at(bytes, 20)?;
+    let height = u32_at(bytes, 24)?.max(1);
+    let depth = u32_at(bytes, 28)?;
+    let layers = u32_at(bytes, 32)?;
+    let faces = u32_at(bytes, 36)?;
+    let levels = u32_at(bytes, 40)?.max(1);
+    let supercompression = u32_at(bytes, 44)?;
+
+    if depth > 1 || layers > 1 || faces > 1 {
+        bail!("[KTX2] Only single 2D textures are supported.");
+    }
+
+    if supercompression != 0 {
+        bail!("[KTX2] Supercompression is not supported.");
+    }
+
+    let format = match vk_format {
+        37 | 43 => TextureFormat::RGBA8,
+        23 | 29 => TextureFormat::RGB8,
+        97 => TextureFormat::RGBA16F,
+        106 => TextureFormat::RGB32F,
+        109 => TextureFormat::RGBA32F,
+        131 | 132 => TextureFormat::S3tcDxt1RGB4BPP,
+        137 | 138 => TextureFormat::S3tcDxt5RGBA8BPP,
+        147 | 148 => TextureFormat::Etc2RGB4BPP,
+        151 | 152 => TextureFormat::Etc2RGBA8BPP,
+        1_000_054_000 | 1_000_054_004 => TextureFormat::PvrtcRGBA2BPP,
+        1_000_054_001 | 1_000_054_005 => TextureFormat::PvrtcRGBA4BPP,
+        0 => bail!("[KTX2] Transcoding of universal formats is not supported."),
+        v => bail!("[KTX2] Unsupported VkFormat {}.", v),
+    };
+
+    check_levels(width, height, levels)?;
+
+    // The level index follows the 80 bytes header, from the largest level.
+    let mut data = Vec::with_capacity(levels as usize);
+    let (mut w, mut h) = (width, height);
+    for i in 0..levels as usize {
+        let offset = u64_at(bytes, 80 + i * 24)?;
+        let len = u64_at(bytes, 80 + i * 24 + 8)?;
+
+        let end = offset.checked_add(len);
+        if level_size(format, w, h).map(|v| v as u64) != Some(len)
+            || end.map(|v| v > bytes.len() as u64).unwrap_or(true)
+        {
+            bail!("[KTX2] Level {} is corrupted.", i);
+        }
+
+        let (offset, len) = (offset as usize, len as usize);
+        data.push(bytes[offset..(offset + len)].to_vec().into_boxed_slice());
+        w = (w / 2).max(1);
+        h = (h / 2).max(1);
+    }
+
+    Ok(texture(format, width, height, data))
+}
+
+/// Checks the number of mipmap levels, which could not be more than a full chain.
+fn check_levels(width: u32, height: u32, levels: u32) -> Result<()> {
+    let max = 32 - width.max(height).max(1).leading_zeros();
+    if levels > max {
+        bail!("{} mipmap levels is too many for {}x{}.", levels, width, height);
+    }
+
+    Ok(())
+}
+
+fn read_levels(
+    bytes: &[u8],
+    mut offset: usize,
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    levels: u32,
+) -> Result<Vec<Box<[u8]>>> {
+    let mut data = Vec::with_capacity(levels as usize);
+    let (mut w, mut h) = (width, height);
+
+    for _ in 0..levels {
+        let len = level_size(format, w, h);
+        let len = match len.and_then(|v| v.checked_add(offset)) {
+            Some(end) if end <= bytes.len() => end - offset,
+            _ => bail!("[DDS] Unexpected end of mipmaps."),
+        };
+
+        data.push(bytes[offset..(offset + len)].to_vec().into_boxed_slice());
+        offset += len;
+        w = (w / 2).max(1);
+        h = (h / 2).max(1);
+    }
+
+    Ok(data)
+}
+
+/// Gets the size in bytes of a mipmap level, compressed formats are always padded
+/// to whole blocks. Returns `None` if the size overflows.
+fn level_size(format: TextureFormat, w: u32, h: u32) -> Option<usize> {
+    let (w, h) = (w as usize, h as usize);
+    let blocks = |bw: usize, bh: usize| {
+        let (x, y) = ((w + bw - 1) / bw, (h + bh - 1) / bh);
+        x.checked_mul(y)
+    };
+
+    match format {
+        TextureFormat::S3tcDxt1RGB4BPP | TextureFormat::Etc2RGB4BPP => {
+            blocks(4, 4).and_then(|v| v.checked_mul(8))
+        }
+        TextureFormat::S3tcDxt5RGBA8BPP | TextureFormat::Etc2RGBA8BPP => {
+            blocks(4, 4).and_then(|v| v.checked_mul(16))
+        }
+        TextureFormat::PvrtcRGB4BPP | TextureFormat::PvrtcRGBA4BPP => {
+            w.max(8).checked_mul(h.max(8)).map(|v| v / 2)
+        }
+        TextureFormat::PvrtcRGB2BPP | TextureFormat::PvrtcRGBA2BPP => {
+            w.max(16).checked_mul(h.max(8)).map(|v| v / 4)
+        }
+        _ => {
+            let pixel = format.size(math::Vector2::new(1, 1)) as usize;
+            w.checked_mul(h).and_then(|v| v.checked_mul(pixel))
+        }
+    }
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Result<u32> {
+    if offset + 4 > bytes.len() {
+        bail!("Unexpected end of texture header.");
+    }
+
+    let v = &bytes[offset..(offset + 4)];
+    Ok(v[0] as u32 | (v[1] as u32) << 8 | (v[2] as u32) << 16 | (v[3] as u32) << 24)
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> Result<u64> {
+    Ok(u32_at(bytes, offset)? as u64 | (u32_at(bytes, offset + 4)? as u64) << 32)
+}
+
+fn texture(
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    levels: Vec<Box<[u8]>>,
+) -> (TextureParams, TextureData) {
+    let mut params = TextureParams::default();
+    params.format = format;
+    params.dimensions = math::Vector2::new(width, height);
+
+    (params, TextureData { bytes: levels })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn le(bytes: &mut Vec<u8>, offset: usize, v: u32) {
+        if bytes.len() < offset + 4 {
+            bytes.resize(offset + 4, 0);
+        }
+
+        for i in 0..4 {
+            bytes[offset + i] = (v >> (i * 8)) as u8;
+        }
+    }
+
+    fn dds(four_cc: &[u8; 4], width: u32, height: u32, levels: u32) -> Vec<u8> {
+        let mut bytes = DDS_MAGIC.to_vec();
+        le(&mut bytes, 4, 124);
+        le(&mut bytes, 8, 0x2_1007);
+        le(&mut bytes, 12, height);
+        le(&mut bytes, 16, width);
+        le(&mut bytes, 28, levels);
+        le(&mut bytes, 76, 32);
+        le(&mut bytes, 80, 0x4);
+        bytes.resize(128, 0);
+        bytes[84..88].copy_from_slice(four_cc);
+        bytes
+    }
+
+    #[test]
+    fn dxt() {
+        let mut bytes = dds(b"DXT5", 8, 4, 3);
+        // 8x4, 4x2 and 2x1 levels, which are padded to 2, 1 and 1 blocks.
+        bytes.extend_from_slice(&[1; 32]);
+        bytes.extend_from_slice(&[2; 16]);
+        bytes.extend_from_slice(&[3; 16]);
+
+        assert!(is_dds(&bytes));
+        let (params, data) = decode_dds(&bytes).unwrap();
+        assert_eq!(params.format, TextureFormat::S3tcDxt5RGBA8BPP);
+        assert_eq!(params.dimensions, math::Vector2::new(8, 4));
+        assert_eq!(data.bytes.len(), 3);
+        assert_eq!(&data.bytes[0][..], &[1; 32][..]);
+        assert_eq!(&data.bytes[2][..], &[3; 16][..]);
+
+        bytes.pop();
+        assert!(decode_dds(&bytes).is_err());
+
+        let mut bytes = dds(b"DXT1", 4, 4, 1);
+        le(&mut bytes, 112, 0x200);
+        bytes.extend_from_slice(&[0; 8 * 6]);
+        assert!(decode_dds(&bytes).is_err());
+    }
+
+    #[test]
+    fn bgra() {
+        let mut bytes = dds(b"\0\0\0\0", 1, 1, 1);
+        le(&mut bytes, 80, 0x41);
+        le(&mut bytes, 88, 32);
+        le(&mut bytes, 92, 0xFF_0000);
+        le(&mut bytes, 96, 0xFF00);
+        le(&mut bytes, 100, 0xFF);
+        bytes.extend_from_slice(&[1, 2, 3, 4]);
+
+        let (params, data) = decode_dds(&bytes).unwrap();
+        assert_eq!(params.format, TextureFormat::RGBA8);
+        assert_eq!(&data.bytes[0][..], &[3, 2, 1, 4]);
+    }
+
+    #[test]
+    fn dx10() {
+        let mut bytes = dds(b"DX10", 4, 4, 1);
+        le(&mut bytes, 128, 2);
+        le(&mut bytes, 140, 1);
+        bytes.resize(148, 0);
+        bytes.extend_from_slice(&[7; 4 * 4 * 16]);
+
+        let (params, data) = decode_dds(&bytes).unwrap();
+        assert_eq!(params.format, TextureFormat::RGBA32F);
+        assert_eq!(data.bytes[0].len(), 256);
+
+        le(&mut bytes, 140, 6);
+        assert!(decode_dds(&bytes).is_err());
+    }
+
+    #[test]
+    fn ktx2() {
+        let mut bytes = KTX2_MAGIC.to_vec();
+        le(&mut bytes, 12, 131);
+        le(&mut bytes, 20, 8);
+        le(&mut bytes, 24, 8);
+        le(&mut bytes, 40, 2);
+
+        // Level index of 8x8 and 4x4 levels, with the smallest stored first.
+        le(&mut bytes, 80, 128 + 8);
+        le(&mut bytes, 88, 32);
+        le(&mut bytes, 104, 128);
+        le(&mut bytes, 112, 8);
+        bytes.resize(128, 0);
+        bytes.extend_from_slice(&[2; 8]);
+        bytes.extend_from_slice(&[1; 32]);
+
+        assert!(is_ktx2(&bytes));
+        let (params, data) = decode_ktx2(&bytes).unwrap();
+        assert_eq!(params.format, TextureFormat::S3tcDxt1RGB4BPP);
+        assert_eq!(params.dimensions, math::Vector2::new(8, 8));
+        assert_eq!(data.bytes.len(), 2);
+        assert_eq!(&data.bytes[0][..], &[1; 32][..]);
+        assert_eq!(&data.bytes[1][..], &[2; 8][..]);
+
+        le(&mut bytes, 44, 1);
+        assert!(decode_ktx2(&bytes).is_err());
+        le(&mut bytes, 44, 0);
+        le(&mut bytes, 36, 6);
+        assert!(decode_ktx2(&bytes).is_err());
+    }
+}
@@ -0,0 +1,613 @@
+//! Decoders of high dynamic range images, which produce float textures that could
+//! be used by environment maps, lightmaps and LUTs.
+//!
+//! Radiance `.hdr` files are decoded into `RGB32F`, with both flat and run-length
+//! encoded scanlines. OpenEXR files are decoded into `RGB32F` or `RGBA32F`, only
+//! single-part scanline images without compression are supported for now.
+//!
+//! Rows are always stored from top to bottom.
+
+use errors::*;
+
+use super::texture::*;
+use math;
+
+const RADIANCE_MAGICS: [&'static [u8]; 2] = [b"#?RADIANCE", b"#?RGBE"];
+const EXR_MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+
+// The run-length encoding stores at most 127 pixels of a channel in 2 bytes, so the
+// pixels of a file could hardly outnumber its bytes by this ratio.
+const RADIANCE_MAX_PIXELS_PER_BYTE: usize = 128;
+
+/// Returns true if `bytes` starts like a Radiance HDR file.
+pub fn is_radiance(bytes: &[u8]) -> bool {
+    RADIANCE_MAGICS.iter().any(|v| bytes.starts_with(v))
+}
+
+/// Returns true if `bytes` starts like an OpenEXR file.
+pub fn is_exr(bytes: &[u8]) -> bool {
+    bytes.starts_with(&EXR_MAGIC)
+}
+
+/// Decodes a Radiance HDR file into a `RGB32F` texture.
+pub fn decode_radiance(bytes: &[u8]) -> Result<(TextureParams, TextureData)> {
+    if !is_radiance(bytes) {
+        bail!("[HDR] MAGIC number not match.");
+    }
+
+    let mut cursor = 0;
+    loop {
+        let line = next_line(bytes, &mut cursor)?;
+        if line.is_empty() {
+            break;
+        }
+
+        if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+            bail!("[HDR] Unsupported pixel format {}.", &line[7..]);
+        }
+    }
+
+    let resolution = next_line(bytes, &mut cursor)?;
+    let tokens: Vec<_> = resolution.split_whitespace().collect();
+    let (flip, height, width) = match tokens.as_slice() {
+        &["-Y", h, "+X", w] => (false, h.parse::<u32>()?, w.parse::<u32>()?),
+        &["+Y", h, "+X", w] => (true, h.parse::<u32>()?, w.parse::<u32>()?),
+        _ => bail!("[HDR] Unsupported resolution {}.", resolution),
+    };
+
+    // Every scanline has 4 bytes at least, and the dimensions are limited by the size
+    // of file, so a corrupted header could not allocate huge buffers.
+    let remaining = bytes.len() - cursor;
+    let pixels = (width as usize)
+        .checked_mul(height as usize)
+        .filter(|&v| v <= remaining.saturating_mul(RADIANCE_MAX_PIXELS_PER_BYTE))
+        .filter(|_| height as usize <= remaining / 4)
+        .ok_or_else(|| format_err!("[HDR] Resolution {} exceeds the file size.", resolution))?;
+
+    let mut rgbe = vec![0u8; pixels * 4];
+    for y in 0..height as usize {
+        let row = if flip { height as usize - y - 1 } else { y };
+        let scanline = &mut rgbe[(row * width as usize * 4)..((row + 1) * width as usize * 4)];
+        read_scanline(bytes, &mut cursor, scanline)?;
+    }
+
+    let mut data = Vec::with_capacity(rgbe.len() * 3);
+    for v in rgbe.chunks(4) {
+        let scale = if v[3] == 0 {
+            0.0
+        } else {
+            2.0f32.powi(v[3] as i32 - (128 + 8))
+        };
+
+        for c in &v[0..3] {
+            push_f32(&mut data, *c as f32 * scale);
+        }
+    }
+
+    Ok(texture(TextureFormat::RGB32F, width, height, data))
+}
+
+fn next_line<'a>(bytes: &'a [u8], cursor: &mut usize) -> Result<&'a str> {
+    let start = *cursor;
+    let len = bytes[start..]
+        .iter()
+        .position(|&v| v == b'\n')
+        .ok_or_else(|| format_err!("[HDR] Unexpected end of header."))?;
+
+    *cursor = start + len + 1;
+    Ok(::std::str::from_utf8(&bytes[start..(start + len)])?.trim())
+}
+
+fn read_scanline(bytes: &[u8], cursor: &mut usize, scanline: &mut [u8]) -> Result<()> {
+    let width = scanline.len() / 4;
+    let take = |cursor: &mut usize, len: usize| -> Result<usize> {
+        if *cursor + len > bytes.len() {
+            bail!("[HDR] Unexpected end of pixels.");
+        }
+
+        *cursor += len;
+        Ok(*cursor - len)
+    };
+
+    let head = take(cursor, 4)?;
+    let head = &bytes[head..(head + 4)];
+
+    // The adaptive run-length encoding stores 4 channels separately.
+    if width >= 8 && width < 0x8000 && head[0] == 2 && head[1] == 2 && head[2] & 0x80 == 0 {
+        if ((head[2] as usize) << 8 | head[3] as usize) != width {
+            bail!("[HDR] Scanline width does not match.");
+        }
+
+        for c in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let count = bytes[take(cursor, 1)?] as usize;
+                if count > 128 {
+                    let count = count - 128;
+                    if count == 0 || x + count > width {
+                        bail!("[HDR] Bad run-length of scanline.");
+                    }
+
+                    let v = bytes[take(cursor, 1)?];
+                    for i in 0..count {
+                        scanline[(x + i) * 4 + c] = v;
+                    }
+
+                    x += count;
+                } else {
+                    if count == 0 || x + count > width {
+                        bail!("[HDR] Bad run-length of scanline.");
+                    }
+
+                    let start = take(cursor, count)?;
+                    for i in 0..count {
+                        scanline[(x + i) * 4 + c] = bytes[start + i];
+                    }
+
+                    x += count;
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    // Flat pixels, with the old run-length encoding that repeats the last pixel.
+    *cursor -= 4;
+    let mut x = 0;
+    let mut shift = 0;
+    while x < width {
+        let start = take(cursor, 4)?;
+        let v = &bytes[start..(start + 4)];
+
+        if v[0] == 1 && v[1] == 1 && v[2] == 1 {
+            if x == 0 {
+                bail!("[HDR] Bad run-length of scanline.");
+            }
+
+            if shift > 24 {
+                bail!("[HDR] Bad run-length of scanline.");
+            }
+
+            let count = (v[3] as u64) << shift;
+            if x as u64 + count > width as u64 {
+                bail!("[HDR] Bad run-length of scanline.");
+            }
+
+            let count = count as usize;
+
+            for i in 0..count {
+                for c in 0..4 {
+                    scanline[(x + i) * 4 + c] = scanline[(x - 1) * 4 + c];
+                }
+            }
+
+            x += count;
+            shift += 8;
+        } else {
+            scanline[(x * 4)..(x * 4 + 4)].copy_from_slice(v);
+            x += 1;
+            shift = 0;
+        }
+    }
+
+    Ok(())
+}
+
+struct ExrChannel {
+    name: String,
+    pixel_type: i32,
+}
+
+/// Decodes an uncompressed, single-part scanline OpenEXR file into a `RGB32F`, or
+/// `RGBA32F` texture if there is an alpha channel. Luminance-only images are
+/// expanded into RGB.
+pub fn decode_exr(bytes: &[u8]) -> Result<(TextureParams, TextureData)> {
+    if !is_exr(bytes) {
+        bail!("[EXR] MAGIC number not match.");
+    }
+
+    let mut r = Reader {
+        bytes: bytes,
+        cursor: 4,
+    };
+
+    let version = r.u32()?;
+    if version & 0xff != 2 {
+        bail!("[EXR] Unsupported version {}.", version & 0xff);
+    }
+
+    if version & (0x200 | 0x800 | 0x1000) != 0 {
+        bail!("[EXR] Only single-part scanline images are supported.");
+    }
+
+    let mut channels = Vec::new();
+    let mut compression = None;
+    let mut window = None;
+
+    loop {
+        let name = r.string()?;
+        if name.is_empty() {
+            break;
+        }
+
+        let kind = r.string()?;
+        let size = r.i32()?;
+        let end = if size < 0 {
+            None
+        } else {
+            r.cursor.checked_add(size as usize)
+        };
+
+        let end = match end {
+            Some(end) if end <= bytes.len() => end,
+            _ => bail!("[EXR] Bad size {} of attribute {}.", size, name),
+        };
+
+        match (name.as_str(), kind.as_str()) {
+            ("channels", "chlist") => loop {
+                let name = r.string()?;
+                if name.is_empty() {
+                    break;
+                }
+
+                let pixel_type = r.i32()?;
+                r.skip(4)?;
+                if r.i32()? != 1 || r.i32()? != 1 {
+                    bail!("[EXR] Subsampled channels are not supported.");
+                }
+
+                channels.push(ExrChannel {
+                    name: name,
+                    pixel_type: pixel_type,
+                });
+            },
+            ("compression", "compression") => compression = Some(r.u8()?),
+            ("dataWindow", "box2i") => {
+                window = Some((r.i32()?, r.i32()?, r.i32()?, r.i32()?));
+            }
+            _ => {}
+        }
+
+        if r.cursor > end {
+            bail!("[EXR] Attribute {} exceeds its size.", name);
+        }
+
+        r.cursor = end;
+    }
+
+    match compression {
+        Some(0) => {}
+        Some(v) => bail!("[EXR] Compression {} is not supported.", v),
+        None => bail!("[EXR] Compression attribute is missing."),
+    }
+
+    let (x0, y0, x1, y1) = window.ok_or_else(|| format_err!("[EXR] DataWindow is missing."))?;
+    if x1 < x0 || y1 < y0 {
+        bail!("[EXR] DataWindow is empty.");
+    }
+
+    if channels.is_empty() {
+        bail!("[EXR] There is no channel.");
+    }
+
+    // Every sample takes 2 bytes at least, so the data window could not be larger than
+    // what the file holds.
+    let (width, height) = (
+        (x1 as i64 - x0 as i64 + 1) as usize,
+        (y1 as i64 - y0 as i64 + 1) as usize,
+    );
+
+    width
+        .checked_mul(height)
+        .and_then(|v| v.checked_mul(channels.len()))
+        .filter(|&v| v <= bytes.len() / 2)
+        .ok_or_else(|| format_err!("[EXR] DataWindow exceeds the file size."))?;
+
+    let find = |name: &str| channels.iter().position(|v| v.name == name);
+    let alpha = find("A").is_some();
+    let rgb = match (find("R"), find("G"), find("B"), find("Y")) {
+        (r, g, b, _) if r.is_some() || g.is_some() || b.is_some() => [r, g, b],
+        (_, _, _, y) if y.is_some() => [y, y, y],
+        _ => bail!("[EXR] There is no color channel."),
+    };
+
+    let components = if alpha { 4 } else { 3 };
+    let mut pixels = vec![0.0f32; width * height * components];
+    if alpha {
+        for v in pixels.chunks_mut(4) {
+            v[3] = 1.0;
+        }
+    }
+
+    // Uncompressed files have one scanline per chunk.
+    let mut offsets = Vec::with_capacity(height);
+    for _ in 0..height {
+        let offset = r.u64()?;
+        if offset > bytes.len() as u64 {
+            bail!("[EXR] Scanline offset is out of the file.");
+        }
+
+        offsets.push(offset as usize);
+    }
+
+    for offset in offsets {
+        r.cursor = offset;
+        let y = r.i32()? as i64 - y0 as i64;
+        let _ = r.i32()?;
+
+        if y < 0 || y as usize >= height {
+            bail!("[EXR] Scanline is out of the data window.");
+        }
+
+        for (i, c) in channels.iter().enumerate() {
+            let slot = if c.name == "A" {
+                Some(3)
+            } else {
+                rgb.iter().position(|v| *v == Some(i))
+            };
+
+            for x in 0..width {
+                let v = match c.pixel_type {
+                    0 => r.u32()? as f32,
+                    1 => half_to_f32(r.u16()?),
+                    2 => f32::from_bits(r.u32()?),
+                    v => bail!("[EXR] Unknown pixel type {}.", v),
+                };
+
+                if let Some(slot) = slot {
+                    let base = (y as usize * width + x) * components;
+                    pixels[base + slot] = v;
+
+                    // Luminance is copied into all the color components.
+                    if c.name == "Y" && rgb[0] == Some(i) {
+                        pixels[base + 1] = v;
+                        pixels[base + 2] = v;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut data = Vec::with_capacity(pixels.len() * 4);
+    for v in pixels {
+        push_f32(&mut data, v);
+    }
+
+    let format = if alpha {
+        TextureFormat::RGBA32F
+    } else {
+        TextureFormat::RGB32F
+    };
+
+    Ok(texture(format, width as u32, height as u32, data))
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let start = self.cursor;
+        match start.checked_add(len) {
+            Some(end) if end <= self.bytes.len() => {
+                self.cursor = end;
+                Ok(&self.bytes[start..end])
+            }
+            _ => bail!("[EXR] Unexpected end of file."),
+        }
+    }
+
+    fn skip(&mut self, len: usize) -> Result<()> {
+        self.take(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let v = self.take(2)?;
+        Ok(v[0] as u16 | (v[1] as u16) << 8)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let v = self.take(4)?;
+        Ok(v[0] as u32 | (v[1] as u32) << 8 | (v[2] as u32) << 16 | (v[3] as u32) << 24)
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(self.u32()? as i32)
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(self.u32()? as u64 | (self.u32()? as u64) << 32)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self
+            .bytes
+            .get(self.cursor..)
+            .unwrap_or(&[])
+            .iter()
+            .position(|&v| v == 0)
+            .ok_or_else(|| format_err!("[EXR] Unexpected end of file."))?;
+
+        let v = ::std::str::from_utf8(self.take(len)?)?.to_owned();
+        self.skip(1)?;
+        Ok(v)
+    }
+}
+
+fn half_to_f32(v: u16) -> f32 {
+    let sign = ((v >> 15) as u32) << 31;
+    let exponent = ((v >> 10) & 0x1f) as u32;
+    let mantissa = (v & 0x3ff) as u32;
+
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        0 => {
+            // Normalizes the subnormal number.
+            let mut e = 127 - 15 + 1;
+            let mut m = mantissa;
+            while m & 0x400 == 0 {
+                m <<= 1;
+                e -= 1;
+            }
+
+            sign | (e << 23) | ((m & 0x3ff) << 13)
+        }
+        0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+
+    f32::from_bits(bits)
+}
+
+fn push_f32(bytes: &mut Vec<u8>, v: f32) {
+    let v = unsafe { ::std::slice::from_raw_parts(&v as *const f32 as *const u8, 4) };
+    bytes.extend_from_slice(v);
+}
+
+fn texture(
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+) -> (TextureParams, TextureData) {
+    let mut params = TextureParams::default();
+    params.format = format;
+    params.dimensions = math::Vector2::new(width, height);
+
+    let data = TextureData {
+        bytes: vec![data.into_boxed_slice()],
+    };
+
+    (params, data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn floats(data: &TextureData) -> Vec<f32> {
+        data.bytes[0]
+            .chunks(4)
+            .map(|v| unsafe { ::std::ptr::read_unaligned(v.as_ptr() as *const f32) })
+            .collect()
+    }
+
+    #[test]
+    fn radiance() {
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 8\n".to_vec();
+        // A run-length encoded scanline with (1.0, 0.5, 0.0).
+        bytes.extend_from_slice(&[2, 2, 0, 8]);
+        bytes.extend_from_slice(&[128 + 8, 128]);
+        bytes.extend_from_slice(&[128 + 8, 64]);
+        bytes.extend_from_slice(&[128 + 8, 0]);
+        bytes.extend_from_slice(&[128 + 8, 129]);
+        // A flat scanline of (2.0, 0.0, 0.0), with old run-length encoding.
+        bytes.extend_from_slice(&[128, 0, 0, 130]);
+        bytes.extend_from_slice(&[1, 1, 1, 7]);
+
+        assert!(is_radiance(&bytes));
+        let (params, data) = decode_radiance(&bytes).unwrap();
+        assert_eq!(params.format, TextureFormat::RGB32F);
+        assert_eq!(params.dimensions, math::Vector2::new(8, 2));
+
+        let pixels = floats(&data);
+        assert_eq!(pixels.len(), 8 * 2 * 3);
+        assert_eq!(&pixels[0..3], &[1.0, 0.5, 0.0]);
+        assert_eq!(&pixels[21..24], &[1.0, 0.5, 0.0]);
+        assert_eq!(&pixels[24..27], &[2.0, 0.0, 0.0]);
+        assert_eq!(&pixels[45..48], &[2.0, 0.0, 0.0]);
+
+        let bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n\0\0\0\0";
+        assert!(decode_radiance(bytes).is_err());
+        assert!(decode_radiance(b"#?RADIANCE\n\n-Y 1 +X 1\n\0\0").is_err());
+    }
+
+    #[test]
+    fn half() {
+        assert_eq!(half_to_f32(0x0000), 0.0);
+        assert_eq!(half_to_f32(0x3c00), 1.0);
+        assert_eq!(half_to_f32(0xc000), -2.0);
+        assert_eq!(half_to_f32(0x3555), 0.333251953125);
+        assert_eq!(half_to_f32(0x0001), 2.0f32.powi(-24));
+        assert!(half_to_f32(0x7c00).is_infinite());
+    }
+
+    fn attribute(bytes: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(kind.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(&le(value.len() as u32));
+        bytes.extend_from_slice(value);
+    }
+
+    fn le(v: u32) -> [u8; 4] {
+        [v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8]
+    }
+
+    #[test]
+    fn exr() {
+        let mut channels = Vec::new();
+        for &(name, pixel_type) in &[("A", 1), ("B", 2), ("G", 1), ("R", 2)] {
+            channels.extend_from_slice(name.as_bytes());
+            channels.push(0);
+            channels.extend_from_slice(&le(pixel_type));
+            channels.extend_from_slice(&[0, 0, 0, 0]);
+            channels.extend_from_slice(&le(1));
+            channels.extend_from_slice(&le(1));
+        }
+        channels.push(0);
+
+        let mut window = Vec::new();
+        for v in &[0, 0, 1, 0] {
+            window.extend_from_slice(&le(*v));
+        }
+
+        let mut bytes = EXR_MAGIC.to_vec();
+        bytes.extend_from_slice(&le(2));
+        attribute(&mut bytes, "channels", "chlist", &channels);
+        attribute(&mut bytes, "compression", "compression", &[0]);
+        attribute(&mut bytes, "dataWindow", "box2i", &window);
+        attribute(&mut bytes, "lineOrder", "lineOrder", &[0]);
+        bytes.push(0);
+
+        // The offset table of the only scanline.
+        let offset = bytes.len() + 8;
+        bytes.extend_from_slice(&le(offset as u32));
+        bytes.extend_from_slice(&le(0));
+
+        // Scanline 0 with 2 pixels, channels are stored in alphabetical order.
+        bytes.extend_from_slice(&le(0));
+        bytes.extend_from_slice(&le(2 * (2 + 4 + 2 + 4)));
+        bytes.extend_from_slice(&[0x00, 0x3c, 0x00, 0x38]);
+        bytes.extend_from_slice(&le(0.25f32.to_bits()));
+        bytes.extend_from_slice(&le(0.75f32.to_bits()));
+        bytes.extend_from_slice(&[0x00, 0x40, 0x00, 0x00]);
+        bytes.extend_from_slice(&le(4.0f32.to_bits()));
+        bytes.extend_from_slice(&le(8.0f32.to_bits()));
+
+        assert!(is_exr(&bytes));
+        let (params, data) = decode_exr(&bytes).unwrap();
+        assert_eq!(params.format, TextureFormat::RGBA32F);
+        assert_eq!(params.dimensions, math::Vector2::new(2, 1));
+        assert_eq!(
+            floats(&data),
+            vec![4.0, 2.0, 0.25, 1.0, 8.0, 0.0, 0.75, 0.5]
+        );
+
+        // Compressed files are rejected.
+        let index = bytes
+            .windows(12)
+            .position(|v| v == b"compression\0")
+            .unwrap();
+        bytes[index + 12 + 12 + 4] = 3;
+        assert!(decode_exr(&bytes).is_err());
+    }
+}
@@ -1,12 +1,14 @@
-use bincode;
 use std::io::Cursor;
 use std::sync::Arc;
 
 use errors::*;
+use res::utils;
 
 use super::super::backends::frame::Command;
 use super::super::DoubleFrame;
 use super::texture::*;
+use super::texture_container;
+use super::texture_hdr;
 
 pub const MAGIC: [u8; 8] = [
     'V' as u8, 'T' as u8, 'E' as u8, 'X' as u8, ' ' as u8, 0, 0, 1,
@@ -21,21 +23,56 @@ impl TextureLoader {
     pub(crate) fn new(frames: Arc<DoubleFrame>) -> Self {
         TextureLoader { frames: frames }
     }
+
+    // Creates the object under `handle`, the parameters must have been validated.
+    fn create(&self, handle: TextureHandle, item: (TextureParams, Option<TextureData>)) -> TextureParams {
+        let mut frame = self.frames.front();
+        let task = Command::CreateTexture(handle, item.0, item.1);
+        frame.cmds.push(task);
+
+        item.0
+    }
 }
 
 impl ::res::registry::Register for TextureLoader {
     type Handle = TextureHandle;
     type Intermediate = (TextureParams, Option<TextureData>);
-    type Value = ();
+    type Value = TextureParams;
 
     fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
-        if &bytes[0..8] != &MAGIC[..] {
+        let decoded = if texture_hdr::is_radiance(bytes) {
+            Some(texture_hdr::decode_radiance(bytes)?)
+        } else if texture_hdr::is_exr(bytes) {
+            Some(texture_hdr::decode_exr(bytes)?)
+        } else if texture_container::is_dds(bytes) {
+            Some(texture_container::decode_dds(bytes)?)
+        } else if texture_container::is_ktx2(bytes) {
+            Some(texture_container::decode_ktx2(bytes)?)
+        } else {
+            None
+        };
+
+        if let Some((params, data)) = decoded {
+            info!(
+                "[TextureLoader] loads {:?} ({}x{} - {:?}, {} levels).",
+                handle,
+                params.dimensions.x,
+                params.dimensions.y,
+                params.format,
+                data.bytes.len()
+            );
+
+            return Ok((params, Some(data)));
+        }
+
+        if !utils::check_magic(bytes, &MAGIC) {
             bail!("[TextureLoader] MAGIC number not match.");
         }
 
+        let limit = bytes.len() as u64;
         let mut file = Cursor::new(&bytes[8..]);
-        let params: TextureParams = bincode::deserialize_from(&mut file)?;
-        let data = bincode::deserialize_from(&mut file)?;
+        let params: TextureParams = utils::deserialize_from(&mut file, limit)?;
+        let data = utils::deserialize_from(&mut file, limit)?;
 
         info!(
             "[TextureLoader] loads {:?} ({}x{} - {:?}).",
@@ -47,16 +84,31 @@ impl ::res::registry::Register for TextureLoader {
 
     fn attach(&self, handle: Self::Handle, item: Self::Intermediate) -> Result<Self::Value> {
         item.0.validate(item.1.as_ref())?;
+        Ok(self.create(handle, item))
+    }
 
-        let mut frame = self.frames.front();
-        let task = Command::CreateTexture(handle, item.0, item.1);
-        frame.cmds.push(task);
+    /// The new texture is created under the same handle, so the old one is deleted
+    /// first, once the new one is known to be valid.
+    fn reattach(
+        &self,
+        handle: Self::Handle,
+        old: Self::Value,
+        item: Self::Intermediate,
+    ) -> ::std::result::Result<Self::Value, (Self::Value, ::failure::Error)> {
+        if let Err(err) = item.0.validate(item.1.as_ref()) {
+            return Err((old, err.into()));
+        }
 
-        Ok(())
+        self.detach(handle, old);
+        Ok(self.create(handle, item))
     }
 
     fn detach(&self, handle: Self::Handle, _: Self::Value) {
         let cmd = Command::DeleteTexture(handle);
         self.frames.front().cmds.push(cmd);
     }
+
+    fn memory(&self, value: &Self::Value) -> usize {
+        value.format.size(value.dimensions) as usize
+    }
 }
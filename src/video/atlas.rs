@@ -0,0 +1,548 @@
+//! Runtime texture atlas which packs many small images into one texture.
+//!
+//! `AtlasAllocator` only does the bookkeeping of rectangles with a shelf packing
+//! algorithm: regions with similar heights are placed in the same horizontal
+//! shelf, and the space of freed regions will be reused by later allocations. It
+//! could be used alone if you are managing the pixels yourself.
+//!
+//! `TextureAtlas` binds an allocator with a dynamic texture, and keeps a copy of
+//! pixels in memory so the regions could be moved when defragmenting.
+//!
+//! ```rust
+//! use crayon::video::prelude::*;
+//! use crayon::math;
+//! let video = VideoSystem::headless(None).shared();
+//!
+//! let mut params = TextureParams::default();
+//! params.hint = TextureHint::Dynamic;
+//! params.dimensions = math::Vector2::new(256, 256);
+//!
+//! let mut atlas = TextureAtlas::new(video, params).unwrap();
+//! let glyph = atlas.insert((8, 12), &[0; 8 * 12 * 4]).unwrap();
+//!
+//! // The region of glyph in pixels and texture coordinates.
+//! let _ = atlas.region(glyph).unwrap();
+//! let _ = atlas.texcoords(glyph).unwrap();
+//!
+//! atlas.remove(glyph);
+//! ```
+
+use std::sync::Arc;
+
+use math;
+use utils::handle_pool::Iter;
+use utils::ObjectPool;
+
+use super::assets::texture::*;
+use super::VideoSystemShared;
+
+impl_handle!(AtlasRegionHandle);
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    // Free horizontal spans of (x, width), ordered by x.
+    spans: Vec<(u32, u32)>,
+    regions: usize,
+}
+
+impl Shelf {
+    fn new(y: u32, height: u32, width: u32) -> Self {
+        Shelf {
+            y: y,
+            height: height,
+            spans: vec![(0, width)],
+            regions: 0,
+        }
+    }
+
+    fn take(&mut self, width: u32) -> Option<u32> {
+        let index = self.spans.iter().position(|v| v.1 >= width)?;
+        let x = self.spans[index].0;
+
+        if self.spans[index].1 == width {
+            self.spans.remove(index);
+        } else {
+            self.spans[index].0 += width;
+            self.spans[index].1 -= width;
+        }
+
+        self.regions += 1;
+        Some(x)
+    }
+
+    fn give_back(&mut self, x: u32, width: u32) {
+        let index = self
+            .spans
+            .iter()
+            .position(|v| v.0 > x)
+            .unwrap_or(self.spans.len());
+        self.spans.insert(index, (x, width));
+
+        // Merges with the next and the previous span if they are adjacent.
+        if index + 1 < self.spans.len() && x + width == self.spans[index + 1].0 {
+            self.spans[index].1 += self.spans[index + 1].1;
+            self.spans.remove(index + 1);
+        }
+
+        if index > 0 && self.spans[index - 1].0 + self.spans[index - 1].1 == x {
+            self.spans[index - 1].1 += self.spans[index].1;
+            self.spans.remove(index);
+        }
+
+        self.regions -= 1;
+    }
+}
+
+/// The rectangle packer of texture atlas.
+pub struct AtlasAllocator {
+    dimensions: math::Vector2<u32>,
+    shelves: Vec<Shelf>,
+    regions: ObjectPool<AtlasRegionHandle, (usize, math::Aabb2<u32>)>,
+}
+
+impl AtlasAllocator {
+    /// Creates a empty allocator with `dimensions` in pixels.
+    pub fn new<T: Into<math::Vector2<u32>>>(dimensions: T) -> Self {
+        AtlasAllocator {
+            dimensions: dimensions.into(),
+            shelves: Vec::new(),
+            regions: ObjectPool::new(),
+        }
+    }
+
+    /// Gets the dimensions of this atlas.
+    #[inline]
+    pub fn dimensions(&self) -> math::Vector2<u32> {
+        self.dimensions
+    }
+
+    /// Gets the number of allocated regions.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// Returns true if there is no allocated region.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Allocates a region with `dimensions`. Returns `None` if there is no enough
+    /// space left.
+    pub fn allocate<T>(&mut self, dimensions: T) -> Option<AtlasRegionHandle>
+    where
+        T: Into<math::Vector2<u32>>,
+    {
+        let (w, h) = {
+            let v = dimensions.into();
+            (v.x, v.y)
+        };
+
+        if w == 0 || h == 0 || w > self.dimensions.x || h > self.dimensions.y {
+            return None;
+        }
+
+        let index = self.select(w, h)?;
+        let shelf = &mut self.shelves[index];
+        let x = shelf.take(w).unwrap();
+
+        let min = math::Point2::new(x, shelf.y);
+        let max = math::Point2::new(x + w, shelf.y + h);
+        Some(self.regions.create((index, math::Aabb2::new(min, max))))
+    }
+
+    /// Finds the shelf that fits `w`x`h` best, opening a new one if it's wasting too
+    /// much space.
+    fn select(&mut self, w: u32, h: u32) -> Option<usize> {
+        let mut best: Option<(usize, u32)> = None;
+
+        for (i, v) in self.shelves.iter().enumerate() {
+            if v.height >= h && v.spans.iter().any(|s| s.1 >= w) {
+                let waste = v.height - h;
+                if best.map(|b| waste < b.1).unwrap_or(true) {
+                    best = Some((i, waste));
+                }
+            }
+        }
+
+        if let Some((index, waste)) = best {
+            if waste <= h / 2 {
+                return Some(index);
+            }
+        }
+
+        let y = self.shelves.last().map(|v| v.y + v.height).unwrap_or(0);
+        if y + h <= self.dimensions.y {
+            self.shelves.push(Shelf::new(y, h, self.dimensions.x));
+            return Some(self.shelves.len() - 1);
+        }
+
+        best.map(|v| v.0)
+    }
+
+    /// Gets the rectangle of region in pixels.
+    #[inline]
+    pub fn region(&self, handle: AtlasRegionHandle) -> Option<math::Aabb2<u32>> {
+        self.regions.get(handle).map(|v| v.1)
+    }
+
+    /// Returns an iterator over the allocated regions.
+    #[inline]
+    pub fn iter(&self) -> Iter<AtlasRegionHandle> {
+        self.regions.iter()
+    }
+
+    /// Frees the region, its space could be reused by following allocations.
+    pub fn free(&mut self, handle: AtlasRegionHandle) -> bool {
+        if let Some((index, rect)) = self.regions.free(handle) {
+            let shelf = &mut self.shelves[index];
+            shelf.give_back(rect.min().x, rect.dim().x);
+
+            // Merges the spans of empty shelf into one. Notes that the shelf keeps its
+            // height, only the empty shelves at the end are released and could be
+            // opened again with any height.
+            if shelf.regions == 0 {
+                shelf.spans = vec![(0, self.dimensions.x)];
+            }
+
+            while self.shelves.last().map(|v| v.regions == 0).unwrap_or(false) {
+                self.shelves.pop();
+            }
+
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Repacks all the regions from scratch, which reclaims the space that has been
+    /// fragmented by frees. Returns the regions that have been moved with their old
+    /// and new rectangles, or `None` if the regions can't be repacked (in this case,
+    /// nothing is changed).
+    pub fn defragment(
+        &mut self,
+    ) -> Option<Vec<(AtlasRegionHandle, math::Aabb2<u32>, math::Aabb2<u32>)>> {
+        let mut handles: Vec<_> = self.regions.iter().collect();
+        handles.sort_by_key(|&v| {
+            let dim = self.regions.get(v).unwrap().1.dim();
+            (::std::u32::MAX - dim.y, ::std::u32::MAX - dim.x)
+        });
+
+        let mut packed = AtlasAllocator::new(self.dimensions);
+        let mut layout = Vec::with_capacity(handles.len());
+
+        for handle in handles {
+            let from = self.regions.get(handle).unwrap().1;
+            let (w, h) = (from.dim().x, from.dim().y);
+            let index = packed.select(w, h)?;
+            let shelf = &mut packed.shelves[index];
+            let x = shelf.take(w).unwrap();
+
+            let min = math::Point2::new(x, shelf.y);
+            let to = math::Aabb2::new(min, math::Point2::new(x + w, shelf.y + h));
+            layout.push((handle, index, from, to));
+        }
+
+        let mut moves = Vec::new();
+        for (handle, index, from, to) in layout {
+            *self.regions.get_mut(handle).unwrap() = (index, to);
+            if from != to {
+                moves.push((handle, from, to));
+            }
+        }
+
+        self.shelves = packed.shelves;
+        Some(moves)
+    }
+}
+
+/// A dynamic texture which packs images at runtime.
+pub struct TextureAtlas {
+    video: Arc<VideoSystemShared>,
+    params: TextureParams,
+    texture: TextureHandle,
+    allocator: AtlasAllocator,
+    pixels: Vec<u8>,
+}
+
+impl TextureAtlas {
+    /// Creates a empty texture atlas. The texture must be dynamic and uncompressed.
+    pub fn new(video: Arc<VideoSystemShared>, params: TextureParams) -> ::errors::Result<Self> {
+        if params.format.is_compression() {
+            bail!("Compressed texture can NOT be used as atlas.");
+        }
+
+        if params.hint == TextureHint::Immutable {
+            bail!("Immutable texture can NOT be used as atlas.");
+        }
+
+        let pixels = vec![0; params.format.size(params.dimensions) as usize];
+        let data = TextureData {
+            bytes: vec![pixels.clone().into_boxed_slice()],
+        };
+
+        let texture = video.create_texture(params, data)?;
+
+        Ok(TextureAtlas {
+            video: video,
+            params: params,
+            texture: texture,
+            allocator: AtlasAllocator::new(params.dimensions),
+            pixels: pixels,
+        })
+    }
+
+    /// Gets the underlying texture.
+    #[inline]
+    pub fn texture(&self) -> TextureHandle {
+        self.texture
+    }
+
+    /// Gets the underlying allocator.
+    #[inline]
+    pub fn allocator(&self) -> &AtlasAllocator {
+        &self.allocator
+    }
+
+    /// Packs an image into the atlas.
+    pub fn insert<T>(&mut self, dimensions: T, bytes: &[u8]) -> ::errors::Result<AtlasRegionHandle>
+    where
+        T: Into<math::Vector2<u32>>,
+    {
+        let dimensions = dimensions.into();
+        if self.params.format.size(dimensions) as usize != bytes.len() {
+            bail!(
+                "The length of bytes does not match the dimensions {:?}.",
+                dimensions
+            );
+        }
+
+        let handle = self
+            .allocator
+            .allocate(dimensions)
+            .ok_or_else(|| format_err!("There is no space left for {:?}.", dimensions))?;
+
+        // Gives the region back if the texture can't be updated, and keeps the pixels
+        // untouched so a following defragment won't upload the image.
+        let area = self.allocator.region(handle).unwrap();
+        if let Err(err) = self.video.update_texture(self.texture, area, bytes) {
+            self.allocator.free(handle);
+            return Err(err);
+        }
+
+        self.blit(area, bytes);
+        Ok(handle)
+    }
+
+    /// Removes the image from atlas.
+    #[inline]
+    pub fn remove(&mut self, handle: AtlasRegionHandle) -> bool {
+        self.allocator.free(handle)
+    }
+
+    /// Gets the region of image in pixels.
+    #[inline]
+    pub fn region(&self, handle: AtlasRegionHandle) -> Option<math::Aabb2<u32>> {
+        self.allocator.region(handle)
+    }
+
+    /// Gets the region of image in normalized texture coordinates.
+    pub fn texcoords(&self, handle: AtlasRegionHandle) -> Option<math::Aabb2<f32>> {
+        let (w, h) = (
+            self.params.dimensions.x as f32,
+            self.params.dimensions.y as f32,
+        );
+
+        self.allocator.region(handle).map(|v| {
+            let (min, max) = (v.min(), v.max());
+            math::Aabb2::new(
+                math::Point2::new(min.x as f32 / w, min.y as f32 / h),
+                math::Point2::new(max.x as f32 / w, max.y as f32 / h),
+            )
+        })
+    }
+
+    /// Repacks the images and uploads the whole texture again. Notes that the regions
+    /// and texture coordinates of images might be changed after this.
+    pub fn defragment(&mut self) -> ::errors::Result<()> {
+        let moves = self
+            .allocator
+            .defragment()
+            .ok_or_else(|| format_err!("Failed to repack the regions of atlas."))?;
+
+        if moves.is_empty() {
+            return Ok(());
+        }
+
+        // Copies the images into a cleared buffer, so freed regions are erased too.
+        let mut images = Vec::with_capacity(self.allocator.len());
+        for handle in self.allocator.iter() {
+            let to = self.allocator.region(handle).unwrap();
+            let from = moves
+                .iter()
+                .find(|v| v.0 == handle)
+                .map(|v| v.1)
+                .unwrap_or(to);
+
+            images.push((to, self.read(from)));
+        }
+
+        for v in &mut self.pixels {
+            *v = 0;
+        }
+
+        for (to, bytes) in images {
+            self.blit(to, &bytes);
+        }
+
+        let dimensions = self.params.dimensions;
+        let area = math::Aabb2::new(
+            math::Point2::new(0, 0),
+            math::Point2::new(dimensions.x, dimensions.y),
+        );
+
+        self.video.update_texture(self.texture, area, &self.pixels)
+    }
+
+    fn row(&self, area: math::Aabb2<u32>) -> (usize, usize, usize) {
+        let size = self.params.format.size(math::Vector2::new(1, 1)) as usize;
+        let pitch = self.params.dimensions.x as usize * size;
+        let offset = area.min().y as usize * pitch + area.min().x as usize * size;
+        (offset, pitch, area.dim().x as usize * size)
+    }
+
+    fn read(&self, area: math::Aabb2<u32>) -> Vec<u8> {
+        let (offset, pitch, len) = self.row(area);
+        let mut bytes = Vec::with_capacity(len * area.dim().y as usize);
+        for i in 0..area.dim().y as usize {
+            let start = offset + i * pitch;
+            bytes.extend_from_slice(&self.pixels[start..(start + len)]);
+        }
+
+        bytes
+    }
+
+    fn blit(&mut self, area: math::Aabb2<u32>, bytes: &[u8]) {
+        let (offset, pitch, len) = self.row(area);
+        for i in 0..area.dim().y as usize {
+            let start = offset + i * pitch;
+            self.pixels[start..(start + len)].copy_from_slice(&bytes[(i * len)..((i + 1) * len)]);
+        }
+    }
+}
+
+impl Drop for TextureAtlas {
+    fn drop(&mut self) {
+        self.video.delete_texture(self.texture);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn allocate() {
+        let mut atlas = AtlasAllocator::new((16, 16));
+        assert!(atlas.allocate((0, 4)).is_none());
+        assert!(atlas.allocate((17, 4)).is_none());
+
+        let r1 = atlas.allocate((8, 4)).unwrap();
+        let r2 = atlas.allocate((8, 3)).unwrap();
+        let r3 = atlas.allocate((4, 8)).unwrap();
+        assert_eq!(atlas.len(), 3);
+
+        // Regions with similar heights are packed in the same shelf.
+        let rect = atlas.region(r1).unwrap();
+        assert_eq!(rect.min(), math::Point2::new(0, 0));
+        assert_eq!(atlas.region(r2).unwrap().min(), math::Point2::new(8, 0));
+        assert_eq!(atlas.region(r3).unwrap().min(), math::Point2::new(0, 4));
+
+        assert!(atlas.allocate((16, 8)).is_none());
+
+        assert!(atlas.free(r1));
+        assert!(!atlas.free(r1));
+        assert!(atlas.region(r1).is_none());
+
+        let r4 = atlas.allocate((4, 4)).unwrap();
+        assert_eq!(atlas.region(r4).unwrap().min(), math::Point2::new(0, 0));
+    }
+
+    #[test]
+    fn reuse() {
+        let mut atlas = AtlasAllocator::new((8, 8));
+        let regions: Vec<_> = (0..4).map(|_| atlas.allocate((2, 8)).unwrap()).collect();
+        assert!(atlas.allocate((1, 1)).is_none());
+
+        // Adjacent free spans are merged.
+        atlas.free(regions[1]);
+        atlas.free(regions[2]);
+        let r = atlas.allocate((4, 8)).unwrap();
+        assert_eq!(atlas.region(r).unwrap().min(), math::Point2::new(2, 0));
+
+        // Empty shelves are released.
+        atlas.free(regions[0]);
+        atlas.free(regions[3]);
+        atlas.free(r);
+        assert!(atlas.is_empty());
+        assert!(atlas.allocate((8, 8)).is_some());
+    }
+
+    #[test]
+    fn defragment() {
+        let mut atlas = AtlasAllocator::new((8, 8));
+        let r1 = atlas.allocate((8, 2)).unwrap();
+        let r2 = atlas.allocate((4, 4)).unwrap();
+        let r3 = atlas.allocate((4, 4)).unwrap();
+        assert!(atlas.allocate((8, 3)).is_none());
+
+        atlas.free(r1);
+        let moves = atlas.defragment().unwrap();
+        assert_eq!(moves.len(), 2);
+        assert_eq!(atlas.region(r2).unwrap().min(), math::Point2::new(0, 0));
+        assert_eq!(atlas.region(r3).unwrap().min(), math::Point2::new(4, 0));
+        assert!(atlas.allocate((8, 3)).is_some());
+    }
+
+    #[test]
+    fn texture() {
+        use video::VideoSystem;
+        let video = VideoSystem::headless(None).shared();
+
+        let mut params = TextureParams::default();
+        params.dimensions = math::Vector2::new(4, 4);
+        assert!(TextureAtlas::new(video.clone(), params).is_err());
+
+        params.hint = TextureHint::Dynamic;
+        params.format = TextureFormat::R8;
+        let mut atlas = TextureAtlas::new(video.clone(), params).unwrap();
+        assert!(atlas.insert((2, 2), &[1; 3]).is_err());
+
+        let r1 = atlas.insert((4, 2), &[1; 8]).unwrap();
+        let r2 = atlas.insert((2, 2), &[2; 4]).unwrap();
+        assert_eq!(atlas.pixels[8..12], [2, 2, 0, 0]);
+
+        let uv = atlas.texcoords(r2).unwrap();
+        assert_eq!(uv.min(), math::Point2::new(0.0, 0.5));
+        assert_eq!(uv.max(), math::Point2::new(0.5, 1.0));
+
+        atlas.remove(r1);
+        atlas.defragment().unwrap();
+        assert_eq!(atlas.region(r2).unwrap().min(), math::Point2::new(0, 0));
+        assert_eq!(atlas.pixels[0..4], [2, 2, 0, 0]);
+        assert_eq!(atlas.pixels[4..8], [2, 2, 0, 0]);
+        assert!(atlas.pixels[8..].iter().all(|&v| v == 0));
+
+        let texture = atlas.texture();
+        video.delete_texture(texture);
+        assert!(atlas.insert((2, 2), &[3; 4]).is_err());
+        assert_eq!(atlas.allocator().len(), 1);
+        assert_eq!(atlas.pixels[0..4], [2, 2, 0, 0]);
+        assert_eq!(atlas.pixels[4..8], [2, 2, 0, 0]);
+
+        drop(atlas);
+        assert!(video.texture(texture).is_none());
+    }
+}
@@ -14,6 +14,8 @@ pub enum Error {
     SurfaceInvalid(String),
     #[fail(display = "Attribute({}) is undefined.", _0)]
     AttributeUndefined(String),
+    #[fail(display = "Keyword({}) is undefined.", _0)]
+    KeywordUndefined(String),
 }
 
 pub type Result<T> = ::std::result::Result<T, Error>;
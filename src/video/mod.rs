@@ -223,27 +223,36 @@ pub const MAX_FRAMEBUFFER_ATTACHMENTS: usize = 8;
 pub const MAX_UNIFORM_VARIABLES: usize = 32;
 /// Maximum number of textures in shader.
 pub const MAX_UNIFORM_TEXTURE_SLOTS: usize = 8;
+/// Maximum number of keywords declared by shader variants.
+pub const MAX_SHADER_KEYWORDS: usize = 32;
 
 #[macro_use]
 pub mod assets;
+pub mod atlas;
 pub mod batch;
 pub mod errors;
+pub mod palette;
+pub mod transient;
+pub mod upload;
+pub mod variants;
 
 mod backends;
 
 pub mod prelude {
     pub use super::assets::prelude::*;
+    pub use super::atlas::{AtlasAllocator, AtlasRegionHandle, TextureAtlas};
     pub use super::batch::{Batch, DrawCall, OrderDrawBatch};
+    pub use super::variants::{ShaderKeywords, ShaderVariants};
     pub use super::{VideoFrameInfo, VideoSystem, VideoSystemShared};
 }
 
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
 use uuid::Uuid;
 
 use application::window::Window;
 use math;
-use res::prelude::{Location, ResourceSystemShared};
+use res::prelude::{Location, Promise, ResourceSystemShared};
 use res::registry::Registry;
 use utils::ObjectPool;
 
@@ -253,6 +262,8 @@ use self::backends::gl::visitor::GLVisitor;
 use self::backends::Visitor;
 use self::batch::DrawCall;
 use self::errors::*;
+use self::transient::TransientPool;
+use self::upload::{UploadJob, UploadQueue, UploadTarget};
 
 /// The information of video module during last frame.
 #[derive(Debug, Copy, Clone, Default)]
@@ -317,10 +328,14 @@ impl VideoSystem {
         self.shared.clone()
     }
 
-    /// Swap internal commands frame.
+    /// Swap internal commands frame, and submits the pending uploads that fit into
+    /// the budget of the next frame. The transient render textures that are unused
+    /// during the last frame are deleted.
     #[inline]
     pub fn swap_frames(&self) {
         self.frames.swap_frames();
+        self.shared.advance_uploads();
+        self.shared.advance_transients();
     }
 
     /// Advance to next frame.
@@ -372,6 +387,8 @@ pub struct VideoSystemShared {
     meshes: MeshRegistry,
     textures: TextureRegistry,
     render_textures: RwLock<ObjectPool<RenderTextureHandle, RenderTextureParams>>,
+    uploads: Mutex<UploadQueue>,
+    transients: Mutex<TransientPool>,
 }
 
 impl VideoSystemShared {
@@ -391,6 +408,8 @@ impl VideoSystemShared {
             meshes: meshes,
             textures: textures,
             render_textures: RwLock::new(ObjectPool::new()),
+            uploads: Mutex::new(UploadQueue::new()),
+            transients: Mutex::new(TransientPool::new()),
         }
     }
 
@@ -525,6 +544,12 @@ impl VideoSystemShared {
         Ok(handle)
     }
 
+    /// Gets the uuid of the mesh if it's created from file.
+    #[inline]
+    pub fn mesh_uuid(&self, handle: MeshHandle) -> Option<Uuid> {
+        self.meshes.uuid(handle)
+    }
+
     /// Gets the `MeshParams` if available.
     #[inline]
     pub fn mesh_aabb(&self, handle: MeshHandle) -> Option<math::Aabb3<f32>> {
@@ -572,6 +597,25 @@ impl VideoSystemShared {
     pub fn delete_mesh(&self, handle: MeshHandle) {
         self.meshes.delete(handle);
     }
+
+    /// Retains the mesh object on behalf of `holder`, returns false if it has been
+    /// deleted. The reference should be dropped with `release_mesh`.
+    #[inline]
+    pub fn retain_mesh(&self, handle: MeshHandle, holder: &'static str) -> bool {
+        self.meshes.retain_by(handle, holder)
+    }
+
+    /// Drops a reference of mesh object that was retained by `holder`.
+    #[inline]
+    pub fn release_mesh(&self, handle: MeshHandle, holder: &'static str) {
+        self.meshes.release_by(handle, holder);
+    }
+
+    /// Gets the holders of mesh object and the number of references they retained.
+    #[inline]
+    pub fn mesh_holders(&self, handle: MeshHandle) -> Vec<(&'static str, u32)> {
+        self.meshes.holders(handle)
+    }
 }
 
 impl VideoSystemShared {
@@ -604,6 +648,12 @@ impl VideoSystemShared {
         Ok(handle)
     }
 
+    /// Gets the `TextureParams` if available.
+    #[inline]
+    pub fn texture(&self, handle: TextureHandle) -> Option<TextureParams> {
+        self.textures.get(handle, |v| *v)
+    }
+
     /// Update a contiguous subregion of an existing two-dimensional texture object.
     pub fn update_texture(
         &self,
@@ -624,6 +674,171 @@ impl VideoSystemShared {
     pub fn delete_texture(&self, handle: TextureHandle) {
         self.textures.delete(handle);
     }
+
+    /// Retains the texture object on behalf of `holder`, returns false if it has been
+    /// deleted. The reference should be dropped with `release_texture`.
+    #[inline]
+    pub fn retain_texture(&self, handle: TextureHandle, holder: &'static str) -> bool {
+        self.textures.retain_by(handle, holder)
+    }
+
+    /// Drops a reference of texture object that was retained by `holder`.
+    #[inline]
+    pub fn release_texture(&self, handle: TextureHandle, holder: &'static str) {
+        self.textures.release_by(handle, holder);
+    }
+
+    /// Gets the holders of texture object and the number of references they retained.
+    #[inline]
+    pub fn texture_holders(&self, handle: TextureHandle) -> Vec<(&'static str, u32)> {
+        self.textures.holders(handle)
+    }
+}
+
+impl VideoSystemShared {
+    /// Sets the maximum number of bytes that could be uploaded with `upload_*` methods
+    /// per frame. A slice is never split further than a row of texture, so the budget
+    /// might be exceeded by one row at most.
+    pub fn set_upload_budget(&self, bytes: usize) {
+        self.uploads.lock().unwrap().budget = bytes.max(1);
+    }
+
+    /// Gets the maximum number of bytes that could be uploaded per frame.
+    pub fn upload_budget(&self) -> usize {
+        self.uploads.lock().unwrap().budget
+    }
+
+    /// Gets the number of bytes that are still waiting to be uploaded.
+    pub fn pending_uploads(&self) -> usize {
+        self.uploads.lock().unwrap().remains()
+    }
+
+    /// Updates a subregion of texture incrementally. Unlike `update_texture`, the data
+    /// is split by rows and spread across frames within the upload budget.
+    ///
+    /// The returned promise will be set once the last slice has been submitted, or if
+    /// the texture has been deleted during uploading. Compressed textures are always
+    /// submitted as a whole.
+    pub fn upload_texture(
+        &self,
+        handle: TextureHandle,
+        area: math::Aabb2<u32>,
+        data: Vec<u8>,
+    ) -> ::errors::Result<Arc<Promise>> {
+        let params = self
+            .texture(handle)
+            .ok_or_else(|| format_err!("{:?}", handle))?;
+
+        let (min, max) = (area.min(), area.max());
+        if max.x > params.dimensions.x || max.y > params.dimensions.y {
+            bail!("Area {:?} is out of the bounds of {:?}.", area, handle);
+        }
+
+        let dimensions = math::Vector2::new(max.x - min.x, max.y - min.y);
+        let len = params.format.size(dimensions) as usize;
+        if data.len() != len {
+            bail!(
+                "Expects {} bytes to update {:?}, but got {}.",
+                len,
+                handle,
+                data.len()
+            );
+        }
+
+        let stride = if params.format.is_compression() || dimensions.y == 0 {
+            0
+        } else {
+            len / dimensions.y as usize
+        };
+
+        let target = UploadTarget::Texture(handle, area);
+        Ok(self.enqueue_upload(UploadJob::new(target, data, stride)))
+    }
+
+    /// Updates a subset of vertex buffer incrementally within the upload budget.
+    pub fn upload_vertex_buffer(
+        &self,
+        handle: MeshHandle,
+        offset: usize,
+        data: Vec<u8>,
+    ) -> ::errors::Result<Arc<Promise>> {
+        if !self.meshes.contains(handle) {
+            bail!("{:?}", handle);
+        }
+
+        let target = UploadTarget::VertexBuffer(handle, offset);
+        Ok(self.enqueue_upload(UploadJob::new(target, data, 1)))
+    }
+
+    /// Updates a subset of index buffer incrementally within the upload budget.
+    pub fn upload_index_buffer(
+        &self,
+        handle: MeshHandle,
+        offset: usize,
+        data: Vec<u8>,
+    ) -> ::errors::Result<Arc<Promise>> {
+        if !self.meshes.contains(handle) {
+            bail!("{:?}", handle);
+        }
+
+        let target = UploadTarget::IndexBuffer(handle, offset);
+        Ok(self.enqueue_upload(UploadJob::new(target, data, 1)))
+    }
+
+    fn enqueue_upload(&self, job: UploadJob) -> Arc<Promise> {
+        let promise = job.promise.clone();
+        if job.remains() == 0 {
+            promise.set(Ok(()));
+        } else {
+            self.uploads.lock().unwrap().jobs.push_back(job);
+        }
+
+        promise
+    }
+
+    /// Submits the pending uploads in order until the budget runs out.
+    pub(crate) fn advance_uploads(&self) {
+        let mut uploads = self.uploads.lock().unwrap();
+        let mut budget = uploads.budget;
+
+        while budget > 0 {
+            let finished = match uploads.jobs.front_mut() {
+                Some(job) => {
+                    let (target, range) = job.next_slice(budget).unwrap();
+                    budget = budget.saturating_sub(range.len());
+
+                    let data = &job.bytes[range];
+                    let submitted = match target {
+                        UploadTarget::Texture(handle, area) => {
+                            self.update_texture(handle, area, data).is_ok()
+                        }
+                        UploadTarget::VertexBuffer(handle, offset) => {
+                            self.update_vertex_buffer(handle, offset, data).is_ok()
+                        }
+                        UploadTarget::IndexBuffer(handle, offset) => {
+                            self.update_index_buffer(handle, offset, data).is_ok()
+                        }
+                    };
+
+                    if !submitted {
+                        let err = format_err!("{:?} has been deleted while uploading.", target);
+                        job.promise.set(Err(err));
+                        true
+                    } else if job.remains() == 0 {
+                        job.promise.set(Ok(()));
+                        true
+                    } else {
+                        false
+                    }
+                }
+                None => break,
+            };
+
+            if finished {
+                uploads.jobs.pop_front();
+            }
+        }
+    }
 }
 
 impl VideoSystemShared {
@@ -654,4 +869,44 @@ impl VideoSystemShared {
             self.frames.front().cmds.push(cmd);
         }
     }
+
+    /// Acquires a render texture that is only used within current frame, e.g. the
+    /// intermediate targets of shadow and post-processing passes. A texture released
+    /// earlier with the same params is reused if possible.
+    ///
+    /// The texture should be released with `release_transient_render_texture` once the
+    /// passes that read it have been submitted, and it's released automatically when
+    /// the frame ends. See `video::transient` for details.
+    pub fn acquire_transient_render_texture(
+        &self,
+        params: RenderTextureParams,
+    ) -> Result<RenderTextureHandle> {
+        let mut transients = self.transients.lock().unwrap();
+        if let Some(handle) = transients.acquire(params) {
+            return Ok(handle);
+        }
+
+        let handle = self.create_render_texture(params)?;
+        transients.insert(handle, params);
+        Ok(handle)
+    }
+
+    /// Releases a transient render texture, so later passes in this frame could reuse it.
+    pub fn release_transient_render_texture(&self, handle: RenderTextureHandle) {
+        if !self.transients.lock().unwrap().release(handle) {
+            warn!("{:?} is not acquired as transient render texture.", handle);
+        }
+    }
+
+    /// Gets the number of transient render textures that are alive.
+    pub fn transient_render_textures(&self) -> usize {
+        self.transients.lock().unwrap().len()
+    }
+
+    pub(crate) fn advance_transients(&self) {
+        let unused = self.transients.lock().unwrap().advance();
+        for handle in unused {
+            self.delete_render_texture(handle);
+        }
+    }
 }
@@ -0,0 +1,197 @@
+//! A ring buffer of matrices which are shared by all the skinned draws of a frame.
+//!
+//! Instead of uploading the bone matrices of every character as uniforms, all of them
+//! are pushed into one `SkinningPalette` and uploaded with a single texture update per
+//! frame. Every draw only needs the offset of its first matrix, which also makes it
+//! possible to draw instanced crowds with per-instance offsets.
+//!
+//! Matrices are stored in a `RGBA32F` texture with one matrix per row, and one column
+//! per texel. The bone `i` of a draw could be fetched in shaders with:
+//!
+//! ```glsl
+//! mat4 bone = mat4(
+//!     texelFetch(u_Palette, ivec2(0, u_PaletteOffset + i), 0),
+//!     texelFetch(u_Palette, ivec2(1, u_PaletteOffset + i), 0),
+//!     texelFetch(u_Palette, ivec2(2, u_PaletteOffset + i), 0),
+//!     texelFetch(u_Palette, ivec2(3, u_PaletteOffset + i), 0));
+//! ```
+//!
+//! The rows are allocated in a ring, so the rows written in this frame never overlap
+//! the ones of last frame, which might still be read by the video device.
+
+use std::sync::Arc;
+
+use math;
+
+use super::assets::texture::*;
+use super::VideoSystemShared;
+
+/// A ring buffer of matrices backed by a dynamic texture.
+pub struct SkinningPalette {
+    video: Arc<VideoSystemShared>,
+    texture: TextureHandle,
+    matrices: Vec<[[f32; 4]; 4]>,
+    // The first row of current frame, and the next row to write.
+    head: usize,
+    cursor: usize,
+    // The rows used in last frame and in current frame, including the skipped ones.
+    last: usize,
+    len: usize,
+}
+
+impl SkinningPalette {
+    /// Creates a new palette which could hold `capacity` matrices. Since the rows of last
+    /// frame are kept, a frame could use at most half of `capacity` in the worst case.
+    pub fn new(video: Arc<VideoSystemShared>, capacity: usize) -> ::errors::Result<Self> {
+        if capacity == 0 {
+            bail!("The capacity of palette must be greater than zero.");
+        }
+
+        let mut params = TextureParams::default();
+        params.hint = TextureHint::Stream;
+        params.format = TextureFormat::RGBA32F;
+        params.filter = TextureFilter::Nearest;
+        params.dimensions = math::Vector2::new(4, capacity as u32);
+
+        let texture = video.create_texture(params, None)?;
+
+        Ok(SkinningPalette {
+            video: video,
+            texture: texture,
+            matrices: vec![[[0.0; 4]; 4]; capacity],
+            head: 0,
+            cursor: 0,
+            last: 0,
+            len: 0,
+        })
+    }
+
+    /// Gets the underlying texture.
+    #[inline]
+    pub fn texture(&self) -> TextureHandle {
+        self.texture
+    }
+
+    /// Gets the maximum number of matrices.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.matrices.len()
+    }
+
+    /// Gets the number of rows that are used in current frame.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if nothing has been pushed in current frame.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Pushes the matrices of a draw, and returns the row of the first one. The matrices
+    /// of a draw are always stored in contiguous rows.
+    pub fn push(&mut self, matrices: &[math::Matrix4<f32>]) -> ::errors::Result<u32> {
+        let capacity = self.capacity();
+        let n = matrices.len();
+
+        // Skips the rows at the end if the matrices do not fit in.
+        let skipped = if self.cursor + n > capacity {
+            capacity - self.cursor
+        } else {
+            0
+        };
+
+        if self.last + self.len + skipped + n > capacity {
+            bail!("Out of the capacity of palette ({} matrices).", capacity);
+        }
+
+        let offset = (self.cursor + skipped) % capacity;
+        for (i, v) in matrices.iter().enumerate() {
+            self.matrices[offset + i] = (*v).into();
+        }
+
+        self.cursor = (offset + n) % capacity;
+        self.len += skipped + n;
+        Ok(offset as u32)
+    }
+
+    /// Uploads the matrices pushed in current frame, and starts the next frame.
+    pub fn submit(&mut self) -> ::errors::Result<()> {
+        if self.len > 0 {
+            let capacity = self.capacity();
+            let end = self.head + self.len;
+            self.update(self.head, end.min(capacity))?;
+            if end > capacity {
+                self.update(0, end - capacity)?;
+            }
+        }
+
+        self.head = self.cursor;
+        self.last = self.len;
+        self.len = 0;
+        Ok(())
+    }
+
+    fn update(&self, start: usize, end: usize) -> ::errors::Result<()> {
+        if start >= end {
+            return Ok(());
+        }
+
+        let rows = &self.matrices[start..end];
+        let len = rows.len() * ::std::mem::size_of::<[[f32; 4]; 4]>();
+        let bytes = unsafe { ::std::slice::from_raw_parts(rows.as_ptr() as *const u8, len) };
+
+        let area = math::Aabb2::new(
+            math::Point2::new(0, start as u32),
+            math::Point2::new(4, end as u32),
+        );
+
+        self.video.update_texture(self.texture, area, bytes)
+    }
+}
+
+impl Drop for SkinningPalette {
+    fn drop(&mut self) {
+        self.video.delete_texture(self.texture);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use math::One;
+    use video::VideoSystem;
+
+    #[test]
+    fn ring() {
+        let video = VideoSystem::headless(None).shared();
+        let mut palette = SkinningPalette::new(video.clone(), 8).unwrap();
+        let bones = [math::Matrix4::one(); 3];
+
+        assert_eq!(palette.push(&bones).unwrap(), 0);
+        palette.submit().unwrap();
+
+        assert_eq!(palette.push(&bones).unwrap(), 3);
+        assert_eq!(palette.push(&bones[0..1]).unwrap(), 6);
+        palette.submit().unwrap();
+
+        // Skips the last row, since the matrices of a draw must be contiguous.
+        assert_eq!(palette.push(&bones[0..2]).unwrap(), 0);
+        assert_eq!(palette.len(), 3);
+
+        // The 4 rows of last frame are kept.
+        assert!(palette.push(&bones[0..2]).is_err());
+        assert_eq!(palette.push(&bones[0..1]).unwrap(), 2);
+        palette.submit().unwrap();
+
+        palette.submit().unwrap();
+        assert!(palette.is_empty());
+        assert_eq!(palette.push(&bones).unwrap(), 3);
+
+        let texture = palette.texture();
+        drop(palette);
+        assert!(video.texture(texture).is_none());
+    }
+}
@@ -0,0 +1,112 @@
+//! A pool of render textures that only live within a frame.
+//!
+//! Multi-pass pipelines (shadows, post-processing) need intermediate render targets
+//! which are written by one pass and read by the next ones, and are useless once the
+//! last reader has been submitted. Instead of keeping every intermediate alive all
+//! frame, passes acquire them from the pool and release them as soon as possible, so
+//! a later pass with the same `RenderTextureParams` could alias the same texture.
+//!
+//! Since commands are executed in the order they are submitted, reusing a released
+//! texture is safe as long as the passes that read it have been submitted before it's
+//! released. Textures that are not acquired during a whole frame are deleted.
+
+use super::assets::texture::{RenderTextureHandle, RenderTextureParams};
+
+pub(crate) struct TransientPool {
+    frees: Vec<(RenderTextureHandle, RenderTextureParams, bool)>,
+    actives: Vec<(RenderTextureHandle, RenderTextureParams)>,
+}
+
+impl TransientPool {
+    pub fn new() -> Self {
+        TransientPool {
+            frees: Vec::new(),
+            actives: Vec::new(),
+        }
+    }
+
+    /// Takes a released texture with the same params if there is any.
+    pub fn acquire(&mut self, params: RenderTextureParams) -> Option<RenderTextureHandle> {
+        let index = self.frees.iter().position(|v| v.1 == params)?;
+        let (handle, params, _) = self.frees.swap_remove(index);
+        self.actives.push((handle, params));
+        Some(handle)
+    }
+
+    /// Tracks a texture that was created for `acquire`.
+    pub fn insert(&mut self, handle: RenderTextureHandle, params: RenderTextureParams) {
+        self.actives.push((handle, params));
+    }
+
+    /// Returns false if `handle` is not acquired from this pool.
+    pub fn release(&mut self, handle: RenderTextureHandle) -> bool {
+        if let Some(index) = self.actives.iter().position(|v| v.0 == handle) {
+            let (handle, params) = self.actives.swap_remove(index);
+            self.frees.push((handle, params, true));
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Gets the number of textures, both acquired and released.
+    pub fn len(&self) -> usize {
+        self.frees.len() + self.actives.len()
+    }
+
+    /// Ends current frame. The textures still being acquired are released, and the
+    /// ones that are not used during this frame are returned to be deleted.
+    pub fn advance(&mut self) -> Vec<RenderTextureHandle> {
+        for (handle, params) in self.actives.drain(..) {
+            self.frees.push((handle, params, true));
+        }
+
+        let mut unused = Vec::new();
+        self.frees.retain(|v| {
+            if !v.2 {
+                unused.push(v.0);
+            }
+
+            v.2
+        });
+
+        for v in &mut self.frees {
+            v.2 = false;
+        }
+
+        unused
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use math;
+    use utils::HandleLike;
+
+    #[test]
+    fn alias() {
+        let mut pool = TransientPool::new();
+        let mut params = RenderTextureParams::default();
+        let h1 = RenderTextureHandle::new(1, 1);
+        let h2 = RenderTextureHandle::new(2, 1);
+
+        assert_eq!(pool.acquire(params), None);
+        pool.insert(h1, params);
+        assert!(pool.release(h1));
+        assert!(!pool.release(h1));
+
+        // The released texture is reused by later passes with the same params.
+        assert_eq!(pool.acquire(params), Some(h1));
+        params.dimensions = math::Vector2::new(256, 256);
+        assert_eq!(pool.acquire(params), None);
+        pool.insert(h2, params);
+
+        // Textures that are not used during a whole frame are deleted.
+        assert!(pool.advance().is_empty());
+        assert_eq!(pool.len(), 2);
+        assert_eq!(pool.acquire(params), Some(h2));
+        assert_eq!(pool.advance(), vec![h1]);
+        assert_eq!(pool.len(), 1);
+    }
+}
@@ -0,0 +1,226 @@
+//! A queue of texture and buffer updates that are spread across frames.
+//!
+//! Large updates are split into slices, and only a limited number of bytes are
+//! submitted to the video device every frame, so streaming a big texture never
+//! spikes a single frame. Textures are sliced by rows, and buffers by bytes.
+
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::Arc;
+
+use math;
+use res::promise::Promise;
+
+use super::assets::mesh::MeshHandle;
+use super::assets::texture::TextureHandle;
+
+/// The default number of bytes that could be uploaded per frame.
+pub const DEFAULT_UPLOAD_BUDGET: usize = 1024 * 1024;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum UploadTarget {
+    Texture(TextureHandle, math::Aabb2<u32>),
+    VertexBuffer(MeshHandle, usize),
+    IndexBuffer(MeshHandle, usize),
+}
+
+pub(crate) struct UploadJob {
+    pub target: UploadTarget,
+    pub bytes: Vec<u8>,
+    pub promise: Arc<Promise>,
+    stride: usize,
+    cursor: usize,
+}
+
+impl UploadJob {
+    /// Creates a new job. Slices are always multiple of `stride` bytes, and the
+    /// whole job will be submitted at once if `stride` is zero.
+    pub fn new(target: UploadTarget, bytes: Vec<u8>, stride: usize) -> Self {
+        UploadJob {
+            target: target,
+            bytes: bytes,
+            promise: Arc::new(Promise::new()),
+            stride: stride,
+            cursor: 0,
+        }
+    }
+
+    #[inline]
+    pub fn remains(&self) -> usize {
+        self.bytes.len() - self.cursor
+    }
+
+    /// Takes the next slice which fits into `budget`. At least one stride will be
+    /// taken to make progress even if it exceeds the budget.
+    pub fn next_slice(&mut self, budget: usize) -> Option<(UploadTarget, Range<usize>)> {
+        let remains = self.remains();
+        if remains == 0 {
+            return None;
+        }
+
+        let len = if self.stride == 0 {
+            remains
+        } else {
+            let len = (budget / self.stride).max(1) * self.stride;
+            len.min(remains)
+        };
+
+        let range = self.cursor..(self.cursor + len);
+        self.cursor += len;
+
+        let target = match self.target {
+            UploadTarget::Texture(handle, area) if self.stride > 0 => {
+                let (min, max) = (area.min(), area.max());
+                let r0 = (range.start / self.stride) as u32;
+                let r1 = (range.end / self.stride) as u32;
+                let area = math::Aabb2::new(
+                    math::Point2::new(min.x, min.y + r0),
+                    math::Point2::new(max.x, (min.y + r1).min(max.y)),
+                );
+
+                UploadTarget::Texture(handle, area)
+            }
+            UploadTarget::Texture(handle, area) => UploadTarget::Texture(handle, area),
+            UploadTarget::VertexBuffer(handle, offset) => {
+                UploadTarget::VertexBuffer(handle, offset + range.start)
+            }
+            UploadTarget::IndexBuffer(handle, offset) => {
+                UploadTarget::IndexBuffer(handle, offset + range.start)
+            }
+        };
+
+        Some((target, range))
+    }
+}
+
+pub(crate) struct UploadQueue {
+    pub budget: usize,
+    pub jobs: VecDeque<UploadJob>,
+}
+
+impl UploadQueue {
+    pub fn new() -> Self {
+        UploadQueue {
+            budget: DEFAULT_UPLOAD_BUDGET,
+            jobs: VecDeque::new(),
+        }
+    }
+
+    /// Gets the number of bytes that are waiting to be uploaded.
+    pub fn remains(&self) -> usize {
+        self.jobs.iter().map(|v| v.remains()).sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sched::latch::LatchProbe;
+    use video::assets::texture::*;
+    use video::VideoSystem;
+
+    #[test]
+    fn texture_slices() {
+        let handle = TextureHandle::default();
+        let area = math::Aabb2::new(math::Point2::new(2, 4), math::Point2::new(6, 8));
+        let target = UploadTarget::Texture(handle, area);
+
+        // 4x4 pixels of RGBA8, which makes 16 bytes per row.
+        let mut job = UploadJob::new(target, vec![0; 64], 16);
+
+        let (t, r) = job.next_slice(40).unwrap();
+        assert_eq!(r, 0..32);
+        assert_eq!(
+            t,
+            UploadTarget::Texture(
+                handle,
+                math::Aabb2::new(math::Point2::new(2, 4), math::Point2::new(6, 6))
+            )
+        );
+
+        // Always makes progress, even if the budget is smaller than a row.
+        let (t, r) = job.next_slice(1).unwrap();
+        assert_eq!(r, 32..48);
+        assert_eq!(
+            t,
+            UploadTarget::Texture(
+                handle,
+                math::Aabb2::new(math::Point2::new(2, 6), math::Point2::new(6, 7))
+            )
+        );
+
+        let (_, r) = job.next_slice(1024).unwrap();
+        assert_eq!(r, 48..64);
+        assert_eq!(job.remains(), 0);
+        assert!(job.next_slice(1024).is_none());
+    }
+
+    #[test]
+    fn buffer_slices() {
+        let handle = MeshHandle::default();
+        let mut job = UploadJob::new(UploadTarget::VertexBuffer(handle, 8), vec![0; 10], 1);
+
+        let (t, r) = job.next_slice(4).unwrap();
+        assert_eq!(r, 0..4);
+        assert_eq!(t, UploadTarget::VertexBuffer(handle, 8));
+
+        let (t, r) = job.next_slice(4).unwrap();
+        assert_eq!(r, 4..8);
+        assert_eq!(t, UploadTarget::VertexBuffer(handle, 12));
+
+        let (t, r) = job.next_slice(4).unwrap();
+        assert_eq!(r, 8..10);
+        assert_eq!(t, UploadTarget::VertexBuffer(handle, 16));
+        assert!(job.next_slice(4).is_none());
+    }
+
+    #[test]
+    fn whole() {
+        let handle = TextureHandle::default();
+        let area = math::Aabb2::new(math::Point2::new(0, 0), math::Point2::new(4, 4));
+        let mut job = UploadJob::new(UploadTarget::Texture(handle, area), vec![0; 8], 0);
+
+        let (t, r) = job.next_slice(1).unwrap();
+        assert_eq!(r, 0..8);
+        assert_eq!(t, UploadTarget::Texture(handle, area));
+        assert!(job.next_slice(1).is_none());
+    }
+
+    #[test]
+    fn queue() {
+        let video = VideoSystem::headless(None);
+        let shared = video.shared();
+
+        let mut params = TextureParams::default();
+        params.hint = TextureHint::Dynamic;
+        params.dimensions = math::Vector2::new(4, 4);
+        let texture = shared.create_texture(params, None).unwrap();
+
+        let area = math::Aabb2::new(math::Point2::new(0, 0), math::Point2::new(4, 2));
+        assert!(shared.upload_texture(texture, area, vec![0; 64]).is_err());
+        let big = math::Aabb2::new(math::Point2::new(0, 0), math::Point2::new(4, 8));
+        assert!(shared.upload_texture(texture, big, vec![0; 128]).is_err());
+
+        shared.set_upload_budget(16);
+        let area = math::Aabb2::new(math::Point2::new(0, 0), math::Point2::new(4, 4));
+        let p1 = shared.upload_texture(texture, area, vec![0; 64]).unwrap();
+        let p2 = shared.upload_texture(texture, area, vec![0; 64]).unwrap();
+        assert_eq!(shared.pending_uploads(), 128);
+
+        for _ in 0..4 {
+            assert!(!p1.is_set());
+            video.swap_frames();
+        }
+
+        assert!(p1.is_set());
+        assert!(p1.result().is_ok());
+        assert_eq!(shared.pending_uploads(), 64);
+
+        video.swap_frames();
+        shared.delete_texture(texture);
+        video.swap_frames();
+        assert!(p2.is_set());
+        assert!(p2.result().is_err());
+        assert_eq!(shared.pending_uploads(), 0);
+    }
+}
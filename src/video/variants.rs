@@ -0,0 +1,248 @@
+//! Shader permutations that are selected by keywords and compiled on demand.
+//!
+//! Instead of maintaining a copy of the shader source for every combination of
+//! features, the source is written once with `#ifdef` blocks, and every keyword
+//! enabled for a variant is injected as a `#define` right after the `#version`
+//! directive.
+//!
+//! ```rust
+//! use crayon::video::prelude::*;
+//! let video = VideoSystem::headless(None).shared();
+//!
+//! let vs = "#version 100\nvoid main() { gl_Position = vec4(0.0); }".to_owned();
+//! let fs = "#version 100\nvoid main() { gl_FragColor = vec4(1.0); }".to_owned();
+//! let params = ShaderParams::default();
+//!
+//! let variants = ShaderVariants::new(video, params, vs, fs, &["FOG", "NORMAL_MAP"]).unwrap();
+//! let keywords = variants.keywords(&["FOG"]).unwrap();
+//!
+//! // The first request compiles the variant, the following ones hit the cache.
+//! let shader = variants.variant(keywords).unwrap();
+//! assert_eq!(variants.variant(keywords).unwrap(), shader);
+//! ```
+
+use std::sync::{Arc, Mutex};
+
+use utils::hash::FastHashMap;
+
+use super::assets::shader::{ShaderHandle, ShaderParams};
+use super::errors::*;
+use super::{VideoSystemShared, MAX_SHADER_KEYWORDS};
+
+/// A set of enabled keywords. It's represented as a bit mask of the keyword indices
+/// declared in `ShaderVariants`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ShaderKeywords(u32);
+
+impl ShaderKeywords {
+    /// Creates a empty keyword set.
+    #[inline]
+    pub fn empty() -> Self {
+        ShaderKeywords(0)
+    }
+
+    /// Enables the keyword at `index`.
+    #[inline]
+    pub fn enable(&mut self, index: usize) {
+        assert!(index < MAX_SHADER_KEYWORDS);
+        self.0 |= 1 << index;
+    }
+
+    /// Disables the keyword at `index`.
+    #[inline]
+    pub fn disable(&mut self, index: usize) {
+        assert!(index < MAX_SHADER_KEYWORDS);
+        self.0 &= !(1 << index);
+    }
+
+    /// Returns true if the keyword at `index` is enabled.
+    #[inline]
+    pub fn is_enabled(&self, index: usize) -> bool {
+        index < MAX_SHADER_KEYWORDS && (self.0 & (1 << index)) != 0
+    }
+}
+
+/// A shader with a fixed list of keywords, whose variants are compiled lazily and
+/// cached until `ShaderVariants` is dropped.
+pub struct ShaderVariants {
+    video: Arc<VideoSystemShared>,
+    params: ShaderParams,
+    vs: String,
+    fs: String,
+    keywords: Vec<String>,
+    variants: Mutex<FastHashMap<ShaderKeywords, ShaderHandle>>,
+}
+
+impl ShaderVariants {
+    /// Declares a shader that could be specialized with `keywords`. Nothing will be
+    /// compiled until the first variant is requested.
+    pub fn new<T: AsRef<str>>(
+        video: Arc<VideoSystemShared>,
+        params: ShaderParams,
+        vs: String,
+        fs: String,
+        keywords: &[T],
+    ) -> Result<Self> {
+        params.validate(&vs, &fs)?;
+
+        if keywords.len() > MAX_SHADER_KEYWORDS {
+            return Err(Error::ShaderInvalid(format!(
+                "Too many keywords (> {:?}).",
+                MAX_SHADER_KEYWORDS
+            )));
+        }
+
+        Ok(ShaderVariants {
+            video: video,
+            params: params,
+            vs: vs,
+            fs: fs,
+            keywords: keywords.iter().map(|v| v.as_ref().to_owned()).collect(),
+            variants: Mutex::new(FastHashMap::default()),
+        })
+    }
+
+    /// Gets the declared keywords.
+    #[inline]
+    pub fn declared(&self) -> &[String] {
+        &self.keywords
+    }
+
+    /// Converts keyword names into `ShaderKeywords`.
+    pub fn keywords<T: AsRef<str>>(&self, names: &[T]) -> Result<ShaderKeywords> {
+        let mut keywords = ShaderKeywords::empty();
+        for v in names {
+            match self.keywords.iter().position(|k| k == v.as_ref()) {
+                Some(index) => keywords.enable(index),
+                None => return Err(Error::KeywordUndefined(v.as_ref().to_owned())),
+            }
+        }
+
+        Ok(keywords)
+    }
+
+    /// Gets the shader variant with `keywords` enabled, compiling it if this is
+    /// the first time that it has been requested.
+    pub fn variant(&self, keywords: ShaderKeywords) -> Result<ShaderHandle> {
+        let mut variants = self.variants.lock().unwrap();
+        if let Some(&handle) = variants.get(&keywords) {
+            return Ok(handle);
+        }
+
+        let mut defines = String::new();
+        for (i, v) in self.keywords.iter().enumerate() {
+            if keywords.is_enabled(i) {
+                defines.push_str("#define ");
+                defines.push_str(v);
+                defines.push('\n');
+            }
+        }
+
+        let vs = inject(&self.vs, &defines);
+        let fs = inject(&self.fs, &defines);
+        let handle = self.video.create_shader(self.params.clone(), vs, fs)?;
+        variants.insert(keywords, handle);
+        Ok(handle)
+    }
+
+    /// Gets the number of variants that have been compiled.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.variants.lock().unwrap().len()
+    }
+
+    /// Returns true if no variant has been compiled yet.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.variants.lock().unwrap().is_empty()
+    }
+}
+
+impl Drop for ShaderVariants {
+    fn drop(&mut self) {
+        for (_, handle) in self.variants.lock().unwrap().drain() {
+            self.video.delete_shader(handle);
+        }
+    }
+}
+
+/// Inserts `defines` into the shader source. The `#version` directive must be the
+/// first statement of a shader, so the defines goes right after it if there is one.
+fn inject(source: &str, defines: &str) -> String {
+    let trimmed = source.trim_start();
+    if trimmed.starts_with("#version") {
+        let (version, body) = match trimmed.find('\n') {
+            Some(index) => trimmed.split_at(index + 1),
+            None => (trimmed, ""),
+        };
+
+        format!("{}\n{}{}", version.trim_end(), defines, body)
+    } else {
+        format!("{}{}", defines, source)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use video::VideoSystem;
+
+    #[test]
+    fn keywords() {
+        let mut keywords = ShaderKeywords::empty();
+        assert!(!keywords.is_enabled(0));
+
+        keywords.enable(0);
+        keywords.enable(31);
+        assert!(keywords.is_enabled(0));
+        assert!(keywords.is_enabled(31));
+        assert!(!keywords.is_enabled(1));
+        assert!(!keywords.is_enabled(32));
+
+        keywords.disable(0);
+        assert!(!keywords.is_enabled(0));
+        assert!(keywords.is_enabled(31));
+    }
+
+    #[test]
+    fn injection() {
+        let vs = "\n  #version 100\nvoid main() {}";
+        assert_eq!(inject(vs, ""), "#version 100\nvoid main() {}");
+        assert_eq!(
+            inject(vs, "#define FOG\n"),
+            "#version 100\n#define FOG\nvoid main() {}"
+        );
+
+        assert_eq!(inject("#version 100", "#define FOG\n"), "#version 100\n#define FOG\n");
+        assert_eq!(inject("void main() {}", "#define FOG\n"), "#define FOG\nvoid main() {}");
+    }
+
+    #[test]
+    fn variants() {
+        let video = VideoSystem::headless(None).shared();
+        let vs = "#version 100\nvoid main() {}".to_owned();
+        let fs = "#version 100\nvoid main() {}".to_owned();
+        let params = ShaderParams::default();
+
+        let variants =
+            ShaderVariants::new(video.clone(), params, vs, fs, &["SKINNED", "FOG"]).unwrap();
+        assert_eq!(variants.declared(), &["SKINNED".to_owned(), "FOG".to_owned()]);
+        assert!(variants.keywords(&["NORMAL_MAP"]).is_err());
+
+        let k1 = variants.keywords(&["FOG"]).unwrap();
+        let k2 = variants.keywords(&["FOG", "SKINNED"]).unwrap();
+        let k3 = variants.keywords(&["SKINNED", "FOG"]).unwrap();
+        assert_eq!(k2, k3);
+
+        let s1 = variants.variant(k1).unwrap();
+        let s2 = variants.variant(k2).unwrap();
+        assert!(s1 != s2);
+        assert_eq!(variants.variant(k3).unwrap(), s2);
+        assert_eq!(variants.len(), 2);
+        assert!(video.shader(s1).is_some());
+
+        drop(variants);
+        assert!(video.shader(s1).is_none());
+        assert!(video.shader(s2).is_none());
+    }
+}
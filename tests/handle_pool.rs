@@ -131,3 +131,52 @@ fn iter() {
         assert_eq!(*handle, iter.next().unwrap());
     }
 }
+
+#[test]
+fn delayed_reuse() {
+    let config = HandlePoolConfig {
+        min_frees: 2,
+        ..Default::default()
+    };
+
+    let mut set: HandlePool<Handle> = HandlePool::with_config(config);
+    let v: Vec<_> = (0..4).map(|_| set.create()).collect();
+
+    set.free(v[0]);
+    set.free(v[1]);
+    assert_eq!(set.len(), 2);
+    assert_eq!(set.create().index(), 4);
+
+    // The first freed index is reused after 2 more frees.
+    set.free(v[2]);
+    let e = set.create();
+    assert_eq!(e.index(), 0);
+    assert!(!set.is_alive(v[0]));
+    assert!(set.is_alive(e));
+    assert_eq!(set.len(), 3);
+}
+
+#[test]
+fn version_bits() {
+    let config = HandlePoolConfig {
+        min_frees: 0,
+        version_bits: 3,
+    };
+
+    let mut set: HandlePool<Handle> = HandlePool::with_config(config);
+    let mut v = vec![];
+    for version in &[1, 3, 5, 7] {
+        let e = set.create();
+        assert_eq!(e.index(), 0);
+        assert_eq!(e.version(), *version);
+        set.free(e);
+        v.push(e);
+    }
+
+    // The index is retired instead of wrapping its version around.
+    let e = set.create();
+    assert_eq!(e.index(), 1);
+    assert!(v.iter().all(|&v| !set.is_alive(v)));
+    assert_eq!(set.len(), 1);
+    assert_eq!(set.iter().collect::<Vec<_>>(), vec![e]);
+}
@@ -0,0 +1,286 @@
+extern crate crayon;
+extern crate rand;
+
+use rand::{Rng, SeedableRng, StdRng};
+
+use crayon::bincode;
+use crayon::res::utils;
+use crayon::res::vfs::manifest::{Manifest, ManifestItem, MAGIC};
+use crayon::uuid::Uuid;
+use crayon::video::assets::mesh::MeshData;
+use crayon::video::assets::{texture_container, texture_hdr};
+
+const DDS_MAGIC: &[u8] = b"DDS ";
+const KTX2_MAGIC: &[u8] = &[
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+const RADIANCE_MAGIC: &[u8] = b"#?RADIANCE\n";
+const EXR_MAGIC: &[u8] = &[0x76, 0x2f, 0x31, 0x01];
+
+fn rng() -> StdRng {
+    StdRng::from_seed([7; 32])
+}
+
+fn random_bytes(rng: &mut StdRng, prefix: &[u8]) -> Vec<u8> {
+    let len = rng.gen_range(0, 512);
+    let mut bytes = prefix.to_vec();
+    bytes.extend((0..len).map(|_| rng.gen::<u8>()));
+    bytes
+}
+
+fn mutate(rng: &mut StdRng, bytes: &[u8]) -> Vec<u8> {
+    let mut bytes = bytes.to_vec();
+    for _ in 0..rng.gen_range(1, 8) {
+        let i = rng.gen_range(0, bytes.len());
+        bytes[i] = rng.gen::<u8>();
+    }
+
+    let len = rng.gen_range(0, bytes.len() + 1);
+    bytes.truncate(len);
+    bytes
+}
+
+fn manifest() -> Vec<u8> {
+    let mut manifest = Manifest::new();
+    let filename = manifest.buf.extend_from_str("a.prefab");
+    let dependencies = manifest.buf.extend_from_slice(&[1usize]);
+    manifest.items.push(ManifestItem {
+        filename: filename,
+        dependencies: dependencies,
+        uuid: Uuid::from_bytes([1; 16]),
+    });
+
+    let filename = manifest.buf.extend_from_str("b.mesh");
+    let dependencies = manifest.buf.extend_from_slice::<usize>(&[]);
+    manifest.items.push(ManifestItem {
+        filename: filename,
+        dependencies: dependencies,
+        uuid: Uuid::from_bytes([2; 16]),
+    });
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend(bincode::serialize(&manifest).unwrap());
+    bytes
+}
+
+#[test]
+fn textures() {
+    let mut rng = rng();
+    for _ in 0..1000 {
+        let _ = texture_container::decode_dds(&random_bytes(&mut rng, DDS_MAGIC));
+        let _ = texture_container::decode_ktx2(&random_bytes(&mut rng, KTX2_MAGIC));
+        let _ = texture_hdr::decode_radiance(&random_bytes(&mut rng, RADIANCE_MAGIC));
+        let _ = texture_hdr::decode_exr(&random_bytes(&mut rng, EXR_MAGIC));
+    }
+}
+
+#[test]
+fn manifests() {
+    let bytes = manifest();
+    let v = Manifest::load_from(&mut bytes.as_slice()).unwrap();
+    assert_eq!(v.filename(Uuid::from_bytes([2; 16])), Some("b.mesh"));
+
+    for i in 0..bytes.len() {
+        assert!(Manifest::load_from(&mut &bytes[0..i]).is_err());
+    }
+
+    let mut rng = rng();
+    for _ in 0..1000 {
+        let _ = Manifest::load_from(&mut mutate(&mut rng, &bytes).as_slice());
+        let _ = Manifest::load_from(&mut random_bytes(&mut rng, &MAGIC).as_slice());
+    }
+}
+
+#[test]
+fn dangling_dependencies() {
+    let mut manifest = Manifest::new();
+    let filename = manifest.buf.extend_from_str("a.prefab");
+    let dependencies = manifest.buf.extend_from_slice(&[3usize]);
+    manifest.items.push(ManifestItem {
+        filename: filename,
+        dependencies: dependencies,
+        uuid: Uuid::nil(),
+    });
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend(bincode::serialize(&manifest).unwrap());
+    assert!(Manifest::load_from(&mut bytes.as_slice()).is_err());
+}
+
+#[test]
+fn length_prefixes() {
+    let data = MeshData {
+        vptr: vec![0; 64].into_boxed_slice(),
+        iptr: vec![0; 12].into_boxed_slice(),
+    };
+
+    let bytes = bincode::serialize(&data).unwrap();
+    let v: MeshData = utils::deserialize_from(bytes.as_slice(), bytes.len() as u64).unwrap();
+    assert_eq!(v.vptr.len(), 64);
+
+    // A corrupted length prefix must not allocate more than the size of input.
+    let mut corrupted = bytes.clone();
+    corrupted[0..8].copy_from_slice(&[0xFF; 8]);
+    let v: Result<MeshData, _> = utils::deserialize_from(corrupted.as_slice(), bytes.len() as u64);
+    assert!(v.is_err());
+
+    let mut rng = rng();
+    for _ in 0..1000 {
+        let bytes = mutate(&mut rng, &bytes);
+        let _: Result<MeshData, _> = utils::deserialize_from(bytes.as_slice(), bytes.len() as u64);
+    }
+}
+
+fn le(v: u32) -> [u8; 4] {
+    [v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8]
+}
+
+fn exr(window: [i32; 4], attributes: &[(&str, &str, u32, &[u8])]) -> Vec<u8> {
+    let mut bytes = EXR_MAGIC.to_vec();
+    bytes.extend_from_slice(&le(2));
+
+    let mut channels = b"R\0".to_vec();
+    channels.extend_from_slice(&le(1));
+    channels.extend_from_slice(&[0, 0, 0, 0]);
+    channels.extend_from_slice(&le(1));
+    channels.extend_from_slice(&le(1));
+    channels.push(0);
+
+    let mut box2i = Vec::new();
+    for v in &window {
+        box2i.extend_from_slice(&le(*v as u32));
+    }
+
+    let mut push = |name: &str, kind: &str, size: u32, value: &[u8]| {
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(kind.as_bytes());
+        bytes.push(0);
+        bytes.extend_from_slice(&le(size));
+        bytes.extend_from_slice(value);
+    };
+
+    push("channels", "chlist", channels.len() as u32, &channels);
+    push("compression", "compression", 1, &[0]);
+    push("dataWindow", "box2i", 16, &box2i);
+    for &(name, kind, size, value) in attributes {
+        push(name, kind, size, value);
+    }
+
+    bytes.push(0);
+    bytes
+}
+
+#[test]
+fn hdr_headers() {
+    // Dimensions that overflow, or exceed what the file could hold.
+    for resolution in &["-Y 4294967295 +X 4294967295", "-Y 100000 +X 100000"] {
+        let mut bytes = RADIANCE_MAGIC.to_vec();
+        bytes.extend_from_slice(format!("\n{}\n", resolution).as_bytes());
+        bytes.extend_from_slice(&[0; 16]);
+        assert!(texture_hdr::decode_radiance(&bytes).is_err());
+    }
+
+    // Consecutive runs of the old encoding with ever-growing shifts. Empty runs fit in
+    // any scanline, so only the shift guard stops them before the shift overflows.
+    let mut bytes = RADIANCE_MAGIC.to_vec();
+    bytes.extend_from_slice(b"\n-Y 1 +X 8\n");
+    bytes.extend_from_slice(&[1, 2, 3, 4]);
+    for _ in 0..16 {
+        bytes.extend_from_slice(&[1, 1, 1, 0]);
+    }
+    assert!(texture_hdr::decode_radiance(&bytes).is_err());
+
+    // A valid header decodes, so the cases below fail for the right reasons.
+    let mut bytes = exr([0, 0, 0, 0], &[]);
+    let offset = bytes.len() as u32 + 8;
+    bytes.extend_from_slice(&le(offset));
+    bytes.extend_from_slice(&le(0));
+    bytes.extend_from_slice(&le(0));
+    bytes.extend_from_slice(&le(2));
+    bytes.extend_from_slice(&[0x00, 0x3c]);
+    assert!(texture_hdr::decode_exr(&bytes).is_ok());
+
+    // Negative, oversized and overflowing sizes of attributes.
+    for &size in &[0xFFFF_FFFF, 0x7FFF_FFFF, 64] {
+        let bytes = exr([0, 0, 0, 0], &[("pad", "int", size, &[0; 4])]);
+        assert!(texture_hdr::decode_exr(&bytes).is_err());
+    }
+
+    // Data windows that overflow, or exceed what the file could hold.
+    let min = ::std::i32::MIN;
+    let max = ::std::i32::MAX;
+    for &window in &[[min, min, max, max], [0, 0, max, max], [0, 0, 4095, 4095]] {
+        let mut bytes = exr(window, &[]);
+        bytes.extend_from_slice(&[0; 64]);
+        assert!(texture_hdr::decode_exr(&bytes).is_err());
+    }
+
+    // Scanline offsets out of the file.
+    let mut bytes = exr([0, 0, 0, 0], &[]);
+    bytes.extend_from_slice(&[0xFF; 8]);
+    assert!(texture_hdr::decode_exr(&bytes).is_err());
+}
+
+fn put(bytes: &mut Vec<u8>, offset: usize, v: u32) {
+    if bytes.len() < offset + 4 {
+        bytes.resize(offset + 4, 0);
+    }
+
+    bytes[offset..(offset + 4)].copy_from_slice(&le(v));
+}
+
+fn dds(width: u32, height: u32, levels: u32) -> Vec<u8> {
+    let mut bytes = DDS_MAGIC.to_vec();
+    put(&mut bytes, 4, 124);
+    put(&mut bytes, 8, 0x2_1007);
+    put(&mut bytes, 12, height);
+    put(&mut bytes, 16, width);
+    put(&mut bytes, 28, levels);
+    put(&mut bytes, 80, 0x4);
+    bytes.resize(128, 0);
+    bytes[84..88].copy_from_slice(b"DXT1");
+    bytes.extend_from_slice(&[0; 64]);
+    bytes
+}
+
+fn ktx2(width: u32, height: u32, levels: u32) -> Vec<u8> {
+    let mut bytes = KTX2_MAGIC.to_vec();
+    put(&mut bytes, 12, 37);
+    put(&mut bytes, 20, width);
+    put(&mut bytes, 24, height);
+    put(&mut bytes, 40, levels);
+    bytes.resize(128, 0);
+    bytes
+}
+
+#[test]
+fn container_headers() {
+    // Valid headers decode, so the cases below fail for the right reasons.
+    assert!(texture_container::decode_dds(&dds(4, 4, 1)).is_ok());
+    let mut bytes = ktx2(1, 1, 1);
+    put(&mut bytes, 80, 128);
+    put(&mut bytes, 88, 4);
+    bytes.extend_from_slice(&[0; 4]);
+    assert!(texture_container::decode_ktx2(&bytes).is_ok());
+
+    // More mipmap levels than a full chain.
+    assert!(texture_container::decode_dds(&dds(4, 4, 4)).is_err());
+    assert!(texture_container::decode_dds(&dds(4, 4, 0xFFFF_FFFF)).is_err());
+    assert!(texture_container::decode_ktx2(&ktx2(4, 4, 0xFFFF_FFFF)).is_err());
+
+    // Dimensions whose level sizes overflow.
+    assert!(texture_container::decode_dds(&dds(0xFFFF_FFFF, 0xFFFF_FFFF, 1)).is_err());
+    let mut bytes = ktx2(0xFFFF_FFFF, 0xFFFF_FFFF, 1);
+    put(&mut bytes, 80, 128);
+    put(&mut bytes, 88, 0xFFFF_FFFC);
+    put(&mut bytes, 92, 0xFFFF_FFFF);
+    assert!(texture_container::decode_ktx2(&bytes).is_err());
+
+    // Offsets that overflow when the length is added.
+    let mut bytes = ktx2(1, 1, 1);
+    put(&mut bytes, 80, 0xFFFF_FFFF);
+    put(&mut bytes, 84, 0xFFFF_FFFF);
+    put(&mut bytes, 88, 4);
+    assert!(texture_container::decode_ktx2(&bytes).is_err());
+}
@@ -1,12 +1,23 @@
 extern crate crayon;
 extern crate rand;
+extern crate zip;
 
+use std::io::Write;
+use std::path::Path;
 use std::sync::mpsc;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
+use crayon::bincode;
+use crayon::errors::*;
 use crayon::res::prelude::*;
+use crayon::res::registry::{CachePolicy, Eviction, Register, Registry};
+use crayon::res::vfs::manifest::{self, Manifest, ManifestItem};
+use crayon::res::vfs::VFS;
+use crayon::res::Loader;
+use crayon::utils::Handle;
+use crayon::uuid::Uuid;
 
 fn testbed() -> Arc<ResourceSystemShared> {
     let dir = ::std::env::current_dir()
@@ -56,3 +67,924 @@ fn from_unknown_vfs() {
 
     video.create_texture_from("unknown:crate.bmp").unwrap();
 }
+
+struct Memory(Vec<u8>);
+
+impl Memory {
+    fn new(uuid: Uuid) -> Self {
+        Memory::with_items(&[("a", uuid, &[])])
+    }
+
+    fn with_items(items: &[(&str, Uuid, &[usize])]) -> Self {
+        let mut manifest = Manifest::new();
+        for &(filename, uuid, dependencies) in items {
+            let filename = manifest.buf.extend_from_str(filename);
+            let dependencies = manifest.buf.extend_from_slice(dependencies);
+            manifest.items.push(ManifestItem {
+                filename: filename,
+                dependencies: dependencies,
+                uuid: uuid,
+            });
+        }
+
+        let mut bytes = manifest::MAGIC.to_vec();
+        bytes.extend(bincode::serialize(&manifest).unwrap());
+        Memory(bytes)
+    }
+}
+
+impl VFS for Memory {
+    fn read_to_end(&self, location: &Path, buf: &mut Vec<u8>) -> Result<usize> {
+        if location == Path::new(manifest::NAME) {
+            buf.extend_from_slice(&self.0);
+            Ok(self.0.len())
+        } else {
+            Ok(0)
+        }
+    }
+
+    fn is_dir(&self, _: &Path) -> bool {
+        false
+    }
+
+    fn exists(&self, _: &Path) -> bool {
+        true
+    }
+
+    fn modified_since(&self, _: &Path, _: SystemTime) -> bool {
+        false
+    }
+}
+
+struct PanicLoader(bool);
+
+impl Loader for PanicLoader {
+    fn load(&self, _: &[u8]) -> Result<()> {
+        if self.0 {
+            panic!("corrupted");
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn panic_isolation() {
+    let uuid = Uuid::from_bytes([1; 16]);
+    let sched = crayon::sched::ScheduleSystem::new(2, None, None);
+    let mut res = ResourceSystem::new(sched.shared()).unwrap();
+    res.mount("mem", Memory::new(uuid)).unwrap();
+    let res = res.shared();
+
+    let promise = res.load_from_uuid(PanicLoader(true), uuid).unwrap();
+    sched.shared().wait_until(promise.as_ref());
+    let err = promise.result().unwrap_err();
+    assert!(format!("{}", err.find_root_cause()).contains("corrupted"));
+
+    // The workers are still alive.
+    let promise = res.load_from_uuid(PanicLoader(false), uuid).unwrap();
+    sched.shared().wait_until(promise.as_ref());
+    assert!(promise.result().is_ok());
+}
+
+#[test]
+fn futures() {
+    let uuid = Uuid::from_bytes([3; 16]);
+    let sched = crayon::sched::ScheduleSystem::new(1, None, None);
+    let mut res = ResourceSystem::new(sched.shared()).unwrap();
+    res.mount("mem", Memory::new(uuid)).unwrap();
+    let res = res.shared();
+
+    let promise = res.load_from_uuid(PanicLoader(false), uuid).unwrap();
+    assert!(block_on(PromiseFuture::from(promise)).is_ok());
+
+    let promise = res.load_from_uuid(PanicLoader(true), uuid).unwrap();
+    assert!(block_on(PromiseFuture::from(promise)).is_err());
+
+    // Resources that are not being loaded resolve immediately.
+    assert!(res.promise(uuid).is_none());
+    assert!(block_on(PromiseFuture::new(None)).is_ok());
+
+    let registry = Registry::new(res.clone(), TextRegister::default());
+    let handle = registry.create_from("mem:a").unwrap();
+    assert!(block_on(registry.future(handle)).is_ok());
+    assert_eq!(registry.get(handle, |v| v.clone()), Some("".to_owned()));
+}
+
+#[test]
+fn retain_and_release() {
+    use crayon::video::assets::texture::TextureParams;
+
+    let video = crayon::video::VideoSystem::headless(None).shared();
+    let handle = video
+        .create_texture(TextureParams::default(), None)
+        .unwrap();
+
+    assert!(video.retain_texture(handle, "ui"));
+    assert!(video.retain_texture(handle, "ui"));
+    assert!(video.retain_texture(handle, "world"));
+    assert_eq!(video.texture_holders(handle), vec![("ui", 2), ("world", 1)]);
+
+    // Releasing by a system that never retained it is ignored.
+    video.release_texture(handle, "audio");
+    video.delete_texture(handle);
+    video.release_texture(handle, "ui");
+    video.release_texture(handle, "world");
+    assert_eq!(video.texture_holders(handle), vec![("ui", 1)]);
+    assert!(video.texture(handle).is_some());
+
+    video.release_texture(handle, "ui");
+    assert!(video.texture(handle).is_none());
+    assert!(!video.retain_texture(handle, "ui"));
+}
+
+#[test]
+fn dependency_graph() {
+    let uuids: Vec<_> = (0..4).map(|i| Uuid::from_bytes([i; 16])).collect();
+    let sched = crayon::sched::ScheduleSystem::new(1, None, None);
+    let mut res = ResourceSystem::new(sched.shared()).unwrap();
+    let items: [(&str, Uuid, &[usize]); 4] = [
+        ("scene.prefab", uuids[0], &[1, 2]),
+        ("mesh", uuids[1], &[2]),
+        ("\"quoted\".png", uuids[2], &[]),
+        ("unused", uuids[3], &[]),
+    ];
+
+    res.mount("mem", Memory::with_items(&items)).unwrap();
+    let res = res.shared();
+
+    assert_eq!(res.dependencies(uuids[0]), vec![uuids[1], uuids[2]]);
+    assert_eq!(res.filename(uuids[1]), Some("mesh".to_owned()));
+
+    let mut graph = Vec::new();
+    res.write_dependency_graph(&uuids[0..1], &mut graph)
+        .unwrap();
+    let graph = String::from_utf8(graph).unwrap();
+    let lines: Vec<_> = graph.lines().collect();
+
+    assert_eq!(lines.len(), 8);
+    assert_eq!(lines[0], "digraph resources {");
+    assert_eq!(
+        lines[1],
+        format!("    \"{}\" [label=\"scene.prefab\"];", uuids[0])
+    );
+    assert_eq!(
+        lines[2],
+        format!("    \"{}\" -> \"{}\";", uuids[0], uuids[1])
+    );
+    assert!(graph.contains("[label=\"\\\"quoted\\\".png\"]"));
+    assert!(!graph.contains(&format!("{}", uuids[3])));
+}
+
+// A file that could be edited, with the manifest of `Memory`.
+#[derive(Clone)]
+struct Editable(Arc<Vec<u8>>, Arc<Mutex<(Vec<u8>, SystemTime)>>);
+
+impl Editable {
+    fn edit(&self, bytes: &[u8]) {
+        thread::sleep(Duration::from_millis(5));
+        let mut file = self.1.lock().unwrap();
+        file.0 = bytes.to_vec();
+        file.1 = SystemTime::now();
+    }
+}
+
+impl VFS for Editable {
+    fn read_to_end(&self, location: &Path, buf: &mut Vec<u8>) -> Result<usize> {
+        let file = self.1.lock().unwrap();
+        let bytes = if location == Path::new(manifest::NAME) {
+            self.0.as_ref()
+        } else {
+            &file.0
+        };
+
+        buf.extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn is_dir(&self, _: &Path) -> bool {
+        false
+    }
+
+    fn exists(&self, _: &Path) -> bool {
+        true
+    }
+
+    fn modified_since(&self, _: &Path, ts: SystemTime) -> bool {
+        self.1.lock().unwrap().1 > ts
+    }
+
+    fn modified(&self, _: &Path) -> Option<SystemTime> {
+        Some(self.1.lock().unwrap().1)
+    }
+}
+
+// Parses the file as a string, and records the detached values.
+#[derive(Clone, Default)]
+struct TextRegister(Arc<Mutex<Vec<String>>>);
+
+impl Register for TextRegister {
+    type Handle = Handle;
+    type Intermediate = String;
+    type Value = String;
+
+    fn load(&self, _: Handle, bytes: &[u8]) -> Result<String> {
+        Ok(String::from_utf8(bytes.to_vec())?)
+    }
+
+    fn attach(&self, _: Handle, item: String) -> Result<String> {
+        if item == "unattachable" {
+            let err = ::std::io::Error::new(::std::io::ErrorKind::InvalidData, item);
+            return Err(err.into());
+        }
+
+        Ok(item)
+    }
+
+    fn detach(&self, _: Handle, value: String) {
+        self.0.lock().unwrap().push(value);
+    }
+}
+
+#[test]
+fn hot_reload() {
+    let uuid = Uuid::from_bytes([1; 16]);
+    let Memory(manifest) = Memory::new(uuid);
+    let file = Arc::new(Mutex::new((b"v1".to_vec(), SystemTime::now())));
+    let vfs = Editable(Arc::new(manifest), file);
+
+    let sched = crayon::sched::ScheduleSystem::new(1, None, None);
+    let mut res = ResourceSystem::new(sched.shared()).unwrap();
+    res.mount("mem", vfs.clone()).unwrap();
+    let res = res.shared();
+
+    // Resources loaded before hot reload is enabled are watched too.
+    let register = TextRegister::default();
+    let registry = Registry::new(res.clone(), register.clone());
+    let handle = registry.create_from("mem:a").unwrap();
+    registry.wait_until(handle).unwrap();
+    assert_eq!(registry.get(handle, |v| v.clone()), Some("v1".to_owned()));
+    res.set_hot_reload(true);
+    assert!(!res.is_modified(uuid));
+    assert!(res.reload_modified().is_empty());
+
+    // Disabling it stops polling only.
+    res.set_hot_reload(false);
+    vfs.edit(b"v1");
+    assert!(res.is_modified(uuid));
+    res.set_hot_reload(true);
+    assert_eq!(res.reload_modified(), vec![uuid]);
+    registry.wait_until(handle).unwrap();
+    assert!(!res.is_modified(uuid));
+    register.0.lock().unwrap().clear();
+
+    // The new value replaces the old one under the same handle.
+    vfs.edit(b"v2");
+    assert!(res.is_modified(uuid));
+    assert_eq!(res.reload_modified(), vec![uuid]);
+    registry.wait_until(handle).unwrap();
+    assert_eq!(registry.get(handle, |v| v.clone()), Some("v2".to_owned()));
+    assert_eq!(*register.0.lock().unwrap(), vec!["v1".to_owned()]);
+    assert!(!res.is_modified(uuid));
+
+    // A broken file keeps the old value. The reload might have finished before waiting,
+    // so the failure is told by the statistics.
+    vfs.edit(&[0xFF]);
+    let failed = res.load_stats().failed;
+    assert!(registry.reload(handle).is_ok());
+    let _ = registry.wait_until(handle);
+    assert_eq!(res.load_stats().failed, failed + 1);
+    assert_eq!(registry.get(handle, |v| v.clone()), Some("v2".to_owned()));
+
+    // So does a file that fails to attach.
+    vfs.edit(b"unattachable");
+    assert!(registry.reload(handle).is_ok());
+    let _ = registry.wait_until(handle);
+    assert_eq!(res.load_stats().failed, failed + 2);
+    assert_eq!(registry.get(handle, |v| v.clone()), Some("v2".to_owned()));
+    assert_eq!(register.0.lock().unwrap().len(), 1);
+
+    // Unloaded resources are not watched anymore.
+    registry.delete(handle);
+    assert_eq!(register.0.lock().unwrap().len(), 2);
+    vfs.edit(b"v3");
+    assert!(!res.is_modified(uuid));
+}
+
+#[test]
+fn zip_archive() {
+    use crayon::res::vfs::ZipFilesystem;
+    use zip::write::{FileOptions, ZipWriter};
+    use zip::CompressionMethod;
+
+    let uuid = Uuid::from_bytes([2; 16]);
+    let Memory(manifest) = Memory::new(uuid);
+    let location = format!("{:X}", uuid.to_simple());
+    let path = ::std::env::temp_dir().join("crayon-zip-archive.pak");
+
+    {
+        let mut zip = ZipWriter::new(::std::fs::File::create(&path).unwrap());
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        zip.start_file(manifest::NAME, stored).unwrap();
+        zip.write_all(&manifest).unwrap();
+        zip.start_file(location.as_str(), FileOptions::default())
+            .unwrap();
+        zip.write_all("crate ".repeat(256).as_bytes()).unwrap();
+        zip.start_file("textures/crate.bmp", FileOptions::default())
+            .unwrap();
+        zip.finish().unwrap();
+    }
+
+    let vfs = ZipFilesystem::new(&path).unwrap();
+    assert_eq!(vfs.len(), 3);
+    assert!(vfs.is_dir(Path::new("textures")));
+    assert!(vfs.exists(Path::new("textures/crate.bmp")));
+    assert!(!vfs.is_dir(Path::new("textures/crate.bmp")));
+    assert!(!vfs.exists(Path::new("crate.bmp")));
+
+    let mut buf = Vec::new();
+    assert!(vfs.read_to_end(Path::new("crate.bmp"), &mut buf).is_err());
+    assert_eq!(
+        vfs.read_to_end(Path::new(&location), &mut buf).unwrap(),
+        1536
+    );
+
+    let sched = crayon::sched::ScheduleSystem::new(1, None, None);
+    let mut res = ResourceSystem::new(sched.shared()).unwrap();
+    res.mount("pak", vfs).unwrap();
+    let res = res.shared();
+
+    let register = TextRegister::default();
+    let registry = Registry::new(res.clone(), register);
+    let handle = registry.create_from("pak:a").unwrap();
+    registry.wait_until(handle).unwrap();
+    assert_eq!(registry.get(handle, |v| v.len()), Some(1536));
+
+    ::std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn memory_filesystem() {
+    use zip::write::{FileOptions, ZipWriter};
+
+    let uuids: Vec<_> = (3..6).map(|i| Uuid::from_bytes([i; 16])).collect();
+    let mut vfs = MemoryFilesystem::new();
+    vfs.add_resource("a", uuids[0], &b"hello"[..]);
+    vfs.add_resource_with_dependencies("b", uuids[1], b"world".to_vec(), &uuids[0..1]);
+    vfs.insert("saves/slot0", &b"embedded"[..]);
+    assert_eq!(vfs.len(), 3);
+    assert!(vfs.is_dir(Path::new("saves")));
+    assert!(vfs.exists(Path::new("saves/slot0")));
+    assert!(!vfs.exists(Path::new("saves/slot1")));
+
+    let sched = crayon::sched::ScheduleSystem::new(1, None, None);
+    let mut res = ResourceSystem::new(sched.shared()).unwrap();
+    res.mount("mem", vfs).unwrap();
+
+    // Undefined dependencies are rejected when the manifest is generated.
+    let mut broken = MemoryFilesystem::new();
+    broken.add_resource_with_dependencies("c", uuids[2], &b""[..], &uuids[0..1]);
+    assert!(res.mount("broken", broken).is_err());
+
+    // The whole archive could be read from memory too.
+    let mut zip = ZipWriter::new(::std::io::Cursor::new(Vec::new()));
+    let Memory(manifest) = Memory::new(uuids[2]);
+    zip.start_file(manifest::NAME, FileOptions::default())
+        .unwrap();
+    zip.write_all(&manifest).unwrap();
+    zip.start_file(format!("{:X}", uuids[2].to_simple()), FileOptions::default())
+        .unwrap();
+    zip.write_all(b"zipped").unwrap();
+    let bytes = zip.finish().unwrap().into_inner();
+    res.mount("pak", ZipFilesystem::from_bytes(bytes).unwrap())
+        .unwrap();
+
+    let res = res.shared();
+    assert_eq!(res.dependencies(uuids[1]), vec![uuids[0]]);
+
+    let registry = Registry::new(res.clone(), TextRegister::default());
+    for &(location, text) in &[("mem:a", "hello"), ("mem:b", "world"), ("pak:a", "zipped")] {
+        let handle = registry.create_from(location).unwrap();
+        registry.wait_until(handle).unwrap();
+        assert_eq!(registry.get(handle, |v| v.clone()), Some(text.to_owned()));
+    }
+}
+
+#[test]
+#[cfg(feature = "http")]
+fn http_cache() {
+    use crayon::res::vfs::HttpFilesystem;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    // Serves `n` requests, and replies 304 if the `ETag` matches.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/data", listener.local_addr().unwrap());
+    let server = thread::spawn(move || {
+        let mut requests = Vec::new();
+        for stream in listener.incoming().take(3) {
+            let mut stream = stream.unwrap();
+            let mut lines = Vec::new();
+            for line in BufReader::new(&stream).lines() {
+                let line = line.unwrap();
+                if line.is_empty() {
+                    break;
+                }
+
+                lines.push(line);
+            }
+
+            let response = if lines.iter().any(|v| v == "If-None-Match: \"v1\"") {
+                "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_owned()
+            } else if lines[0].starts_with("GET /data/textures/a ") {
+                "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello"
+                    .to_owned()
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_owned()
+            };
+
+            stream.write_all(response.as_bytes()).unwrap();
+            requests.push(lines.remove(0));
+        }
+
+        requests
+    });
+
+    let cache = ::std::env::temp_dir().join("crayon-http-cache");
+    let _ = ::std::fs::remove_dir_all(&cache);
+    let vfs = HttpFilesystem::new(url)
+        .unwrap()
+        .with_cache(&cache)
+        .unwrap();
+    let location = Path::new("textures/a");
+
+    let mut buf = Vec::new();
+    assert_eq!(vfs.read_to_end(location, &mut buf).unwrap(), 5);
+    assert!(vfs.read_to_end(Path::new("b"), &mut buf).is_err());
+    assert_eq!(vfs.read_to_end(location, &mut buf).unwrap(), 5);
+    assert_eq!(buf, b"hellohello");
+
+    let requests = server.join().unwrap();
+    assert!(requests[2].starts_with("GET /data/textures/a "));
+
+    // The cached file is used if the server is unreachable.
+    assert_eq!(vfs.read_to_end(location, &mut buf).unwrap(), 5);
+    assert!(vfs.exists(location));
+    assert!(!vfs.exists(Path::new("b")));
+
+    // Locations out of the cache directory are rejected, even if the file exists.
+    let escaped = ::std::env::temp_dir().join("crayon-http-escaped");
+    ::std::fs::write(&escaped, b"escaped").unwrap();
+    assert!(vfs.read_to_end(Path::new("../crayon-http-escaped"), &mut buf).is_err());
+    assert!(vfs.read_to_end(&escaped, &mut buf).is_err());
+    assert!(!vfs.exists(Path::new("../crayon-http-escaped")));
+    assert!(!vfs.exists(&escaped));
+    assert_eq!(buf.len(), 15);
+
+    ::std::fs::remove_file(&escaped).unwrap();
+    ::std::fs::remove_dir_all(&cache).unwrap();
+}
+
+type Gate = (mpsc::Sender<()>, mpsc::Receiver<()>);
+
+// Records the order of loads, and blocks until the gate is opened if there is one.
+struct OrderLoader(usize, Arc<Mutex<Vec<usize>>>, Mutex<Option<Gate>>);
+
+impl Loader for OrderLoader {
+    fn load(&self, _: &[u8]) -> Result<()> {
+        if let Some((ref started, ref gate)) = *self.2.lock().unwrap() {
+            started.send(()).unwrap();
+            gate.recv().unwrap();
+        }
+
+        self.1.lock().unwrap().push(self.0);
+        Ok(())
+    }
+}
+
+#[test]
+fn priority_and_cancel() {
+    let uuids: Vec<_> = (0..5).map(|i| Uuid::from_bytes([i + 10; 16])).collect();
+    let names = ["a", "b", "c", "d", "e"];
+    let items: Vec<(&str, Uuid, &[usize])> = names
+        .iter()
+        .zip(&uuids)
+        .map(|(&name, &uuid)| (name, uuid, &[][..]))
+        .collect();
+
+    let sched = crayon::sched::ScheduleSystem::new(1, None, None);
+    let mut res = ResourceSystem::new(sched.shared()).unwrap();
+    res.mount("mem", Memory::with_items(&items)).unwrap();
+    let res = res.shared();
+
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let load = |i: usize, priority: i32, gate: Option<Gate>| {
+        let loader = OrderLoader(i, order.clone(), Mutex::new(gate));
+        res.load_from_uuid_with_priority(loader, uuids[i], priority)
+            .unwrap()
+    };
+
+    // Keeps the only worker busy until all the loads are queued.
+    let (tx, rx) = mpsc::channel();
+    let (started_tx, started_rx) = mpsc::channel();
+    let first = load(0, 0, Some((started_tx, rx)));
+    started_rx.recv().unwrap();
+
+    let promises = vec![
+        first,
+        load(1, 0, None),
+        load(2, 5, None),
+        load(3, 1, None),
+        load(4, 9, None),
+    ];
+
+    promises[3].set_priority(10);
+    promises[4].cancel();
+    tx.send(()).unwrap();
+
+    for v in &promises {
+        sched.shared().wait_until(v.as_ref());
+    }
+
+    assert_eq!(*order.lock().unwrap(), vec![0, 3, 2, 1]);
+    assert!(promises[1].result().is_ok());
+    let err = promises[4].result().unwrap_err();
+    assert!(format!("{}", err.find_root_cause()).contains("Cancelled"));
+    assert_eq!(res.load_stats().failed, 1);
+}
+
+#[test]
+fn cancel_registry() {
+    let uuids: Vec<_> = (0..3).map(|i| Uuid::from_bytes([i + 20; 16])).collect();
+    let items: Vec<(&str, Uuid, &[usize])> = vec![
+        ("gate", uuids[0], &[]),
+        ("a", uuids[1], &[]),
+        ("b", uuids[2], &[]),
+    ];
+
+    let sched = crayon::sched::ScheduleSystem::new(1, None, None);
+    let mut res = ResourceSystem::new(sched.shared()).unwrap();
+    res.mount("mem", Memory::with_items(&items)).unwrap();
+    let res = res.shared();
+
+    // Keeps the only worker busy until the loads of registry are cancelled.
+    let (tx, rx) = mpsc::channel();
+    let (started_tx, started_rx) = mpsc::channel();
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let loader = OrderLoader(0, order, Mutex::new(Some((started_tx, rx))));
+    let gate = res.load_from_uuid(loader, uuids[0]).unwrap();
+    started_rx.recv().unwrap();
+
+    let registry = Registry::new(res.clone(), TextRegister::default());
+    let a = registry.create_from_uuid(uuids[1]).unwrap();
+    let b = registry.create_from_uuid(uuids[2]).unwrap();
+    registry.delete(b);
+
+    let promises = vec![gate, res.promise(uuids[1]).unwrap(), res.promise(uuids[2]).unwrap()];
+    promises[1].cancel();
+    promises[2].cancel();
+    tx.send(()).unwrap();
+
+    for v in &promises {
+        sched.shared().wait_until(v.as_ref());
+    }
+
+    assert!(promises[1].result().is_err());
+    assert!(promises[2].result().is_err());
+
+    // The cancelled resource fails instead of loading forever.
+    assert!(!registry.is_loading(a));
+    assert_eq!(registry.get(a, |v| v.clone()), None);
+
+    // The one that has been deleted is freed with its redirect.
+    assert!(!registry.contains(b));
+    assert_eq!(registry.len(), 1);
+
+    registry.delete(a);
+    assert!(!registry.contains(a));
+
+    let a = registry.create_from_uuid(uuids[1]).unwrap();
+    registry.wait_until(a).unwrap();
+    assert_eq!(registry.get(a, |v| v.clone()), Some(String::new()));
+}
+
+#[test]
+fn shared_failures() {
+    let uuids = [Uuid::from_bytes([30; 16]), Uuid::from_bytes([31; 16])];
+    let mut vfs = MemoryFilesystem::new();
+    vfs.add_resource("gate", uuids[0], &b""[..]);
+    vfs.add_resource("bad", uuids[1], &b"unattachable"[..]);
+
+    let sched = crayon::sched::ScheduleSystem::new(1, None, None);
+    let mut res = ResourceSystem::new(sched.shared()).unwrap();
+    res.mount("mem", vfs).unwrap();
+    let res = res.shared();
+
+    // Keeps the only worker busy until both of the futures are awaiting.
+    let (tx, rx) = mpsc::channel();
+    let (started_tx, started_rx) = mpsc::channel();
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let loader = OrderLoader(0, order, Mutex::new(Some((started_tx, rx))));
+    res.load_from_uuid(loader, uuids[0]).unwrap();
+    started_rx.recv().unwrap();
+
+    let registry = Registry::new(res.clone(), TextRegister::default());
+    let handle = registry.create_from_uuid(uuids[1]).unwrap();
+    let futures = (registry.future(handle), registry.future(handle));
+    tx.send(()).unwrap();
+
+    // Every awaiter gets the error.
+    assert!(block_on(futures.0).is_err());
+    assert!(block_on(futures.1).is_err());
+
+    // So do the ones after the load finished.
+    assert!(res.promise(uuids[1]).is_none());
+    assert!(block_on(registry.future(handle)).is_err());
+    assert!(registry.wait_until(handle).is_err());
+}
+
+// Creates the children of every node in `load`, and keeps them until `detach`.
+#[derive(Clone)]
+struct TreeRegister(Arc<Registry<Handle, TextRegister>>, Vec<Uuid>);
+
+impl Register for TreeRegister {
+    type Handle = Handle;
+    type Intermediate = Vec<Handle>;
+    type Value = Vec<Handle>;
+
+    fn load(&self, _: Handle, _: &[u8]) -> Result<Vec<Handle>> {
+        self.1.iter().map(|&v| self.0.create_from_uuid(v)).collect()
+    }
+
+    fn attach(&self, _: Handle, item: Vec<Handle>) -> Result<Vec<Handle>> {
+        Ok(item)
+    }
+
+    fn detach(&self, _: Handle, value: Vec<Handle>) {
+        for v in value {
+            self.0.delete(v);
+        }
+    }
+
+    fn dependencies(&self, item: &Vec<Handle>) -> Vec<Uuid> {
+        item.iter().filter_map(|&v| self.0.uuid(v)).collect()
+    }
+}
+
+#[test]
+fn dependency_loading() {
+    let uuids: Vec<_> = (0..4).map(|i| Uuid::from_bytes([i + 20; 16])).collect();
+    let items: [(&str, Uuid, &[usize]); 4] = [
+        ("material", uuids[0], &[]),
+        ("albedo", uuids[1], &[]),
+        ("normal", uuids[2], &[]),
+        ("model", uuids[3], &[0]),
+    ];
+
+    let sched = crayon::sched::ScheduleSystem::new(2, None, None);
+    let mut res = ResourceSystem::new(sched.shared()).unwrap();
+    res.mount("mem", Memory::with_items(&items)).unwrap();
+    let res = res.shared();
+
+    let textures = Arc::new(Registry::new(res.clone(), TextRegister::default()));
+    let register = TreeRegister(textures.clone(), vec![uuids[1], uuids[2]]);
+    let materials = Registry::new(res.clone(), register);
+
+    let handle = materials.create_from("mem:material").unwrap();
+    materials.wait_until(handle).unwrap();
+    assert!(!materials.is_loading(handle));
+    assert_eq!(materials.progress(handle), 1.0);
+    assert_eq!(res.dependencies(uuids[0]), vec![uuids[1], uuids[2]]);
+
+    let children = materials.get(handle, |v| v.clone()).unwrap();
+    for &v in &children {
+        assert_eq!(textures.get(v, |v| v.clone()), Some("".to_owned()));
+    }
+
+    // The model is still being loaded, while its material and textures are resident.
+    let (tx, rx) = mpsc::channel();
+    let (started_tx, started_rx) = mpsc::channel();
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let loader = OrderLoader(3, order, Mutex::new(Some((started_tx, rx))));
+    let promise = res.load_from_uuid(loader, uuids[3]).unwrap();
+    started_rx.recv().unwrap();
+    assert_eq!(res.progress(uuids[3]), 0.75);
+
+    tx.send(()).unwrap();
+    sched.shared().wait_until(promise.as_ref());
+    assert_eq!(res.progress(uuids[3]), 1.0);
+
+    // Dependencies are kept alive by the resource, and released with it.
+    materials.delete(handle);
+    for &v in &children {
+        assert!(!textures.contains(v));
+    }
+
+    assert!(res.dependencies(uuids[0]).is_empty());
+}
+
+#[test]
+fn load_group() {
+    let uuids: Vec<_> = (0..3).map(|i| Uuid::from_bytes([i + 30; 16])).collect();
+    let items: [(&str, Uuid, &[usize]); 3] = [
+        ("level", uuids[0], &[2]),
+        ("music", uuids[1], &[]),
+        ("terrain", uuids[2], &[]),
+    ];
+
+    let sched = crayon::sched::ScheduleSystem::new(2, None, None);
+    let mut res = ResourceSystem::new(sched.shared()).unwrap();
+    res.mount("mem", Memory::with_items(&items)).unwrap();
+    let res = res.shared();
+
+    assert!(res.load_group(&["mem:unknown"]).is_err());
+    assert_eq!(res.load_group_from_uuids(&[]).progress(), 1.0);
+
+    let (tx, rx) = mpsc::channel();
+    let (started_tx, started_rx) = mpsc::channel();
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let gated = OrderLoader(2, order.clone(), Mutex::new(Some((started_tx, rx))));
+    res.load_from_uuid(gated, uuids[2]).unwrap();
+    started_rx.recv().unwrap();
+
+    let promise = res
+        .load_from_uuid(OrderLoader(1, order, Mutex::new(None)), uuids[1])
+        .unwrap();
+    sched.shared().wait_until(promise.as_ref());
+
+    // The terrain is a dependency of level, which is still being loaded.
+    let group = res.load_group(&["mem:level", "mem:music"]).unwrap();
+    assert_eq!(group.uuids(), &uuids[0..2]);
+    assert_eq!(group.progress(), 2.0 / 3.0);
+    assert!(!group.is_finished());
+
+    tx.send(()).unwrap();
+    block_on(group.future());
+    assert!(group.is_finished());
+    assert_eq!(group.progress(), 1.0);
+}
+
+// Pretends every resource takes 64 bytes, and counts the detached values.
+#[derive(Clone, Default)]
+struct BlobRegister(Arc<Mutex<usize>>);
+
+impl Register for BlobRegister {
+    type Handle = Handle;
+    type Intermediate = ();
+    type Value = ();
+
+    fn load(&self, _: Handle, bytes: &[u8]) -> Result<()> {
+        if !bytes.is_empty() {
+            let err = ::std::io::Error::new(::std::io::ErrorKind::InvalidData, "not empty");
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+
+    fn attach(&self, _: Handle, _: ()) -> Result<()> {
+        Ok(())
+    }
+
+    fn detach(&self, _: Handle, _: ()) {
+        *self.0.lock().unwrap() += 1;
+    }
+
+    fn memory(&self, _: &()) -> usize {
+        64
+    }
+}
+
+#[test]
+fn cache_eviction() {
+    let uuids: Vec<_> = (0..3).map(|i| Uuid::from_bytes([i + 40; 16])).collect();
+    let items: [(&str, Uuid, &[usize]); 3] =
+        [("a", uuids[0], &[]), ("b", uuids[1], &[]), ("c", uuids[2], &[])];
+
+    let sched = crayon::sched::ScheduleSystem::new(2, None, None);
+    let mut res = ResourceSystem::new(sched.shared()).unwrap();
+    res.mount("mem", Memory::with_items(&items)).unwrap();
+
+    let mut broken = MemoryFilesystem::new();
+    broken.add_resource("d", Uuid::from_bytes([43; 16]), &b"broken"[..]);
+    res.mount("broken", broken).unwrap();
+    let res = res.shared();
+
+    let register = BlobRegister::default();
+    let registry = Registry::new(res.clone(), register.clone());
+    let create = |name: &str| {
+        let handle = registry.create_from(name).unwrap();
+        registry.wait_until(handle).unwrap();
+        handle
+    };
+
+    // Unused resources are unloaded immediately without a cache policy.
+    let a = create("mem:a");
+    registry.delete(a);
+    assert!(!registry.contains(a));
+    assert_eq!(*register.0.lock().unwrap(), 1);
+
+    // Cached resources are reused with the same handles.
+    registry.set_cache_policy(CachePolicy::default());
+
+    // Except the failed ones, which are loaded again when they are created.
+    let d = registry.create_from("broken:d").unwrap();
+    assert!(registry.wait_until(d).is_err());
+    registry.delete(d);
+    assert!(!registry.contains(d));
+    assert_eq!(registry.cached_len(), 0);
+
+    let d = registry.create_from("broken:d").unwrap();
+    assert!(registry.wait_until(d).is_err());
+    assert_eq!(res.load_stats().failed, 2);
+    registry.delete(d);
+
+    let a = create("mem:a");
+    registry.delete(a);
+    assert_eq!(registry.cached_len(), 1);
+    assert_eq!(registry.cached_bytes(), 64);
+
+    let entries = res.entries();
+    assert_eq!(entries.len(), 1);
+    assert_eq!((entries[0].uuid, entries[0].rc), (uuids[0], 0));
+    assert_eq!(entries[0].bytes, 64);
+
+    // Deleting a cached resource again is ignored.
+    registry.delete(a);
+    assert_eq!(registry.rc(a), Some(0));
+    assert_eq!(registry.cached_len(), 1);
+    assert_eq!(create("mem:a"), a);
+    assert_eq!(registry.rc(a), Some(1));
+    assert_eq!(registry.cached_len(), 0);
+
+    // The least recently used one goes first.
+    let mut policy = CachePolicy::default();
+    policy.max_items = 2;
+    registry.set_cache_policy(policy);
+    let (b, c) = (create("mem:b"), create("mem:c"));
+    registry.delete(a);
+    registry.delete(b);
+    registry.delete(c);
+    assert!(!registry.contains(a));
+    assert!(registry.contains(b) && registry.contains(c));
+    assert_eq!(*register.0.lock().unwrap(), 2);
+
+    // The least frequently used one goes first, even if it was released later.
+    assert_eq!(create("mem:b"), b);
+    registry.delete(b);
+    let a = create("mem:a");
+    registry.delete(a);
+    assert!(!registry.contains(c));
+
+    policy.max_items = 1;
+    policy.eviction = Eviction::Lfu;
+    registry.set_cache_policy(policy);
+    assert!(registry.contains(b));
+    assert!(!registry.contains(a));
+
+    // The memory budget is shared by all the caches.
+    registry.set_cache_policy(CachePolicy::default());
+    let (a, c) = (create("mem:a"), create("mem:c"));
+    registry.delete(a);
+    registry.delete(c);
+    assert_eq!(res.cached_bytes(), 192);
+
+    res.set_memory_budget(100);
+    assert_eq!(res.trim_caches(), 2);
+    assert_eq!(registry.cached_len(), 1);
+    assert!(registry.contains(c));
+
+    registry.set_cache_policy(None);
+    assert_eq!(res.cached_bytes(), 0);
+    assert_eq!(*register.0.lock().unwrap(), 7);
+}
+
+#[test]
+fn writable() {
+    let root = ::std::env::temp_dir().join(format!("crayon-writable-{}", ::std::process::id()));
+    let sched = crayon::sched::ScheduleSystem::new(1, None, None);
+    let mut res = ResourceSystem::new(sched.shared()).unwrap();
+    res.mount_writable("saves", UserDirFilesystem::with_root(&root).unwrap())
+        .unwrap();
+    assert!(res.mount("saves", Memory::new(Uuid::nil())).is_err());
+    let res = res.shared();
+
+    assert!(!res.exists("saves:slots/1.sav"));
+    res.write("saves:slots/1.sav", b"level 1").unwrap();
+    assert!(res.exists("saves:slots/1.sav"));
+    assert_eq!(res.read("saves:slots/1.sav").unwrap(), b"level 1");
+    assert!(root.join("slots").join("1.sav").is_file());
+
+    res.write("saves:slots/1.sav", b"level 2").unwrap();
+    assert_eq!(res.read("saves:slots/1.sav").unwrap(), b"level 2");
+
+    // Files could not be written out of the root, or into read-only file systems.
+    assert!(res.write("saves:../escaped.sav", b"").is_err());
+    assert!(res.write("res:1.sav", b"").is_err());
+    assert!(res.read("saves:slots/2.sav").is_err());
+
+    res.remove("saves:slots").unwrap();
+    assert!(!res.exists("saves:slots/1.sav"));
+    ::std::fs::remove_dir_all(&root).unwrap();
+}